@@ -0,0 +1,37 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use launcher::content::{ContentClassifier, ContentOptions};
+
+/// App searches of several words, which don't need to look for units.
+const SEARCHES: [&str; 4] = [
+    "text editor for markdown",
+    "open the pod bay doors",
+    "weather in berlin",
+    "system monitor with graphs",
+];
+
+/// Classifying every prefix of `text`, like typing it does.
+fn typing(classifier: &ContentClassifier, text: &str) {
+    for (end, _) in text.char_indices().skip(1) {
+        criterion::black_box(classifier.classify(&text[..end])).ok();
+    }
+    criterion::black_box(classifier.classify(text)).ok();
+}
+
+fn keystrokes(c: &mut Criterion) {
+    let classifier = ContentClassifier::new(ContentOptions::default());
+    let mut group = c.benchmark_group("typing");
+    group.bench_function("multi-word searches", |b| {
+        b.iter(|| {
+            for text in SEARCHES {
+                typing(&classifier, text);
+            }
+        })
+    });
+    group.bench_function("conversion", |b| {
+        b.iter(|| typing(&classifier, "12.5 cm to inch"))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, keystrokes);
+criterion_main!(benches);
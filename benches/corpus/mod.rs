@@ -0,0 +1,75 @@
+//! The fixture corpus shared by the search benchmark and the ranking test.
+
+use launcher::{
+    cache::{DesktopEntryCache, Entry},
+    search::{search, sort_search_results, SearchMatch, SearchMatchKind, SearchTuning},
+    text::Collator,
+};
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    sync::{Arc, Mutex},
+};
+
+const ENTRIES: &str = include_str!("../fixtures/entries.tsv");
+const QUERIES: &str = include_str!("../fixtures/queries.txt");
+
+/// How many results per query are kept in the rankings.
+const RANKED: usize = 5;
+
+/// A cache of the fixture entries, one per line as file name, name, generic
+/// name, and exec separated by tabs.
+pub fn cache() -> Arc<Mutex<DesktopEntryCache>> {
+    let entries = ENTRIES
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let generic_name = Some(fields[2]).filter(|name| !name.is_empty());
+            Entry::new(fields[0], fields[1], generic_name, fields[3])
+        })
+        .collect();
+    Arc::new(Mutex::new(DesktopEntryCache::from_entries(entries)))
+}
+
+pub fn queries() -> impl Iterator<Item = &'static str> {
+    QUERIES.lines().filter(|line| !line.is_empty())
+}
+
+/// Searches like the launcher does for typed text, without `PATH` or
+/// history.
+pub fn rank(query: &str, cache: &Arc<Mutex<DesktopEntryCache>>) -> Vec<SearchMatch> {
+    let tuning = SearchTuning::default();
+    let (mut results, _) = search(query, cache.clone(), None, &tuning, None, None);
+    sort_search_results(
+        &mut results,
+        &HashMap::new(),
+        None,
+        &tuning,
+        &Collator::default(),
+    );
+    results
+}
+
+/// The top results of every query with their score.
+pub fn rankings() -> String {
+    let cache = cache();
+    let mut rankings = String::new();
+    for query in queries() {
+        writeln!(rankings, "{query}").unwrap();
+        for result in rank(query, &cache).iter().take(RANKED) {
+            let SearchMatchKind::DeskopEntry(entry) = result.unwrap() else {
+                unreachable!()
+            };
+            let file_name = cache.lock().unwrap().get_entry(entry.id).file_name.clone();
+            writeln!(
+                rankings,
+                "    {:.3} {} ({file_name})",
+                result.score(),
+                entry.name
+            )
+            .unwrap();
+        }
+    }
+    rankings
+}
@@ -0,0 +1,28 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+mod corpus;
+
+/// Searching for all fixture queries, including scoring and sorting.
+fn all_queries(c: &mut Criterion) {
+    let cache = corpus::cache();
+    c.bench_function("all queries", |b| {
+        b.iter(|| {
+            for query in corpus::queries() {
+                criterion::black_box(corpus::rank(query, &cache));
+            }
+        })
+    });
+}
+
+/// The queries one by one, to see which of them a change affects.
+fn each_query(c: &mut Criterion) {
+    let cache = corpus::cache();
+    let mut group = c.benchmark_group("query");
+    for query in corpus::queries() {
+        group.bench_function(query, |b| b.iter(|| corpus::rank(query, &cache)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, all_queries, each_query);
+criterion_main!(benches);
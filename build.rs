@@ -1,4 +1,6 @@
 fn main() {
     println!("cargo:rustc-link-lib=X11");
     println!("cargo:rustc-link-lib=Xinerama");
+    println!("cargo:rustc-link-lib=Xi");
+    println!("cargo:rustc-link-lib=Xrandr");
 }
@@ -1,38 +1,133 @@
 use crate::{
-    cache::DesktopEntryCache,
+    browser::{resolve_browser, BrowserCommand, BrowserConfig, BrowserEnvironment},
+    cache::{DesktopEntryCache, ExecArgument},
     config::Config,
-    content::{ClassificationError, Content, ContentClassifier},
-    history::History,
-    input::{self, InputContext},
+    content::{command_line, ClassificationError, Content, ContentClassifier},
+    emoji::{self, EmojiMatch},
+    gpu,
+    history::{Entry, History},
+    input::{self, InputContext, Key},
+    instance::PRESENT_ATOM,
+    launch_feedback::LaunchFeedback,
+    list_geometry::Navigation,
+    list_view::{Render, RowKind},
+    perf::{self, Phase},
     search::{
-        self, search_path_for_exact_match, sort_search_results, SearchMatch, SearchMatchKind,
+        self, merge_late, search_with_argument, sort_search_results, LateResults, PathSearch,
+        SearchMatch, SearchMatchKind, SearchTuning,
     },
     smart_content::{Action, ReadyContent, SmartContentCommitAction},
-    ui::Ui,
-    units::{convert, default_unit_mapping, Unit},
-    util::{copy, launch_orphan},
-    x::Display,
+    static_units::factor,
+    text::Collator,
+    tour,
+    ui::{FocusDirection, Ui},
+    units::{
+        add_currencies, convert, default_unit_mapping, fetch_currencies, list_units,
+        CurrencyResult, Unit, UnitListing, UNITS_COMMAND,
+    },
+    util::{copy, launch_orphan, launch_orphan_with_env, shell_command_with_env, shell_quote},
+    warnings::{self, warning, Warning, LOG_COMMAND},
+    window_matching::WindowClass,
+    x::{Display, Window, XInput2, XWindow},
 };
 use std::{
     borrow::Borrow,
+    cell::OnceCell,
     collections::HashMap,
     ops::Deref,
     sync::{
-        mpsc::{channel, Receiver, Sender},
+        mpsc::{channel, Receiver, Sender, TryRecvError},
         Arc, Mutex,
     },
+    time::{Duration, Instant},
+};
+use x11::xlib::{
+    Atom, ButtonPress, ClientMessage, Expose, FocusOut, KeyPress, LASTEvent, MotionNotify,
+    PropertyChangeMask, PropertyNotify, XClientMessageEvent, XEvent, XFilterEvent,
 };
-use x11::xlib::{ButtonPress, KeyPress, LASTEvent, XEvent, XFilterEvent};
 
 const SIGNAL_EVENT: i32 = LASTEvent + 1;
 
+/// How often to check whether the currencies or a rebuilt cache finished
+/// loading.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often to check for matches of late search providers, shorter than
+/// `POLL_INTERVAL` since the list is incomplete until they arrive.
+const LATE_RESULTS_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How long the row of a `$` command stays after the text stopped being one,
+/// so deleting the `$` and typing it again doesn't swap the list views back
+/// and forth.
+const COMMAND_HIDE_DELAY: Duration = Duration::from_millis(300);
+
+/// Shown when trying to run something other than a desktop entry in
+/// restricted mode.
+const RESTRICTED_HINT: &str = "Only applications can be launched";
+
+/// Shown for a `$` without a command.
+const COMMAND_HINT: &str = "Type a command after $";
+
+/// Shown when deleting an item that is not in the history.
+const NOTHING_TO_DELETE_HINT: &str = "Only history entries can be deleted";
+
+/// Shown when only the start of the clipboard was pasted.
+const PASTE_TRUNCATED_HINT: &str = "Pasted text truncated";
+
+/// Shown while the desktop entry cache is rebuilt.
+const REBUILDING_HINT: &str = "Looking for new applications…";
+
 pub enum Signal {
     SearchTextChanged(String),
     CursorPositionChanged((i32, i32)),
-    SwapFocus,
+    SwapFocus(FocusDirection),
     Quit,
     Commit(Option<usize>),
-    DeleteEntry(usize),
+    /// An action row was committed, see `RowKind::Action`.
+    CommitAction(&'static str),
+    /// Like `Commit` but keeps the launcher open, for picking several emoji.
+    /// Anything else is committed normally.
+    Pick(Option<usize>),
+    /// Remove the entry from the history, `None` if the item that should be
+    /// deleted is not in the history.
+    DeleteEntry(Option<Entry>),
+    /// Show or hide the command that would be run when committing the given
+    /// item (same meaning as the value of `Commit`).
+    ToggleDetails(Option<usize>),
+    /// Copy the command or path committing the given item would run, instead
+    /// of running it.
+    CopyCommand(Option<usize>),
+    /// Move the list selection without moving the focus to the list.
+    Navigate(Navigation),
+    /// Read the desktop entries again, for applications installed while
+    /// running.
+    RebuildCache,
+    /// Fetch the currency rates again instead of using the cached ones.
+    RefreshCurrencies,
+    /// Only the start of the clipboard was pasted, sent after the
+    /// `SearchTextChanged` for it.
+    PasteTruncated,
+}
+
+/// Whether `event` is the window manager asking us to close, like when the
+/// window's close button was pressed.
+fn is_delete_request(
+    event: &XClientMessageEvent,
+    wm_protocols: Atom,
+    wm_delete_window: Atom,
+) -> bool {
+    event.message_type == wm_protocols
+        && event.format == 32
+        && event.data.get_long(0) as Atom == wm_delete_window
+}
+
+/// The received `signal` as the main loop handles it.
+fn handled_signal(signal: Signal, picking_emoji: bool) -> Signal {
+    match signal {
+        // Only emoji can be picked without closing
+        Signal::Pick(id) if !picking_emoji => Signal::Commit(id),
+        signal => signal,
+    }
 }
 
 pub fn send_signal(display: &Display, sender: &Sender<Signal>, signal: Signal) {
@@ -47,6 +142,319 @@ pub fn send_signal(display: &Display, sender: &Sender<Signal>, signal: Signal) {
     display.push_event(event);
 }
 
+/// Whether to show the smart content `content` for `entry_text` when the best
+/// search result has `best_score`.  An expression typed as words, like
+/// `code * 42` with `code` defined as a constant, gives way to results scoring
+/// at least `threshold` since they're more likely what was meant.  Explicit
+/// `=` expressions and other content are always shown.
+fn shows_smart_content(
+    content: &Content,
+    entry_text: &str,
+    best_score: Option<f64>,
+    threshold: f64,
+) -> bool {
+    let has_word = entry_text
+        .split(|c: char| !c.is_alphabetic())
+        .any(|word| word.chars().count() > 1);
+    !(matches!(content, Content::BasicExpression(_))
+        && has_word
+        && best_score.is_some_and(|score| score >= threshold))
+}
+
+/// Stores `new` in `shown` and returns it if it's different, `None` if it's
+/// already shown.
+fn changed<T: Clone + PartialEq>(shown: &mut T, new: T) -> Option<T> {
+    if *shown == new {
+        None
+    } else {
+        *shown = new.clone();
+        Some(new)
+    }
+}
+
+/// Whether the results for `previous` can be narrowed down to the ones for
+/// `text` instead of searching everything.  Not while the desktop entries of
+/// `previous` are still being searched, most of them would be missing.
+fn narrows(previous: &str, text: &str, late: Option<&LateResults>) -> bool {
+    previous.len() >= 3
+        && text.starts_with(previous)
+        && !late.is_some_and(|late| late.is_running(Phase::DesktopEntries))
+}
+
+/// Whether committing `entry_text` runs it as a command instead of an item.
+fn runs_command(entry_text: &str) -> bool {
+    command_line(entry_text).is_some_and(|command| !command.is_empty())
+}
+
+/// The text to search for, without the `$` prefix of a command and with the
+/// whitespace trimmed and collapsed.
+fn search_text(entry_text: &str) -> String {
+    let text = command_line(entry_text).unwrap_or(entry_text);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The text to find an argument in, like `search_text` but with the spacing
+/// inside kept since it can be part of a file name.
+fn argument_text(entry_text: &str) -> &str {
+    command_line(entry_text).unwrap_or(entry_text.trim())
+}
+
+/// How the launcher was closed, this is the exit status.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Exit {
+    /// Something was launched, opened, or copied.
+    Committed,
+    /// Closed without committing anything.
+    Cancelled,
+    /// Could not start, for example without a display.
+    StartupError,
+}
+
+impl Exit {
+    pub fn code(self) -> i32 {
+        match self {
+            Exit::Committed => 0,
+            Exit::Cancelled => 1,
+            Exit::StartupError => 2,
+        }
+    }
+}
+
+/// Remembers whether something was launched, so a second Enter or a
+/// double-click followed by Enter can't launch twice.
+#[derive(Default)]
+struct CommitLatch {
+    committed: bool,
+}
+
+impl CommitLatch {
+    /// Whether further commits should be ignored.
+    fn is_committed(&self) -> bool {
+        self.committed
+    }
+
+    /// Records that a commit launched something.
+    fn commit(&mut self) {
+        self.committed = true;
+    }
+
+    fn exit(&self) -> Exit {
+        if self.committed {
+            Exit::Committed
+        } else {
+            Exit::Cancelled
+        }
+    }
+
+    /// What a commit does in `state`, the checks are in order of precedence.
+    fn route(&self, state: CommitState) -> CommitRoute {
+        if state.showing_tour {
+            CommitRoute::DismissTour
+        } else if state.read_only {
+            CommitRoute::ReadOnly
+        } else if self.committed {
+            CommitRoute::Ignore
+        } else if state.restricted_command {
+            CommitRoute::Refuse
+        } else if state.picking_emoji {
+            CommitRoute::PickEmoji
+        } else {
+            CommitRoute::Launch
+        }
+    }
+}
+
+/// What `CommitLatch::route` needs to know about the launcher.
+#[derive(Debug, Default, Copy, Clone)]
+struct CommitState {
+    showing_tour: bool,
+    /// The list shows warnings or units.
+    read_only: bool,
+    /// A command is entered but commands are restricted.
+    restricted_command: bool,
+    picking_emoji: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum CommitRoute {
+    DismissTour,
+    ReadOnly,
+    /// Something was already launched.
+    Ignore,
+    Refuse,
+    PickEmoji,
+    /// Launches the item, or commits the smart content.
+    Launch,
+}
+
+/// Fetches the currencies on another thread, see `fetch_currencies`.
+fn spawn_currency_fetch(reference: String, refresh: bool) -> Receiver<CurrencyResult> {
+    let (sender, receiver) = channel();
+    std::thread::spawn(move || {
+        sender.send(fetch_currencies(&reference, refresh)).ok();
+    });
+    receiver
+}
+
+/// Rebuilds the desktop entry cache on a worker thread, so the launcher stays
+/// usable with the old entries until the new ones are ready.
+#[derive(Default)]
+struct CacheRebuild {
+    receiver: Option<Receiver<DesktopEntryCache>>,
+}
+
+impl CacheRebuild {
+    /// Runs `build` on a worker thread, unless a rebuild is already running.
+    /// Returns whether it was started.
+    fn start(&mut self, build: impl FnOnce() -> DesktopEntryCache + Send + 'static) -> bool {
+        if self.in_progress() {
+            return false;
+        }
+        let (sender, receiver) = channel();
+        std::thread::spawn(move || {
+            sender.send(build()).ok();
+        });
+        self.receiver = Some(receiver);
+        true
+    }
+
+    fn in_progress(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    /// The rebuilt cache once it's ready.
+    fn poll(&mut self) -> Option<DesktopEntryCache> {
+        let result = self.receiver.as_ref()?.try_recv();
+        match result {
+            Ok(cache) => {
+                self.receiver = None;
+                Some(cache)
+            }
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                self.receiver = None;
+                None
+            }
+        }
+    }
+}
+
+/// Replaces the entries in `cache` with the rebuilt ones and moves the history
+/// over to them.
+fn install_cache(
+    cache: &Mutex<DesktopEntryCache>,
+    rebuilt: DesktopEntryCache,
+    history: &mut History,
+) {
+    let mut cache = cache.lock().unwrap();
+    let old = std::mem::replace(&mut *cache, rebuilt);
+    history.remap(&old, &cache);
+}
+
+fn is_command_content(content: &Option<ReadyContent>) -> bool {
+    matches!(
+        content,
+        Some(ReadyContent::Action(Action::Run, ..) | ReadyContent::Hint(Action::Run, _))
+    )
+}
+
+/// Delays replacing the row of a `$` command while editing, see
+/// `COMMAND_HIDE_DELAY`.
+#[derive(Default)]
+struct CommandHideDelay {
+    pending: Option<(Instant, Option<ReadyContent>)>,
+}
+
+impl CommandHideDelay {
+    /// The content to show instead of `shown` now, `None` if showing `new`
+    /// waits.  The deadline is not moved by further edits, so the row can't
+    /// get stuck while typing.
+    fn update(
+        &mut self,
+        shown: &Option<ReadyContent>,
+        new: Option<ReadyContent>,
+        now: Instant,
+    ) -> Option<Option<ReadyContent>> {
+        if is_command_content(shown) && !is_command_content(&new) {
+            let deadline = self.deadline().unwrap_or(now + COMMAND_HIDE_DELAY);
+            self.pending = Some((deadline, new));
+            None
+        } else {
+            self.pending = None;
+            Some(new)
+        }
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.pending.as_ref().map(|(deadline, _)| *deadline)
+    }
+
+    /// The waiting content once its deadline passed.
+    fn expired(&mut self, now: Instant) -> Option<Option<ReadyContent>> {
+        if self.deadline()? <= now {
+            self.take()
+        } else {
+            None
+        }
+    }
+
+    /// The waiting content, regardless of the deadline.
+    fn take(&mut self) -> Option<Option<ReadyContent>> {
+        self.pending.take().map(|(_, content)| content)
+    }
+
+    fn cancel(&mut self) {
+        self.pending = None;
+    }
+}
+
+/// The emoji picked during one emoji query, they are copied together so
+/// several can be picked without closing.
+#[derive(Default)]
+struct EmojiPicks(String);
+
+impl EmojiPicks {
+    /// Adds `glyph` and returns everything picked so far.
+    fn pick(&mut self, glyph: &str) -> &str {
+        self.0.push_str(glyph);
+        &self.0
+    }
+
+    /// The picks end with the emoji query, the next one starts over.
+    fn query_changed(&mut self, is_emoji_query: bool) {
+        if !is_emoji_query {
+            self.0.clear();
+        }
+    }
+}
+
+/// Closes the launcher if it's left open without input.
+struct InactivityTimer {
+    timeout: Option<Duration>,
+    last_input: Instant,
+}
+
+impl InactivityTimer {
+    fn new(timeout: Option<Duration>, now: Instant) -> Self {
+        Self {
+            timeout,
+            last_input: now,
+        }
+    }
+
+    fn input(&mut self, now: Instant) {
+        self.last_input = now;
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.timeout.map(|timeout| self.last_input + timeout)
+    }
+
+    fn expired(&self, now: Instant) -> bool {
+        self.deadline().is_some_and(|deadline| deadline <= now)
+    }
+}
+
 pub struct App {
     display: Display,
     signal_receiver: Receiver<Signal>,
@@ -56,8 +464,62 @@ pub struct App {
     search_results: Vec<SearchMatch>,
     history: History,
     search_text: String,
+    /// The entry text as typed, see `search_text` for what's searched.
+    entry_text: String,
     content_classifier: ContentClassifier,
+    /// What was last sent to the smart content row.
+    smart_content: Option<ReadyContent>,
+    command_hide_delay: CommandHideDelay,
+    /// See `shows_smart_content`.
+    results_threshold: f64,
     default_unit_mapping: HashMap<Unit, Unit>,
+    default_currency: String,
+    /// Receives the currencies while they are being loaded.
+    currency_receiver: Option<Receiver<CurrencyResult>>,
+    /// The currencies being loaded replace outdated rates.
+    refreshing_currencies: bool,
+    cache_rebuild: CacheRebuild,
+    search_tuning: SearchTuning,
+    search_timeout: Option<Duration>,
+    /// Matches of search providers that missed the timeout.
+    late_results: Option<LateResults>,
+    path_search: PathSearch,
+    /// Orders results with equal scores.
+    collator: Collator,
+    restricted: bool,
+    commit_latch: CommitLatch,
+    inactivity: InactivityTimer,
+    /// The warnings shown while the entry text is `:log`.
+    warnings: Option<Vec<Warning>>,
+    /// The units shown while the entry text is `units [dimension]`.
+    unit_list: Option<Vec<UnitListing>>,
+    /// Whether the first-run tour is shown instead of an empty list.
+    tour: bool,
+    /// The emoji shown while the entry text starts with `emoji_prefix`.
+    emojis: Option<Vec<EmojiMatch>>,
+    emoji_prefix: String,
+    picked: EmojiPicks,
+    browser: BrowserConfig,
+    /// Asking xdg-settings for the default browser runs a process, so this is
+    /// only done once it's needed and then kept.
+    browser_environment: OnceCell<BrowserEnvironment<'static>>,
+    /// Run entries preferring a non-default GPU on it.
+    gpu_offload: bool,
+    /// Only queried once it's needed.
+    gpu_environment: OnceCell<Vec<(String, String)>>,
+    /// Sent by instances started while this one is running.
+    present_atom: Atom,
+    wm_protocols_atom: Atom,
+    wm_delete_window_atom: Atom,
+    /// Key events of keyboards grabbed with XInput2 are turned into core
+    /// events.
+    xi2: Option<XInput2>,
+    /// How long to show the launch feedback for, `None` to exit right away.
+    launch_feedback: Option<Duration>,
+    /// Waiting for the window of a launched program.
+    launching: Option<LaunchFeedback>,
+    client_list_atom: Atom,
+    window_class_overrides: HashMap<String, String>,
 }
 
 impl App {
@@ -66,6 +528,10 @@ impl App {
         let (signal_sender, signal_receiver) = channel();
         let ui = Ui::new(&display, signal_sender, cache.clone(), &config);
         let ic = input::init(&display, &ui.main_window);
+        let xi2 = ui.xi2();
+        // Fetching the rates can take a while, so we don't want to wait for
+        // it before showing the window.
+        let currency_receiver = spawn_currency_fetch(config.default_currency.clone(), false);
         Self {
             display,
             signal_receiver,
@@ -75,11 +541,52 @@ impl App {
             search_results: Vec::new(),
             history,
             search_text: String::new(),
+            entry_text: String::new(),
             content_classifier: ContentClassifier::new(config.smart_content_options),
-            default_unit_mapping: default_unit_mapping(&config.default_currency).mapping,
+            smart_content: None,
+            command_hide_delay: CommandHideDelay::default(),
+            results_threshold: config.smart_content_results_threshold,
+            default_unit_mapping: default_unit_mapping(),
+            default_currency: config.default_currency,
+            currency_receiver: Some(currency_receiver),
+            refreshing_currencies: false,
+            cache_rebuild: CacheRebuild::default(),
+            search_tuning: config.search_tuning,
+            search_timeout: config.search_timeout,
+            late_results: None,
+            path_search: PathSearch::default(),
+            collator: match &config.locale {
+                Some(locale) => Collator::new(locale),
+                None => Collator::from_env(),
+            },
+            restricted: config.restricted,
+            commit_latch: CommitLatch::default(),
+            inactivity: InactivityTimer::new(config.auto_close, Instant::now()),
+            warnings: None,
+            unit_list: None,
+            tour: false,
+            emojis: None,
+            emoji_prefix: config.emoji_prefix,
+            picked: EmojiPicks::default(),
+            browser: config.browser,
+            gpu_offload: config.gpu_offload,
+            gpu_environment: OnceCell::new(),
+            browser_environment: OnceCell::new(),
+            present_atom: display.intern_atom(PRESENT_ATOM),
+            wm_protocols_atom: display.intern_atom(c"WM_PROTOCOLS"),
+            wm_delete_window_atom: display.intern_atom(c"WM_DELETE_WINDOW"),
+            xi2,
+            launch_feedback: config.launch_feedback,
+            launching: None,
+            client_list_atom: display.intern_atom(c"_NET_CLIENT_LIST"),
+            window_class_overrides: config.window_class_overrides,
         }
     }
 
+    pub fn main_window(&self) -> XWindow {
+        self.ui.main_window.handle()
+    }
+
     fn process_smart_content(
         &self,
         classified: Result<Option<Content>, ClassificationError>,
@@ -92,9 +599,11 @@ impl App {
                 Err(error) => ReadyContent::Error(format!("{}", error)),
             },
             Ok(Some(Content::DefaultConversion(value, from))) => {
-                if let Some(to) = self.default_unit_mapping.get(&from) {
-                    let value = convert(value, from.into(), to.clone().into());
-                    ReadyContent::Conversion(value, from.into(), to.clone().into())
+                if let Some(&to) = self.default_unit_mapping.get(&from) {
+                    match convert(value, from, to) {
+                        Ok(value) => ReadyContent::Conversion(value, from, to),
+                        Err(error) => ReadyContent::Error(error.to_string()),
+                    }
                 } else {
                     ReadyContent::Error(format!("No default conversion for {from}"))
                 }
@@ -103,36 +612,71 @@ impl App {
                 if let Some(from) =
                     maybe_from.or_else(|| self.default_unit_mapping.get(&to).copied())
                 {
-                    let value = convert(value, from.into(), to.into());
-                    ReadyContent::Conversion(value, from.into(), to.into())
+                    match convert(value, from, to) {
+                        Ok(value) => ReadyContent::Conversion(value, from, to),
+                        Err(error) => ReadyContent::Error(error.to_string()),
+                    }
                 } else {
                     ReadyContent::Error(format!("No default conversion for {to}"))
                 }
             }
-            Ok(Some(Content::Path)) => {
-                ReadyContent::Action(Action::Path, "Open", s.to_string())
-            }
-            Ok(Some(Content::URL)) => ReadyContent::Action(Action::Web, "Open", s.to_string()),
+            Ok(Some(Content::Factor(unit))) => match factor(unit) {
+                Some(factor) => ReadyContent::Factor(factor),
+                None => ReadyContent::Error(format!("{unit} has no fixed conversion factor")),
+            },
+            Ok(Some(Content::Path)) => ReadyContent::Action(Action::Path, "Open", s.to_string()),
+            Ok(Some(Content::URL(url))) => ReadyContent::Action(Action::Web, "Open", url),
             Ok(Some(Content::Command)) => {
-                let command = &s[1..].trim();
+                let command = command_line(s).unwrap_or_default();
                 ReadyContent::Action(Action::Run, "Run", command.to_string())
             }
+            Ok(Some(Content::EmptyCommand)) => ReadyContent::Hint(Action::Run, COMMAND_HINT),
+            Ok(Some(Content::Clock(query))) => {
+                // Don't shadow an application called "Time" or "Date", adding
+                // a space still shows the clock.
+                if !query.explicit && self.cache.lock().unwrap().has_exact_name(s.trim()) {
+                    return None;
+                }
+                ReadyContent::Clock(query)
+            }
+            Ok(Some(Content::DateCalc(calc))) => ReadyContent::DateCalc(calc),
+            Ok(Some(Content::BaseConversion(value, base))) => {
+                ReadyContent::BaseConversion(value, base)
+            }
             Ok(None) => return None,
             Err(error) => ReadyContent::Error(format!("{}", error)),
         }
         .into()
     }
 
-    pub fn run(&mut self) {
-        if !self.history.is_empty() {
-            self.ui.set_items(self.history.entries(), "");
+    pub fn run(&mut self) -> Exit {
+        let first_paint = perf::timer(Phase::FirstPaint);
+        self.tour = tour::should_show(self.history.is_empty());
+        if self.tour || !self.history.is_empty() {
+            self.show_history();
+        }
+        if let Some(notice) = self.history.take_notice() {
+            self.set_smart_content(Some(ReadyContent::Error(notice)));
         }
         self.ui.redraw();
         self.display.sync(true);
+        drop(first_paint);
         let mut running = true;
         let mut event: XEvent = unsafe { std::mem::zeroed() };
         while running {
-            self.display.next_event(&mut event);
+            if !self.next_event(&mut event) {
+                let now = Instant::now();
+                if let Some(content) = self.command_hide_delay.expired(now) {
+                    self.set_smart_content(content);
+                }
+                if self.launching.as_ref().is_some_and(|l| l.expired(now)) {
+                    running = false;
+                } else if self.inactivity.expired(now) {
+                    println!("Closing after inactivity");
+                    running = false;
+                }
+                continue;
+            }
             if unsafe { event.type_ } == SIGNAL_EVENT {
                 // Need to catch these before XFilterEvent
                 let maybe_signal = self.signal_receiver.recv();
@@ -140,61 +684,174 @@ impl App {
                     println!("Signal receive error: {error}");
                     continue;
                 }
-                match maybe_signal.unwrap() {
+                let signal = handled_signal(maybe_signal.unwrap(), self.emojis.is_some());
+                // Anything but typing acts on what the text is now
+                if !matches!(
+                    signal,
+                    Signal::SearchTextChanged(_) | Signal::CursorPositionChanged(_)
+                ) {
+                    if let Some(content) = self.command_hide_delay.take() {
+                        self.set_smart_content(content);
+                    }
+                }
+                match signal {
+                    // Left over from before committing
+                    _ if self.launching.is_some() => {}
                     Signal::SearchTextChanged(text) => {
                         self.on_text_changed(text);
                     }
                     Signal::CursorPositionChanged((x, y)) => {
                         self.ic.set_cursor_position(x, y);
                     }
-                    Signal::SwapFocus => {
-                        self.ui.swap_focus();
+                    Signal::SwapFocus(direction) => {
+                        self.ui.swap_focus(direction);
                     }
                     Signal::Quit => {
                         running = false;
                     }
-                    Signal::Commit(id) => {
-                        // If there is smart content, pressing enter with the
-                        // entry focused should interact with it.
-                        if let Some(id) = id.or_else(|| {
-                            if self.ui.showing_useful_smart_content() {
-                                None
-                            } else {
-                                Some(0)
+                    Signal::CommitAction(action) => {
+                        self.commit_action(action);
+                    }
+                    Signal::DeleteEntry(_) if self.showing_tour() => {
+                        self.dismiss_tour();
+                        self.show_history();
+                    }
+                    // The warnings and units are read-only
+                    Signal::DeleteEntry(_) | Signal::CopyCommand(_) if self.is_read_only() => {}
+                    Signal::Commit(id) => match self.commit_latch.route(self.commit_state()) {
+                        CommitRoute::DismissTour => {
+                            self.dismiss_tour();
+                            self.show_history();
+                        }
+                        CommitRoute::ReadOnly | CommitRoute::Ignore => {}
+                        CommitRoute::Refuse => self.refuse(),
+                        CommitRoute::PickEmoji => {
+                            if self.pick_emoji(id).is_some() {
+                                self.commit_latch.commit();
+                                running = false;
                             }
-                        }) {
-                            if let Some(exec) = self.get_exec(id) {
-                                self.launch(exec);
-                                if self.search_results.is_empty() {
-                                    self.history.renew(id);
-                                } else {
-                                    self.history.add(
-                                        self.search_results[id].unwrap(),
-                                        self.cache.lock().unwrap().borrow(),
-                                    );
+                        }
+                        CommitRoute::Launch => {
+                            let id = self.default_item(id);
+                            if let Some(RowKind::Action(action)) =
+                                id.and_then(|id| self.ui.list_view().row_kind(id))
+                            {
+                                self.commit_action(action);
+                            } else if let Some(id) = id {
+                                if self.restricted && self.is_path_item(id) {
+                                    self.refuse();
+                                    continue;
                                 }
+                                let mut feedback = None;
+                                if let Some(exec) = self.get_exec(id) {
+                                    let environment = self.get_environment(id);
+                                    let entry = self.entry_id(id);
+                                    self.commit_latch.commit();
+                                    feedback = self
+                                        .watch_launch(entry, Some(&exec))
+                                        .map(|watch| (watch, self.item_name(id)));
+                                    self.launch(exec, &environment);
+                                    if self.search_results.is_empty() {
+                                        let entry = self.history.entries()[id].entry.clone();
+                                        self.history
+                                            .renew(&entry, self.cache.lock().unwrap().borrow());
+                                    } else {
+                                        let cache = self.cache.lock().unwrap();
+                                        let result = self.search_results[id].unwrap();
+                                        self.history.add(result, &cache);
+                                        self.history.learn(&self.search_text, result, &cache);
+                                    }
+                                }
+                                running = self.show_launch_feedback(feedback);
+                            } else if let Some(action) = self.ui.smart_content.commit() {
+                                if self.restricted
+                                    && matches!(
+                                        action,
+                                        SmartContentCommitAction::OpenPath(_)
+                                            | SmartContentCommitAction::Run(_)
+                                    )
+                                {
+                                    self.refuse();
+                                    continue;
+                                }
+                                self.commit_latch.commit();
+                                let feedback = self.watch_action(&action);
+                                self.do_smart_content_commit_action(action);
+                                running = self.show_launch_feedback(feedback);
                             }
-                            running = false;
-                        } else if let Some(action) = self.ui.smart_content.commit() {
-                            self.do_smart_content_commit_action(action);
-                            running = false;
+                        }
+                    },
+                    Signal::Pick(id) => {
+                        if let Some(picked) = self.pick_emoji(id) {
+                            self.ui.show_copied(picked);
+                        }
+                    }
+                    Signal::DeleteEntry(Some(entry)) => {
+                        if self
+                            .history
+                            .delete(&entry, self.cache.lock().unwrap().borrow())
+                        {
+                            self.refresh_list();
+                        }
+                    }
+                    Signal::DeleteEntry(None) => {
+                        self.set_smart_content(Some(ReadyContent::Error(
+                            NOTHING_TO_DELETE_HINT.to_string(),
+                        )));
+                    }
+                    Signal::ToggleDetails(id) => {
+                        if !self.ui.hide_details() && !self.is_read_only() {
+                            self.show_details(id);
                         }
                     }
-                    Signal::DeleteEntry(id) => {
-                        if self.search_results.is_empty() && self.search_text.is_empty() {
-                            self.history.delete(id, self.cache.lock().unwrap().borrow());
+                    Signal::CopyCommand(id) => {
+                        if let Some(command) = self.command_for(id) {
+                            copy(&command);
+                            self.ui.show_copied(command);
                         }
-                        self.ui.set_items(self.history.entries(), "");
+                    }
+                    Signal::Navigate(navigation) => {
+                        self.ui.list_view().navigate(navigation);
+                    }
+                    Signal::RebuildCache => {
+                        self.rebuild_cache();
+                    }
+                    Signal::RefreshCurrencies => {
+                        self.refresh_currencies();
+                    }
+                    Signal::PasteTruncated => {
+                        self.set_smart_content(Some(ReadyContent::Error(
+                            PASTE_TRUNCATED_HINT.to_string(),
+                        )));
                     }
                 }
                 continue;
             }
+            if let Some(key) = self
+                .xi2
+                .and_then(|xi2| xi2.key_event(&self.display, &mut event))
+            {
+                event.key = key;
+            }
             if unsafe { XFilterEvent(&mut event, 0) != 0 } {
                 continue;
             }
             #[allow(non_upper_case_globals)]
             match unsafe { event.type_ } {
+                KeyPress if self.launching.is_some() => {
+                    // Hides the feedback, the program is launched anyway
+                    let event = unsafe { event.key };
+                    if input::translate_key(&event)
+                        .is_some_and(|key| matches!(key.key, Key::Escape))
+                    {
+                        running = false;
+                    }
+                }
+                ButtonPress if self.launching.is_some() => {
+                    running = false;
+                }
                 KeyPress => {
+                    self.inactivity.input(Instant::now());
                     let mut event = unsafe { event.key };
                     if let Some(key) = input::translate_key(&event) {
                         self.ui.key_press(key);
@@ -203,78 +860,449 @@ impl App {
                     }
                 }
                 ButtonPress => {
+                    self.inactivity.input(Instant::now());
                     self.ui.button_press(unsafe { &mut event.button });
                 }
+                MotionNotify => {
+                    self.ui.pointer_motion(unsafe { &event.motion });
+                }
+                Expose => {
+                    self.ui.expose(unsafe { &event.expose });
+                }
+                FocusOut if self.launching.is_none() => {
+                    self.ui.focus_out(unsafe { &event.focus_change });
+                }
+                PropertyNotify => {
+                    if unsafe { event.property.atom } == self.client_list_atom
+                        && self.launched_window_appeared()
+                    {
+                        running = false;
+                    }
+                }
+                ClientMessage => {
+                    let message = unsafe { event.client_message };
+                    if message.message_type == self.present_atom {
+                        self.ui.present();
+                    } else if is_delete_request(
+                        &message,
+                        self.wm_protocols_atom,
+                        self.wm_delete_window_atom,
+                    ) {
+                        // Same as `Signal::Quit`, instead of the window
+                        // manager killing the connection
+                        running = false;
+                    }
+                }
                 _ => continue,
             }
         }
-        self.history.store();
+        if self.commit_latch.is_committed() {
+            self.dismiss_tour();
+        }
+        if let Err(error) = self.history.store() {
+            warning!("Failed to save history: {error}");
+        }
+        self.commit_latch.exit()
     }
 
-    fn on_text_changed(&mut self, text: String) {
-        if text == self.search_text {
+    /// Waits for the next event while performing timed UI updates when their
+    /// deadline passes, returns `false` if there is no event to process.
+    fn next_event(&mut self, event: &mut XEvent) -> bool {
+        self.poll_currencies();
+        self.poll_cache_rebuild();
+        self.poll_late_results();
+        let poll = (self.currency_receiver.is_some() || self.cache_rebuild.in_progress())
+            .then(|| Instant::now() + POLL_INTERVAL);
+        let late_poll = self
+            .late_results
+            .is_some()
+            .then(|| Instant::now() + LATE_RESULTS_POLL_INTERVAL);
+        let deadline = [
+            self.ui.next_deadline(),
+            poll,
+            late_poll,
+            self.inactivity.deadline(),
+            self.command_hide_delay.deadline(),
+            self.launching.as_ref().map(LaunchFeedback::deadline),
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+        let Some(deadline) = deadline else {
+            self.display.next_event(event);
+            return true;
+        };
+        let now = Instant::now();
+        if deadline <= now {
+            self.ui.tick(now);
+            return false;
+        }
+        self.display.next_event_timeout(event, deadline - now)
+    }
+
+    /// Installs the currencies if they finished loading.
+    fn poll_currencies(&mut self) {
+        let Some(receiver) = &self.currency_receiver else {
+            return;
+        };
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                self.currency_receiver = None;
+                return;
+            }
+        };
+        self.currency_receiver = None;
+        let refreshed = std::mem::take(&mut self.refreshing_currencies);
+        match result {
+            Ok(data) => {
+                add_currencies(data, &self.default_currency, &mut self.default_unit_mapping);
+                println!("Currencies loaded");
+                if refreshed {
+                    // Shows the new date even if the converted value is the
+                    // same.
+                    self.smart_content = None;
+                }
+                self.refresh_smart_content();
+            }
+            Err(error) if refreshed => {
+                warning!("Failed to refresh currencies: {error}");
+                self.set_smart_content(Some(ReadyContent::Error(format!(
+                    "Failed to refresh currency rates: {error}"
+                ))));
+            }
+            Err(error) => warning!("Failed to load currencies: {error}"),
+        }
+    }
+
+    /// Adds the matches of late search providers to the list.
+    fn poll_late_results(&mut self) {
+        let Some(late) = &mut self.late_results else {
+            return;
+        };
+        let matches = late.poll();
+        if late.is_done() {
+            self.late_results = None;
+        }
+        if matches.is_empty() {
             return;
         }
-        ///////////////////////////////////////////////////////////////////////
-        // Smart Content
-        self.ui.set_smart_content(
-            self.process_smart_content(self.content_classifier.classify(&text), &text),
+        merge_late(&mut self.search_results, matches);
+        let choice = self
+            .history
+            .choice(&self.search_text, &self.cache.lock().unwrap());
+        sort_search_results(
+            &mut self.search_results,
+            self.history.borrow().desktop_ids(),
+            choice,
+            &self.search_tuning,
+            &self.collator,
         );
-        // Note: this breaks the equivalence check at the start but it doesn't
-        //       really matter.
-        let text = if text.starts_with('$') {
-            text[1..].trim().to_string()
-        } else {
-            text
+        self.update_smart_content();
+        self.ui.set_items(&self.search_results, &self.search_text);
+    }
+
+    /// Fetches the currency rates again, unless they're still loading.
+    fn refresh_currencies(&mut self) {
+        if self.currency_receiver.is_some() {
+            return;
+        }
+        self.currency_receiver = Some(spawn_currency_fetch(self.default_currency.clone(), true));
+        self.refreshing_currencies = true;
+    }
+
+    /// Starts rebuilding the desktop entry cache, the key is ignored while a
+    /// rebuild is running.
+    fn rebuild_cache(&mut self) {
+        let mut rebuilt = self.cache.lock().unwrap().with_same_settings();
+        let started = self.cache_rebuild.start(move || {
+            rebuilt.rebuild();
+            rebuilt
+        });
+        if started {
+            self.set_smart_content(Some(ReadyContent::Hint(Action::Path, REBUILDING_HINT)));
+        }
+    }
+
+    /// Installs the rebuilt cache if it's ready and searches again with it.
+    fn poll_cache_rebuild(&mut self) {
+        let Some(rebuilt) = self.cache_rebuild.poll() else {
+            return;
         };
+        if let Some(error) = rebuilt.error() {
+            warning!("Failed to rebuild desktop entry cache: {error}");
+            self.set_smart_content(Some(ReadyContent::Error(format!(
+                "Failed to rebuild desktop entry cache: {error}"
+            ))));
+            return;
+        }
+        install_cache(&self.cache, rebuilt, &mut self.history);
+        self.set_smart_content(None);
+        self.refresh_list();
+    }
+
+    /// Classifies the entry text again after something the smart content
+    /// depends on changed.
+    fn refresh_smart_content(&mut self) {
+        // The entry may have been cleared since
+        if self.entry_text.trim().is_empty() {
+            return;
+        }
+        let content = self.classify_entry_text();
+        self.set_smart_content(content);
+    }
+
+    /// Shows `content` in the smart content row, unless it's already shown so
+    /// retyping the same text doesn't swap the list views around.
+    fn set_smart_content(&mut self, content: Option<ReadyContent>) {
+        self.command_hide_delay.cancel();
+        if let Some(content) = changed(&mut self.smart_content, content) {
+            self.ui.set_smart_content(content);
+        }
+    }
+
+    /// Classifies the entry text as typed.
+    fn update_smart_content(&mut self) {
+        let content = self.classify_entry_text();
+        if let Some(content) =
+            self.command_hide_delay
+                .update(&self.smart_content, content, Instant::now())
+        {
+            self.set_smart_content(content);
+        }
+    }
+
+    /// The smart content for the entry text, unless the search results for it
+    /// are more relevant.
+    fn classify_entry_text(&self) -> Option<ReadyContent> {
+        let classified = self.content_classifier.classify(&self.entry_text);
+        if let Ok(Some(content)) = &classified {
+            let best_score = self.search_results.first().map(SearchMatch::score);
+            if !shows_smart_content(
+                content,
+                &self.entry_text,
+                best_score,
+                self.results_threshold,
+            ) {
+                return None;
+            }
+        }
+        self.process_smart_content(classified, &self.entry_text)
+    }
+
+    /// Shows the list again after the history changed, without its deleted
+    /// entries or history markers.
+    fn refresh_list(&mut self) {
+        if self.search_text.is_empty() {
+            self.show_history();
+        } else {
+            // Search again without reusing the results
+            self.search_text.clear();
+            self.search_results.clear();
+            self.on_text_changed(self.entry_text.clone());
+        }
+    }
+
+    /// Shows the list for an empty entry text: the history, or the tour
+    /// until it's dismissed.
+    fn show_history(&mut self) {
+        if self.tour {
+            self.ui.set_items(&tour::ITEMS, "");
+        } else if self.history.is_empty() {
+            self.ui.set_items::<SearchMatch>(&[], "");
+        } else {
+            self.ui.set_items(self.history.entries(), "");
+        }
+    }
+
+    fn showing_tour(&self) -> bool {
+        self.tour && self.search_text.is_empty()
+    }
+
+    fn dismiss_tour(&mut self) {
+        if std::mem::take(&mut self.tour) {
+            tour::dismiss();
+        }
+    }
+
+    fn show_warnings(&mut self, text: String) {
+        self.set_smart_content(None);
+        let warnings = self.warnings.insert(warnings::recent());
+        self.ui.set_items(warnings, "");
+        self.search_results.clear();
+        self.search_text = text;
+    }
+
+    fn unit_listing(&self, text: &str) -> Option<Vec<UnitListing>> {
+        // Don't shadow an application called "Units"
+        if text == UNITS_COMMAND && self.cache.lock().unwrap().has_exact_name(text) {
+            return None;
+        }
+        list_units(text)
+    }
+
+    fn show_unit_list(&mut self, units: Vec<UnitListing>, text: String) {
+        self.set_smart_content(None);
+        let units = self.unit_list.insert(units);
+        self.ui.set_items(units, "");
+        self.search_results.clear();
+        self.search_text = text;
+    }
+
+    fn show_emojis(&mut self, emojis: Vec<EmojiMatch>, query: &str, text: String) {
+        self.set_smart_content(None);
+        let emojis = self.emojis.insert(emojis);
+        self.ui.set_items(emojis, query);
+        self.search_results.clear();
+        self.search_text = text;
+    }
+
+    /// Adds the emoji `id` refers to to the picked ones and copies all of
+    /// them, returns what was copied or `None` if there is no such emoji.
+    fn pick_emoji(&mut self, id: Option<usize>) -> Option<String> {
+        let id = self.default_item(id);
+        let emoji = id.and_then(|id| self.emojis.as_ref()?.get(id))?;
+        let picked = self.picked.pick(emoji.glyph()).to_string();
+        self.do_smart_content_commit_action(SmartContentCommitAction::Copy(picked.clone()));
+        Some(picked)
+    }
+
+    /// Whether the list shows information instead of things to launch.
+    fn is_read_only(&self) -> bool {
+        self.warnings.is_some() || self.unit_list.is_some() || self.showing_tour()
+    }
+
+    fn commit_state(&self) -> CommitState {
+        CommitState {
+            showing_tour: self.showing_tour(),
+            read_only: self.is_read_only(),
+            restricted_command: self.restricted && self.is_command(),
+            picking_emoji: self.emojis.is_some(),
+        }
+    }
+
+    fn on_text_changed(&mut self, text: String) {
+        let text_changed = text != self.entry_text;
+        self.entry_text = text;
+        let text = search_text(&self.entry_text);
+        if text == self.search_text {
+            // Only the spacing changed, which doesn't matter for the search
+            // but can for the smart content, like inside a `$` command.
+            if text_changed && !self.is_read_only() && self.emojis.is_none() {
+                self.update_smart_content();
+            }
+            return;
+        }
+        // Whatever is shown next, the late results are for the old text
+        let late_results = self.late_results.take();
+        self.picked
+            .query_changed(emoji::query(&text, &self.emoji_prefix).is_some());
+        if text == LOG_COMMAND {
+            self.show_warnings(text);
+            return;
+        }
+        self.warnings = None;
+        if let Some(units) = self.unit_listing(&text) {
+            self.show_unit_list(units, text);
+            return;
+        }
+        self.unit_list = None;
+        if let Some(query) = emoji::query(&text, &self.emoji_prefix).map(str::to_string) {
+            self.show_emojis(emoji::search(&query), &query, text);
+            return;
+        }
+        self.emojis = None;
         ///////////////////////////////////////////////////////////////////////
         // Search
         if text.is_empty() {
             self.search_text.clear();
             self.search_results.clear();
-            if self.history.is_empty() {
-                self.ui.set_items::<SearchMatch>(&[], "");
-            } else {
-                self.ui.set_items(self.history.entries(), "");
-            }
+            self.update_smart_content();
+            self.show_history();
             return;
         }
         // Only searching for a subset with a short search text will likely
         // results in not finding things we want to find with the current text.
-        if self.search_text.len() >= 3 && text.starts_with(&self.search_text) {
-            self.search_results = search::search(
-                &text,
-                self.cache.clone(),
-                Some(std::mem::take(&mut self.search_results)),
-            );
+        let previous = if narrows(&self.search_text, &text, late_results.as_ref()) {
+            Some(std::mem::take(&mut self.search_results))
         } else {
-            self.search_results = search::search(&text, self.cache.clone(), None);
+            None
+        };
+        (self.search_results, self.late_results) = search::search(
+            &text,
+            self.cache.clone(),
+            previous,
+            &self.search_tuning,
+            (!self.restricted).then_some(&self.path_search),
+            self.search_timeout,
+        );
+        let with_argument = search_with_argument(
+            argument_text(&self.entry_text),
+            &self.cache.lock().unwrap(),
+            &self.search_tuning,
+        );
+        self.search_results.splice(0..0, with_argument);
+        if self.search_results.is_empty() {
+            self.search_results
+                .extend(search::suggest(&text, &self.cache.lock().unwrap()));
         }
+        let choice = self.history.choice(&text, &self.cache.lock().unwrap());
         sort_search_results(
             &mut self.search_results,
             self.history.borrow().desktop_ids(),
+            choice,
+            &self.search_tuning,
+            &self.collator,
         );
+        // After the search since the results can win over the smart content,
+        // and before showing them so they go to the right list view.
+        self.update_smart_content();
         self.ui.set_items(&self.search_results, &text);
         self.search_text = text;
     }
 
+    /// Whether the item `id` is an executable from `PATH` rather than a
+    /// desktop entry.
+    fn is_path_item(&mut self, id: usize) -> bool {
+        if !self.search_results.is_empty() {
+            matches!(
+                self.search_results.get(id).map(SearchMatch::unwrap),
+                Some(SearchMatchKind::PathEntry(_))
+            )
+        } else {
+            self.search_text.is_empty()
+                && matches!(
+                    self.history.entries().get(id).map(|item| &item.entry),
+                    Some(Entry::Path(_))
+                )
+        }
+    }
+
+    /// Shows that the committed item can't be run in restricted mode.
+    fn refuse(&mut self) {
+        self.set_smart_content(Some(ReadyContent::Error(RESTRICTED_HINT.to_string())));
+    }
+
     fn get_exec(&mut self, id: usize) -> Option<String> {
         if !self.search_results.is_empty() {
             Some(match &self.search_results[id].unwrap() {
                 SearchMatchKind::PathEntry(path) => path.to_str().unwrap().to_string(),
-                SearchMatchKind::DeskopEntry(entry) => {
-                    self.cache.lock().unwrap().get_entry(entry.id).exec.clone()
-                }
+                SearchMatchKind::DeskopEntry(entry) => self
+                    .cache
+                    .lock()
+                    .unwrap()
+                    .get_entry(entry.id)
+                    .command(entry.argument.as_ref()),
             })
         } else if !self.history.is_empty() && self.search_text.is_empty() {
-            use crate::history::Entry;
-            Some(match &self.history.entries()[id] {
+            Some(match &self.history.entries()[id].entry {
                 Entry::Path(path) => path.to_str().unwrap().to_string(),
                 Entry::DesktopEntry(file_name) => {
                     let guard = self.cache.lock().unwrap();
                     let cache = guard.deref();
                     let id = cache.find_file(file_name).unwrap();
-                    cache.get_entry(id).exec.clone()
+                    cache.get_entry(id).command(None)
                 }
             })
         } else {
@@ -282,8 +1310,208 @@ impl App {
         }
     }
 
-    fn launch(&self, exec: String) {
-        launch_orphan(&exec);
+    /// The desktop entry of the item `id`.
+    fn entry_id(&mut self, id: usize) -> Option<usize> {
+        if !self.search_results.is_empty() {
+            match self.search_results[id].unwrap() {
+                SearchMatchKind::DeskopEntry(entry) => Some(entry.id),
+                SearchMatchKind::PathEntry(_) => None,
+            }
+        } else if self.search_text.is_empty() {
+            match self.history.entries().get(id).map(|item| &item.entry) {
+                Some(Entry::DesktopEntry(file_name)) => {
+                    self.cache.lock().unwrap().find_file(file_name)
+                }
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Name of the item `id` as shown in the list.
+    fn item_name(&mut self, id: usize) -> String {
+        let cache = self.cache.lock().unwrap();
+        if !self.search_results.is_empty() {
+            self.search_results[id].plain_text(&cache)
+        } else {
+            self.history.entries()[id].plain_text(&cache)
+        }
+    }
+
+    /// Additional environment variables for running the item `id`.
+    fn get_environment(&mut self, id: usize) -> Vec<(String, String)> {
+        let entry_id = self.entry_id(id);
+        let guard = self.cache.lock().unwrap();
+        let cache = guard.deref();
+        let Some(entry) = entry_id.map(|id| cache.get_entry(id)) else {
+            return Vec::new();
+        };
+        let mut environment = entry.environment.clone();
+        if entry.prefers_non_default_gpu && self.gpu_offload {
+            environment.extend(
+                self.gpu_environment
+                    .get_or_init(gpu::offload_environment)
+                    .iter()
+                    .cloned(),
+            );
+        }
+        environment
+    }
+
+    /// The item a signal without an explicit item refers to.  If there is
+    /// smart content, pressing enter with the entry focused should interact
+    /// with it, otherwise it refers to the first item.  An item selected from
+    /// the entry with the navigation keys takes precedence over both.  `$`
+    /// commands never refer to an item, the results only show what the
+    /// command matches.
+    fn default_item(&mut self, id: Option<usize>) -> Option<usize> {
+        if self.is_command() {
+            return None;
+        }
+        id.or_else(|| self.ui.list_view().navigated_item())
+            .or_else(|| {
+                if self.ui.showing_useful_smart_content() {
+                    None
+                } else {
+                    Some(0)
+                }
+            })
+            .filter(|&id| {
+                self.ui
+                    .list_view()
+                    .row_kind(id)
+                    .is_none_or(RowKind::is_selectable)
+            })
+    }
+
+    /// Runs the action of an action row, no list has any yet.
+    fn commit_action(&mut self, action: &'static str) {
+        warning!("Unknown list action: {action}");
+    }
+
+    /// Whether the entry text is a `$` command, these are always run as typed.
+    /// A `$` alone commits the list selection instead, as does any command if
+    /// commands are disabled.  In restricted mode commands are still recognized
+    /// so committing one can be refused.
+    fn is_command(&self) -> bool {
+        runs_command(&self.entry_text)
+            && (self.restricted || self.content_classifier.runs_commands())
+    }
+
+    /// The command that committing `id` would run.
+    fn command_for(&mut self, id: Option<usize>) -> Option<String> {
+        if let Some(id) = self.default_item(id) {
+            self.get_exec(id)
+        } else {
+            self.ui.smart_content.command().map(str::to_string)
+        }
+    }
+
+    /// Shows the command that committing `id` would run, without running it.
+    fn show_details(&mut self, id: Option<usize>) {
+        let environment = self
+            .default_item(id)
+            .map(|id| self.get_environment(id))
+            .unwrap_or_default();
+        if let Some(command) = self.command_for(id) {
+            self.ui
+                .show_details(shell_command_with_env(&command, &environment));
+        }
+    }
+
+    /// Starts watching for the window of a program that is about to be
+    /// launched, if the launch feedback is enabled.
+    fn watch_launch(&self, entry: Option<usize>, command: Option<&str>) -> Option<LaunchFeedback> {
+        let duration = self.launch_feedback?;
+        Some(LaunchFeedback::new(
+            duration,
+            Instant::now(),
+            entry,
+            command,
+            &self.display.client_list(),
+        ))
+    }
+
+    /// Like `watch_launch` for a smart content action, with the name to show.
+    fn watch_action(&self, action: &SmartContentCommitAction) -> Option<(LaunchFeedback, String)> {
+        use crate::smart_content::SmartContentCommitAction::*;
+        match action {
+            Copy(_) => None,
+            // These go through xdg-open or the browser, any new window will do
+            OpenPath(what) | OpenWeb(what) => Some((self.watch_launch(None, None)?, what.clone())),
+            Run(command) => Some((self.watch_launch(None, Some(command))?, command.clone())),
+        }
+    }
+
+    /// Shows the launch feedback, returns whether to keep running for it.
+    fn show_launch_feedback(&mut self, feedback: Option<(LaunchFeedback, String)>) -> bool {
+        let Some((feedback, name)) = feedback else {
+            return false;
+        };
+        self.display
+            .select_input(self.display.root(), PropertyChangeMask);
+        self.ui.show_launching(name);
+        self.launching = Some(feedback);
+        true
+    }
+
+    /// Hides the window, keeping everything so `show` can show it again.
+    pub fn hide(&mut self) {
+        self.ui.hide();
+    }
+
+    /// Shows the window again after `hide` for a new session.  Use this
+    /// instead of `Ui::show`, what the app remembers from the last session is
+    /// reset along with the window.
+    pub fn show(&mut self) {
+        self.reset_session();
+        self.ui.show();
+        self.show_history();
+    }
+
+    /// The app side of `Ui::reset_session`.
+    fn reset_session(&mut self) {
+        self.entry_text.clear();
+        self.search_text.clear();
+        self.search_results.clear();
+        self.late_results = None;
+        // The window forgot it, so the same content must be sent again
+        self.smart_content = None;
+        self.command_hide_delay.cancel();
+        self.warnings = None;
+        self.unit_list = None;
+        self.emojis = None;
+        self.picked.query_changed(false);
+        self.commit_latch = CommitLatch::default();
+        self.launching = None;
+        self.inactivity.input(Instant::now());
+    }
+
+    /// Whether the launched program mapped a window since the last check.
+    fn launched_window_appeared(&mut self) -> bool {
+        let Some(launching) = &mut self.launching else {
+            return false;
+        };
+        let display = self.display;
+        let class_of = |handle| {
+            let mut class_hint = None;
+            let error = display.trap_errors(|| {
+                class_hint = Window::from_handle(&display, handle).class_hint();
+            });
+            let (instance, class) = class_hint.filter(|_| !error)?;
+            Some(WindowClass { instance, class })
+        };
+        launching.window_appeared(
+            &self.display.client_list(),
+            class_of,
+            &self.cache.lock().unwrap(),
+            &self.window_class_overrides,
+        )
+    }
+
+    fn launch(&self, exec: String, environment: &[(String, String)]) {
+        launch_orphan_with_env(&exec, environment);
     }
 
     fn do_smart_content_commit_action(&self, action: SmartContentCommitAction) {
@@ -293,31 +1521,338 @@ impl App {
                 copy(&text);
             }
             OpenPath(path) => launch_orphan(&format!("xdg-open {path}")),
-            OpenWeb(url) => 'out: {
-                // We are a lot looser with URLs than
-                // xdg-open (at least in loose URL mod), so
-                // we really want to open it manually.
-                if let Ok(browser) = std::env::var("BROWSER") {
-                    launch_orphan(&format!("{browser} {url}"))
-                } else if url.starts_with("http") {
-                    launch_orphan(&format!("xdg-open {url}"))
-                } else {
-                    println!(
-                        "$BROWSER not set nad URL doesn't look xdg-openable; trying some common browsers"
-                    );
-                    for browser in ["firefox", "chromium", "google-chrome", "epiphany"] {
-                        println!("  {browser}");
-                        if search_path_for_exact_match(browser) {
-                            println!("   -> Found");
-                            launch_orphan(&format!("{browser} {url}"));
-                            break 'out;
-                        }
+            OpenWeb(url) => {
+                let cache = self.cache.lock().unwrap();
+                let env = self
+                    .browser_environment
+                    .get_or_init(BrowserEnvironment::current);
+                match resolve_browser(&cache, &self.browser, env) {
+                    BrowserCommand::Program(browser) => {
+                        launch_orphan(&format!("{browser} {}", shell_quote(&url)))
+                    }
+                    BrowserCommand::DesktopEntry(id) => {
+                        let entry = cache.get_entry(id);
+                        let command = entry.command(Some(&ExecArgument::Url(url)));
+                        launch_orphan_with_env(&command, &entry.environment);
                     }
-                    println!("None found, trying xdg-open");
-                    launch_orphan(&format!("xdg-open {url}"));
                 }
             }
             Run(command) => launch_orphan(&command),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::Entry as DesktopEntry;
+
+    #[test]
+    fn delete_requests() {
+        let (wm_protocols, wm_delete_window, wm_take_focus) = (300, 301, 302);
+        let message = |message_type, format, protocol| {
+            let mut event: XClientMessageEvent = unsafe { std::mem::zeroed() };
+            event.type_ = ClientMessage;
+            event.message_type = message_type;
+            event.format = format;
+            event.data.set_long(0, protocol as i64);
+            event
+        };
+        let is_delete = |event| is_delete_request(&event, wm_protocols, wm_delete_window);
+        assert!(is_delete(message(wm_protocols, 32, wm_delete_window)));
+        assert!(!is_delete(message(wm_protocols, 32, wm_take_focus)));
+        assert!(!is_delete(message(wm_delete_window, 32, wm_delete_window)));
+        assert!(!is_delete(message(wm_protocols, 8, wm_delete_window)));
+    }
+
+    #[test]
+    fn expression_or_results() {
+        let expression = Content::BasicExpression(84.0);
+        let shows = |content: &Content, text, score| shows_smart_content(content, text, score, 1.2);
+        // An exact match for a word in the expression
+        assert!(!shows(&expression, "code * 42", Some(1.44)));
+        assert!(!shows(&expression, "code * 42", Some(1.2)));
+        // Only weak matches
+        assert!(shows(&expression, "code * 42", Some(0.8)));
+        assert!(shows(&expression, "code * 42", None));
+        // Numbers and single letter variables aren't words
+        assert!(shows(&expression, "2 * 42", Some(1.44)));
+        assert!(shows(&expression, "x * 42", Some(1.44)));
+        // Other content is never suppressed
+        let lead = Content::LeadExpression(Ok(84.0));
+        assert!(shows(&lead, "= code * 42", Some(1.44)));
+        assert!(shows(&Content::Path, "/usr/bin/code", Some(1.44)));
+        assert!(shows(&Content::Command, "$ code", Some(1.44)));
+    }
+
+    #[test]
+    fn unchanged_smart_content() {
+        let mut shown = None;
+        let three = || Some(ReadyContent::Expression(3.0));
+        assert_eq!(changed(&mut shown, three()), Some(three()));
+        // Typing `1+2 ` after `1+2`
+        assert_eq!(changed(&mut shown, three()), None);
+        assert_eq!(changed(&mut shown, None), Some(None));
+        assert_eq!(changed(&mut shown, None), None);
+        let hint = || Some(ReadyContent::Hint(Action::Run, COMMAND_HINT));
+        assert_eq!(changed(&mut shown, hint()), Some(hint()));
+        assert_eq!(changed(&mut shown, hint()), None);
+        let run = Some(ReadyContent::Action(Action::Run, "Run", "ls".to_string()));
+        assert_eq!(changed(&mut shown, run.clone()), Some(run));
+    }
+
+    #[test]
+    fn double_commit() {
+        let command = CommitState {
+            restricted_command: true,
+            ..Default::default()
+        };
+        let item = CommitState::default();
+        let mut latch = CommitLatch::default();
+        // A refused command, then Enter pressed twice and a double-click
+        let routes = [command, item, item, item].map(|state| {
+            let route = latch.route(state);
+            if route == CommitRoute::Launch {
+                latch.commit();
+            }
+            route
+        });
+        use CommitRoute::*;
+        assert_eq!(routes, [Refuse, Launch, Ignore, Ignore]);
+        assert_eq!(latch.exit().code(), 0);
+        // The tour and read-only lists come first, they never launch anything
+        let tour = CommitState {
+            showing_tour: true,
+            read_only: true,
+            ..Default::default()
+        };
+        assert_eq!(latch.route(tour), DismissTour);
+        let units = CommitState {
+            read_only: true,
+            ..Default::default()
+        };
+        assert_eq!(latch.route(units), ReadOnly);
+        let emoji = CommitState {
+            picking_emoji: true,
+            ..Default::default()
+        };
+        assert_eq!(CommitLatch::default().route(emoji), PickEmoji);
+    }
+
+    #[test]
+    fn emoji_picks() {
+        let mut picks = EmojiPicks::default();
+        picks.query_changed(true);
+        assert_eq!(picks.pick("🔥"), "🔥");
+        // Searching for the next one in the same query keeps the picks
+        picks.query_changed(true);
+        assert_eq!(picks.pick("❤️"), "🔥❤️");
+        picks.query_changed(false);
+        picks.query_changed(true);
+        assert_eq!(picks.pick("👍"), "👍");
+    }
+
+    #[test]
+    fn inactivity() {
+        let start = Instant::now();
+        let never = InactivityTimer::new(None, start);
+        assert_eq!(never.deadline(), None);
+        assert!(!never.expired(start + Duration::from_secs(3600)));
+        let mut timer = InactivityTimer::new(Some(Duration::from_secs(10)), start);
+        assert!(!timer.expired(start + Duration::from_secs(9)));
+        timer.input(start + Duration::from_secs(9));
+        assert!(!timer.expired(start + Duration::from_secs(10)));
+        assert_eq!(timer.deadline(), Some(start + Duration::from_secs(19)));
+        assert!(timer.expired(start + Duration::from_secs(19)));
+    }
+
+    #[test]
+    fn command_row_stays_while_editing() {
+        let start = Instant::now();
+        let command = Some(ReadyContent::Action(
+            Action::Run,
+            "Run",
+            "cargo build".to_string(),
+        ));
+        let search = None;
+        let mut delay = CommandHideDelay::default();
+        // Deleting the `$` keeps the command row for now
+        assert_eq!(delay.update(&command, search.clone(), start), None);
+        assert_eq!(delay.deadline(), Some(start + COMMAND_HIDE_DELAY));
+        // Typing it again right away shows the command without a swap
+        let later = start + COMMAND_HIDE_DELAY / 2;
+        assert_eq!(
+            delay.update(&command, command.clone(), later),
+            Some(command.clone())
+        );
+        assert_eq!(delay.deadline(), None);
+        assert_eq!(delay.expired(start + COMMAND_HIDE_DELAY), None);
+        // Further edits don't move the deadline
+        assert_eq!(delay.update(&command, search.clone(), later), None);
+        let error = Some(ReadyContent::Error("1 / 0".to_string()));
+        assert_eq!(
+            delay.update(&command, error.clone(), start + COMMAND_HIDE_DELAY),
+            None
+        );
+        assert_eq!(delay.expired(later), None);
+        assert_eq!(
+            delay.expired(later + COMMAND_HIDE_DELAY),
+            Some(error.clone())
+        );
+        assert_eq!(delay.deadline(), None);
+        // Other changes are shown right away
+        assert_eq!(delay.update(&search, error.clone(), start), Some(error));
+        let hint = Some(ReadyContent::Hint(Action::Run, COMMAND_HINT));
+        assert_eq!(delay.update(&command, hint.clone(), start), Some(hint));
+    }
+
+    #[test]
+    fn padded_search_text() {
+        assert_eq!(search_text("  fire"), "fire");
+        assert_eq!(search_text("fire "), search_text("fire"));
+        assert_eq!(search_text(" gimp   ~/shot.png\t"), "gimp ~/shot.png");
+        assert_eq!(search_text("$ ls  -la /tmp "), "ls -la /tmp");
+        assert_eq!(search_text(" $git status"), "git status");
+        assert_eq!(search_text(" :log "), LOG_COMMAND);
+        assert_eq!(search_text("   "), "");
+        assert_eq!(search_text(" $ "), "");
+    }
+
+    #[test]
+    fn argument_with_spaces() {
+        assert_eq!(argument_text(" gimp /tmp/a  b.png\t"), "gimp /tmp/a  b.png");
+        assert_eq!(argument_text("$ feh  a  b.png "), "feh  a  b.png");
+    }
+
+    #[test]
+    fn empty_command_commits_item() {
+        assert!(!runs_command("$"));
+        assert!(!runs_command("$ "));
+        assert!(!runs_command(" $  \t"));
+        assert!(runs_command("$ ls"));
+        assert!(!runs_command("ls"));
+    }
+
+    #[test]
+    fn exit_status() {
+        let mut latch = CommitLatch::default();
+        assert_eq!(latch.exit().code(), 1);
+        latch.commit();
+        assert_eq!(latch.exit().code(), 0);
+        assert_eq!(Exit::StartupError.code(), 2);
+    }
+
+    #[test]
+    fn rebuild_cache() {
+        let tuning = SearchTuning::default();
+        let cache = Arc::new(Mutex::new(DesktopEntryCache::from_entries(vec![
+            DesktopEntry::new("gimp", "GIMP", None, "gimp"),
+            DesktopEntry::new("files", "Files", None, "nautilus"),
+        ])));
+        let mut history = History::new(10);
+        for name in ["files", "gimp"] {
+            let (results, _) = search::search(name, cache.clone(), None, &tuning, None, None);
+            history.add(results[0].unwrap(), &cache.lock().unwrap());
+        }
+
+        let mut rebuild = CacheRebuild::default();
+        let (release, wait) = channel();
+        // GIMP was uninstalled and Blender installed since
+        assert!(rebuild.start(move || {
+            wait.recv().ok();
+            DesktopEntryCache::from_entries(vec![
+                DesktopEntry::new("blender", "Blender", None, "blender"),
+                DesktopEntry::new("files", "Files", None, "nautilus"),
+            ])
+        }));
+        // Pressing the key again while it's running does nothing
+        assert!(!rebuild.start(|| unreachable!()));
+        assert!(rebuild.in_progress());
+        assert!(rebuild.poll().is_none());
+        release.send(()).unwrap();
+        let rebuilt = loop {
+            if let Some(rebuilt) = rebuild.poll() {
+                break rebuilt;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        };
+        assert!(!rebuild.in_progress());
+
+        install_cache(&cache, rebuilt, &mut history);
+        let files = cache.lock().unwrap().find_file("files").unwrap();
+        assert_eq!(history.desktop_ids().keys().collect::<Vec<_>>(), [&files]);
+        assert_eq!(history.entries().len(), 1);
+        let (results, _) = search::search("blender", cache.clone(), None, &tuning, None, None);
+        let blender = cache.lock().unwrap().find_file("blender").unwrap();
+        assert!(matches!(
+            results[0].unwrap(),
+            SearchMatchKind::DeskopEntry(entry) if entry.id == blender
+        ));
+    }
+
+    #[test]
+    fn narrowing_late_search() {
+        let tuning = SearchTuning::default();
+        let cache = Arc::new(Mutex::new(DesktopEntryCache::from_file_names(&[
+            "Firefox", "Files",
+        ])));
+        let search = |text: &str, previous, timeout| {
+            search::search(text, cache.clone(), previous, &tuning, None, timeout)
+        };
+        // The desktop entry provider misses the deadline while the cache is
+        // locked
+        let locked = cache.lock().unwrap();
+        let (results, late) = search("fir", None, Some(Duration::from_millis(10)));
+        assert!(results.is_empty());
+        assert!(!narrows("fir", "fire", late.as_ref()));
+        drop(locked);
+        let firefox = cache.lock().unwrap().find_file("Firefox").unwrap();
+        let finds_firefox = |results: &[SearchMatch]| {
+            results.iter().any(|result| {
+                matches!(result.unwrap(), SearchMatchKind::DeskopEntry(entry) if entry.id == firefox)
+            })
+        };
+        // Narrowing down the incomplete results would find nothing
+        assert!(search("fire", Some(results), None).0.is_empty());
+        let (results, _) = search("fire", None, None);
+        assert!(finds_firefox(&results));
+        // Once it finished the results can be narrowed down
+        assert!(narrows("fire", "firef", None));
+        assert!(finds_firefox(&search("firef", Some(results), None).0));
+        assert!(!narrows("fi", "fir", None));
+    }
+
+    #[test]
+    fn committed_actions_reach_the_app() {
+        use crate::{input::KeyEvent, list_view::row_key_signal};
+        let (sender, receiver) = channel();
+        let key = |key, is_shift, is_ctrl| KeyEvent {
+            key,
+            is_shift,
+            is_ctrl,
+        };
+        // Enter on an action row, picking emoji or not
+        for picking_emoji in [false, true] {
+            let signal = row_key_signal(
+                &key(Key::Enter, false, false),
+                Some(RowKind::Action("web")),
+                3,
+            );
+            sender.send(signal.unwrap()).unwrap();
+            let signal = handled_signal(receiver.recv().unwrap(), picking_emoji);
+            assert!(matches!(signal, Signal::CommitAction("web")));
+        }
+        // Action rows can only be committed, not picked
+        let signal = row_key_signal(
+            &key(Key::Enter, true, false),
+            Some(RowKind::Action("web")),
+            3,
+        );
+        assert!(signal.is_none());
+        // Picking anything but emoji commits
+        let signal = handled_signal(Signal::Pick(Some(3)), false);
+        assert!(matches!(signal, Signal::Commit(Some(3))));
+        let signal = handled_signal(Signal::Pick(Some(3)), true);
+        assert!(matches!(signal, Signal::Pick(Some(3))));
+    }
+}
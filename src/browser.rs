@@ -0,0 +1,170 @@
+//! Choosing the program to open URLs from smart content with.
+
+use crate::{
+    cache::{DesktopEntryCache, ExecArgument},
+    search::search_path_for_exact_match,
+};
+use std::process::Command;
+
+/// Programs tried if there is no default browser, unless the config gives
+/// its own list.
+pub const DEFAULT_CANDIDATES: [&str; 4] = ["firefox", "chromium", "google-chrome", "epiphany"];
+
+/// Browser settings from the config.
+#[derive(Debug, Clone)]
+pub struct BrowserConfig {
+    /// Takes precedence over everything else.
+    pub browser: Option<String>,
+    /// Programs to try if there is no default browser.
+    pub candidates: Vec<String>,
+}
+
+impl Default for BrowserConfig {
+    fn default() -> Self {
+        Self {
+            browser: None,
+            candidates: DEFAULT_CANDIDATES.map(String::from).to_vec(),
+        }
+    }
+}
+
+/// The parts of the system `resolve_browser` looks at.
+pub struct BrowserEnvironment<'a> {
+    /// Desktop file name of the default browser.
+    pub default_browser: Option<String>,
+    /// `$BROWSER`
+    pub browser_var: Option<String>,
+    pub is_installed: &'a dyn Fn(&str) -> bool,
+}
+
+impl BrowserEnvironment<'static> {
+    pub fn current() -> Self {
+        Self {
+            default_browser: default_browser(),
+            browser_var: std::env::var("BROWSER")
+                .ok()
+                .filter(|browser| !browser.is_empty()),
+            is_installed: &search_path_for_exact_match,
+        }
+    }
+}
+
+/// Asks xdg-settings for the desktop file of the default browser.
+fn default_browser() -> Option<String> {
+    let output = Command::new("xdg-settings")
+        .args(["get", "default-web-browser"])
+        .output()
+        .ok()?;
+    let file_name = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (output.status.success() && !file_name.is_empty()).then_some(file_name)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum BrowserCommand {
+    /// A program that gets the URL as its argument.
+    Program(String),
+    /// A desktop entry in the cache that accepts URLs.
+    DesktopEntry(usize),
+}
+
+/// Finds the browser to use.  In order of precedence this is the configured
+/// browser, the default browser's desktop entry, `$BROWSER`, the first
+/// installed candidate, and finally xdg-open.
+pub fn resolve_browser(
+    cache: &DesktopEntryCache,
+    config: &BrowserConfig,
+    env: &BrowserEnvironment,
+) -> BrowserCommand {
+    if let Some(browser) = &config.browser {
+        return BrowserCommand::Program(browser.clone());
+    }
+    let default_entry = env.default_browser.as_deref().and_then(|file_name| {
+        cache.find_file(file_name).filter(|&id| {
+            cache
+                .get_entry(id)
+                .accepts(&ExecArgument::Url(String::new()))
+        })
+    });
+    if let Some(id) = default_entry {
+        return BrowserCommand::DesktopEntry(id);
+    }
+    if let Some(browser) = &env.browser_var {
+        return BrowserCommand::Program(browser.clone());
+    }
+    let candidate = config
+        .candidates
+        .iter()
+        .find(|browser| (env.is_installed)(browser));
+    BrowserCommand::Program(match candidate {
+        Some(browser) => browser.clone(),
+        None => {
+            println!("No browser found, trying xdg-open");
+            "xdg-open".to_string()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::is_in_path;
+
+    #[test]
+    fn precedence() {
+        let bin = std::env::temp_dir().join(format!("launcher-path-{}", std::process::id()));
+        std::fs::create_dir_all(&bin).unwrap();
+        std::fs::write(bin.join("chromium"), "").unwrap();
+        let path = format!("/does/not/exist::{}", bin.display());
+        let installed = |name: &str| is_in_path(name, &path);
+        let mut cache = DesktopEntryCache::from_file_names(&["firefox.desktop", "viewer.desktop"]);
+        cache.set_exec("firefox.desktop", "firefox %u");
+        cache.set_exec("viewer.desktop", "viewer %f");
+        let program = |name: &str| BrowserCommand::Program(name.to_string());
+        let resolve = |configured: Option<&str>, default: Option<&str>, var: Option<&str>| {
+            let config = BrowserConfig {
+                browser: configured.map(String::from),
+                ..BrowserConfig::default()
+            };
+            let env = BrowserEnvironment {
+                default_browser: default.map(String::from),
+                browser_var: var.map(String::from),
+                is_installed: &installed,
+            };
+            resolve_browser(&cache, &config, &env)
+        };
+        assert_eq!(
+            resolve(Some("qutebrowser"), Some("firefox.desktop"), Some("lynx")),
+            program("qutebrowser")
+        );
+        assert_eq!(
+            resolve(None, Some("firefox.desktop"), Some("lynx")),
+            BrowserCommand::DesktopEntry(cache.find_file("firefox.desktop").unwrap())
+        );
+        // Entries that are missing or can't open URLs are skipped
+        assert_eq!(
+            resolve(None, Some("missing.desktop"), Some("lynx")),
+            program("lynx")
+        );
+        assert_eq!(
+            resolve(None, Some("viewer.desktop"), Some("lynx")),
+            program("lynx")
+        );
+        assert_eq!(resolve(None, None, None), program("chromium"));
+        let config = BrowserConfig {
+            browser: None,
+            candidates: vec!["links".to_string(), "chromium".to_string()],
+        };
+        let env = BrowserEnvironment {
+            default_browser: None,
+            browser_var: None,
+            is_installed: &|name: &str| name == "links",
+        };
+        assert_eq!(resolve_browser(&cache, &config, &env), program("links"));
+        let env = BrowserEnvironment {
+            is_installed: &|_: &str| false,
+            ..env
+        };
+        assert_eq!(resolve_browser(&cache, &config, &env), program("xdg-open"));
+        std::fs::remove_dir_all(&bin).unwrap();
+    }
+}
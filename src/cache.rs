@@ -1,13 +1,23 @@
 use crate::{
-    res::find_icon,
-    search::{MatchKind, SIMILARITY_THRESHHOLD},
+    config::ICON_THEME,
+    icon_theme::IconRegistry,
+    paths,
+    perf::{self, Phase},
+    search::{MatchKind, SearchTuning},
+    text::{escape_markup, fold},
+    ui::colors,
+    util::shell_quote,
+    warnings::warning,
 };
 use freedesktop_desktop_entry::DesktopEntry;
 use std::{
-    collections::{hash_map::DefaultHasher, HashSet},
-    hash::{Hash, Hasher},
+    collections::HashSet,
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
 };
 
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
 /// Get the `lang`, `COUNTRY`, and `MODIFIER` parts from `LC_MESSAGES` or `LANG`.
 fn get_locale() -> Option<(String, Option<String>, Option<String>)> {
     let mut locale = std::env::var("LC_MESSAGES")
@@ -42,15 +52,107 @@ fn expand_exec(
         .map(|i| format!("--icon {i}"))
         .unwrap_or_else(String::new);
     let file_location = format!("{}/{}", path, file_name);
-    exec.replace("%f", "")
-        .replace("%F", "")
-        .replace("%u", "")
-        .replace("%U", "")
-        .replace("%i", &icon)
+    // The file and URL field codes are substituted when launching, see
+    // `Entry::command`.
+    exec.replace("%i", &icon)
         .replace("%c", translated_name.unwrap_or(name))
         .replace("%k", &file_location)
 }
 
+/// The folded file name of the program a command line runs.
+pub fn exec_name(exec: &str) -> String {
+    let exec = exec.trim_start();
+    let program = match exec.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next().unwrap_or_default(),
+        None => exec.split_whitespace().next().unwrap_or_default(),
+    };
+    fold(program.rsplit('/').next().unwrap_or_default())
+}
+
+/// Splits a leading `env NAME=value ...` off the command line, these are
+/// passed as environment instead.  Only simple assignments without quoting
+/// are split off, anything else is left to the shell.
+fn split_environment(exec: &str) -> (Vec<(String, String)>, String) {
+    let unchanged = || (Vec::new(), exec.to_string());
+    let mut words = exec.split_whitespace();
+    if words.next() != Some("env") {
+        return unchanged();
+    }
+    let mut environment = Vec::new();
+    let mut rest = Vec::new();
+    for word in words.by_ref() {
+        let Some((name, value)) = word.split_once('=') else {
+            rest.push(word);
+            break;
+        };
+        let valid_name = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_name || value.contains(['\'', '"', '\\', '$', '`']) {
+            return unchanged();
+        }
+        environment.push((name.to_string(), value.to_string()));
+    }
+    rest.extend(words);
+    if environment.is_empty() || rest.is_empty() || rest[0].starts_with('-') {
+        return unchanged();
+    }
+    (environment, rest.join(" "))
+}
+
+/// Field codes for files and URLs, only one of these may appear in the exec
+/// key.
+const FILE_FIELD_CODES: [&str; 2] = ["%f", "%F"];
+const URL_FIELD_CODES: [&str; 2] = ["%u", "%U"];
+
+/// Minimum length of a search text to be matched inside a program name.
+const MIN_EXEC_SUBSTRING: usize = 3;
+
+/// A file or URL to open with a desktop entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExecArgument {
+    File(PathBuf),
+    Url(String),
+}
+
+impl ExecArgument {
+    /// Parses an existing path, with `~` expanded, or a URL.
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        if s.is_empty() {
+            return None;
+        }
+        if s.contains("://") && !s.contains(char::is_whitespace) {
+            return Some(Self::Url(s.to_string()));
+        }
+        let path = match s.strip_prefix('~') {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+                PathBuf::from(std::env::var_os("HOME")?).join(rest.trim_start_matches('/'))
+            }
+            _ => PathBuf::from(s),
+        };
+        std::fs::canonicalize(path).ok().map(Self::File)
+    }
+
+    /// Short name to show in the results list.
+    pub fn display_name(&self) -> String {
+        match self {
+            Self::File(path) => path
+                .file_name()
+                .unwrap_or(path.as_os_str())
+                .to_string_lossy()
+                .into_owned(),
+            Self::Url(url) => url.clone(),
+        }
+    }
+
+    fn as_str(&self) -> std::borrow::Cow<'_, str> {
+        match self {
+            Self::File(path) => path.to_string_lossy(),
+            Self::Url(url) => url.into(),
+        }
+    }
+}
+
 #[derive(Copy, Clone)]
 pub enum MatchField {
     Name(MatchKind),
@@ -58,6 +160,8 @@ pub enum MatchField {
     GenericName(MatchKind),
     LocalizedGenericName(MatchKind),
     FileName(MatchKind),
+    /// The name of the program in the exec key.
+    ExecName(MatchKind),
 }
 
 impl MatchField {
@@ -68,6 +172,7 @@ impl MatchField {
             Self::GenericName(kind) => kind,
             Self::LocalizedGenericName(kind) => kind,
             Self::FileName(kind) => kind,
+            Self::ExecName(kind) => kind,
         }
     }
 }
@@ -84,22 +189,57 @@ pub struct Entry {
     pub localized_name: Option<String>,
     pub generic_name: Option<String>,
     pub localized_generic_name: Option<String>,
-    // These are for searching
-    lower_name: String,
-    lower_localized_name: Option<String>,
-    lower_generic_name: Option<String>,
-    lower_localized_generic_name: Option<String>,
+    // These are for searching, see `text::fold`.
+    folded_name: String,
+    folded_localized_name: Option<String>,
+    folded_generic_name: Option<String>,
+    folded_localized_generic_name: Option<String>,
     pub file_name: String,
     pub exec: String,
+    /// File name of the program `exec` runs, like `nvim` for `nvim %F`,
+    /// folded for searching.
+    exec_name: String,
     pub icon: Option<String>,
+    /// `StartupWMClass`, the `WM_CLASS` the application's windows have.
+    pub startup_wm_class: Option<String>,
+    /// Localized if available, only used for the tooltip.
+    pub comment: Option<String>,
+    /// `PrefersNonDefaultGPU` or `X-KDE-RunOnDiscreteGpu`.
+    pub prefers_non_default_gpu: bool,
+    /// Variables set by an `env` prefix of the exec key.
+    pub environment: Vec<(String, String)>,
 }
 
 impl Entry {
+    /// An entry that doesn't come from a desktop file, for building a cache
+    /// in memory.
+    pub fn new(file_name: &str, name: &str, generic_name: Option<&str>, exec: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            localized_name: None,
+            generic_name: generic_name.map(str::to_string),
+            localized_generic_name: None,
+            folded_name: fold(name),
+            folded_localized_name: None,
+            folded_generic_name: generic_name.map(fold),
+            folded_localized_generic_name: None,
+            file_name: file_name.to_string(),
+            exec: exec.to_string(),
+            exec_name: exec_name(exec),
+            icon: None,
+            startup_wm_class: None,
+            comment: None,
+            prefers_non_default_gpu: false,
+            environment: Vec::new(),
+        }
+    }
+
     pub fn from_desktop_entry(
         file_name: String,
         de: &DesktopEntry,
         locales: &[String],
         path: &str,
+        icons: &IconRegistry,
     ) -> Option<Self> {
         let mut localized_name = None;
         let mut localized_generic_name = None;
@@ -115,6 +255,11 @@ impl Entry {
                 break;
             }
         }
+        let comment = locales
+            .iter()
+            .find_map(|locale| de.comment(Some(locale)))
+            .or_else(|| de.comment(None))
+            .map(|cow_str| cow_str.to_string());
         let generic_name = de
             .generic_name(None)
             .map(|cow_str| cow_str.to_string())
@@ -126,39 +271,97 @@ impl Entry {
             .or_else(|| generic_name.clone());
         if let Some(name) = name {
             let icon = de.icon();
+            // Already checked this exists in `DesktopEntryCache::rebuild`.
+            let (environment, exec) = split_environment(de.exec().unwrap());
             let exec = expand_exec(
-                // Already checked this exists in `DesktopEntryCache::rebuild`.
-                de.exec().unwrap(),
+                &exec,
                 &file_name,
                 path,
                 &name,
                 localized_name.as_deref(),
                 icon,
             );
-            let lower_name = name.to_lowercase();
-            let lower_localized_name = localized_name.as_deref().map(str::to_lowercase);
-            let lower_generic_name = generic_name.as_deref().map(str::to_lowercase);
-            let lower_localized_generic_name =
-                localized_generic_name.as_deref().map(str::to_lowercase);
+            let folded_name = fold(&name);
+            let folded_localized_name = localized_name.as_deref().map(fold);
+            let folded_generic_name = generic_name.as_deref().map(fold);
+            let folded_localized_generic_name = localized_generic_name.as_deref().map(fold);
             Some(Self {
                 name,
                 localized_name,
                 generic_name,
                 localized_generic_name,
-                lower_name,
-                lower_localized_name,
-                lower_generic_name,
-                lower_localized_generic_name,
+                folded_name,
+                folded_localized_name,
+                folded_generic_name,
+                folded_localized_generic_name,
                 file_name,
+                exec_name: exec_name(&exec),
                 exec,
-                icon: icon.and_then(find_icon),
+                icon: icon.and_then(|icon| icons.lookup(icon)),
+                startup_wm_class: de.startup_wm_class().map(str::to_string),
+                comment,
+                prefers_non_default_gpu: de.prefers_non_default_gpu()
+                    || de.desktop_entry("X-KDE-RunOnDiscreteGpu") == Some("true"),
+                environment,
             })
         } else {
-            eprintln!("No suitable name found in {}.", file_name);
+            warning!("No suitable name found in {}.", file_name);
             None
         }
     }
 
+    /// Whether the exec key has a field code `argument` can be passed to.
+    /// Files can also be passed as URLs.
+    pub fn accepts(&self, argument: &ExecArgument) -> bool {
+        let has_code = |codes: &[&str]| codes.iter().any(|code| self.exec.contains(code));
+        match argument {
+            ExecArgument::File(_) => has_code(&FILE_FIELD_CODES) || has_code(&URL_FIELD_CODES),
+            ExecArgument::Url(_) => has_code(&URL_FIELD_CODES),
+        }
+    }
+
+    /// The command line to launch the entry with, the file and URL field
+    /// codes are replaced by `argument` if it's accepted, or removed.
+    pub fn command(&self, argument: Option<&ExecArgument>) -> String {
+        let mut exec = self.exec.clone();
+        if let Some(argument) = argument.filter(|argument| self.accepts(argument)) {
+            let position = FILE_FIELD_CODES
+                .iter()
+                .chain(URL_FIELD_CODES.iter())
+                .filter_map(|code| exec.find(code))
+                .min()
+                .unwrap();
+            exec.replace_range(position..position + 2, &shell_quote(&argument.as_str()));
+        }
+        for code in FILE_FIELD_CODES.iter().chain(URL_FIELD_CODES.iter()) {
+            exec = exec.replace(code, "");
+        }
+        exec
+    }
+
+    /// Markup for the tooltip: the name, generic name, comment, and command.
+    pub fn tooltip_markup(&self) -> String {
+        let name = self.localized_name.as_deref().unwrap_or(&self.name);
+        let generic_name = self
+            .localized_generic_name
+            .as_deref()
+            .or(self.generic_name.as_deref());
+        let mut markup = format!("<b>{}</b>", escape_markup(name));
+        for line in [generic_name, self.comment.as_deref()]
+            .into_iter()
+            .flatten()
+        {
+            markup.push('\n');
+            markup.push_str(&escape_markup(line));
+        }
+        markup.push_str(&format!(
+            "\n<span color=\"{}\">{}</span>",
+            colors::list_match_name(),
+            escape_markup(&self.command(None))
+        ));
+        markup
+    }
+
     pub fn get_field(&self, field: MatchField) -> &str {
         match field {
             MatchField::Name(_) => &self.name,
@@ -166,6 +369,7 @@ impl Entry {
             MatchField::GenericName(_) => self.generic_name.as_ref().unwrap(),
             MatchField::LocalizedGenericName(_) => self.localized_generic_name.as_ref().unwrap(),
             MatchField::FileName(_) => &self.file_name,
+            MatchField::ExecName(_) => &self.exec_name,
         }
     }
 }
@@ -174,14 +378,24 @@ pub struct DesktopEntryCache {
     entries: Vec<Entry>,
     locale: Option<String>,
     error: Option<std::io::Error>,
+    similarity_threshold: f64,
+    /// (desktop file name, icon name) of the entries whose icon was not found
+    /// in the last rebuild.
+    missing_icons: Vec<(String, String)>,
+    /// Changes whenever the entries are rebuilt, so anything derived from
+    /// them can tell when it's outdated.
+    generation: u64,
 }
 
 impl DesktopEntryCache {
-    pub fn new(locale: &Option<String>) -> Self {
+    pub fn new(locale: &Option<String>, tuning: &SearchTuning) -> Self {
         Self {
             entries: Vec::with_capacity(128),
             locale: locale.clone(),
             error: None,
+            similarity_threshold: tuning.similarity_threshold,
+            missing_icons: Vec::new(),
+            generation: GENERATION.fetch_add(1, Ordering::Relaxed),
         }
     }
 
@@ -224,29 +438,40 @@ impl DesktopEntryCache {
     }
 
     pub fn rebuild(&mut self) {
+        let _timer = perf::timer(Phase::CacheRebuild);
         self.entries.clear();
+        self.generation = GENERATION.fetch_add(1, Ordering::Relaxed);
+        self.index(&paths::data_dirs(), &ICON_THEME.read().unwrap());
+    }
+
+    /// Reads the entries in the `applications` directory of each data
+    /// directory.  The first file with a desktop file id claims it, so a file
+    /// in an earlier directory overrides the ones after it, and if it's
+    /// `Hidden` or `NoDisplay` it removes them.  Icons are looked up in
+    /// `icons`.
+    fn index(&mut self, data_dirs: &[PathBuf], icons: &IconRegistry) {
+        self.missing_icons.clear();
         let locales = self.get_locales();
-        let data_dirs = std::env::var("XDG_DATA_DIRS")
-            .map(|s| s.split(':').map(|s| s.to_owned()).collect())
-            .unwrap_or_else(|_| {
-                vec![
-                    "/usr/share/applications".to_string(),
-                    "/usr/local/share/applications".to_string(),
-                ]
-            });
+        let mut claimed = HashSet::new();
+        let mut overridden = 0;
         let mut ok = false;
         let mut error = None;
         for data_dir in data_dirs {
-            let dir_path = format!("{}/applications", data_dir);
-            let dir = std::fs::read_dir(&dir_path);
-            if let Err(err) = dir {
-                eprintln!("Could not read {dir_path}: {err}");
-                error = Some(err);
-                continue;
-            }
+            let dir_path = format!("{}/applications", data_dir.display());
+            let dir = match std::fs::read_dir(&dir_path) {
+                Ok(dir) => dir,
+                Err(err) => {
+                    // Most systems don't have all of them
+                    if err.kind() != std::io::ErrorKind::NotFound {
+                        warning!("Could not read {dir_path}: {err}");
+                    }
+                    error = Some(err);
+                    continue;
+                }
+            };
             ok = true;
             println!("Indexing: {dir_path}");
-            for file in dir.unwrap().flatten() {
+            for file in dir.flatten() {
                 let file_name = if let Some(file_name) = file.file_name().to_str() {
                     file_name.to_owned()
                 } else {
@@ -255,23 +480,37 @@ impl DesktopEntryCache {
                 if !file_name.ends_with(".desktop") {
                     continue;
                 }
+                if claimed.contains(&file_name) {
+                    overridden += 1;
+                    continue;
+                }
                 let content = std::fs::read_to_string(file.path());
                 if let Err(error) = content {
-                    eprintln!("Could not read {}: {}", file_name, error);
+                    warning!("Could not read {}: {}", file_name, error);
                     continue;
                 }
                 let path = file.path().as_path().to_owned();
                 let maybe_de = DesktopEntry::decode(&path, content.as_ref().unwrap());
                 if let Err(error) = maybe_de {
-                    eprintln!("Could not decode {}: {}", file_name, error);
+                    warning!("Could not decode {}: {}", file_name, error);
                     continue;
                 }
                 let de = maybe_de.unwrap();
-                if de.exec().is_none() {
+                claimed.insert(file_name.clone());
+                if de.exec().is_none()
+                    || de.no_display()
+                    || de.desktop_entry("Hidden") == Some("true")
+                {
                     continue;
                 }
-                if let Some(entry) = Entry::from_desktop_entry(file_name, &de, &locales, &dir_path)
+                if let Some(entry) =
+                    Entry::from_desktop_entry(file_name, &de, &locales, &dir_path, icons)
                 {
+                    // Drawn without one, but the theme may just be incomplete
+                    if let Some(icon) = de.icon().filter(|_| entry.icon.is_none()) {
+                        self.missing_icons
+                            .push((entry.file_name.clone(), icon.to_string()));
+                    }
                     self.entries.push(entry);
                 }
             }
@@ -279,27 +518,22 @@ impl DesktopEntryCache {
         if !ok {
             self.error = error;
         }
-        let len_before = self.entries.len();
-        println!("Deduplicating");
-        let mut unique = HashSet::new();
-        self.entries.retain(|e| {
-            // Could miss some due to hash collision but it's unlikely and we don't
-            // need to clone each filename this way.
-            let mut hasher = DefaultHasher::new();
-            e.file_name.hash(&mut hasher);
-            unique.insert(hasher.finish())
-        });
-        let len_after = self.entries.len();
-        println!(" -> removed {} duplicates", len_before - len_after);
-        println!("Finished building cache with {} items", len_after);
+        println!(" -> skipped {overridden} overridden entries");
+        if !self.missing_icons.is_empty() {
+            warning!(
+                "{} desktop entries have missing icons, see `launcher diag --icons`",
+                self.missing_icons.len()
+            );
+        }
+        println!("Finished building cache with {} items", self.entries.len());
     }
 
-    fn get_match(name: &str, entry_value: &str) -> Option<MatchKind> {
+    fn get_match(&self, name: &str, entry_value: &str) -> Option<MatchKind> {
         if entry_value == name {
             Some(MatchKind::Exact)
         } else {
             let sim = strsim::jaro_winkler(name, entry_value);
-            if sim >= SIMILARITY_THRESHHOLD {
+            if sim >= self.similarity_threshold {
                 Some(MatchKind::Similar(sim))
             } else {
                 None
@@ -307,6 +541,42 @@ impl DesktopEntryCache {
         }
     }
 
+    /// Like `get_match` but the search text can also start the program name,
+    /// so `gimp` finds `gimp-2.10`, or be contained anywhere in it, so `code`
+    /// finds `vscode`.  The more of the name it covers the more similar that
+    /// is, but it's always above the threshold.
+    fn get_exec_match(&self, name: &str, exec_name: &str) -> Option<MatchKind> {
+        if exec_name.is_empty() {
+            return None;
+        }
+        if exec_name == name {
+            return Some(MatchKind::Exact);
+        }
+        let length = name.chars().count();
+        if length >= MIN_EXEC_SUBSTRING {
+            if exec_name.starts_with(name) {
+                return Some(MatchKind::Prefix);
+            }
+            if exec_name.contains(name) {
+                let covered = length as f64 / exec_name.chars().count() as f64;
+                let floor = self.similarity_threshold;
+                let sim =
+                    strsim::jaro_winkler(name, exec_name).max(floor + (1.0 - floor) * covered);
+                return Some(MatchKind::Similar(sim));
+            }
+        }
+        self.get_match(name, exec_name)
+    }
+
+    /// Whether there is an entry whose name is exactly `name`, ignoring case
+    /// and accents.
+    pub fn has_exact_name(&self, name: &str) -> bool {
+        let name = fold(name);
+        self.entries.iter().any(|entry| {
+            entry.folded_name == name || entry.folded_localized_name.as_ref() == Some(&name)
+        })
+    }
+
     pub fn find_all(&self, name: &str) -> Vec<Match> {
         self.find_subset(name, 0..self.entries.len())
     }
@@ -322,7 +592,7 @@ impl DesktopEntryCache {
                 ($field:expr, $match_field:ident) => {
                     if let Some(value) = $field {
                         for word in value.split(' ') {
-                            if let Some(match_) = Self::get_match(&name, word) {
+                            if let Some(match_) = self.get_match(&name, word) {
                                 matches.push(Match {
                                     id,
                                     field: MatchField::$match_field(match_),
@@ -336,18 +606,48 @@ impl DesktopEntryCache {
             // TODO: a value with lower priority could still get a higher score, to
             //       accommodate for this we should chose the maximum score of these
             //       instead of shortcircuting on the first match.
-            check!(entry.lower_localized_name.as_ref(), LocalizedName);
-            check!(Some(&entry.lower_name), Name);
+            check!(entry.folded_localized_name.as_ref(), LocalizedName);
+            check!(Some(&entry.folded_name), Name);
             check!(
-                entry.lower_localized_generic_name.as_ref(),
+                entry.folded_localized_generic_name.as_ref(),
                 LocalizedGenericName
             );
-            check!(entry.lower_generic_name.as_ref(), GenericName);
-            check!(Some(&entry.file_name), FileName);
+            check!(entry.folded_generic_name.as_ref(), GenericName);
+            // The program name wins over a file name that's only similar,
+            // since it can also match by prefix, like `gimp` in `gimp-2.10`
+            // where `gimp.desktop` is only similar.
+            let file_name = self.get_match(name, &entry.file_name);
+            let exec_name = self.get_exec_match(name, &entry.exec_name);
+            let field = match (file_name, exec_name) {
+                (Some(file_name), None) | (Some(file_name @ MatchKind::Exact), _) => {
+                    MatchField::FileName(file_name)
+                }
+                (_, Some(exec_name)) => MatchField::ExecName(exec_name),
+                (None, None) => continue,
+            };
+            matches.push(Match { id, field });
         }
         matches
     }
 
+    /// The entry whose name or one of its words is the most similar to
+    /// `name`, even if it's below the similarity threshold.
+    pub fn closest(&self, name: &str) -> Option<(usize, f64)> {
+        let mut best: Option<(usize, f64)> = None;
+        for (id, entry) in self.entries.iter().enumerate() {
+            let names = std::iter::once(&entry.folded_name).chain(&entry.folded_localized_name);
+            for value in names {
+                for candidate in std::iter::once(value.as_str()).chain(value.split(' ')) {
+                    let sim = strsim::jaro_winkler(name, candidate);
+                    if best.is_none_or(|(_, best)| sim > best) {
+                        best = Some((id, sim));
+                    }
+                }
+            }
+        }
+        best
+    }
+
     pub fn find_file(&self, file_name: &str) -> Option<usize> {
         for (id, entry) in self.entries.iter().enumerate() {
             if entry.file_name == file_name {
@@ -361,7 +661,294 @@ impl DesktopEntryCache {
         self.error.as_ref()
     }
 
+    /// (desktop file name, icon name) of the entries whose icon was not found.
+    pub fn missing_icons(&self) -> &[(String, String)] {
+        &self.missing_icons
+    }
+
+    /// A cache of the given entries without reading any desktop files, with
+    /// the default locale and search tuning.
+    pub fn from_entries(entries: Vec<Entry>) -> Self {
+        let mut cache = Self::new(&None, &SearchTuning::default());
+        cache.entries = entries;
+        cache
+    }
+
+    /// An empty cache with the same locale and tuning, so it can be rebuilt
+    /// without locking this one.
+    pub fn with_same_settings(&self) -> Self {
+        Self {
+            entries: Vec::with_capacity(self.entries.len()),
+            locale: self.locale.clone(),
+            error: None,
+            similarity_threshold: self.similarity_threshold,
+            missing_icons: Vec::new(),
+            generation: GENERATION.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
     pub fn get_entry(&self, id: usize) -> &Entry {
         &self.entries[id]
     }
+
+    /// All entries, their index is their id.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+}
+
+#[cfg(test)]
+impl DesktopEntryCache {
+    /// A cache containing a minimal entry for each of the given file names.
+    pub fn from_file_names(file_names: &[&str]) -> Self {
+        Self::from_entries(
+            file_names
+                .iter()
+                .map(|file_name| Entry::new(file_name, file_name, None, file_name))
+                .collect(),
+        )
+    }
+
+    pub fn set_exec(&mut self, file_name: &str, exec: &str) {
+        let id = self.find_file(file_name).unwrap();
+        self.entries[id].exec = exec.to_string();
+        self.entries[id].exec_name = exec_name(exec);
+    }
+
+    pub fn set_startup_wm_class(&mut self, file_name: &str, class: &str) {
+        let id = self.find_file(file_name).unwrap();
+        self.entries[id].startup_wm_class = Some(class.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exec_arguments() {
+        let mut cache = DesktopEntryCache::from_file_names(&["gimp", "browser", "plain"]);
+        cache.set_exec("gimp", "gimp-2.10 %U");
+        cache.set_exec("browser", "firefox %u --new-window");
+        let gimp = cache.get_entry(cache.find_file("gimp").unwrap());
+        let browser = cache.get_entry(cache.find_file("browser").unwrap());
+        let plain = cache.get_entry(cache.find_file("plain").unwrap());
+        let file = ExecArgument::File("/home/user/my shot's.png".into());
+        let url = ExecArgument::Url("https://example.com".to_string());
+        assert!(gimp.accepts(&file) && gimp.accepts(&url));
+        assert!(!plain.accepts(&file));
+        assert_eq!(gimp.command(None), "gimp-2.10 ");
+        assert_eq!(
+            gimp.command(Some(&file)),
+            r"gimp-2.10 '/home/user/my shot'\''s.png'"
+        );
+        assert_eq!(
+            browser.command(Some(&url)),
+            "firefox 'https://example.com' --new-window"
+        );
+        assert_eq!(plain.command(Some(&file)), "plain");
+        assert_eq!(file.display_name(), "my shot's.png");
+    }
+
+    #[test]
+    fn exec_name_matches() {
+        assert_eq!(exec_name("nvim %F"), "nvim");
+        assert_eq!(exec_name("/usr/bin/GEdit --new-window %U"), "gedit");
+        assert_eq!(exec_name("\"/opt/My Apps/vscode\" %F"), "vscode");
+        let mut cache = DesktopEntryCache::from_file_names(&["Notepad", "Visual Studio", "Paint"]);
+        cache.set_exec("Notepad", "/usr/bin/GEdit %U");
+        cache.set_exec("Visual Studio", "\"/opt/My Apps/vscode\" %F");
+        cache.set_exec("Paint", "gimp-2.10 %U");
+        let find = |name: &str| {
+            cache
+                .find_all(&fold(name))
+                .into_iter()
+                .map(|match_| {
+                    let exact = matches!(match_.field.into_inner(), MatchKind::Exact);
+                    let field = cache.get_entry(match_.id).get_field(match_.field);
+                    assert!(matches!(match_.field, MatchField::ExecName(_)));
+                    (field.to_string(), exact)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(find("gedit"), [("gedit".to_string(), true)]);
+        assert_eq!(find("GEdit"), [("gedit".to_string(), true)]);
+        assert_eq!(find("code"), [("vscode".to_string(), false)]);
+        assert_eq!(find("gimp"), [("gimp-2.10".to_string(), false)]);
+        // Too short to match inside the name
+        assert_eq!(find("de"), []);
+        let kind = |name: &str| cache.find_all(name)[0].field.into_inner();
+        assert!(matches!(kind("gimp"), MatchKind::Prefix));
+        let MatchKind::Similar(sim) = kind("code") else {
+            panic!("not a similar match");
+        };
+        assert!(sim > cache.similarity_threshold);
+    }
+
+    #[test]
+    fn environment_prefix() {
+        let env = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            split_environment("env GDK_BACKEND=x11 QT_SCALE=2 steam %U"),
+            (
+                env(&[("GDK_BACKEND", "x11"), ("QT_SCALE", "2")]),
+                "steam %U".to_string()
+            )
+        );
+        for unchanged in [
+            "steam %U",
+            "env -u FOO steam",
+            "env FOO=bar",
+            "env FOO=\"a b\" steam",
+            "env 1FOO=bar steam",
+            "environment FOO=bar",
+        ] {
+            assert_eq!(
+                split_environment(unchanged),
+                (Vec::new(), unchanged.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn tooltip() {
+        let mut cache = DesktopEntryCache::from_file_names(&["gimp"]);
+        cache.set_exec("gimp", "gimp-2.10 %U");
+        let id = cache.find_file("gimp").unwrap();
+        cache.entries[id].generic_name = Some("Image Editor".to_string());
+        cache.entries[id].comment = Some("Create & edit <images>".to_string());
+        assert_eq!(
+            cache.get_entry(id).tooltip_markup(),
+            format!(
+                "<b>gimp</b>\nImage Editor\nCreate &amp; edit &lt;images&gt;\n<span color=\"{}\">gimp-2.10 </span>",
+                colors::list_match_name()
+            )
+        );
+    }
+
+    #[test]
+    fn parse_exec_argument() {
+        assert_eq!(
+            ExecArgument::parse(" https://example.com/a?b "),
+            Some(ExecArgument::Url("https://example.com/a?b".to_string()))
+        );
+        assert_eq!(ExecArgument::parse("https://example.com/a b"), None);
+        assert_eq!(ExecArgument::parse("/does/not/exist.png"), None);
+        let dir = std::env::temp_dir().join(format!("launcher-arg-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("shot.png"), "").unwrap();
+        let expected = Some(ExecArgument::File(
+            std::fs::canonicalize(dir.join("shot.png")).unwrap(),
+        ));
+        assert_eq!(
+            ExecArgument::parse(dir.join("shot.png").to_str().unwrap()),
+            expected
+        );
+        crate::paths::with_home(Some(&dir), || {
+            assert_eq!(ExecArgument::parse("~/shot.png"), expected);
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn layered_data_dirs() {
+        let root = std::env::temp_dir().join(format!("launcher-data-{}", std::process::id()));
+        let [home, local, system] = ["home", "local", "system"].map(|dir| root.join(dir));
+        let write = |data_dir: &PathBuf, file_name: &str, keys: &str| {
+            let dir = data_dir.join("applications");
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join(file_name), format!("[Desktop Entry]\n{keys}\n")).unwrap();
+        };
+        write(&system, "editor.desktop", "Name=Editor\nExec=editor");
+        write(
+            &home,
+            "editor.desktop",
+            "Name=My Editor\nExec=editor --mine",
+        );
+        // Hidden in the user directory removes the system entry
+        write(&system, "ads.desktop", "Name=Ads\nExec=ads");
+        write(&home, "ads.desktop", "Name=Ads\nExec=ads\nHidden=true");
+        // So does NoDisplay in a directory before it
+        write(&system, "helper.desktop", "Name=Helper\nExec=helper");
+        write(
+            &local,
+            "helper.desktop",
+            "Name=Helper\nExec=helper\nNoDisplay=true",
+        );
+        // Hidden in a later directory doesn't matter
+        write(&local, "browser.desktop", "Name=Browser\nExec=browser");
+        write(&system, "browser.desktop", "Name=Browser\nHidden=true");
+        write(&system, "terminal.desktop", "Name=Terminal\nExec=terminal");
+
+        let mut cache = DesktopEntryCache::new(&None, &SearchTuning::default());
+        cache.index(
+            &[home, root.join("missing"), local, system],
+            &IconRegistry::empty(),
+        );
+        let mut names = cache
+            .entries()
+            .iter()
+            .map(|entry| {
+                (
+                    entry.file_name.as_str(),
+                    entry.name.as_str(),
+                    entry.exec.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                ("browser.desktop", "Browser", "browser"),
+                ("editor.desktop", "My Editor", "editor --mine"),
+                ("terminal.desktop", "Terminal", "terminal"),
+            ]
+        );
+        assert!(cache.error.is_none());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn missing_icons() {
+        let root = std::env::temp_dir().join(format!("launcher-missing-{}", std::process::id()));
+        let theme = root.join("theme");
+        std::fs::create_dir_all(&theme).unwrap();
+        std::fs::write(theme.join("editor.svg"), "").unwrap();
+        let icons = IconRegistry::with_directories(vec![theme.display().to_string()]);
+        let applications = root.join("data/applications");
+        std::fs::create_dir_all(&applications).unwrap();
+        for (file_name, keys) in [
+            ("editor.desktop", "Name=Editor\nExec=editor\nIcon=editor"),
+            ("viewer.desktop", "Name=Viewer\nExec=viewer\nIcon=viewer"),
+            ("tool.desktop", "Name=Tool\nExec=tool"),
+        ] {
+            std::fs::write(
+                applications.join(file_name),
+                format!("[Desktop Entry]\n{keys}\n"),
+            )
+            .unwrap();
+        }
+        let mut cache = DesktopEntryCache::new(&None, &SearchTuning::default());
+        cache.index(&[root.join("data")], &icons);
+        assert_eq!(cache.entries().len(), 3);
+        assert_eq!(
+            cache.missing_icons(),
+            [("viewer.desktop".to_string(), "viewer".to_string())]
+        );
+        // Cleared on each rebuild
+        std::fs::write(theme.join("viewer.svg"), "").unwrap();
+        cache.index(&[root.join("data")], &icons);
+        assert!(cache.missing_icons().is_empty());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
 }
@@ -0,0 +1,192 @@
+use crate::warnings::warning;
+use meval::{tokenizer::Token, ContextProvider, Expr};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// User defined constants and functions for the calculator.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CalcDefinitions {
+    /// Constants, `name = value`.
+    #[serde(default)]
+    pub constants: BTreeMap<String, f64>,
+    /// Single argument functions, `name = "expression"` where the argument is
+    /// called `x`.
+    #[serde(default)]
+    pub functions: BTreeMap<String, String>,
+}
+
+enum FunctionState {
+    Visiting,
+    Defined,
+    Rejected,
+}
+
+/// Evaluation context for calculator expressions, containing the meval
+/// built-ins and the user definitions.
+pub struct Calculator {
+    context: meval::Context<'static>,
+}
+
+impl Calculator {
+    pub fn new(definitions: &CalcDefinitions) -> Self {
+        let builtins = meval::Context::new();
+        let is_builtin =
+            |name: &str| builtins.get_var(name).is_some() || is_builtin_function(&builtins, name);
+        let mut context = meval::Context::new();
+        for (name, &value) in definitions.constants.iter() {
+            if is_builtin(name) {
+                warning!("calc: constant `{name}` collides with a built-in, ignoring it");
+                continue;
+            }
+            context.var(name.as_str(), value);
+        }
+        let mut functions = HashMap::new();
+        for (name, source) in definitions.functions.iter() {
+            if is_builtin(name) || definitions.constants.contains_key(name) {
+                warning!("calc: function `{name}` collides with an existing name, ignoring it");
+                continue;
+            }
+            match source.parse::<Expr>() {
+                Ok(expr) => {
+                    functions.insert(name.as_str(), expr);
+                }
+                Err(error) => warning!("calc: invalid function `{name}`: {error}"),
+            }
+        }
+        let mut states = HashMap::new();
+        for name in definitions.functions.keys() {
+            if functions.contains_key(name.as_str()) {
+                define_function(name, &functions, &mut states, &mut context);
+            }
+        }
+        Self { context }
+    }
+
+    pub fn eval(&self, expr: &str) -> Result<f64, meval::Error> {
+        meval::eval_str_with_context(expr, &self.context)
+    }
+}
+
+impl Default for Calculator {
+    fn default() -> Self {
+        Self::new(&CalcDefinitions::default())
+    }
+}
+
+fn is_builtin_function(builtins: &meval::Context, name: &str) -> bool {
+    !matches!(
+        builtins.eval_func(name, &[]),
+        Err(meval::FuncEvalError::UnknownFunction)
+    )
+}
+
+/// Defines the function `name` after all user functions it depends on,
+/// returns whether it got defined.
+fn define_function(
+    name: &str,
+    functions: &HashMap<&str, Expr>,
+    states: &mut HashMap<String, FunctionState>,
+    context: &mut meval::Context<'static>,
+) -> bool {
+    match states.get(name) {
+        Some(FunctionState::Defined) => return true,
+        Some(FunctionState::Rejected) => return false,
+        Some(FunctionState::Visiting) => {
+            warning!("calc: function `{name}` is defined recursively, ignoring it");
+            return false;
+        }
+        None => {}
+    }
+    states.insert(name.to_string(), FunctionState::Visiting);
+    let expr = &functions[name];
+    let mut ok = true;
+    for token in expr.iter() {
+        if let Token::Func(dependency, _) = token {
+            if functions.contains_key(dependency.as_str())
+                && !define_function(dependency, functions, states, context)
+            {
+                ok = false;
+            }
+        }
+    }
+    if ok {
+        // Evaluate once to reject unknown variables and functions up front
+        if let Err(error) = expr.eval_with_context((("x", 1.0), &*context)) {
+            warning!("calc: invalid function `{name}`: {error}");
+            ok = false;
+        }
+    } else {
+        warning!("calc: function `{name}` depends on an invalid function, ignoring it");
+    }
+    if ok {
+        let expr = expr.clone();
+        let captured = context.clone();
+        context.func(name.to_string(), move |x| {
+            expr.eval_with_context((("x", x), &captured))
+                .unwrap_or(f64::NAN)
+        });
+    }
+    let state = if ok {
+        FunctionState::Defined
+    } else {
+        FunctionState::Rejected
+    };
+    states.insert(name.to_string(), state);
+    ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calculator(constants: &[(&str, f64)], functions: &[(&str, &str)]) -> Calculator {
+        Calculator::new(&CalcDefinitions {
+            constants: constants
+                .iter()
+                .map(|(name, value)| (name.to_string(), *value))
+                .collect(),
+            functions: functions
+                .iter()
+                .map(|(name, source)| (name.to_string(), source.to_string()))
+                .collect(),
+        })
+    }
+
+    #[test]
+    fn definitions() {
+        let c = calculator(
+            &[("rate", 0.85)],
+            &[("vat", "x * 1.19"), ("net", "vat(x) * rate")],
+        );
+        assert_eq!(c.eval("rate * 2").unwrap(), 1.7);
+        assert!((c.eval("vat(100)").unwrap() - 119.0).abs() < 1e-9);
+        assert!((c.eval("net(100)").unwrap() - 101.15).abs() < 1e-9);
+        assert!((c.eval("sqrt(vat(4) / 1.19)").unwrap() - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn builtin_collisions() {
+        let c = calculator(&[("pi", 3.0)], &[("sin", "x")]);
+        assert_eq!(c.eval("pi").unwrap(), std::f64::consts::PI);
+        assert_eq!(c.eval("sin(0)").unwrap(), 0.0);
+    }
+
+    #[test]
+    fn recursion_is_rejected() {
+        let c = calculator(
+            &[],
+            &[
+                ("f", "f(x) + 1"),
+                ("g", "h(x)"),
+                ("h", "g(x)"),
+                ("k", "g(x) * 2"),
+                ("ok", "x + 1"),
+            ],
+        );
+        assert!(c.eval("f(1)").is_err());
+        assert!(c.eval("g(1)").is_err());
+        assert!(c.eval("h(1)").is_err());
+        assert!(c.eval("k(1)").is_err());
+        assert_eq!(c.eval("ok(1)").unwrap(), 2.0);
+    }
+}
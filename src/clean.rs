@@ -0,0 +1,130 @@
+use crate::paths;
+use clap::Args;
+use std::io::ErrorKind;
+
+#[derive(Args, Debug, Default)]
+pub struct CleanArgs {
+    /// Remove the launch history.
+    #[arg(long)]
+    history: bool,
+    /// Remove the cached currency names and rates.
+    #[arg(long)]
+    currency: bool,
+    /// Remove the desktop entry cache.
+    #[arg(long)]
+    cache: bool,
+    /// Remove everything the launcher stores.
+    #[arg(long)]
+    all: bool,
+    /// Clean even if another instance of the launcher is running.
+    #[arg(long)]
+    force: bool,
+}
+
+impl CleanArgs {
+    /// Names of the selected files inside the cache directory.
+    fn selected_files(&self) -> Vec<&'static str> {
+        let mut files = Vec::new();
+        if self.history || self.all {
+            files.push(paths::HISTORY);
+        }
+        if self.currency || self.all {
+            files.push(paths::CURRENCIES);
+            files.extend(paths::LEGACY_CURRENCY_FILES);
+        }
+        if self.cache || self.all {
+            files.push(paths::DESKTOP_ENTRIES);
+        }
+        files
+    }
+}
+
+/// Whether another process is running the same executable as us.  A running
+/// launcher would write its history back when it exits.
+fn other_instance_running() -> bool {
+    let Ok(exe) = std::env::current_exe() else {
+        return false;
+    };
+    let Ok(processes) = std::fs::read_dir("/proc") else {
+        return false;
+    };
+    let own_pid = std::process::id();
+    processes
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter(|&pid| pid != own_pid)
+        .any(|pid| std::fs::read_link(format!("/proc/{pid}/exe")).is_ok_and(|path| path == exe))
+}
+
+/// Removes the files selected by `args`, returns whether it was successful.
+pub fn clean(args: &CleanArgs) -> bool {
+    let files = args.selected_files();
+    if files.is_empty() {
+        eprintln!(
+            "Nothing to clean, select at least one of --history, --currency, --cache, or --all"
+        );
+        return false;
+    }
+    if !args.force && other_instance_running() {
+        eprintln!("The launcher is currently running, close it first or use --force");
+        return false;
+    }
+    let Some(dir) = paths::cache_dir() else {
+        eprintln!(
+            "Cannot locate the cache directory: {}",
+            paths::no_cache_dir()
+        );
+        return false;
+    };
+    let mut success = true;
+    let mut removed_any = false;
+    for file in files {
+        let path = dir.join(file);
+        match std::fs::remove_file(&path) {
+            Ok(()) => {
+                println!("Removed {}", path.display());
+                removed_any = true;
+            }
+            Err(error) if error.kind() == ErrorKind::NotFound => {}
+            Err(error) => {
+                eprintln!("Failed to remove {}: {error}", path.display());
+                success = false;
+            }
+        }
+    }
+    if args.all && std::fs::remove_dir(&dir).is_ok() {
+        println!("Removed {}", dir.display());
+        removed_any = true;
+    }
+    if !removed_any && success {
+        println!("Nothing to remove");
+    }
+    success
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selection() {
+        assert!(CleanArgs::default().selected_files().is_empty());
+        let history = CleanArgs {
+            history: true,
+            ..Default::default()
+        };
+        assert_eq!(history.selected_files(), [paths::HISTORY]);
+        let currency = CleanArgs {
+            currency: true,
+            ..Default::default()
+        };
+        assert_eq!(currency.selected_files().len(), 4);
+        assert!(!currency.selected_files().contains(&paths::HISTORY));
+        let all = CleanArgs {
+            all: true,
+            history: true,
+            ..Default::default()
+        };
+        assert_eq!(all.selected_files().len(), 6);
+    }
+}
@@ -0,0 +1,103 @@
+use chrono::{DateTime, Utc};
+use std::{ffi::CStr, time::Duration};
+
+/// A query for the current time or date: `time`, `date`, optionally followed
+/// by `utc` or `in utc`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ClockQuery {
+    /// Show the date instead of the time.
+    pub date: bool,
+    pub utc: bool,
+    /// Whether the keyword was followed by anything, including whitespace.  A
+    /// bare keyword may also just be the beginning of an application name.
+    pub explicit: bool,
+}
+
+impl ClockQuery {
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim_start();
+        let explicit = s.ends_with(char::is_whitespace);
+        let lower = s.to_lowercase();
+        let mut words = lower.split_whitespace();
+        let date = match words.next()? {
+            "time" => false,
+            "date" => true,
+            _ => return None,
+        };
+        let utc = match (words.next(), words.next(), words.next()) {
+            (None, None, None) => false,
+            (Some("utc"), None, None) | (Some("in"), Some("utc"), None) => true,
+            _ => return None,
+        };
+        Some(Self {
+            date,
+            utc,
+            explicit: explicit || utc,
+        })
+    }
+
+    /// Formats the current time.
+    pub fn format(&self) -> String {
+        self.format_at(Utc::now())
+    }
+
+    /// Formats the given time using the locales preferred time or date
+    /// representation.
+    pub fn format_at(&self, time: DateTime<Utc>) -> String {
+        let format: &CStr = if self.date { c"%x" } else { c"%X" };
+        let timestamp = time.timestamp() as libc::time_t;
+        let mut buf = [0u8; 128];
+        let len = unsafe {
+            let mut tm = std::mem::zeroed();
+            if self.utc {
+                libc::gmtime_r(&timestamp, &mut tm);
+            } else {
+                libc::localtime_r(&timestamp, &mut tm);
+            }
+            libc::strftime(buf.as_mut_ptr().cast(), buf.len(), format.as_ptr(), &tm)
+        };
+        let formatted = String::from_utf8_lossy(&buf[..len]);
+        if self.utc {
+            format!("{formatted} UTC")
+        } else {
+            formatted.into_owned()
+        }
+    }
+}
+
+/// Time until the displayed clock value needs to be updated.
+pub fn until_next_second(now: DateTime<Utc>) -> Duration {
+    Duration::from_secs(1)
+        - Duration::from_nanos(now.timestamp_subsec_nanos().min(999_999_999) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let query = |date, utc, explicit| {
+            Some(ClockQuery {
+                date,
+                utc,
+                explicit,
+            })
+        };
+        assert_eq!(ClockQuery::parse("time"), query(false, false, false));
+        assert_eq!(ClockQuery::parse("Date"), query(true, false, false));
+        assert_eq!(ClockQuery::parse("time "), query(false, false, true));
+        assert_eq!(ClockQuery::parse("time in utc"), query(false, true, true));
+        assert_eq!(ClockQuery::parse("date UTC"), query(true, true, true));
+        assert_eq!(ClockQuery::parse("timer"), None);
+        assert_eq!(ClockQuery::parse("time in"), None);
+        assert_eq!(ClockQuery::parse("time tracker"), None);
+        assert_eq!(ClockQuery::parse("time in utc now"), None);
+    }
+
+    #[test]
+    fn next_second() {
+        let time = DateTime::from_timestamp(1_700_000_000, 250_000_000).unwrap();
+        assert_eq!(until_next_second(time), Duration::from_millis(750));
+    }
+}
@@ -1,15 +1,22 @@
 use crate::{
-    content::{ContentOptions, UrlMode},
+    browser::BrowserConfig,
+    calc::CalcDefinitions,
+    content::{ContentOptions, DateOrder, UrlMode},
+    emoji,
     history::DEFAULT_MAX_SIZE,
     icon_theme::IconRegistry,
+    instance::InstanceMode,
+    paths,
+    search::SearchTuning,
+    theme::Theme,
     units::user_currency,
+    warnings::warning,
 };
 use serde::Deserialize;
-use std::cell::RefCell;
+use std::{collections::HashMap, sync::RwLock, time::Duration};
 
-thread_local! {
-    pub static ICON_THEME: RefCell<IconRegistry> = Default::default();
-}
+/// Shared with the thread rebuilding the desktop entry cache.
+pub static ICON_THEME: RwLock<IconRegistry> = RwLock::new(IconRegistry::empty());
 
 #[derive(Deserialize, Default)]
 pub struct ParsedConfig {
@@ -25,10 +32,148 @@ pub struct ParsedConfig {
     scroll_speed: Option<i32>,
     locale: Option<String>,
     scroll_bar_width: Option<u32>,
+    scroll_fade: Option<bool>,
     history_entries: Option<usize>,
     default_currency: Option<String>,
+    smart_content: Option<bool>,
+    smart_calculator: Option<bool>,
+    smart_conversions: Option<bool>,
+    smart_paths: Option<bool>,
+    smart_urls: Option<bool>,
+    smart_commands: Option<bool>,
     smart_content_urls: Option<String>,
     smart_content_dynamic_conversions: Option<bool>,
+    smart_content_number_formats: Option<bool>,
+    smart_content_results_threshold: Option<f64>,
+    date_order: Option<String>,
+    restricted: Option<bool>,
+    search: Option<ParsedSearchTuning>,
+    search_timeout_ms: Option<u64>,
+    position: Option<WindowPlacement>,
+    calc: Option<CalcDefinitions>,
+    window_class_overrides: Option<HashMap<String, String>>,
+    browser: Option<String>,
+    browser_candidates: Option<Vec<String>>,
+    opaque_colors: Option<bool>,
+    theme: Option<String>,
+    gpu_offload: Option<bool>,
+    navigate_list_from_entry: Option<bool>,
+    auto_close_seconds: Option<u64>,
+    close_on_focus_loss: Option<bool>,
+    xi2: Option<bool>,
+    emoji_prefix: Option<String>,
+    max_paste_length: Option<usize>,
+    launch_feedback_seconds: Option<u64>,
+    single_instance: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ParsedSearchTuning {
+    localized_name_weight: Option<f64>,
+    name_weight: Option<f64>,
+    localized_generic_name_weight: Option<f64>,
+    generic_name_weight: Option<f64>,
+    file_name_weight: Option<f64>,
+    exec_name_weight: Option<f64>,
+    path_weight: Option<f64>,
+    exact_base: Option<f64>,
+    similarity_threshold: Option<f64>,
+    history_boost: Option<f64>,
+    equal_threshold: Option<f64>,
+}
+
+impl ParsedSearchTuning {
+    fn resolve(self) -> SearchTuning {
+        let default = SearchTuning::default();
+        macro_rules! get {
+            ($field:ident, $min:expr, $max:expr) => {
+                match self.$field {
+                    Some(value) if !(($min)..=($max)).contains(&value) => {
+                        let clamped = if value.is_nan() {
+                            default.$field
+                        } else {
+                            value.clamp($min, $max)
+                        };
+                        warning!(
+                            "search.{} must be in range [{}, {}], using {}",
+                            stringify!($field),
+                            $min,
+                            $max,
+                            clamped
+                        );
+                        clamped
+                    }
+                    Some(value) => value,
+                    None => default.$field,
+                }
+            };
+        }
+        SearchTuning {
+            localized_name_weight: get!(localized_name_weight, 0.0, 10.0),
+            name_weight: get!(name_weight, 0.0, 10.0),
+            localized_generic_name_weight: get!(localized_generic_name_weight, 0.0, 10.0),
+            generic_name_weight: get!(generic_name_weight, 0.0, 10.0),
+            file_name_weight: get!(file_name_weight, 0.0, 10.0),
+            exec_name_weight: get!(exec_name_weight, 0.0, 10.0),
+            path_weight: get!(path_weight, 0.0, 10.0),
+            exact_base: get!(exact_base, 0.0, 10.0),
+            similarity_threshold: get!(similarity_threshold, 0.0, 1.0),
+            history_boost: get!(history_boost, 0.0, 100.0),
+            equal_threshold: get!(equal_threshold, 0.0, 1.0),
+        }
+    }
+}
+
+/// Where the window is placed on a monitor in percent of the space around
+/// it, 0 is the left or top edge and 50 centers it.
+#[derive(Deserialize, Default, Clone, Copy, Debug, PartialEq)]
+pub struct Placement {
+    x_percent: Option<u32>,
+    y_percent: Option<u32>,
+}
+
+/// The `[position]` section: a placement for all monitors and overrides for
+/// single outputs like `[position."DP-1"]`.
+#[derive(Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct WindowPlacement {
+    #[serde(flatten)]
+    global: Placement,
+    #[serde(flatten)]
+    outputs: HashMap<String, Placement>,
+}
+
+impl WindowPlacement {
+    /// The horizontal and vertical percentages for the monitor of `output`,
+    /// each from its override, then the global setting, then centered.
+    pub fn resolve(&self, output: &str) -> (u32, u32) {
+        let output = self.outputs.get(output).copied().unwrap_or_default();
+        let get = |output: Option<u32>, global: Option<u32>| output.or(global).unwrap_or(50);
+        (
+            get(output.x_percent, self.global.x_percent),
+            get(output.y_percent, self.global.y_percent),
+        )
+    }
+
+    /// Clamps the percentages to 100.
+    fn validate(mut self) -> Self {
+        let placements = std::iter::once(("position".to_string(), &mut self.global)).chain(
+            self.outputs
+                .iter_mut()
+                .map(|(name, placement)| (format!("position.\"{name}\""), placement)),
+        );
+        for (section, placement) in placements {
+            for (key, value) in [
+                ("x_percent", &mut placement.x_percent),
+                ("y_percent", &mut placement.y_percent),
+            ] {
+                if let Some(percent) = value.filter(|&percent| percent > 100) {
+                    warning!("{section}.{key} must be at most 100, got {percent}");
+                    *value = Some(100);
+                }
+            }
+        }
+        self
+    }
 }
 
 #[derive(Clone)]
@@ -44,34 +189,103 @@ pub struct Config {
     pub scroll_speed: i32,
     pub locale: Option<String>,
     pub scroll_bar_width: u32,
+    pub scroll_fade: bool,
     pub history_entries: usize,
     pub default_currency: String,
     pub smart_content_options: ContentOptions,
+    /// Show fractions and engineering notation after expression results.
+    pub smart_content_number_formats: bool,
+    /// Search score from which the results win over an expression typed as
+    /// words.
+    pub smart_content_results_threshold: f64,
+    pub search_tuning: SearchTuning,
+    /// Show the results without the search providers that take longer than
+    /// this, `None` to wait for all of them.
+    pub search_timeout: Option<Duration>,
+    /// Where the window goes on the monitor it's shown on.
+    pub position: WindowPlacement,
+    /// Only allow launching desktop entries.
+    pub restricted: bool,
+    /// Desktop file names for window classes or instances, see
+    /// `window_matching`.
+    pub window_class_overrides: HashMap<String, String>,
+    pub browser: BrowserConfig,
+    /// Flatten translucent colors, `None` to decide based on whether there is
+    /// a compositor.
+    pub opaque_colors: Option<bool>,
+    pub theme: Theme,
+    /// Run desktop entries that prefer a non-default GPU on it.
+    pub gpu_offload: bool,
+    /// Up and Down in the entry move the list selection instead of the focus.
+    pub navigate_list_from_entry: bool,
+    /// Close the launcher after this long without input.
+    pub auto_close: Option<Duration>,
+    /// Close the launcher when another window gets the input focus.
+    pub close_on_focus_loss: bool,
+    /// Grab each XInput2 master keyboard instead of the core keyboard.
+    pub xi2: bool,
+    /// Entry text starting with this searches emoji, empty to disable.
+    pub emoji_prefix: String,
+    /// Pasted text is cut to this many characters.
+    pub max_paste_length: usize,
+    /// Keep showing what was launched for up to this long after committing.
+    pub launch_feedback: Option<Duration>,
+    /// What to do if the launcher is already running.
+    pub single_instance: InstanceMode,
 }
 
 impl Config {
-    pub fn load() -> Self {
-        let home = std::env::var("HOME").unwrap();
-        let pathname = format!("{home}/.config/launcher.toml");
-        let parsed = if let Ok(content) = std::fs::read_to_string(pathname) {
+    /// Loads the config file, `restricted` enables the restricted mode no
+    /// matter what the file says.
+    pub fn load(restricted: bool) -> Self {
+        let content = paths::config_file().and_then(|path| std::fs::read_to_string(path).ok());
+        let parsed = if let Some(content) = content {
             toml::from_str(&content).unwrap_or_else(|error| {
-                eprintln!("Config loading error: {error}");
+                warning!("Config loading error: {error}");
                 ParsedConfig::default()
             })
         } else {
             ParsedConfig::default()
         };
+        Self::from_parsed(parsed, restricted)
+    }
+
+    /// Resolves the defaults for everything missing from `parsed`.
+    pub fn from_parsed(parsed: ParsedConfig, restricted: bool) -> Self {
         let theme_name = parsed.icon_theme.as_deref().unwrap_or("Papirus");
-        ICON_THEME.with_borrow_mut(|t| *t = IconRegistry::new(theme_name).unwrap());
+        *ICON_THEME.write().unwrap() = IconRegistry::new(theme_name);
         let url_mode = match parsed.smart_content_urls.as_deref() {
+            _ if parsed.smart_urls == Some(false) => UrlMode::None,
             Some("none") => UrlMode::None,
             Some("http") => UrlMode::Http,
             Some("all") | Some("loose") | None => UrlMode::Loose,
             Some(x) => {
-                eprintln!("Invalid URL mode: {x}");
+                warning!("Invalid URL mode: {x}");
                 UrlMode::Loose
             }
         };
+        let date_order = parsed.date_order.as_deref().and_then(|name| {
+            let order = DateOrder::from_name(name);
+            if order.is_none() {
+                warning!("Invalid date order: {name}");
+            }
+            order
+        });
+        let single_instance = match parsed.single_instance.as_deref() {
+            None => InstanceMode::Off,
+            Some(name) => InstanceMode::from_name(name).unwrap_or_else(|| {
+                warning!("Invalid single instance mode: {name}");
+                InstanceMode::Off
+            }),
+        };
+        let theme = match parsed.theme.as_deref() {
+            None => Theme::Dark,
+            Some(name) => Theme::from_name(name).unwrap_or_else(|| {
+                warning!("Invalid theme: {name}");
+                Theme::Dark
+            }),
+        };
+        let restricted = restricted || parsed.restricted.unwrap_or(false);
         Config {
             window_width_percent: parsed.window_width_percent.unwrap_or(50),
             window_height_percent: parsed.window_height_percent.unwrap_or(50),
@@ -88,15 +302,125 @@ impl Config {
             scroll_speed: parsed.scroll_speed.unwrap_or(10),
             locale: parsed.locale,
             scroll_bar_width: parsed.scroll_bar_width.unwrap_or(8),
+            scroll_fade: parsed.scroll_fade.unwrap_or(true),
             history_entries: parsed.history_entries.unwrap_or(DEFAULT_MAX_SIZE),
             default_currency: parsed
                 .default_currency
                 .unwrap_or_else(|| user_currency())
                 .to_lowercase(),
             smart_content_options: ContentOptions {
+                enabled: parsed.smart_content.unwrap_or(true),
+                calculator: parsed.smart_calculator.unwrap_or(true),
+                conversions: parsed.smart_conversions.unwrap_or(true),
+                paths: parsed.smart_paths.unwrap_or(true),
                 dynamic_conversions: parsed.smart_content_dynamic_conversions.unwrap_or(true),
                 url_mode,
+                calc: parsed.calc.unwrap_or_default(),
+                date_order,
+                commands: parsed.smart_commands.unwrap_or(true) && !restricted,
+            },
+            smart_content_number_formats: parsed.smart_content_number_formats.unwrap_or(true),
+            smart_content_results_threshold: parsed.smart_content_results_threshold.unwrap_or(1.2),
+            search_tuning: parsed.search.unwrap_or_default().resolve(),
+            search_timeout: Some(parsed.search_timeout_ms.unwrap_or(150))
+                .filter(|&ms| ms != 0)
+                .map(Duration::from_millis),
+            position: parsed.position.unwrap_or_default().validate(),
+            restricted,
+            window_class_overrides: parsed.window_class_overrides.unwrap_or_default(),
+            browser: BrowserConfig {
+                browser: parsed.browser.filter(|browser| !browser.trim().is_empty()),
+                candidates: parsed
+                    .browser_candidates
+                    .unwrap_or_else(|| BrowserConfig::default().candidates),
             },
+            opaque_colors: parsed.opaque_colors,
+            theme,
+            gpu_offload: parsed.gpu_offload.unwrap_or(true),
+            navigate_list_from_entry: parsed.navigate_list_from_entry.unwrap_or(false),
+            auto_close: parsed
+                .auto_close_seconds
+                .filter(|&seconds| seconds != 0)
+                .map(Duration::from_secs),
+            close_on_focus_loss: parsed.close_on_focus_loss.unwrap_or(false),
+            xi2: parsed.xi2.unwrap_or(false),
+            emoji_prefix: parsed
+                .emoji_prefix
+                .unwrap_or_else(|| emoji::DEFAULT_PREFIX.to_string()),
+            max_paste_length: parsed.max_paste_length.unwrap_or(4096),
+            launch_feedback: parsed
+                .launch_feedback_seconds
+                .filter(|&seconds| seconds != 0)
+                .map(Duration::from_secs),
+            single_instance,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{scores, SIMILARITY_THRESHHOLD};
+
+    fn parse_tuning(s: &str) -> SearchTuning {
+        let parsed: ParsedConfig = toml::from_str(s).unwrap();
+        parsed.search.unwrap_or_default().resolve()
+    }
+
+    #[test]
+    fn default_search_tuning() {
+        let expected = SearchTuning {
+            localized_name_weight: scores::LOCALIZED_NAME_WEIGHT,
+            name_weight: scores::NAME_WEIGHT,
+            localized_generic_name_weight: scores::LOCALIZED_GENERIC_NAME_WEIGHT,
+            generic_name_weight: scores::GENERIC_NAME_WEIGHT,
+            file_name_weight: scores::FILE_NAME_WEIGHT,
+            exec_name_weight: scores::EXEC_NAME_WEIGHT,
+            path_weight: scores::PATH_WEIGHT,
+            exact_base: scores::EXACT_BASE,
+            similarity_threshold: SIMILARITY_THRESHHOLD,
+            history_boost: scores::HISTORY_BOOST,
+            equal_threshold: scores::EQUAL_THREHOLD,
+        };
+        assert_eq!(SearchTuning::default(), expected);
+        assert_eq!(parse_tuning(""), expected);
+        assert_eq!(parse_tuning("[search]"), expected);
+    }
+
+    #[test]
+    fn search_tuning_clamps() {
+        let tuning = parse_tuning(
+            "[search]\nname_weight = 1.5\nsimilarity_threshold = 1.5\nhistory_boost = -1.0",
+        );
+        assert_eq!(tuning.name_weight, 1.5);
+        assert_eq!(tuning.similarity_threshold, 1.0);
+        assert_eq!(tuning.history_boost, 0.0);
+        assert_eq!(tuning.path_weight, scores::PATH_WEIGHT);
+    }
+
+    #[test]
+    fn window_placement() {
+        let parsed: ParsedConfig = toml::from_str(
+            r#"
+[position]
+y_percent = 30
+[position."DP-1"]
+y_percent = 20
+[position."HDMI-A-1"]
+x_percent = 0
+y_percent = 150
+"#,
+        )
+        .unwrap();
+        let position = parsed.position.unwrap().validate();
+        // The override wins over the global setting, which wins over centering
+        assert_eq!(position.resolve("DP-1"), (50, 20));
+        assert_eq!(position.resolve("HDMI-A-1"), (0, 100));
+        assert_eq!(position.resolve("eDP-1"), (50, 30));
+        // Monitors without a name only get the global setting
+        assert_eq!(position.resolve(""), (50, 30));
+        let parsed: ParsedConfig = toml::from_str("").unwrap();
+        let position = parsed.position.unwrap_or_default().validate();
+        assert_eq!(position.resolve("DP-1"), (50, 50));
+    }
+}
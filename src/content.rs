@@ -1,7 +1,12 @@
-use meval::Context;
+use chrono::{Datelike, Local, Months, NaiveDate, TimeDelta};
 use regex::Regex;
 
-use crate::{static_units::Distance, units::Unit};
+use crate::{
+    calc::{CalcDefinitions, Calculator},
+    clock::ClockQuery,
+    static_units::Distance,
+    units::{currency, Unit},
+};
 
 #[derive(Debug, Clone)]
 pub enum Content {
@@ -14,6 +19,8 @@ pub enum Content {
     DefaultConversion(f64, Unit),
     /// Input string is a number, with an optional unit, followed by `[to/in] <unit>`
     Conversion(f64, Option<Unit>, Unit),
+    /// Input string is `factor <unit>`, asking for its conversion factor.
+    Factor(Unit),
     // TODO:
     // The usefulness of this is dubious at the moment, since it requires a
     // full path ~~and there isn't even pasting~~.  Providing full suggestions
@@ -24,27 +31,57 @@ pub enum Content {
     // entry cache would be too slow which it wasn't this may be fine as well.
     /// The input string is a valid path. (`access(2)` reports read access)
     Path,
-    /// The input string is a valid URL.
-    URL,
+    /// The input string contains a URL, without the punctuation around it.
+    URL(String),
     /// The input string starts with a `$`
     Command,
+    /// The input string is a `$` without a command after it.
+    EmptyCommand,
+    /// The input string asks for the current time or date.
+    Clock(ClockQuery),
+    /// The input string is a date calculation.
+    DateCalc(DateCalc),
+    /// Input string is an integer followed by `[to/in/as] <base>`.
+    BaseConversion(i128, NumberBase),
 }
 
 #[derive(Debug, Clone)]
 pub struct ContentOptions {
+    /// Whether there is any smart content, the other options only matter if
+    /// this is set.
+    pub enabled: bool,
+    /// Whether to evaluate arithmetic expressions.
+    pub calculator: bool,
+    /// Whether to convert units.
+    pub conversions: bool,
+    /// Whether existing paths can be opened.
+    pub paths: bool,
     /// Whether to allow dynamic conversions, that is, conversions that are not
     /// based on a fixed conversion rate, meaning they will need to be fetched
     /// from the internet.
     pub dynamic_conversions: bool,
     /// What URLs to allow.
     pub url_mode: UrlMode,
+    /// User defined calculator constants and functions.
+    pub calc: CalcDefinitions,
+    /// How to read ambiguous dates, `None` to use the locale.
+    pub date_order: Option<DateOrder>,
+    /// Whether input starting with a `$` is a command.
+    pub commands: bool,
 }
 
 impl Default for ContentOptions {
     fn default() -> Self {
         Self {
+            enabled: true,
+            calculator: true,
+            conversions: true,
+            paths: true,
             dynamic_conversions: true,
             url_mode: UrlMode::Loose,
+            calc: CalcDefinitions::default(),
+            date_order: None,
+            commands: true,
         }
     }
 }
@@ -66,9 +103,9 @@ pub enum UrlMode {
 }
 
 impl UrlMode {
-    // https://stackoverflow.com/a/3809435
-    const LOOSE_URL_REGEX: &str =
-        r#"[-a-zA-Z0-9@:%._\+~#=]{1,256}\.[a-zA-Z0-9()]{1,6}\b([-a-zA-Z0-9()@:%_\+.~#?&//=]*)"#;
+    // https://stackoverflow.com/a/3809435, with any scheme so it's part of
+    // the match.
+    const LOOSE_URL_REGEX: &str = r#"([a-zA-Z][-a-zA-Z0-9+.]*:\/\/)?[-a-zA-Z0-9@:%._\+~#=]{1,256}\.[a-zA-Z0-9()]{1,6}\b([-a-zA-Z0-9()@:%_\+.~#?&//=]*)"#;
 
     const HTTP_URL_REGEX: &str = r#"https?:\/\/(www\.)?[-a-zA-Z0-9@:%._\+~#=]{1,256}\.[a-zA-Z0-9()]{1,6}\b([-a-zA-Z0-9()@:%_\+.~#?&//=]*)"#;
 
@@ -81,10 +118,304 @@ impl UrlMode {
     }
 }
 
+/// Removes the punctuation after a URL found in text, like autolinkers do, so
+/// `see example.com.` opens `example.com`.  Closing brackets are only removed
+/// if they don't close one opened in the URL, like in Wikipedia links.
+fn trim_url(url: &str) -> &str {
+    let mut url = url;
+    while let Some(last) = url.chars().last() {
+        let trim = match last {
+            '.' | ',' | ';' | ':' | '!' | '?' | '\'' | '"' => true,
+            ')' | ']' | '}' => {
+                let open = match last {
+                    ')' => '(',
+                    ']' => '[',
+                    _ => '{',
+                };
+                url.matches(open).count() < url.matches(last).count()
+            }
+            _ => false,
+        };
+        if !trim {
+            break;
+        }
+        url = &url[..url.len() - last.len_utf8()];
+    }
+    url
+}
+
+/// Order of the day, month, and year in dates that are not ISO 8601.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateOrder {
+    DayMonthYear,
+    MonthDayYear,
+    YearMonthDay,
+}
+
+impl DateOrder {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dmy" => Some(DateOrder::DayMonthYear),
+            "mdy" => Some(DateOrder::MonthDayYear),
+            "ymd" => Some(DateOrder::YearMonthDay),
+            _ => None,
+        }
+    }
+
+    /// Gets the order from the date format of the current locale.
+    pub fn from_locale() -> Self {
+        let format = unsafe { std::ffi::CStr::from_ptr(libc::nl_langinfo(libc::D_FMT)) };
+        Self::from_format(&format.to_string_lossy())
+    }
+
+    /// Gets the order from a `strftime` date format.
+    fn from_format(format: &str) -> Self {
+        let format = format.replace("%F", "%Y-%m-%d").replace("%D", "%m/%d/%y");
+        let position = |specifiers: &[&str]| {
+            specifiers
+                .iter()
+                .filter_map(|specifier| format.find(specifier))
+                .min()
+                .unwrap_or(usize::MAX)
+        };
+        let day = position(&["%d", "%e"]);
+        let month = position(&["%m", "%b"]);
+        let year = position(&["%y", "%Y"]);
+        if year < month && year < day {
+            DateOrder::YearMonthDay
+        } else if month < day {
+            DateOrder::MonthDayYear
+        } else {
+            DateOrder::DayMonthYear
+        }
+    }
+
+    fn example(self) -> &'static str {
+        match self {
+            DateOrder::DayMonthYear => "DD.MM.YYYY or YYYY-MM-DD",
+            DateOrder::MonthDayYear => "MM/DD/YYYY or YYYY-MM-DD",
+            DateOrder::YearMonthDay => "YYYY-MM-DD",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DateCalc {
+    /// ISO week number
+    Week(u32),
+    /// Number of days
+    Days(i64),
+    Date(NaiveDate),
+    /// The input looks like a date calculation but could not be evaluated.
+    Hint(String),
+}
+
+impl DateCalc {
+    /// Parses and evaluates a date expression:
+    /// - `week`
+    /// - `days until <date>`, `days since <date>`
+    /// - `<date> (+|-) <number> (days|weeks|months|years)`
+    ///
+    /// Dates are ISO 8601, `today`, `tomorrow`, `yesterday`, or use the given
+    /// order with `.`, `/`, or `-` separators, the year may be omitted.
+    fn parse(s: &str, today: NaiveDate, order: DateOrder) -> Option<Self> {
+        let lower = s.to_lowercase();
+        let words: Vec<&str> = lower.split_whitespace().collect();
+        let date = |s| {
+            parse_date(s, today, order).ok_or_else(|| {
+                DateCalc::Hint(format!("Invalid date, expected {}", order.example()))
+            })
+        };
+        let result = match words.as_slice() {
+            ["week"] => Ok(DateCalc::Week(today.iso_week().week())),
+            ["days", "until", to] => date(to).map(|to| DateCalc::Days((to - today).num_days())),
+            ["days", "since", from] => {
+                date(from).map(|from| DateCalc::Days((today - from).num_days()))
+            }
+            [from, op @ ("+" | "-"), amount, unit] if looks_like_date(from) => {
+                let amount: u32 = amount.parse().ok()?;
+                let months = match *unit {
+                    "day" | "days" | "week" | "weeks" => None,
+                    "month" | "months" => Some(amount),
+                    "year" | "years" => Some(amount.checked_mul(12)?),
+                    _ => return None,
+                };
+                date(from).and_then(|from| {
+                    let to = if let Some(months) = months {
+                        let months = Months::new(months);
+                        if *op == "+" {
+                            from.checked_add_months(months)
+                        } else {
+                            from.checked_sub_months(months)
+                        }
+                    } else {
+                        let days = amount as i64 * if unit.starts_with('w') { 7 } else { 1 };
+                        let delta = TimeDelta::days(if *op == "+" { days } else { -days });
+                        from.checked_add_signed(delta)
+                    };
+                    to.map(DateCalc::Date)
+                        .ok_or_else(|| DateCalc::Hint("Date out of range".to_string()))
+                })
+            }
+            _ => return None,
+        };
+        Some(result.unwrap_or_else(|hint| hint))
+    }
+}
+
+fn looks_like_date(s: &str) -> bool {
+    matches!(s, "today" | "tomorrow" | "yesterday")
+        || (s.contains(['.', '/', '-'])
+            && s.chars().all(|c| c.is_ascii_digit() || ".-/".contains(c)))
+}
+
+fn parse_date(s: &str, today: NaiveDate, order: DateOrder) -> Option<NaiveDate> {
+    match s {
+        "today" => return Some(today),
+        "tomorrow" => return today.succ_opt(),
+        "yesterday" => return today.pred_opt(),
+        _ => {}
+    }
+    let parts: Vec<&str> = s.trim_end_matches('.').split(['.', '/', '-']).collect();
+    let numbers: Vec<u32> = parts
+        .iter()
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+    let year = |index: usize| {
+        let year = numbers[index] as i32;
+        // Two digit years
+        if parts[index].len() <= 2 {
+            year + 2000
+        } else {
+            year
+        }
+    };
+    let (year, month, day) = match (numbers.len(), order) {
+        (3, _) if parts[0].len() == 4 => (year(0), numbers[1], numbers[2]),
+        (3, DateOrder::DayMonthYear) => (year(2), numbers[1], numbers[0]),
+        (3, DateOrder::MonthDayYear) => (year(2), numbers[0], numbers[1]),
+        (3, DateOrder::YearMonthDay) => (year(0), numbers[1], numbers[2]),
+        (2, DateOrder::DayMonthYear) => (today.year(), numbers[1], numbers[0]),
+        (2, _) => (today.year(), numbers[0], numbers[1]),
+        _ => return None,
+    };
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NumberBase {
+    Binary,
+    Octal,
+    Decimal,
+    Hexadecimal,
+}
+
+impl NumberBase {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bin" | "binary" => Some(NumberBase::Binary),
+            "oct" | "octal" => Some(NumberBase::Octal),
+            "dec" | "decimal" => Some(NumberBase::Decimal),
+            "hex" | "hexadecimal" => Some(NumberBase::Hexadecimal),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            NumberBase::Binary => "binary",
+            NumberBase::Octal => "octal",
+            NumberBase::Decimal => "decimal",
+            NumberBase::Hexadecimal => "hexadecimal",
+        }
+    }
+
+    /// Formats `value` with the prefix of the base, like `0xFF`.
+    pub fn format(self, value: i128) -> String {
+        let sign = if value < 0 { "-" } else { "" };
+        let value = value.unsigned_abs();
+        match self {
+            NumberBase::Binary => format!("{sign}0b{value:b}"),
+            NumberBase::Octal => format!("{sign}0o{value:o}"),
+            NumberBase::Decimal => format!("{sign}{value}"),
+            NumberBase::Hexadecimal => format!("{sign}0x{value:X}"),
+        }
+    }
+}
+
+/// Parses an integer that's decimal or has a `0x`, `0b`, or `0o` prefix.
+/// This has to happen before lexing, which splits the prefix from the
+/// digits.
+fn parse_integer(s: &str) -> Option<i128> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let lower = s.to_ascii_lowercase();
+    let (radix, digits) = match lower.get(..2) {
+        Some("0x") => (16, &lower[2..]),
+        Some("0b") => (2, &lower[2..]),
+        Some("0o") => (8, &lower[2..]),
+        _ => (10, lower.as_str()),
+    };
+    // `from_str_radix` would accept another sign after the prefix
+    if digits.starts_with(['+', '-']) {
+        return None;
+    }
+    let value = i128::from_str_radix(digits, radix).ok()?;
+    Some(if negative { -value } else { value })
+}
+
+/// Classifies `<integer> [to/in/as] <base>`, `None` if the target isn't a
+/// base or the first word doesn't start like a number, so searches like
+/// `trash in bin` are left alone.
+fn base_conversion(s: &str) -> Option<Result<Content, ClassificationError>> {
+    let [number, "to" | "in" | "as", base] = s.split_whitespace().collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let digits = number.strip_prefix('-').unwrap_or(number);
+    if !digits.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let base = NumberBase::from_name(&base.to_lowercase())?;
+    Some(match parse_integer(number) {
+        Some(value) => Ok(Content::BaseConversion(value, base)),
+        None => Err(ClassificationError::InvalidInteger),
+    })
+}
+
+/// The command line of a `$` command, `$ ls -la` and `$ls -la` both run
+/// `ls -la`.
+pub fn command_line(s: &str) -> Option<&str> {
+    s.trim().strip_prefix('$').map(str::trim)
+}
+
+/// Whether `s` is clearly a search of several words, like `weather in
+/// berlin`, so there is no point in looking for units.  Without a number a
+/// conversion is at most `<unit> to <unit>`, anything longer needs one.
+fn is_multi_word_search(s: &str) -> bool {
+    let words = s.split_whitespace().collect::<Vec<_>>();
+    if words.len() < 3 || s.bytes().any(|b| b.is_ascii_digit()) {
+        return false;
+    }
+    words.len() > 3 || Unit::from_str(words[0]).is_none()
+}
+
 fn consider_for_basic_expression(s: &str) -> bool {
-    // Filter out strings with just a single number, these would of course
-    // evaluate correctly but it's not useful.
-    !s.trim().bytes().all(|b| b.is_ascii_digit())
+    // Filter out strings with just a single number, optionally signed and in
+    // parentheses, these would of course evaluate correctly but it's not
+    // useful.  Anything else after the number, like a `%`, is still an
+    // operator.
+    let tokens = lex(s)
+        .into_iter()
+        .filter(
+            |token| !matches!(token, Token::Symbol(c) if c.is_whitespace() || "()".contains(*c)),
+        )
+        .collect::<Vec<_>>();
+    !matches!(
+        tokens.as_slice(),
+        [Token::Number(_)] | [Token::Symbol('+' | '-'), Token::Number(_)]
+    )
 }
 
 //
@@ -125,6 +456,22 @@ fn is_letter(b: u8) -> Option<usize> {
     }
 }
 
+/// Parses the quotation mark notation for feet and inches at the start of the
+/// tokens, `5'11"` is the total in inches and `6'` stays in feet.  Returns the
+/// value, its unit, and the number of tokens used.
+fn feet_and_inches(tokens: &[Token]) -> Option<(f64, Unit, usize)> {
+    let (Some(Token::Number(feet)), Some(Token::Symbol('\''))) = (tokens.first(), tokens.get(1))
+    else {
+        return None;
+    };
+    match (tokens.get(2), tokens.get(3)) {
+        (Some(Token::Number(inches)), Some(Token::Symbol('"'))) => {
+            Some((feet * 12.0 + inches, Unit::Distance(Distance::Inch), 4))
+        }
+        _ => Some((*feet, Unit::Distance(Distance::Feet), 2)),
+    }
+}
+
 // We could use an iterator to lex without allocating but it really doesn't
 // matter and random access is convenient.
 fn lex(s: &str) -> Vec<Token> {
@@ -193,10 +540,13 @@ pub enum ClassificationError {
     /// User entered `1inch to centmeter`; we can be 100% sure this is a
     /// mistake and display it as an error.
     InvalidToUnit,
-    /// 2 valid units but they can't be converted.
-    InvalidConversion,
+    /// 2 valid units of different dimensions, like `5 usd to kg`.
+    InvalidConversion(Unit, Unit),
     /// User entered `1cm to`; this will likely be removed
     MissingToUnit,
+    /// User entered `1.5 to hex`, only integers can be written in other
+    /// bases.
+    InvalidInteger,
 }
 
 impl std::fmt::Display for ClassificationError {
@@ -204,8 +554,14 @@ impl std::fmt::Display for ClassificationError {
         match self {
             ClassificationError::InvalidUnit => write!(f, "Invalid unit"),
             ClassificationError::InvalidToUnit => write!(f, "Invalid `to` unit"),
-            ClassificationError::InvalidConversion => write!(f, "Invalid conversion"),
+            ClassificationError::InvalidConversion(from, to) => write!(
+                f,
+                "Can't convert {from} ({}) to {to} ({})",
+                from.dimension(),
+                to.dimension()
+            ),
             ClassificationError::MissingToUnit => write!(f, "Missing or invalid `to` unit"),
+            ClassificationError::InvalidInteger => write!(f, "Not an integer"),
         }
     }
 }
@@ -213,50 +569,81 @@ impl std::fmt::Display for ClassificationError {
 pub struct ContentClassifier {
     options: ContentOptions,
     url_regex: Option<Regex>,
-    eval_cx: Context<'static>,
+    calculator: Calculator,
+    date_order: DateOrder,
 }
 
 impl ContentClassifier {
     pub fn new(options: ContentOptions) -> Self {
         let url_regex = options.url_mode.regex().map(|r| Regex::new(r).unwrap());
-        let eval_cx = Context::new();
+        let calculator = Calculator::new(&options.calc);
+        let date_order = options.date_order.unwrap_or_else(DateOrder::from_locale);
         Self {
             options,
             url_regex,
-            eval_cx,
+            calculator,
+            date_order,
         }
     }
 
-    fn is_url(&self, s: &str) -> bool {
-        if let Some(regex) = &self.url_regex {
-            regex.is_match(s)
-        } else {
-            false
-        }
+    /// Whether `$` commands are classified as such.
+    pub fn runs_commands(&self) -> bool {
+        self.options.enabled && self.options.commands
+    }
+
+    fn find_url(&self, s: &str) -> Option<String> {
+        let url = trim_url(self.url_regex.as_ref()?.find(s)?.as_str());
+        (!url.is_empty()).then(|| url.to_string())
     }
 
     /// Classify the input string without checking units.
-    fn classify_unchecked(&self, s: &str) -> Result<Option<Content>, ClassificationError> {
-        let s = s.trim();
+    fn classify_unchecked(&self, input: &str) -> Result<Option<Content>, ClassificationError> {
+        let s = input.trim();
         if s.is_empty() {
             return Ok(None);
-        } else if s.starts_with('=') {
+        } else if let Some(query) = ClockQuery::parse(input) {
+            return Ok(Some(Content::Clock(query)));
+        } else if let Some(calc) = DateCalc::parse(s, Local::now().date_naive(), self.date_order) {
+            return Ok(Some(Content::DateCalc(calc)));
+        } else if s.starts_with('=') && self.options.calculator {
             let expr = s[1..].trim();
-            return Ok(Some(Content::LeadExpression(meval::eval_str(expr))));
-        } else if s.starts_with('$') {
-            return Ok(Some(Content::Command));
-        } else if std::fs::metadata(s).is_ok() {
+            return Ok(Some(Content::LeadExpression(self.calculator.eval(expr))));
+        } else if let Some(command) = command_line(s).filter(|_| self.options.commands) {
+            return Ok(Some(if command.is_empty() {
+                Content::EmptyCommand
+            } else {
+                Content::Command
+            }));
+        } else if let Some(result) = base_conversion(s).filter(|_| self.options.conversions) {
+            // Before URLs, `1.5 to hex` would be one
+            return result.map(Some);
+        } else if self.options.paths && std::fs::metadata(s).is_ok() {
             // XXX: check read access?
             return Ok(Some(Content::Path));
-        } else if self.is_url(s) {
-            return Ok(Some(Content::URL));
-        } else if consider_for_basic_expression(s) {
-            if let Ok(result) = meval::eval_str_with_context(s, &self.eval_cx) {
+        } else if let Some(url) = self.find_url(s) {
+            return Ok(Some(Content::URL(url)));
+        } else if self.options.calculator && consider_for_basic_expression(s) {
+            if let Ok(result) = self.calculator.eval(s) {
                 return Ok(Some(Content::BasicExpression(result)));
             }
         }
+        if !self.options.conversions {
+            return Ok(None);
+        }
+        if let Some(unit) = s.strip_prefix("factor ") {
+            return match Unit::from_str(unit.trim()) {
+                Some(unit) => Ok(Some(Content::Factor(unit))),
+                None => Err(ClassificationError::InvalidUnit),
+            };
+        }
+        // After everything that's recognized by a keyword or prefix
+        if is_multi_word_search(s) {
+            return Ok(None);
+        }
         fn get_unit(tokens: &mut [Token], index: &mut usize) -> Option<Unit> {
             match tokens.get(*index) {
+                // Attoseconds, but `as` is only used to start the target unit
+                Some(Token::Text("as")) => None,
                 Some(Token::Text(t)) => {
                     if let Some(unit) = Unit::from_str(t) {
                         *index += 1;
@@ -269,6 +656,26 @@ impl ContentClassifier {
             }
         }
         let mut tokens = lex(s);
+        // The notation has to be the whole input apart from a target unit,
+        // text like `5" bay` or `3'x5'` is left to the search.
+        if let Some((num, unit, used)) = feet_and_inches(&tokens) {
+            return match tokens[used..] {
+                [] => Ok(Some(Content::DefaultConversion(num, unit))),
+                [Token::Text("to" | "in" | "as")] => Err(ClassificationError::MissingToUnit),
+                [Token::Text("to" | "in" | "as"), Token::Text(to)] => match Unit::from_str(to) {
+                    Some(to) => Ok(Some(Content::Conversion(num, Some(unit), to))),
+                    None => Err(ClassificationError::InvalidToUnit),
+                },
+                _ => Ok(None),
+            };
+        }
+        // `usd 100 to eur` is read as `100 usd to eur`.  Only currencies are
+        // written like this, for other units `cm 5` would be too ambiguous.
+        if let [Token::Text(code), Token::Number(_), ..] = tokens[..] {
+            if currency(code).is_some() {
+                tokens.swap(0, 1);
+            }
+        }
         let mut index = 1;
         let mut no_number = false;
         let num = match tokens.get(0) {
@@ -332,22 +739,6 @@ impl ContentClassifier {
             }
             return Ok(Some(Content::DefaultConversion(num, unit_a)));
         }
-        // Handle the quotation mark notation for feet and inches
-        if matches!(tokens.get(1), Some(Token::Symbol('\''))) {
-            if let Some(Token::Number(maybe_inch)) = tokens.get(2) {
-                if matches!(tokens.get(3), Some(Token::Symbol('"'))) {
-                    let total = num * 12.0 + maybe_inch;
-                    return Ok(Some(Content::DefaultConversion(
-                        total,
-                        Unit::Distance(Distance::Inch),
-                    )));
-                }
-            }
-            return Ok(Some(Content::DefaultConversion(
-                num,
-                Unit::Distance(Distance::Feet),
-            )));
-        }
         Ok(None)
     }
 
@@ -358,9 +749,12 @@ impl ContentClassifier {
     /// just gets evaluated immediately and that result used for the check,
     /// with the result already contained in the returned value.
     pub fn classify<'a>(&self, s: &'a str) -> Result<Option<Content>, ClassificationError> {
+        if !self.options.enabled {
+            return Ok(None);
+        }
         let result = self.classify_unchecked(s);
         match result {
-            Ok(Some(Content::DefaultConversion(_, unit))) => {
+            Ok(Some(Content::DefaultConversion(_, unit) | Content::Factor(unit))) => {
                 if self.options.is_allowed_unit(&unit) {
                     result
                 } else {
@@ -373,7 +767,7 @@ impl ContentClassifier {
                         return Ok(None);
                     }
                     if !a.valid_conversion(&b) {
-                        return Err(ClassificationError::InvalidConversion);
+                        return Err(ClassificationError::InvalidConversion(a, b));
                     }
                 }
                 if !self.options.is_allowed_unit(&b) {
@@ -412,6 +806,18 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn trivial_expression() {
+        let c = ContentClassifier::new(ContentOptions::default());
+        for trivial in ["42", "  42 ", "+7", "-7", "(5)", "( -(5) )", "1_000", "\t3"] {
+            assert!(!consider_for_basic_expression(trivial), "{trivial:?}");
+            assert!(matches!(c.classify(trivial), Ok(None)), "{trivial:?}");
+        }
+        for expression in ["-7 + 1", "(5) * 2", "5%", "5!", "2^3", "--5"] {
+            assert!(consider_for_basic_expression(expression), "{expression:?}");
+        }
+    }
+
     #[test]
     fn lead_expression() {
         let c = ContentClassifier::new(ContentOptions::default());
@@ -484,6 +890,307 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn duration_conversion() {
+        use crate::static_units::Duration::*;
+        let c = ContentClassifier::new(ContentOptions::default());
+        assert!(matches!(
+            c.classify("90 min to h"),
+            Ok(Some(Content::Conversion(
+                90.0,
+                Some(Unit::Duration(Minute)),
+                Unit::Duration(Hour)
+            )))
+        ));
+        assert!(matches!(
+            c.classify("90min"),
+            Ok(Some(Content::DefaultConversion(
+                90.0,
+                Unit::Duration(Minute)
+            )))
+        ));
+        assert!(matches!(
+            c.classify("3 days in h"),
+            Ok(Some(Content::Conversion(
+                3.0,
+                Some(Unit::Duration(Day)),
+                Unit::Duration(Hour)
+            )))
+        ));
+        assert!(matches!(
+            c.classify("5 as h"),
+            Ok(Some(Content::Conversion(5.0, None, Unit::Duration(Hour))))
+        ));
+    }
+
+    #[test]
+    fn base_conversion() {
+        use NumberBase::*;
+        let c = ContentClassifier::new(ContentOptions::default());
+        let converted = |s| match c.classify(s) {
+            Ok(Some(Content::BaseConversion(value, base))) => Some(base.format(value)),
+            _ => None,
+        };
+        assert_eq!(converted("255 to hex").as_deref(), Some("0xFF"));
+        assert_eq!(converted("0b1010 to dec").as_deref(), Some("10"));
+        assert_eq!(converted("0xff in bin").as_deref(), Some("0b11111111"));
+        assert_eq!(converted("0O17 to Decimal").as_deref(), Some("15"));
+        assert_eq!(converted("8 as oct").as_deref(), Some("0o10"));
+        assert_eq!(converted("-42 to hex").as_deref(), Some("-0x2A"));
+        assert!(matches!(
+            c.classify("1.5 to hex"),
+            Err(ClassificationError::InvalidInteger)
+        ));
+        assert!(matches!(
+            c.classify("0b102 to dec"),
+            Err(ClassificationError::InvalidInteger)
+        ));
+        assert!(matches!(c.classify("0x-1 to dec"), Err(_)));
+        // Searches that happen to end in a base
+        assert!(matches!(c.classify("trash in bin"), Ok(None)));
+        assert!(matches!(c.classify("photos as hex"), Ok(None)));
+        assert_eq!(
+            parse_integer("170141183460469231731687303715884105727"),
+            Some(i128::MAX)
+        );
+        assert_eq!(Decimal.format(0), "0");
+        // Units still go through the unit path
+        assert!(matches!(
+            c.classify("5 cm to inch"),
+            Ok(Some(Content::Conversion(..)))
+        ));
+    }
+
+    #[test]
+    fn pressure_conversion() {
+        use crate::static_units::{Pressure::*, SiPrefix};
+        let c = ContentClassifier::new(ContentOptions::default());
+        assert!(matches!(
+            c.classify("2 bar to psi"),
+            Ok(Some(Content::Conversion(
+                2.0,
+                Some(Unit::Pressure(Bar)),
+                Unit::Pressure(Psi)
+            )))
+        ));
+        assert!(matches!(
+            c.classify("1013mbar in kPa"),
+            Ok(Some(Content::Conversion(
+                1013.0,
+                Some(Unit::Pressure(Pascal(SiPrefix::Hecto))),
+                Unit::Pressure(Pascal(SiPrefix::Kilo))
+            )))
+        ));
+    }
+
+    #[test]
+    fn angle_conversion() {
+        use crate::static_units::Angle::*;
+        let c = ContentClassifier::new(ContentOptions::default());
+        assert!(matches!(
+            c.classify("180 deg to rad"),
+            Ok(Some(Content::Conversion(
+                180.0,
+                Some(Unit::Angle(Degree)),
+                Unit::Angle(Radian)
+            )))
+        ));
+        // π is only a constant of the calculator
+        assert!(matches!(
+            c.classify("2*pi"),
+            Ok(Some(Content::BasicExpression(_)))
+        ));
+        assert!(matches!(c.classify("pi rad to deg"), Ok(None)));
+    }
+
+    #[test]
+    fn data_size_conversion() {
+        use crate::static_units::DataSize::*;
+        let c = ContentClassifier::new(ContentOptions::default());
+        let conversion = |s| match c.classify(s) {
+            Ok(Some(Content::Conversion(amount, Some(from), to))) => {
+                Some((from, to, crate::units::convert(amount, from, to).unwrap()))
+            }
+            _ => None,
+        };
+        assert_eq!(
+            conversion("1 GiB to MB"),
+            Some((
+                Unit::DataSize(Gibibyte),
+                Unit::DataSize(Megabyte),
+                1073.741824
+            ))
+        );
+        assert_eq!(
+            conversion("500MB in MiB"),
+            Some((
+                Unit::DataSize(Megabyte),
+                Unit::DataSize(Mebibyte),
+                476.837158203125
+            ))
+        );
+        assert!(matches!(
+            c.classify("4 GiB"),
+            Ok(Some(Content::DefaultConversion(
+                4.0,
+                Unit::DataSize(Gibibyte)
+            )))
+        ));
+    }
+
+    #[test]
+    fn currency_before_number() {
+        use crate::units::{Currency, CURRENCIES, CURRENCY_CODES};
+        let [usd, eur, gbp] = ["usd", "eur", "gbp"].map(|code| {
+            let key = CURRENCIES.with_borrow_mut(|c| {
+                c.insert(Currency {
+                    full_name: String::new(),
+                    currency_code: code.to_string(),
+                    rate: 1.0,
+                })
+            });
+            CURRENCY_CODES.with_borrow_mut(|c| c.insert(code.to_string(), key));
+            Unit::Currency(key)
+        });
+        let c = ContentClassifier::new(ContentOptions::default());
+        let conversion = |s| match c.classify(s) {
+            Ok(Some(Content::Conversion(amount, from, to))) => Some((amount, from, to)),
+            _ => None,
+        };
+        assert_eq!(conversion("usd 100 to eur"), Some((100.0, Some(usd), eur)));
+        assert_eq!(conversion("USD100 in gbp"), Some((100.0, Some(usd), gbp)));
+        assert_eq!(conversion("eur50 in usd"), Some((50.0, Some(eur), usd)));
+        assert!(matches!(
+            c.classify("usd 100"),
+            Ok(Some(Content::DefaultConversion(100.0, unit))) if unit == usd
+        ));
+        // Only currencies can come first
+        assert!(matches!(c.classify("meter 5"), Ok(None) | Err(_)));
+        assert!(matches!(c.classify("cm 5 to inch"), Ok(None) | Err(_)));
+    }
+
+    #[test]
+    fn mismatched_dimensions() {
+        use crate::units::{Currency, CURRENCIES, CURRENCY_CODES};
+        let key = CURRENCIES.with_borrow_mut(|c| {
+            c.insert(Currency {
+                full_name: "US Dollar".to_string(),
+                currency_code: "usd".to_string(),
+                rate: 1.0,
+            })
+        });
+        CURRENCY_CODES.with_borrow_mut(|c| c.insert("usd".to_string(), key));
+        let usd = Unit::Currency(key);
+        const KG: Unit = Unit::Mass(Mass::Gram(SiPrefix::Kilo));
+        const CELSIUS: Unit = Unit::Temperature(Temperature::Celsius);
+        const KPH: Unit = Unit::Speed(Speed::kph());
+        let c = ContentClassifier::new(ContentOptions::default());
+        for (s, from, to) in [
+            ("5 usd to kg", usd, KG),
+            ("5 kg to usd", KG, usd),
+            ("usd 5 in cm", usd, CM),
+            ("5 inch to usd", INCH, usd),
+            ("20 C to kph", CELSIUS, KPH),
+            ("5 kg in cm", KG, CM),
+        ] {
+            assert!(
+                matches!(c.classify(s), Err(ClassificationError::InvalidConversion(a, b)) if a == from && b == to),
+                "{s}"
+            );
+        }
+        assert_eq!(
+            c.classify("5 usd to kg").unwrap_err().to_string(),
+            "Can't convert US Dollar (currency) to kg (mass)"
+        );
+    }
+
+    #[test]
+    fn factor_keyword() {
+        let c = ContentClassifier::new(ContentOptions::default());
+        assert!(matches!(
+            c.classify("factor cm"),
+            Ok(Some(Content::Factor(CM)))
+        ));
+        assert!(matches!(
+            c.classify("factor  inch "),
+            Ok(Some(Content::Factor(INCH)))
+        ));
+        assert!(matches!(
+            c.classify("factor km/h"),
+            Ok(Some(Content::Factor(Unit::Speed(speed)))) if speed == Speed::kph()
+        ));
+        assert!(matches!(
+            c.classify("factor centmeter"),
+            Err(ClassificationError::InvalidUnit)
+        ));
+        assert!(matches!(c.classify("factor"), Ok(None)));
+        let c = ContentClassifier::new(ContentOptions {
+            conversions: false,
+            ..ContentOptions::default()
+        });
+        assert!(matches!(c.classify("factor cm"), Ok(None)));
+    }
+
+    #[test]
+    fn feet_and_inches_notation() {
+        const FEET: Unit = Unit::Distance(Distance::Feet);
+        const METER: Unit = Unit::Distance(Distance::Meter(SiPrefix::None));
+        let c = ContentClassifier::new(ContentOptions::default());
+        assert!(matches!(
+            c.classify("5'11\""),
+            Ok(Some(Content::DefaultConversion(71.0, INCH)))
+        ));
+        assert!(matches!(
+            c.classify("6'"),
+            Ok(Some(Content::DefaultConversion(6.0, FEET)))
+        ));
+        assert!(matches!(
+            c.classify("6' to cm"),
+            Ok(Some(Content::Conversion(6.0, Some(FEET), CM)))
+        ));
+        assert!(matches!(
+            c.classify("5'11\" in m"),
+            Ok(Some(Content::Conversion(71.0, Some(INCH), METER)))
+        ));
+        assert!(matches!(
+            c.classify("5'11\" to"),
+            Err(ClassificationError::MissingToUnit)
+        ));
+        assert!(matches!(
+            c.classify("6' to centmeter"),
+            Err(ClassificationError::InvalidToUnit)
+        ));
+        assert!(matches!(
+            c.classify("6' to kg"),
+            Err(ClassificationError::InvalidConversion(..))
+        ));
+        assert!(matches!(c.classify("5'11"), Ok(None)));
+    }
+
+    #[test]
+    fn quotation_marks_in_text() {
+        let c = ContentClassifier::new(ContentOptions::default());
+        assert!(matches!(
+            c.classify("5'11\""),
+            Ok(Some(Content::DefaultConversion(71.0, INCH)))
+        ));
+        // Only the notation on its own is a conversion
+        for text in [
+            "5\" bay",
+            "5\" drive bay",
+            "don't",
+            "say \"hi\"",
+            "3'x5'",
+            "5'11\" tall",
+            "6' under",
+            "5'11\" to cm please",
+            "12\"",
+            "'5",
+        ] {
+            assert!(matches!(c.classify(text), Ok(None)), "{text}");
+        }
+    }
+
     #[test]
     fn path() {
         let c = ContentClassifier::new(ContentOptions::default());
@@ -512,20 +1219,195 @@ mod tests {
         assert!(matches!(http.classify("example"), Ok(None)));
         assert!(matches!(
             http.classify("https://example.com"),
-            Ok(Some(Content::URL))
+            Ok(Some(Content::URL(_)))
         ));
         assert!(matches!(http.classify("example.com"), Ok(None)));
         assert!(matches!(loose.classify("example"), Ok(None)));
         assert!(matches!(
             loose.classify("https://example.com"),
-            Ok(Some(Content::URL))
+            Ok(Some(Content::URL(_)))
         ));
         assert!(matches!(
             loose.classify("example.com"),
-            Ok(Some(Content::URL))
+            Ok(Some(Content::URL(_)))
         ));
     }
 
+    #[test]
+    fn url_punctuation() {
+        let c = ContentClassifier::new(ContentOptions::default());
+        let url = |s| match c.classify(s) {
+            Ok(Some(Content::URL(url))) => Some(url),
+            _ => None,
+        };
+        for (text, expected) in [
+            ("example.com", "example.com"),
+            ("example.com.", "example.com"),
+            ("check out example.com.", "example.com"),
+            ("(see example.com)", "example.com"),
+            ("example.com/path?", "example.com/path"),
+            ("example.com/v1.2/", "example.com/v1.2/"),
+            ("example.com!!", "example.com"),
+            ("example.com:", "example.com"),
+            ("example.com;", "example.com"),
+            ("example.com),", "example.com"),
+            (
+                "https://example.com/index.html.",
+                "https://example.com/index.html",
+            ),
+            (
+                "https://en.wikipedia.org/wiki/Rust_(programming_language)",
+                "https://en.wikipedia.org/wiki/Rust_(programming_language)",
+            ),
+            (
+                "(https://en.wikipedia.org/wiki/Rust_(programming_language))",
+                "https://en.wikipedia.org/wiki/Rust_(programming_language)",
+            ),
+            (
+                "https://en.wikipedia.org/wiki/Rust_(programming_language).",
+                "https://en.wikipedia.org/wiki/Rust_(programming_language)",
+            ),
+            ("https://example.com/?q=a.b", "https://example.com/?q=a.b"),
+            ("ftp://example.com/file.txt;", "ftp://example.com/file.txt"),
+        ] {
+            assert_eq!(url(text).as_deref(), Some(expected), "{text}");
+        }
+        for (url, expected) in [
+            ("a.b)", "a.b"),
+            ("a.b]", "a.b"),
+            ("a.b/[x]", "a.b/[x]"),
+            ("a.b/{x}}", "a.b/{x}"),
+            ("a.b/(x))).", "a.b/(x)"),
+            ("a.b\"", "a.b"),
+            ("a.b'", "a.b"),
+            ("...", ""),
+        ] {
+            assert_eq!(trim_url(url), expected, "{url}");
+        }
+    }
+
+    #[test]
+    fn calc_definitions() {
+        let c = ContentClassifier::new(ContentOptions {
+            calc: CalcDefinitions {
+                constants: [("rate".to_string(), 0.5)].into_iter().collect(),
+                functions: [("double".to_string(), "x * 2".to_string())]
+                    .into_iter()
+                    .collect(),
+            },
+            ..ContentOptions::default()
+        });
+        assert!(matches!(
+            c.classify("double(3) * rate"),
+            Ok(Some(Content::BasicExpression(3.0)))
+        ));
+        assert!(matches!(
+            c.classify("= double(rate)"),
+            Ok(Some(Content::LeadExpression(Ok(1.0))))
+        ));
+    }
+
+    #[test]
+    fn clock() {
+        let c = ContentClassifier::new(ContentOptions::default());
+        assert!(matches!(
+            c.classify("time in utc"),
+            Ok(Some(Content::Clock(ClockQuery {
+                date: false,
+                utc: true,
+                ..
+            })))
+        ));
+        assert!(matches!(
+            c.classify("date"),
+            Ok(Some(Content::Clock(ClockQuery { date: true, .. })))
+        ));
+        assert!(matches!(c.classify("timeshift"), Ok(None)));
+    }
+
+    #[test]
+    fn date_calc() {
+        use DateOrder::*;
+        let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let date = |y, m, d| Some(DateCalc::Date(NaiveDate::from_ymd_opt(y, m, d).unwrap()));
+        let parse = |s| DateCalc::parse(s, today, DayMonthYear);
+        assert_eq!(parse("week"), Some(DateCalc::Week(9)));
+        assert_eq!(parse("days until 2024-12-24"), Some(DateCalc::Days(298)));
+        assert_eq!(parse("days since 2024-02-28"), Some(DateCalc::Days(2)));
+        assert_eq!(parse("2024-03-01 + 45 days"), date(2024, 4, 15));
+        assert_eq!(parse("today - 2 weeks"), date(2024, 2, 16));
+        assert_eq!(parse("31.1.2024 + 1 month"), date(2024, 2, 29));
+        assert_eq!(parse("1.3.24 + 1 year"), date(2025, 3, 1));
+        assert_eq!(parse("days until 24.12."), Some(DateCalc::Days(298)));
+        assert!(matches!(
+            parse("days until 2024-02-30"),
+            Some(DateCalc::Hint(_))
+        ));
+        assert!(matches!(
+            parse("13/31/2024 + 1 day"),
+            Some(DateCalc::Hint(_))
+        ));
+        assert_eq!(parse("1 + 2 days"), None);
+        assert_eq!(parse("weekly"), None);
+        assert_eq!(
+            DateCalc::parse("12/24/2024 - 1 day", today, MonthDayYear),
+            date(2024, 12, 23)
+        );
+        assert_eq!(
+            DateCalc::parse("2024/12/24 - 1 day", today, YearMonthDay),
+            date(2024, 12, 23)
+        );
+    }
+
+    #[test]
+    fn multi_word_searches() {
+        let c = ContentClassifier::new(ContentOptions::default());
+        for text in [
+            "weather in berlin",
+            "open the pod bay doors",
+            "how to draw",
+            "text editor for markdown",
+            "cm to inch please",
+        ] {
+            assert!(is_multi_word_search(text), "{text:?}");
+            assert!(matches!(c.classify(text), Ok(None)), "{text:?}");
+        }
+        // Keywords and numbers still go through
+        assert!(matches!(
+            c.classify("cm to inch"),
+            Ok(Some(Content::Conversion(..)))
+        ));
+        assert!(matches!(
+            c.classify("5 cm to inch"),
+            Ok(Some(Content::Conversion(..)))
+        ));
+        assert!(matches!(
+            c.classify("time in utc"),
+            Ok(Some(Content::Clock(_)))
+        ));
+        assert!(matches!(
+            c.classify("days until 2024-12-24"),
+            Ok(Some(Content::DateCalc(_)))
+        ));
+        assert!(matches!(
+            c.classify("$ ls -la dir"),
+            Ok(Some(Content::Command))
+        ));
+        assert!(!is_multi_word_search("firefox"));
+        assert!(!is_multi_word_search("text editor"));
+    }
+
+    #[test]
+    fn date_order_from_format() {
+        assert_eq!(DateOrder::from_format("%m/%d/%y"), DateOrder::MonthDayYear);
+        assert_eq!(DateOrder::from_format("%d.%m.%Y"), DateOrder::DayMonthYear);
+        assert_eq!(
+            DateOrder::from_format("%Y年%m月%d日"),
+            DateOrder::YearMonthDay
+        );
+        assert_eq!(DateOrder::from_format("%F"), DateOrder::YearMonthDay);
+    }
+
     #[test]
     fn command() {
         let c = ContentClassifier::new(ContentOptions::default());
@@ -538,5 +1420,105 @@ mod tests {
             c.classify("$:(){ :|:& };:"),
             Ok(Some(Content::Command))
         ));
+        let restricted = ContentClassifier::new(ContentOptions {
+            commands: false,
+            ..Default::default()
+        });
+        assert!(!matches!(
+            restricted.classify("$ rm -rf /"),
+            Ok(Some(Content::Command))
+        ));
+    }
+
+    #[test]
+    fn command_prefix() {
+        let c = ContentClassifier::new(ContentOptions::default());
+        for (text, command) in [
+            ("$ htop", "htop"),
+            ("$ ls -la /tmp", "ls -la /tmp"),
+            ("$git status", "git status"),
+            (" $  echo $HOME ", "echo $HOME"),
+        ] {
+            assert_eq!(command_line(text), Some(command), "{text:?}");
+            assert!(matches!(c.classify(text), Ok(Some(Content::Command))));
+        }
+        assert_eq!(command_line("echo $HOME"), None);
+        for text in ["$", "$ ", " $   "] {
+            assert_eq!(command_line(text), Some(""), "{text:?}");
+            assert!(matches!(c.classify(text), Ok(Some(Content::EmptyCommand))));
+        }
+        assert!(matches!(c.classify("$ ls"), Ok(Some(Content::Command))));
+    }
+
+    #[test]
+    fn disabled_categories() {
+        const INPUTS: [&str; 6] = ["1+2", "=2*3", "5 cm to inch", "/tmp", "example.com", "$ ls"];
+        let categories = |options: ContentOptions| {
+            let c = ContentClassifier::new(options);
+            INPUTS.map(|input| match c.classify(input) {
+                Ok(Some(Content::BasicExpression(_) | Content::LeadExpression(_))) => "calculator",
+                Ok(Some(Content::Conversion(..))) => "conversions",
+                Ok(Some(Content::Path)) => "paths",
+                Ok(Some(Content::URL(_))) => "urls",
+                Ok(Some(Content::Command)) => "commands",
+                _ => "",
+            })
+        };
+        let all = categories(ContentOptions::default());
+        assert_eq!(
+            all,
+            [
+                "calculator",
+                "calculator",
+                "conversions",
+                "paths",
+                "urls",
+                "commands"
+            ]
+        );
+        let without = |category: &str, options: ContentOptions| {
+            let expected = all.map(|c| if c == category { "" } else { c });
+            assert_eq!(categories(options), expected, "without {category}");
+        };
+        without(
+            "calculator",
+            ContentOptions {
+                calculator: false,
+                ..Default::default()
+            },
+        );
+        without(
+            "conversions",
+            ContentOptions {
+                conversions: false,
+                ..Default::default()
+            },
+        );
+        without(
+            "paths",
+            ContentOptions {
+                paths: false,
+                ..Default::default()
+            },
+        );
+        without(
+            "urls",
+            ContentOptions {
+                url_mode: UrlMode::None,
+                ..Default::default()
+            },
+        );
+        without(
+            "commands",
+            ContentOptions {
+                commands: false,
+                ..Default::default()
+            },
+        );
+        let disabled = ContentOptions {
+            enabled: false,
+            ..Default::default()
+        };
+        assert_eq!(categories(disabled), [""; 6]);
     }
 }
@@ -0,0 +1,120 @@
+use crate::{
+    cache::DesktopEntryCache,
+    config::Config,
+    window_matching::{match_window, WindowClass},
+    x::{Display, Monitor, Window},
+};
+use clap::Args;
+
+#[derive(Args, Debug, Default)]
+pub struct DiagArgs {
+    /// List the open windows with their WM_CLASS and the desktop entry they
+    /// are mapped to.
+    #[arg(long)]
+    window_matching: bool,
+    /// List the desktop entries whose icon is not in the icon theme.
+    #[arg(long)]
+    icons: bool,
+}
+
+/// Prints the selected diagnostics, returns whether it was successful.
+pub fn diag(args: &DiagArgs, cache: &DesktopEntryCache, config: &Config) -> bool {
+    if !args.window_matching && !args.icons {
+        eprintln!("Nothing to show, select --window-matching or --icons");
+        return false;
+    }
+    if args.icons {
+        missing_icons(cache);
+    }
+    !args.window_matching || window_matching(cache, config)
+}
+
+fn missing_icons(cache: &DesktopEntryCache) {
+    let missing = cache.missing_icons();
+    if missing.is_empty() {
+        println!("All icons were found");
+    }
+    for (file_name, icon) in missing {
+        println!("{file_name}: icon \"{icon}\" not found");
+    }
+}
+
+fn window_matching(cache: &DesktopEntryCache, config: &Config) -> bool {
+    let mut display = match Display::connect(None) {
+        Ok(display) => display,
+        Err(error) => {
+            eprintln!("{error}");
+            return false;
+        }
+    };
+    let clients = display.client_list();
+    if clients.is_empty() {
+        eprintln!("No windows found, the window manager may not support _NET_CLIENT_LIST");
+    }
+    for handle in clients {
+        let Some((instance, class)) = Window::from_handle(&display, handle).class_hint() else {
+            println!("{handle:#x}: no WM_CLASS");
+            continue;
+        };
+        let window = WindowClass { instance, class };
+        let entry = match match_window(&window, cache, &config.window_class_overrides) {
+            Some(id) => cache.get_entry(id).file_name.as_str(),
+            None => "none",
+        };
+        let overridden = config.window_class_overrides.contains_key(&window.class)
+            || config.window_class_overrides.contains_key(&window.instance);
+        println!(
+            "{handle:#x}: \"{}\", \"{}\" -> {entry}{}",
+            window.instance,
+            window.class,
+            if overridden { " (override)" } else { "" }
+        );
+    }
+    display.close();
+    true
+}
+
+fn describe_monitor(monitor: &Monitor, config: &Config, main: bool) -> String {
+    let (x_percent, y_percent) = config.position.resolve(&monitor.name);
+    let name = if monitor.name.is_empty() {
+        "(no name)"
+    } else {
+        &monitor.name
+    };
+    format!(
+        "{name}: {}x{}+{}+{}, placed at {x_percent}% {y_percent}%{}{}",
+        monitor.width,
+        monitor.height,
+        monitor.x,
+        monitor.y,
+        if monitor.primary { ", primary" } else { "" },
+        if main {
+            ", the launcher opens here"
+        } else {
+            ""
+        }
+    )
+}
+
+/// Prints the monitors and where the window goes on each, returns whether
+/// it was successful.
+pub fn print_monitors(config: &Config) -> bool {
+    let mut display = match Display::connect(None) {
+        Ok(display) => display,
+        Err(error) => {
+            eprintln!("{error}");
+            return false;
+        }
+    };
+    let main = display.main_monitor();
+    let monitors = display.monitors();
+    if monitors.is_empty() {
+        println!("RandR 1.5 is not available, monitors have no names");
+        println!("{}", describe_monitor(&main, config, true));
+    }
+    for monitor in &monitors {
+        println!("{}", describe_monitor(monitor, config, *monitor == main));
+    }
+    display.close();
+    true
+}
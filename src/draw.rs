@@ -7,10 +7,40 @@ use cairo::{Context, LinearGradient, Operator, Surface};
 use cairo_sys::cairo_xlib_surface_create;
 use pango::{EllipsizeMode, FontDescription, Layout};
 use pangocairo::functions::{create_layout, show_layout};
+use std::sync::atomic::{AtomicBool, Ordering};
 use x11::xlib::{
     Drawable, XCopyArea, XCreateGC, XCreatePixmap, XFreeGC, XFreePixmap, XVisualInfo, GC,
 };
 
+/// Whether translucent colors are flattened, see `set_opaque_colors`.
+static OPAQUE_COLORS: AtomicBool = AtomicBool::new(false);
+
+/// What translucent colors are composed over when they are flattened, this is
+/// what most X servers show behind windows without a compositor.
+const OPAQUE_BACKDROP: Color = Color::new(0, 0, 0, 255);
+
+/// Without a compositor the alpha channel isn't blended with what's behind
+/// the window but some drivers still show it as dark fringes, so when this is
+/// enabled colors that replace the window content are made opaque.  Colors
+/// drawn on top of other content with `blend` are not affected.
+pub fn set_opaque_colors(opaque: bool) {
+    OPAQUE_COLORS.store(opaque, Ordering::Relaxed);
+}
+
+/// Sets `color` as the source, flattening it if needed.
+fn set_source_color(context: &Context, color: Color) {
+    let (r, g, b, a) = source_color(context, color).float_parts();
+    context.set_source_rgba(r, g, b, a);
+}
+
+fn source_color(context: &Context, color: Color) -> Color {
+    if OPAQUE_COLORS.load(Ordering::Relaxed) && context.operator() == Operator::Source {
+        color.flatten(OPAQUE_BACKDROP)
+    } else {
+        color
+    }
+}
+
 pub struct DrawingContext {
     pixmap: Drawable,
     gc: GC,
@@ -73,8 +103,7 @@ impl DrawingContext {
     }
 
     pub fn set_color(&self, color: Color) {
-        let (r, g, b, a) = color.float_parts();
-        self.context.set_source_rgba(r, g, b, a);
+        set_source_color(&self.context, color);
     }
 
     pub fn rect(&mut self, rect: &Rectangle) -> ShapeBuilder {
@@ -111,16 +140,21 @@ impl DrawingContext {
             .unwrap()
     }
 
+    /// Draws the SVG as a mask for `color`.  The mask is only rendered once at
+    /// the origin, so the same SVG can be drawn at different positions.
     pub fn colored_svg(&mut self, svg: &mut Svg, color: Color, rect: &Rectangle) {
         if svg.pattern.is_none() {
             self.context.save().unwrap();
             self.context.push_group();
-            self.svg(svg, rect);
+            self.svg(svg, &Rectangle::new(0, 0, rect.width, rect.height));
             svg.pattern = Some(self.context.pop_group().unwrap());
             self.context.restore().unwrap();
         }
+        self.context.save().unwrap();
+        self.context.translate(rect.x as f64, rect.y as f64);
         self.set_color(color);
         self.context.mask(svg.pattern.as_ref().unwrap()).unwrap();
+        self.context.restore().unwrap();
     }
 
     pub fn fill(&mut self, color: Color) {
@@ -129,40 +163,37 @@ impl DrawingContext {
     }
 
     pub fn render(&self, window: Window, rect: &Rectangle) {
-        self.surface.flush();
-        unsafe {
-            XCopyArea(
-                self.display.as_raw(),
-                self.pixmap,
-                window.handle(),
-                self.gc,
-                rect.x,
-                rect.y,
-                rect.width,
-                rect.height,
-                rect.x,
-                rect.y,
-            );
-        }
-        self.display.flush();
-        self.display.sync(false);
+        self.render_at(window, rect, (rect.x, rect.y));
     }
 
     pub fn render_to_00(&self, window: Window, rect: &Rectangle) {
+        self.render_at(window, rect, (0, 0));
+    }
+
+    /// Copies `rect` of the pixmap to `dest` in the window.
+    pub fn render_at(&self, window: Window, rect: &Rectangle, dest: (i32, i32)) {
+        self.render_areas(window, &[(*rect, dest)]);
+    }
+
+    /// Copies each rectangle of the pixmap to its destination in the window,
+    /// waiting for the server only once.
+    pub fn render_areas(&self, window: Window, areas: &[(Rectangle, (i32, i32))]) {
         self.surface.flush();
-        unsafe {
-            XCopyArea(
-                self.display.as_raw(),
-                self.pixmap,
-                window.handle(),
-                self.gc,
-                rect.x,
-                rect.y,
-                rect.width,
-                rect.height,
-                0,
-                0,
-            );
+        for (rect, dest) in areas {
+            unsafe {
+                XCopyArea(
+                    self.display.as_raw(),
+                    self.pixmap,
+                    window.handle(),
+                    self.gc,
+                    rect.x,
+                    rect.y,
+                    rect.width,
+                    rect.height,
+                    dest.0,
+                    dest.1,
+                );
+            }
         }
         self.display.flush();
         self.display.sync(false);
@@ -173,7 +204,7 @@ pub enum ShapeKind {
     Rectangle,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Color {
     pub red: u8,
     pub green: u8,
@@ -229,6 +260,19 @@ impl Color {
         this
     }
 
+    /// The opaque color of drawing this color over `backdrop`.
+    pub const fn flatten(&self, backdrop: Color) -> Self {
+        const fn mix(top: u8, bottom: u8, alpha: u8) -> u8 {
+            ((top as u16 * alpha as u16 + bottom as u16 * (255 - alpha as u16) + 127) / 255) as u8
+        }
+        Self {
+            red: mix(self.red, backdrop.red, self.alpha),
+            green: mix(self.green, backdrop.green, self.alpha),
+            blue: mix(self.blue, backdrop.blue, self.alpha),
+            alpha: 255,
+        }
+    }
+
     fn float_parts(&self) -> (f64, f64, f64, f64) {
         (
             self.red as f64 / 255.0,
@@ -308,7 +352,6 @@ impl<'a> ShapeBuilder<'a> {
         self
     }
 
-    #[allow(dead_code)]
     pub fn gradient(&mut self, spec: GradientSpec) -> &mut Self {
         self.color = ColorKind::Gradient(spec);
         self
@@ -385,20 +428,20 @@ impl<'a> ShapeBuilder<'a> {
     fn set_color(&self, color: &ColorKind) {
         match color {
             ColorKind::None => {}
-            ColorKind::Solid(ref color) => {
-                let (r, g, b, a) = color.float_parts();
-                self.context.set_source_rgba(r, g, b, a);
+            ColorKind::Solid(color) => {
+                set_source_color(self.context, *color);
             }
             ColorKind::Gradient(ref spec) => {
+                // The points are relative to the bounding box.
                 let gradient = LinearGradient::new(
-                    spec.start_point.0,
-                    spec.start_point.1,
-                    spec.end_point.0 * self.width,
-                    spec.end_point.1 * self.height,
+                    self.x + spec.start_point.0 * self.width,
+                    self.y + spec.start_point.1 * self.height,
+                    self.x + spec.end_point.0 * self.width,
+                    self.y + spec.end_point.1 * self.height,
                 );
-                let (r, g, b, a) = spec.start.float_parts();
+                let (r, g, b, a) = source_color(self.context, spec.start).float_parts();
                 gradient.add_color_stop_rgba(0.0, r, g, b, a);
-                let (r, g, b, a) = spec.end.float_parts();
+                let (r, g, b, a) = source_color(self.context, spec.end).float_parts();
                 gradient.add_color_stop_rgba(1.0, r, g, b, a);
                 self.context.set_source(gradient).unwrap();
             }
@@ -472,3 +515,23 @@ impl<'a> TextBuilder<'a> {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flatten() {
+        let black = Color::new(0, 0, 0, 255);
+        let white = Color::new(255, 255, 255, 255);
+        let background = Color::new(44, 44, 46, 204);
+        assert_eq!(background.flatten(black), Color::new(35, 35, 37, 255));
+        assert_eq!(background.flatten(white), Color::new(86, 86, 88, 255));
+        assert_eq!(white.flatten(black), white);
+        assert_eq!(white.with_alpha(0).flatten(black), black);
+        assert_eq!(
+            Color::new(10, 132, 255, 96).flatten(Color::new(20, 40, 60, 128)),
+            Color::new(16, 75, 133, 255)
+        );
+    }
+}
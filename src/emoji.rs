@@ -0,0 +1,209 @@
+//! Picking emoji by name or keyword, by typing the emoji prefix followed by a
+//! search like `:fire`.
+//!
+//! The table in `res/emoji.txt` is only parsed when the picker is first used.
+
+use crate::{
+    cache::DesktopEntryCache,
+    list_view::Render,
+    search::highlight_match,
+    text::{escape_markup, fold},
+    ui::colors,
+};
+use std::sync::OnceLock;
+
+static TABLE: &str = include_str!("../res/emoji.txt");
+
+/// The default `emoji_prefix`.
+pub const DEFAULT_PREFIX: &str = ":";
+
+/// At most this many emoji are listed, the rest only makes scrolling slower.
+const MAX_RESULTS: usize = 200;
+
+struct Emoji {
+    glyph: &'static str,
+    name: &'static str,
+    keywords: &'static str,
+    folded_name: String,
+    folded_keywords: String,
+}
+
+fn parse(table: &'static str) -> Vec<Emoji> {
+    table
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let glyph = fields.next()?;
+            let name = fields.next()?;
+            let keywords = fields.next().unwrap_or("");
+            Some(Emoji {
+                glyph,
+                name,
+                keywords,
+                folded_name: fold(name),
+                folded_keywords: fold(keywords),
+            })
+        })
+        .collect()
+}
+
+fn table() -> &'static [Emoji] {
+    static EMOJI: OnceLock<Vec<Emoji>> = OnceLock::new();
+    EMOJI.get_or_init(|| parse(TABLE))
+}
+
+/// How well an emoji matches, worst first.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum MatchKind {
+    /// Some of the words only start a keyword.
+    Keyword,
+    /// Each word starts a word of the name.
+    NameWords,
+    /// The name starts with the search.
+    NamePrefix,
+    Name,
+}
+
+fn starts_word(text: &str, word: &str) -> bool {
+    text.split(' ').any(|text_word| text_word.starts_with(word))
+}
+
+fn match_kind(emoji: &Emoji, search: &str, words: &[&str]) -> Option<MatchKind> {
+    if emoji.folded_name == search {
+        Some(MatchKind::Name)
+    } else if emoji.folded_name.starts_with(search) {
+        Some(MatchKind::NamePrefix)
+    } else if words
+        .iter()
+        .all(|word| starts_word(&emoji.folded_name, word))
+    {
+        Some(MatchKind::NameWords)
+    } else if words.iter().all(|word| {
+        starts_word(&emoji.folded_name, word) || starts_word(&emoji.folded_keywords, word)
+    }) {
+        Some(MatchKind::Keyword)
+    } else {
+        None
+    }
+}
+
+pub struct EmojiMatch {
+    emoji: &'static Emoji,
+    kind: MatchKind,
+}
+
+impl EmojiMatch {
+    pub fn glyph(&self) -> &'static str {
+        self.emoji.glyph
+    }
+}
+
+impl Render for EmojiMatch {
+    fn markup(&self, search: &str, _cache: &DesktopEntryCache) -> String {
+        // The list font likely has no emoji, this makes Pango fall back to an
+        // emoji font even if the list font's family has a symbol font.
+        let mut markup = format!(
+            "<span font_family=\"emoji\">{}</span>  {}",
+            self.emoji.glyph,
+            highlight_match(self.emoji.name, search)
+        );
+        if self.kind == MatchKind::Keyword {
+            markup.push_str(&format!(
+                " <span color=\"{}\">{}</span>",
+                colors::list_match_name(),
+                escape_markup(self.emoji.keywords)
+            ));
+        }
+        markup
+    }
+
+    fn plain_text(&self, _cache: &DesktopEntryCache) -> String {
+        format!("{}  {}", self.emoji.glyph, self.emoji.name)
+    }
+
+    fn tooltip(&self, _cache: &DesktopEntryCache) -> Option<String> {
+        Some(format!(
+            "<b>{}</b>\n{}",
+            escape_markup(self.emoji.name),
+            escape_markup(self.emoji.keywords)
+        ))
+    }
+
+    fn key(&self, _cache: &DesktopEntryCache) -> Option<String> {
+        Some(self.emoji.glyph.to_string())
+    }
+}
+
+/// The search for the picker if `text` starts with `prefix`.  An empty
+/// prefix disables the picker.
+pub fn query<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    if prefix.is_empty() {
+        return None;
+    }
+    text.strip_prefix(prefix).map(str::trim)
+}
+
+/// The emoji whose name or keywords start with each word of `search`, the
+/// best matches first and otherwise in the order of the table.  An empty
+/// search lists the first ones.
+pub fn search(search: &str) -> Vec<EmojiMatch> {
+    let search = fold(search);
+    let words = search.split_whitespace().collect::<Vec<_>>();
+    let mut matches = table()
+        .iter()
+        .filter_map(|emoji| {
+            Some(EmojiMatch {
+                emoji,
+                kind: match_kind(emoji, &search, &words)?,
+            })
+        })
+        .collect::<Vec<_>>();
+    // Stable, so equal matches stay in the order of the table
+    matches.sort_by_key(|match_| std::cmp::Reverse(match_.kind));
+    matches.truncate(MAX_RESULTS);
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyphs(search_text: &str) -> Vec<&'static str> {
+        search(search_text).iter().map(EmojiMatch::glyph).collect()
+    }
+
+    #[test]
+    fn table_is_complete() {
+        let table = table();
+        assert!(table.len() > 1000);
+        assert!(table
+            .iter()
+            .all(|emoji| !emoji.glyph.is_empty() && !emoji.name.is_empty()));
+        assert_eq!(table[0].glyph, "😀");
+    }
+
+    #[test]
+    fn searching() {
+        assert_eq!(query(":fire", ":"), Some("fire"));
+        assert_eq!(query(":  thumbs up ", ":"), Some("thumbs up"));
+        assert_eq!(query("fire", ":"), None);
+        assert_eq!(query(":fire", ""), None);
+        assert_eq!(query("emoji fire", "emoji "), Some("fire"));
+
+        // The exact name wins over names starting with it
+        assert_eq!(glyphs("fire")[0], "🔥");
+        assert_eq!(glyphs("FIRE")[0], "🔥");
+        assert!(glyphs("fire").contains(&"🚒"));
+        // Words can be in any order and incomplete
+        assert_eq!(glyphs("up thumb")[0], "👍");
+        // Shortcodes are keywords
+        assert_eq!(glyphs("tada")[0], "🎉");
+        let tada = search("tada");
+        let cache = DesktopEntryCache::from_entries(Vec::new());
+        assert!(tada[0].markup("tada", &cache).contains("tada"));
+        assert!(search("nothing like this").is_empty());
+        assert_eq!(search("").len(), MAX_RESULTS);
+        assert_eq!(glyphs("")[0], "😀");
+    }
+}
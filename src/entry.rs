@@ -1,16 +1,114 @@
 use crate::app::{send_signal, Signal};
 use crate::config::Config;
-use crate::draw::{Color, ColorKind, DrawingContext, GradientSpec};
+use crate::draw::{ColorKind, DrawingContext, GradientSpec};
+use crate::fonts;
 use crate::input::{Key, KeyEvent};
 use crate::layout::{EntryLayout, Rectangle};
+use crate::list_geometry::Navigation;
 use crate::res::*;
-use crate::ui::colors;
+use crate::ui::{colors, FocusDirection};
 use crate::util::{copy, paste};
 use crate::x::{Display, Window};
-use pango::{EllipsizeMode, FontDescription};
+use pango::EllipsizeMode;
 use std::sync::mpsc::Sender;
 use x11::xlib::*;
 
+/// Inserts `inserted` at the cursor, replacing the selection if there is one,
+/// and returns the cursor position after the inserted text.
+fn insert(text: &mut Vec<char>, cursor: usize, selection: Option<usize>, inserted: &str) -> usize {
+    let start = selection.map_or(cursor, |sel| sel.min(cursor));
+    let end = selection.map_or(cursor, |sel| sel.max(cursor));
+    let len_before = text.len();
+    text.splice(start..end, inserted.chars());
+    end + text.len() - len_before
+}
+
+/// The start of `pasted` that fits in `max_len` characters, and whether that's
+/// not all of it.
+fn paste_head(pasted: &str, max_len: usize) -> (&str, bool) {
+    match pasted.char_indices().nth(max_len) {
+        Some((end, _)) => (&pasted[..end], true),
+        None => (pasted, false),
+    }
+}
+
+/// Replaces the characters after the cursor with `typed`, appending what goes
+/// past the end, and returns the cursor position after it.
+fn overwrite(text: &mut Vec<char>, cursor: usize, typed: &str) -> usize {
+    let len_before = text.len();
+    let typed_len = typed.chars().count();
+    let end = (cursor + typed_len).min(len_before);
+    text.splice(cursor..end, typed.chars());
+    cursor + typed_len
+}
+
+/// Swaps the characters before and at the cursor and moves it past both, like
+/// readline's Ctrl+T.  At the end of the text the last two are swapped.
+/// Returns the new cursor position, `None` if there is nothing to swap.
+fn transpose(text: &mut [char], cursor: usize) -> Option<usize> {
+    if cursor == 0 || text.len() < 2 {
+        return None;
+    }
+    let right = cursor.min(text.len() - 1);
+    text.swap(right - 1, right);
+    Some(right + 1)
+}
+
+/// Removes everything from the cursor to the end of the next word, returns
+/// whether anything was removed.
+fn delete_word_forward(text: &mut Vec<char>, cursor: usize) -> bool {
+    let end = word_boundary(text, cursor, false);
+    text.drain(cursor..end);
+    end > cursor
+}
+
+/// Whether the character at `pos` is part of a word: alphanumeric, or a run
+/// of `-` and `_` next to an alphanumeric character so flags like
+/// `--release` and names like `snake_case` are single words.
+fn is_word_char(text: &[char], pos: usize) -> bool {
+    let is_joiner = |c: &char| matches!(c, '-' | '_');
+    if text[pos].is_alphanumeric() {
+        return true;
+    }
+    if !is_joiner(&text[pos]) {
+        return false;
+    }
+    let start = text[..pos]
+        .iter()
+        .rposition(|c| !is_joiner(c))
+        .map_or(0, |before| before + 1);
+    let end = text[pos..]
+        .iter()
+        .position(|c| !is_joiner(c))
+        .map_or(text.len(), |after| pos + after);
+    (start > 0 && text[start - 1].is_alphanumeric())
+        || (end < text.len() && text[end].is_alphanumeric())
+}
+
+/// Where Ctrl+Left or Ctrl+Right move the cursor to from `cursor`, like
+/// readline: skip everything that isn't part of a word, then the word.  See
+/// `is_word_char` for what words are made of.
+fn word_boundary(text: &[char], cursor: usize, left: bool) -> usize {
+    let is_word = |pos: usize| is_word_char(text, pos);
+    let mut pos = cursor.min(text.len());
+    if left {
+        while pos > 0 && !is_word(pos - 1) {
+            pos -= 1;
+        }
+        while pos > 0 && is_word(pos - 1) {
+            pos -= 1;
+        }
+    } else {
+        while pos < text.len() && !is_word(pos) {
+            pos += 1;
+        }
+        while pos < text.len() && is_word(pos) {
+            pos += 1;
+        }
+    }
+    pos
+}
+
 pub struct Entry {
     pub window: Window,
     text: Vec<char>,
@@ -25,6 +123,11 @@ pub struct Entry {
     pub display: Display,
     signal_sender: Sender<Signal>,
     pub is_focused: bool,
+    /// Up and Down move the list selection instead of the focus.
+    navigate_list: bool,
+    /// Typed characters replace the ones after the cursor, toggled by Insert.
+    overwrite: bool,
+    max_paste_length: usize,
 }
 
 impl Entry {
@@ -42,7 +145,7 @@ impl Entry {
                 attributes
                     .colormap(colormap)
                     .border_pixel(0)
-                    .background_pixel(colors::BACKGROUND.pack());
+                    .background_pixel(colors::background().pack());
             })
             .visual(visual_info.visual)
             .depth(visual_info.depth)
@@ -53,7 +156,7 @@ impl Entry {
             layout.window.height,
             visual_info,
         );
-        dc.set_font(&FontDescription::from_string(&config.entry_font));
+        dc.set_font(&fonts::load(&config.entry_font));
         Self {
             window,
             text: Vec::new(),
@@ -66,6 +169,9 @@ impl Entry {
             display: *display,
             signal_sender,
             is_focused: true,
+            navigate_list: config.navigate_list_from_entry,
+            overwrite: false,
+            max_paste_length: config.max_paste_length,
         }
     }
 
@@ -73,6 +179,16 @@ impl Entry {
         self.text.iter().collect()
     }
 
+    /// Empties the text, the app sees this like any other edit.
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor_position = 0;
+        self.selection = None;
+        self.overwrite = false;
+        self.text_changed(true);
+        self.cursor_changed();
+    }
+
     fn selection_range(&self) -> Option<(usize, usize)> {
         self.selection.map(|sel| {
             let start = usize::min(sel, self.cursor_position);
@@ -93,19 +209,19 @@ impl Entry {
     }
 
     fn draw_box(&mut self) {
-        const GRADIENT_TOP: Color = colors::ENTRY_FOCUSED_BORDER.scale(105);
-        const GRADIENT_BOT: Color = colors::ENTRY_FOCUSED_BORDER.scale(95);
-        self.dc.fill(colors::BACKGROUND);
+        let gradient_top = colors::entry_focused_border().scale(105);
+        let gradient_bot = colors::entry_focused_border().scale(95);
+        self.dc.fill(colors::background());
         self.dc
             .rect(&self.layout.box_)
-            .color(colors::ENTRY_BACKGROUND)
+            .color(colors::entry_background())
             .corner_radius(self.layout.corner_radius)
             .stroke(
                 self.layout.stroke,
                 if self.is_focused {
-                    ColorKind::Gradient(GradientSpec::new_vertical(GRADIENT_TOP, GRADIENT_BOT))
+                    ColorKind::Gradient(GradientSpec::new_vertical(gradient_top, gradient_bot))
                 } else {
-                    ColorKind::Solid(colors::ENTRY_NORMAL_BORDER)
+                    ColorKind::Solid(colors::entry_normal_border())
                 },
             )
             .draw();
@@ -114,12 +230,12 @@ impl Entry {
     pub fn draw(&mut self) {
         self.draw_box();
         self.dc
-            .colored_svg(&mut self.icon, colors::TEXT, &self.layout.icon);
+            .colored_svg(&mut self.icon, colors::text(), &self.layout.icon);
         let text = if self.text.is_empty() {
-            self.dc.set_color(colors::ENTRY_PLACEHOLDER_TEXT);
+            self.dc.set_color(colors::entry_placeholder_text());
             "Search".to_string()
         } else {
-            self.dc.set_color(colors::TEXT);
+            self.dc.set_color(colors::text());
             self.text()
         };
         self.dc
@@ -130,38 +246,61 @@ impl Entry {
         self.dc.render(self.window, &self.layout.window);
     }
 
+    /// Positions of the character boundaries, one per character plus the
+    /// end, so every cursor position has one even inside a cluster.
     fn update_character_positions(&mut self) {
         self.character_positions.clear();
-        self.character_positions.push(0);
-        if !self.text.is_empty() {
-            let mut it = self.dc.layout().iter();
-            loop {
-                let extents = it.char_extents();
-                let x = (extents.x() + extents.width()) / pango::SCALE;
-                self.character_positions.push(x);
-                if !it.next_cluster() {
-                    break;
-                }
-            }
+        let layout = self.dc.layout();
+        let mut index = 0;
+        let mut end = 0;
+        for c in self.text.iter() {
+            let rect = layout.index_to_pos(index);
+            self.character_positions.push(rect.x() / pango::SCALE);
+            end = (rect.x() + rect.width()) / pango::SCALE;
+            index += c.len_utf8() as i32;
         }
+        self.character_positions.push(end);
     }
 
-    pub fn draw_cursor_and_selection(&mut self) {
-        // I'm sometimes crashing here but don't know how to reproduce it
-        if self.cursor_position >= self.character_positions.len() {
-            eprintln!("CURSOR OUT OF BOUNDS");
-            self.cursor_position = self.character_positions.len() - 1;
+    /// The overwrite mode cursor, a block over the character that's replaced
+    /// next with that character drawn on top in the background color.
+    fn draw_block_cursor(&mut self, x: i32) {
+        let width = match self.character_positions.get(self.cursor_position + 1) {
+            Some(&next) if self.cursor_position < self.text.len() => (next - x) as u32,
+            _ => self.layout.cursor_height / 2,
+        };
+        let block = Rectangle::new(
+            self.layout.text.x + x,
+            self.layout.cursor_y,
+            width.max(self.layout.cursor_width),
+            self.layout.cursor_height,
+        );
+        self.dc.rect(&block).color(colors::entry_cursor()).draw();
+        if let Some(c) = self.text.get(self.cursor_position) {
+            let rect = Rectangle::new(block.x, self.layout.text.y, width, self.layout.text.height);
+            self.dc.set_color(colors::entry_background());
+            self.dc
+                .text(&c.to_string(), rect, false)
+                .center_height()
+                .draw();
         }
+    }
+
+    pub fn draw_cursor_and_selection(&mut self) {
         let x = self.character_positions[self.cursor_position];
-        self.dc
-            .rect(&Rectangle::new(
-                self.layout.text.x + x,
-                self.layout.cursor_y,
-                self.layout.cursor_width,
-                self.layout.cursor_height,
-            ))
-            .color(colors::ENTRY_CURSOR)
-            .draw();
+        if self.overwrite {
+            self.draw_block_cursor(x);
+        } else {
+            self.dc
+                .rect(&Rectangle::new(
+                    self.layout.text.x + x,
+                    self.layout.cursor_y,
+                    self.layout.cursor_width,
+                    self.layout.cursor_height,
+                ))
+                .color(colors::entry_cursor())
+                .draw();
+        }
 
         if let Some(sel) = self.selection {
             let start = usize::min(sel, self.cursor_position);
@@ -176,7 +315,7 @@ impl Entry {
                     (end - start) as u32,
                     self.layout.cursor_height,
                 ))
-                .color(colors::ENTRY_SELECTION)
+                .color(colors::entry_selection())
                 .draw();
             self.dc.blend(false);
         }
@@ -210,82 +349,43 @@ impl Entry {
     }
 
     pub fn text_input(&mut self, text: &str) {
-        if self.selection.is_some() {
-            if text == "(" || text == ")" {
-                let sel = self.selection.unwrap();
-                let begin = usize::min(sel, self.cursor_position);
-                let end = usize::max(sel, self.cursor_position);
-                self.text.insert(end, ')');
-                self.text.insert(begin, '(');
-                // This makes the cursor stay on the inside of the parentheses
-                // at either end of the selection
-                self.cursor_position += 1;
-                self.selection = Some(sel + 1);
-                self.text_changed(true);
-                self.cursor_changed();
-                return;
-            } else {
-                self.delete_selection();
-            }
-        }
-        let is_at_end =
-            self.text.is_empty() || self.cursor_position == self.character_positions.len() - 1;
-        for c in text.chars() {
-            if is_at_end {
-                self.text.push(c);
-            } else {
-                self.text.insert(self.cursor_position, c);
-                self.cursor_position += 1;
-            }
+        if let Some(sel) = self.selection.filter(|_| text == "(" || text == ")") {
+            let begin = usize::min(sel, self.cursor_position);
+            let end = usize::max(sel, self.cursor_position);
+            self.text.insert(end, ')');
+            self.text.insert(begin, '(');
+            // This makes the cursor stay on the inside of the parentheses
+            // at either end of the selection
+            self.cursor_position += 1;
+            self.selection = Some(sel + 1);
+            self.text_changed(true);
+            self.cursor_changed();
+            return;
         }
+        self.cursor_position = match self.selection.take() {
+            None if self.overwrite => overwrite(&mut self.text, self.cursor_position, text),
+            selection => insert(&mut self.text, self.cursor_position, selection, text),
+        };
         self.text_changed(true);
-        if is_at_end {
-            self.cursor_position = self.character_positions.len() - 1;
-        }
         self.cursor_changed();
     }
 
-    fn jump(&self, left: bool) -> usize {
-        fn scan(
-            chars: &[char],
-            current: Option<&char>,
-            range: impl Iterator<Item = usize>,
-            or: usize,
-        ) -> usize {
-            let cond = current.map(|c| !c.is_alphanumeric()).unwrap_or(true);
-            for pos in range {
-                if chars[pos].is_alphanumeric() == cond {
-                    return pos;
-                }
-            }
-            or
+    /// Inserts the clipboard like typed text, or its start if it's too long.
+    fn paste_clipboard(&mut self) {
+        let text = paste();
+        let (head, truncated) = paste_head(&text, self.max_paste_length);
+        if !head.is_empty() {
+            self.text_input(head);
         }
-        #[allow(clippy::collapsible_else_if)]
-        if left {
-            if self.cursor_position == 0 {
-                self.cursor_position
-            } else {
-                let current = self.text.get(self.cursor_position - 1);
-                scan(
-                    &self.text,
-                    current,
-                    (0..self.cursor_position - 1).rev(),
-                    usize::MAX,
-                )
-                .overflowing_add(1)
-                .0
-            }
-        } else {
-            if self.cursor_position == self.character_positions.len() - 1 {
-                self.cursor_position
-            } else {
-                let current = self.text.get(self.cursor_position);
-                let end = self.character_positions.len() - 1;
-                scan(&self.text, current, self.cursor_position + 1..end, end)
-            }
+        if truncated {
+            send_signal(&self.display, &self.signal_sender, Signal::PasteTruncated);
         }
     }
 
+    fn jump(&self, left: bool) -> usize {
+        word_boundary(&self.text, self.cursor_position, left)
+    }
+
     fn delete_selection(&mut self) {
         if self.selection.is_none() {
             return;
@@ -298,30 +398,62 @@ impl Entry {
         self.selection = None;
     }
 
+    fn toggle_overwrite(&mut self) {
+        self.overwrite = !self.overwrite;
+        self.draw();
+        self.draw_cursor_and_selection();
+    }
+
+    /// List navigation done while typing, the focus stays in the entry.
+    fn navigation(&self, event: &KeyEvent) -> Option<Navigation> {
+        Some(match event.key {
+            Key::PageUp => Navigation::PageUp,
+            Key::PageDown => Navigation::PageDown,
+            Key::Home if event.is_ctrl => Navigation::First,
+            Key::End if event.is_ctrl => Navigation::Last,
+            Key::Up if self.navigate_list => Navigation::Up,
+            Key::Down if self.navigate_list => Navigation::Down,
+            _ => return None,
+        })
+    }
+
     pub fn key_press(&mut self, event: KeyEvent) {
+        if let Some(navigation) = self.navigation(&event) {
+            send_signal(
+                &self.display,
+                &self.signal_sender,
+                Signal::Navigate(navigation),
+            );
+            return;
+        }
         if self.text.is_empty() {
             match event.key {
                 Key::Escape | Key::CtrlC => {
                     send_signal(&self.display, &self.signal_sender, Signal::Quit)
                 }
-                Key::Tab | Key::Down => {
-                    send_signal(&self.display, &self.signal_sender, Signal::SwapFocus)
-                }
+                Key::Tab => send_signal(
+                    &self.display,
+                    &self.signal_sender,
+                    Signal::SwapFocus(event.tab_direction()),
+                ),
+                Key::Down => send_signal(
+                    &self.display,
+                    &self.signal_sender,
+                    Signal::SwapFocus(FocusDirection::Forward),
+                ),
+                Key::Enter if event.is_ctrl => send_signal(
+                    &self.display,
+                    &self.signal_sender,
+                    Signal::CopyCommand(None),
+                ),
                 Key::Enter => send_signal(&self.display, &self.signal_sender, Signal::Commit(None)),
-                Key::CtrlV => {
-                    let text = paste();
-                    if !text.is_empty() {
-                        self.text_input(&text);
-                        self.draw();
-                        self.text_changed(false);
-                        if !self.text.is_empty()
-                            && self.cursor_position >= self.character_positions.len()
-                        {
-                            self.cursor_position = self.character_positions.len() - 1;
-                        }
-                        self.cursor_changed();
-                    }
-                }
+                Key::CtrlI => send_signal(
+                    &self.display,
+                    &self.signal_sender,
+                    Signal::ToggleDetails(None),
+                ),
+                Key::CtrlV => self.paste_clipboard(),
+                Key::Insert => self.toggle_overwrite(),
                 _ => {}
             }
             return;
@@ -357,14 +489,31 @@ impl Entry {
                     text_changed = true;
                 } else if self.cursor_position < self.text.len() {
                     if event.is_ctrl {
-                        let to = self.jump(false);
-                        self.text.drain(self.cursor_position..to);
+                        delete_word_forward(&mut self.text, self.cursor_position);
                     } else {
                         self.text.remove(self.cursor_position);
                     }
                     text_changed = true;
                 }
             }
+            Key::AltD => {
+                if self.selection.is_some() {
+                    self.delete_selection();
+                    text_changed = true;
+                } else {
+                    text_changed = delete_word_forward(&mut self.text, self.cursor_position);
+                }
+            }
+            Key::CtrlT => {
+                if let Some(cursor) = transpose(&mut self.text, self.cursor_position) {
+                    self.cursor_position = cursor;
+                    text_changed = true;
+                }
+            }
+            Key::Insert => {
+                self.overwrite = !self.overwrite;
+                keep_selection = true;
+            }
             Key::Left => {
                 if event.is_ctrl {
                     self.cursor_position = self.jump(true);
@@ -375,7 +524,7 @@ impl Entry {
             Key::Right => {
                 if event.is_ctrl {
                     self.cursor_position = self.jump(false);
-                } else if self.cursor_position < self.character_positions.len() - 1 {
+                } else if self.cursor_position < self.text.len() {
                     self.cursor_position += 1;
                 }
             }
@@ -383,7 +532,7 @@ impl Entry {
                 self.cursor_position = 0;
             }
             Key::End => {
-                self.cursor_position = self.character_positions.len() - 1;
+                self.cursor_position = self.text.len();
             }
             Key::CtrlA => {
                 self.selection = Some(0);
@@ -391,11 +540,9 @@ impl Entry {
                 keep_selection = true;
             }
             Key::CtrlV => {
-                let text = paste();
-                if !text.is_empty() {
-                    self.text_input(&text);
-                    text_changed = true;
-                }
+                // This updates and draws everything itself
+                self.paste_clipboard();
+                return;
             }
             Key::CtrlC => {
                 if let Some((start, end)) = self.selection_range() {
@@ -424,14 +571,42 @@ impl Entry {
                 return;
             }
             Key::Down => {
-                send_signal(&self.display, &self.signal_sender, Signal::SwapFocus);
+                send_signal(
+                    &self.display,
+                    &self.signal_sender,
+                    Signal::SwapFocus(FocusDirection::Forward),
+                );
+                return;
+            }
+            Key::Enter if event.is_ctrl => {
+                send_signal(
+                    &self.display,
+                    &self.signal_sender,
+                    Signal::CopyCommand(None),
+                );
+                return;
+            }
+            Key::Enter if event.is_shift => {
+                send_signal(&self.display, &self.signal_sender, Signal::Pick(None));
                 return;
             }
             Key::Enter => {
                 send_signal(&self.display, &self.signal_sender, Signal::Commit(None));
                 return;
             }
-            Key::Tab => send_signal(&self.display, &self.signal_sender, Signal::SwapFocus),
+            Key::CtrlI => {
+                send_signal(
+                    &self.display,
+                    &self.signal_sender,
+                    Signal::ToggleDetails(None),
+                );
+                return;
+            }
+            Key::Tab => send_signal(
+                &self.display,
+                &self.signal_sender,
+                Signal::SwapFocus(event.tab_direction()),
+            ),
             _ => {
                 return;
             }
@@ -442,9 +617,6 @@ impl Entry {
         self.draw();
         if text_changed {
             self.text_changed(false);
-            if !self.text.is_empty() && self.cursor_position >= self.character_positions.len() {
-                self.cursor_position = self.character_positions.len() - 1;
-            }
         }
         self.cursor_changed();
     }
@@ -460,3 +632,167 @@ impl Drop for Entry {
         self.dc.destroy();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paste(text: &str, cursor: usize, selection: Option<usize>, pasted: &str) -> (String, usize) {
+        let mut text: Vec<char> = text.chars().collect();
+        let cursor = insert(&mut text, cursor, selection, pasted);
+        assert!(cursor <= text.len());
+        (text.into_iter().collect(), cursor)
+    }
+
+    #[test]
+    fn insertion() {
+        assert_eq!(paste("", 0, None, "firefox"), ("firefox".to_string(), 7));
+        assert_eq!(paste("fox", 0, None, "fire"), ("firefox".to_string(), 4));
+        assert_eq!(paste("frefox", 1, None, "i"), ("firefox".to_string(), 2));
+        assert_eq!(paste("fire", 4, None, "fox"), ("firefox".to_string(), 7));
+        assert_eq!(paste("ab", 1, None, "é🦊"), ("aé🦊b".to_string(), 3));
+        // Replacing a selection, made in either direction
+        assert_eq!(
+            paste("fire dog", 5, Some(8), "fox"),
+            ("fire fox".to_string(), 8)
+        );
+        assert_eq!(
+            paste("a dog b", 5, Some(2), "fox"),
+            ("a fox b".to_string(), 5)
+        );
+        assert_eq!(paste("dog", 3, Some(0), "fox"), ("fox".to_string(), 3));
+        assert_eq!(paste("abc", 1, Some(2), ""), ("ac".to_string(), 1));
+    }
+
+    #[test]
+    fn long_paste() {
+        let megabyte = "a".repeat(1 << 20);
+        let (head, truncated) = paste_head(&megabyte, 4096);
+        assert!(truncated);
+        let (text, cursor) = paste("", 0, None, head);
+        assert_eq!((text.len(), cursor), (4096, 4096));
+        // The cap counts characters
+        let foxes = "🦊".repeat(5000);
+        let (head, truncated) = paste_head(&foxes, 4096);
+        assert!(truncated);
+        assert_eq!(head.chars().count(), 4096);
+        assert_eq!(paste_head("fox", 3), ("fox", false));
+        assert_eq!(paste_head("é🦊x", 2), ("é🦊", true));
+        assert_eq!(paste_head("", 0), ("", false));
+    }
+
+    #[test]
+    fn word_boundaries() {
+        // `|` is the cursor, `<` and `>` where Ctrl+Left and Ctrl+Right move it
+        let cases = [
+            "<foo |bar>",
+            "foo <bar|>",
+            "foo <bar  |>",
+            "<|foo> bar",
+            "<f|oo> bar",
+            "foo <bar|.baz>",
+            "foo <bar.|baz>",
+            "<foo|...>",
+            "<foo..|.>",
+            "<|>",
+            "<|  >",
+            "<  |>",
+            "<héllo| wörld>",
+            "a <über|>",
+            "x <42|, y>",
+            "(1 + <2)|>",
+            "$ cargo build <--release|>",
+            "$ cargo <build| --release>",
+            "<|snake_case>",
+            "<foo|-bar>",
+            "foo <bar_|>",
+            "foo -- <bar|>",
+            "<foo| -- bar>",
+            "x - <y|>",
+        ];
+        for case in cases {
+            let text: Vec<char> = case.chars().filter(|c| !"|<>".contains(*c)).collect();
+            let index = |marker| {
+                let pos = case.chars().position(|c| c == marker).unwrap();
+                // Number of other markers before it
+                pos - case
+                    .chars()
+                    .take(pos)
+                    .filter(|c| "|<>".contains(*c))
+                    .count()
+            };
+            let cursor = index('|');
+            assert_eq!(
+                word_boundary(&text, cursor, true),
+                index('<'),
+                "{case} left"
+            );
+            assert_eq!(
+                word_boundary(&text, cursor, false),
+                index('>'),
+                "{case} right"
+            );
+        }
+    }
+
+    fn edit(text: &str, f: impl FnOnce(&mut Vec<char>) -> usize) -> (String, usize) {
+        let mut text: Vec<char> = text.chars().collect();
+        let cursor = f(&mut text);
+        assert!(cursor <= text.len());
+        (text.into_iter().collect(), cursor)
+    }
+
+    #[test]
+    fn overwriting() {
+        let typed = |text, cursor, typed| edit(text, |text| overwrite(text, cursor, typed));
+        assert_eq!(typed("firefux", 5, "o"), ("firefox".to_string(), 6));
+        assert_eq!(typed("fire", 0, "g"), ("gire".to_string(), 1));
+        // Past the end it appends
+        assert_eq!(typed("fire", 4, "fox"), ("firefox".to_string(), 7));
+        assert_eq!(typed("firefix", 5, "oxes"), ("firefoxes".to_string(), 9));
+        assert_eq!(typed("", 0, "a"), ("a".to_string(), 1));
+        assert_eq!(typed("aé🦊b", 1, "xy"), ("axyb".to_string(), 3));
+    }
+
+    #[test]
+    fn transposition() {
+        let swapped = |text, cursor| {
+            let mut chars: Vec<char> = String::from(text).chars().collect();
+            let cursor = transpose(&mut chars, cursor)?;
+            assert!(cursor <= chars.len());
+            Some((chars.into_iter().collect::<String>(), cursor))
+        };
+        assert_eq!(swapped("fierfox", 3), Some(("firefox".to_string(), 4)));
+        // At the end the last two are swapped
+        assert_eq!(swapped("firefxo", 7), Some(("firefox".to_string(), 7)));
+        assert_eq!(swapped("ab", 1), Some(("ba".to_string(), 2)));
+        assert_eq!(swapped("ab", 2), Some(("ba".to_string(), 2)));
+        assert_eq!(swapped("é🦊", 1), Some(("🦊é".to_string(), 2)));
+        // Nothing before the cursor or nothing to swap with
+        assert_eq!(swapped("ab", 0), None);
+        assert_eq!(swapped("a", 1), None);
+        assert_eq!(swapped("", 0), None);
+    }
+
+    #[test]
+    fn word_deletion() {
+        let deleted = |text, cursor| {
+            let mut removed = false;
+            let result = edit(text, |text| {
+                removed = delete_word_forward(text, cursor);
+                cursor
+            });
+            (result.0, removed)
+        };
+        assert_eq!(deleted("foo bar baz", 3), ("foo baz".to_string(), true));
+        assert_eq!(deleted("foo bar baz", 5), ("foo b baz".to_string(), true));
+        assert_eq!(deleted("foo bar", 0), (" bar".to_string(), true));
+        assert_eq!(deleted("foo...", 3), ("foo".to_string(), true));
+        assert_eq!(
+            deleted("$ cargo build --release", 13),
+            ("$ cargo build".to_string(), true)
+        );
+        assert_eq!(deleted("foo", 3), ("foo".to_string(), false));
+        assert_eq!(deleted("", 0), ("".to_string(), false));
+    }
+}
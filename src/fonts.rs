@@ -0,0 +1,136 @@
+//! Loading the fonts given in the config.
+//!
+//! Pango silently substitutes fonts that are not installed, so we check the
+//! requested families ourselves to tell the user about typos.
+
+use crate::warnings::warning;
+use pango::{prelude::*, FontDescription};
+use std::{cell::RefCell, collections::HashMap};
+
+/// Families fontconfig always resolves to something, these are usually not
+/// listed by the font map.
+const GENERIC_FAMILIES: &[&str] = &[
+    "sans",
+    "sans-serif",
+    "serif",
+    "monospace",
+    "mono",
+    "system-ui",
+    "cursive",
+    "fantasy",
+    "emoji",
+    "math",
+];
+
+thread_local! {
+    static LOADED: RefCell<HashMap<String, FontDescription>> = Default::default();
+}
+
+/// Parses a Pango font description like `"Fira Code, monospace 20"`, using the
+/// first of the comma separated families that is installed.
+pub fn load(description: &str) -> FontDescription {
+    LOADED.with_borrow_mut(|loaded| {
+        loaded
+            .entry(description.to_string())
+            .or_insert_with(|| resolve(description))
+            .clone()
+    })
+}
+
+fn resolve(description: &str) -> FontDescription {
+    let mut font = FontDescription::from_string(description);
+    let Some(families) = font.family() else {
+        return font;
+    };
+    let installed: Vec<String> = pangocairo::FontMap::default()
+        .list_families()
+        .iter()
+        .map(|family| family.name().to_string())
+        .collect();
+    let (chosen, missing) = choose_family(&families, &installed);
+    for family in missing {
+        let close = close_matches(family, &installed);
+        if close.is_empty() {
+            warning!("Font `{family}` is not installed");
+        } else {
+            warning!(
+                "Font `{family}` is not installed, did you mean: {}",
+                close.join(", ")
+            );
+        }
+    }
+    match chosen {
+        Some(family) => font.set_family(family),
+        None => warning!("No font in `{description}` is installed, using the default"),
+    }
+    font
+}
+
+fn is_installed(family: &str, installed: &[String]) -> bool {
+    GENERIC_FAMILIES
+        .iter()
+        .copied()
+        .chain(installed.iter().map(String::as_str))
+        .any(|name| name.eq_ignore_ascii_case(family))
+}
+
+/// Finds the first installed family in the comma separated list, also
+/// returns the requested families before it that are not installed.
+fn choose_family<'a>(families: &'a str, installed: &[String]) -> (Option<&'a str>, Vec<&'a str>) {
+    let mut missing = Vec::new();
+    for family in families.split(',').map(str::trim) {
+        if family.is_empty() {
+            continue;
+        }
+        if is_installed(family, installed) {
+            return (Some(family), missing);
+        }
+        missing.push(family);
+    }
+    (None, missing)
+}
+
+/// Installed families similar to `family`, best match first.
+fn close_matches<'a>(family: &str, installed: &'a [String]) -> Vec<&'a str> {
+    let family = family.to_lowercase();
+    let mut matches: Vec<(f64, &str)> = installed
+        .iter()
+        .map(|name| {
+            (
+                strsim::jaro_winkler(&family, &name.to_lowercase()),
+                name.as_str(),
+            )
+        })
+        .filter(|(similarity, _)| *similarity > 0.85)
+        .collect();
+    matches.sort_by(|a, b| b.0.total_cmp(&a.0));
+    matches.into_iter().take(3).map(|(_, name)| name).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fallbacks() {
+        let installed = ["Input Mono", "DejaVu Sans", "DejaVu Sans Mono"].map(String::from);
+        assert_eq!(
+            choose_family("Input Mono", &installed),
+            (Some("Input Mono"), vec![])
+        );
+        assert_eq!(
+            choose_family("Inputt Mono, dejavu sans mono", &installed),
+            (Some("dejavu sans mono"), vec!["Inputt Mono"])
+        );
+        assert_eq!(
+            choose_family("Inputt Mono,Fira Code,Monospace", &installed),
+            (Some("Monospace"), vec!["Inputt Mono", "Fira Code"])
+        );
+        assert_eq!(
+            choose_family("Fira Code", &installed),
+            (None, vec!["Fira Code"])
+        );
+        assert_eq!(close_matches("Inputt Mono", &installed), ["Input Mono"]);
+        assert!(close_matches("Fira Code", &installed).is_empty());
+    }
+}
@@ -0,0 +1,97 @@
+//! Environment for running applications on the discrete GPU, for desktop
+//! entries with `PrefersNonDefaultGPU` or `X-KDE-RunOnDiscreteGpu`.
+
+use gio::{prelude::*, BusType, Cancellable, DBusCallFlags};
+use glib::{Variant, VariantDict, VariantTy};
+
+/// Used if switcheroo-control is not available, this works for Mesa drivers.
+const FALLBACK: (&str, &str) = ("DRI_PRIME", "1");
+
+/// Environment variables to put an application on a non-default GPU.
+pub fn offload_environment() -> Vec<(String, String)> {
+    choose_environment(switcheroo_gpus().unwrap_or_default())
+}
+
+/// Queries the GPUs from switcheroo-control as `(is default, environment)`
+/// where the environment alternates between names and values.
+fn switcheroo_gpus() -> Option<Vec<(bool, Vec<String>)>> {
+    let connection = gio::bus_get_sync(BusType::System, Cancellable::NONE).ok()?;
+    let reply = connection
+        .call_sync(
+            Some("net.hadess.SwitcherooControl"),
+            "/net/hadess/SwitcherooControl",
+            "org.freedesktop.DBus.Properties",
+            "Get",
+            Some(&("net.hadess.SwitcherooControl", "GPUs").to_variant()),
+            VariantTy::new("(v)").ok(),
+            DBusCallFlags::NONE,
+            1000,
+            Cancellable::NONE,
+        )
+        .ok()?;
+    let gpus = reply.child_value(0).as_variant()?;
+    Some(
+        gpus.iter()
+            .map(|gpu: Variant| {
+                let dict = VariantDict::new(Some(&gpu));
+                let default = dict.lookup::<bool>("Default").ok().flatten();
+                let environment = dict.lookup::<Vec<String>>("Environment").ok().flatten();
+                (default.unwrap_or(false), environment.unwrap_or_default())
+            })
+            .collect(),
+    )
+}
+
+/// Uses the environment of the first non-default GPU.
+fn choose_environment(gpus: Vec<(bool, Vec<String>)>) -> Vec<(String, String)> {
+    let environment = gpus
+        .into_iter()
+        .find(|(default, environment)| !default && !environment.is_empty())
+        .map(|(_, environment)| {
+            environment
+                .chunks_exact(2)
+                .map(|pair| (pair[0].clone(), pair[1].clone()))
+                .collect()
+        });
+    environment.unwrap_or_else(|| vec![(FALLBACK.0.to_string(), FALLBACK.1.to_string())])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(s: &[&str]) -> Vec<String> {
+        s.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn environment() {
+        let fallback = vec![("DRI_PRIME".to_string(), "1".to_string())];
+        assert_eq!(choose_environment(vec![]), fallback);
+        assert_eq!(
+            choose_environment(vec![(true, strings(&["DRI_PRIME", "0"]))]),
+            fallback
+        );
+        assert_eq!(
+            choose_environment(vec![
+                (true, strings(&[])),
+                (
+                    false,
+                    strings(&[
+                        "__NV_PRIME_RENDER_OFFLOAD",
+                        "1",
+                        "__GLX_VENDOR_LIBRARY_NAME",
+                        "nvidia"
+                    ])
+                ),
+            ]),
+            vec![
+                ("__NV_PRIME_RENDER_OFFLOAD".to_string(), "1".to_string()),
+                (
+                    "__GLX_VENDOR_LIBRARY_NAME".to_string(),
+                    "nvidia".to_string()
+                ),
+            ]
+        );
+    }
+}
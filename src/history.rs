@@ -1,113 +1,351 @@
-use crate::{cache::DesktopEntryCache, list_view::Render, res::Svg, search::SearchMatchKind};
+use crate::{
+    cache::DesktopEntryCache,
+    list_view::Render,
+    paths,
+    res::Svg,
+    search::{path_markup, SearchMatchKind},
+    text::{escape_markup, fold},
+    warnings::warning,
+};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::{HashMap, VecDeque},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
-const FILE: &str = "history";
 pub const DEFAULT_MAX_SIZE: usize = 100;
 
-#[derive(Serialize, Deserialize, Eq, PartialEq)]
+/// Version of the history file format written by this binary.  Files without
+/// a version are the bare list of entries older versions wrote.
+pub const VERSION: u32 = 1;
+
+/// How many queries the committed entry is remembered for.
+const MAX_CHOICES: usize = 200;
+
+/// How many times something else has to be picked for a query, at most, to
+/// replace the remembered choice.
+const MAX_CHOICE_COUNT: u32 = 3;
+
+/// Shorter queries match too much to rank by what was picked for them.
+const MIN_CHOICE_QUERY_LEN: usize = 2;
+
+/// The desktop entry committed for a query, see `History::choice`.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
+struct Choice {
+    /// The folded search text.
+    query: String,
+    file_name: String,
+    /// Picks of the entry minus picks of something else for the same query.
+    count: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub enum Entry {
     Path(PathBuf),
     DesktopEntry(String),
 }
 
-impl Render for Entry {
+/// What a desktop entry in the history resolved to, so drawing the history
+/// doesn't have to look it up again.
+#[derive(Clone, Debug)]
+struct Resolved {
+    /// The `DesktopEntryCache::generation` this was resolved in.
+    generation: u64,
+    id: usize,
+    name: String,
+    icon: Option<String>,
+}
+
+impl Resolved {
+    fn new(file_name: &str, cache: &DesktopEntryCache) -> Option<Self> {
+        let id = cache.find_file(file_name)?;
+        let entry = cache.get_entry(id);
+        Some(Self {
+            generation: cache.generation(),
+            id,
+            name: entry.name.clone(),
+            icon: entry.icon.clone(),
+        })
+    }
+}
+
+/// An entry of the history as it's shown in the list.
+#[derive(Clone, Debug)]
+pub struct HistoryItem {
+    pub entry: Entry,
+    resolved: Option<Resolved>,
+}
+
+impl HistoryItem {
+    fn new(entry: Entry, cache: &DesktopEntryCache) -> Self {
+        let resolved = match &entry {
+            Entry::DesktopEntry(file_name) => Resolved::new(file_name, cache),
+            Entry::Path(_) => None,
+        };
+        Self { entry, resolved }
+    }
+
+    /// The resolved desktop entry, `None` if the cache was rebuilt since.
+    fn resolved(&self, cache: &DesktopEntryCache) -> Option<&Resolved> {
+        self.resolved
+            .as_ref()
+            .filter(|resolved| resolved.generation == cache.generation())
+    }
+
+    /// Name and icon of the desktop entry, these are only looked up in the
+    /// cache if it was rebuilt since the entry was resolved.
+    fn name_and_icon<'a>(
+        &'a self,
+        file_name: &str,
+        cache: &'a DesktopEntryCache,
+    ) -> (&'a str, Option<&'a str>) {
+        if let Some(resolved) = self.resolved(cache) {
+            return (&resolved.name, resolved.icon.as_deref());
+        }
+        let entry = cache.get_entry(cache.find_file(file_name).unwrap());
+        (&entry.name, entry.icon.as_deref())
+    }
+}
+
+impl Render for HistoryItem {
     fn markup(&self, _search: &str, cache: &DesktopEntryCache) -> String {
-        match self {
+        match &self.entry {
             Entry::DesktopEntry(file_name) => {
-                let id = cache.find_file(file_name).unwrap();
-                cache.get_entry(id).name.clone()
+                escape_markup(self.name_and_icon(file_name, cache).0).into_owned()
             }
-            Entry::Path(path) => path.file_name().unwrap().to_str().unwrap().to_string(),
+            Entry::Path(path) => path_markup(path, ""),
+        }
+    }
+
+    fn plain_text(&self, cache: &DesktopEntryCache) -> String {
+        match &self.entry {
+            Entry::DesktopEntry(file_name) => self.name_and_icon(file_name, cache).0.to_string(),
+            Entry::Path(path) => path.file_name().unwrap().to_string_lossy().into_owned(),
         }
     }
 
     fn icon(&self, cache: &DesktopEntryCache) -> Option<Svg> {
-        match self {
+        match &self.entry {
             Entry::Path(_) => None,
             Entry::DesktopEntry(file_name) => {
-                let id = cache.find_file(file_name).unwrap();
-                cache
-                    .get_entry(id)
-                    .icon
-                    .as_ref()
-                    .map(|icon_path| Svg::open(icon_path))
+                self.name_and_icon(file_name, cache).1.and_then(Svg::open)
             }
         }
     }
 
+    fn tooltip(&self, cache: &DesktopEntryCache) -> Option<String> {
+        Some(match &self.entry {
+            Entry::Path(path) => escape_markup(&path.to_string_lossy()).into_owned(),
+            Entry::DesktopEntry(file_name) => {
+                let id = match self.resolved(cache) {
+                    Some(resolved) => resolved.id,
+                    None => cache.find_file(file_name).unwrap(),
+                };
+                cache.get_entry(id).tooltip_markup()
+            }
+        })
+    }
+
+    fn key(&self, _cache: &DesktopEntryCache) -> Option<String> {
+        Some(match &self.entry {
+            Entry::Path(path) => format!("path:{}", path.display()),
+            Entry::DesktopEntry(file_name) => format!("desktop:{file_name}"),
+        })
+    }
+
+    fn history_entry(&self, _cache: &DesktopEntryCache) -> Option<Entry> {
+        Some(self.entry.clone())
+    }
+
+    fn is_executable(&self) -> bool {
+        matches!(self.entry, Entry::Path(_))
+    }
+
     // `is_in_history` is not implemented since it's pointless to show that the
     // history entries are in the history when we're only showing the history.
 }
 
-pub struct History {
+#[derive(Serialize)]
+struct HistoryFile<'a> {
+    version: u32,
+    entries: Vec<&'a Entry>,
+    choices: &'a VecDeque<Choice>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct HistoryFileOwned {
     entries: VecDeque<Entry>,
+    /// Not in files from before choices were remembered.
+    #[serde(default)]
+    choices: VecDeque<Choice>,
+}
+
+#[derive(Deserialize)]
+struct HistoryFileVersion {
+    version: u32,
+}
+
+/// Why the history file could not be loaded.
+#[derive(Debug, PartialEq, Eq)]
+pub enum LoadError {
+    Corrupt(String),
+    /// Written by a newer version of the launcher.
+    FutureVersion(u32),
+}
+
+/// Parses the contents of the history file.
+fn parse(data: &str) -> Result<HistoryFileOwned, LoadError> {
+    if let Ok(entries) = ron::from_str::<VecDeque<Entry>>(data) {
+        return Ok(HistoryFileOwned {
+            entries,
+            choices: VecDeque::new(),
+        });
+    }
+    let version = ron::from_str::<HistoryFileVersion>(data)
+        .map_err(|error| LoadError::Corrupt(error.to_string()))?
+        .version;
+    if version > VERSION {
+        return Err(LoadError::FutureVersion(version));
+    }
+    ron::from_str::<HistoryFileOwned>(data).map_err(|error| LoadError::Corrupt(error.to_string()))
+}
+
+/// Moves the history file out of the way so it doesn't get overwritten,
+/// returns the backup path.
+fn back_up(pathname: &Path, suffix: &str) -> std::io::Result<PathBuf> {
+    let mut backup = pathname.as_os_str().to_owned();
+    backup.push(".");
+    backup.push(suffix);
+    let backup = PathBuf::from(backup);
+    std::fs::rename(pathname, &backup)?;
+    Ok(backup)
+}
+
+pub struct History {
+    entries: VecDeque<HistoryItem>,
     // maps IDs in the desktop cache to their recency score.
     desktop_ids: HashMap<usize, usize>,
     next_score: usize,
     max_size: usize,
+    // hint for the user about a history file that was not loaded.
+    notice: Option<String>,
+    // set if the file on disk could not be backed up and must be kept as is.
+    read_only: bool,
+    // most recently committed first.
+    choices: VecDeque<Choice>,
 }
 
 impl History {
-    fn new(max_size: usize) -> Self {
+    pub fn new(max_size: usize) -> Self {
         Self {
             entries: VecDeque::new(),
             desktop_ids: HashMap::new(),
             next_score: 0,
             max_size,
+            notice: None,
+            read_only: false,
+            choices: VecDeque::new(),
         }
     }
 
-    fn dirpath() -> String {
-        // no reason storing this, this happens once at start and once when quitting
-        format!("{}/.cache/launcher", std::env::var("HOME").unwrap())
-    }
-
     pub fn load(cache: &DesktopEntryCache, max_size: usize) -> Self {
-        let pathname = format!("{}/{}", Self::dirpath(), FILE);
-        println!("Loading history from {}", pathname);
-        if let Ok(history_data) = std::fs::read_to_string(pathname) {
-            if history_data.is_empty() {
-                return Self::new(max_size);
-            }
-            let entries: VecDeque<Entry> = ron::from_str(&history_data).unwrap();
-            let entries: VecDeque<Entry> = entries
-                .into_iter()
-                .filter(|e| match e {
-                    Entry::Path(path) => std::fs::metadata(path).is_ok(),
-                    Entry::DesktopEntry(file_name) => cache.find_file(file_name).is_some(),
-                })
-                .collect();
-            let mut desktop_ids = HashMap::new();
-            for (idx, entry) in entries.iter().enumerate() {
-                if let Entry::DesktopEntry(file_name) = entry {
-                    if let Some(id) = cache.find_file(file_name) {
-                        desktop_ids.insert(id, entries.len() - idx);
+        let Some(pathname) = paths::cache_file(paths::HISTORY) else {
+            warning!("No cache directory, history will not be loaded");
+            return Self::new(max_size);
+        };
+        println!("Loading history from {}", pathname.display());
+        let Ok(history_data) = std::fs::read_to_string(&pathname) else {
+            return Self::new(max_size);
+        };
+        if history_data.is_empty() {
+            return Self::new(max_size);
+        }
+        let HistoryFileOwned { entries, choices } = match parse(&history_data) {
+            Ok(file) => file,
+            Err(error) => {
+                let mut history = Self::new(max_size);
+                let (suffix, reason) = match &error {
+                    LoadError::Corrupt(error) => {
+                        warning!("History file is corrupt: {error}");
+                        ("corrupt".to_string(), "is corrupt".to_string())
                     }
-                }
+                    LoadError::FutureVersion(version) => {
+                        warning!(
+                            "History file has version {version} but only {VERSION} is supported"
+                        );
+                        (
+                            format!("v{version}"),
+                            "is from a newer launcher".to_string(),
+                        )
+                    }
+                };
+                history.notice = Some(match back_up(&pathname, &suffix) {
+                    Ok(backup) => {
+                        warning!("Moved history file to {}", backup.display());
+                        format!(
+                            "History {reason}, it was kept in {} but not loaded",
+                            backup.display()
+                        )
+                    }
+                    Err(error) => {
+                        warning!("Failed to back up history file: {error}");
+                        // Don't overwrite what we couldn't move away
+                        history.read_only = true;
+                        format!("History {reason}, it was not loaded and will not be saved")
+                    }
+                });
+                return history;
             }
-            let next_score = entries.len();
-            Self {
-                entries,
-                desktop_ids,
-                next_score,
-                max_size,
+        };
+        let entries: VecDeque<HistoryItem> = entries
+            .into_iter()
+            .map(|entry| HistoryItem::new(entry, cache))
+            .filter(|item| match &item.entry {
+                Entry::Path(path) => std::fs::metadata(path).is_ok(),
+                Entry::DesktopEntry(_) => item.resolved.is_some(),
+            })
+            .collect();
+        let mut desktop_ids = HashMap::new();
+        for (idx, item) in entries.iter().enumerate() {
+            if let Some(resolved) = &item.resolved {
+                desktop_ids.insert(resolved.id, entries.len() - idx);
             }
-        } else {
-            Self::new(max_size)
+        }
+        let next_score = entries.len();
+        Self {
+            entries,
+            desktop_ids,
+            next_score,
+            max_size,
+            notice: None,
+            read_only: false,
+            choices,
         }
     }
 
-    pub fn store(&self) {
-        let dir = Self::dirpath();
-        std::fs::create_dir_all(&dir).unwrap();
-        let pathname = format!("{}/{}", dir, FILE);
-        let data = ron::to_string(&self.entries).unwrap();
-        std::fs::write(&pathname, data).unwrap();
-        println!("History saved to {}", pathname);
+    /// Takes the hint about a history file that could not be loaded, this is
+    /// only returned once.
+    pub fn take_notice(&mut self) -> Option<String> {
+        self.notice.take()
+    }
+
+    pub fn store(&self) -> std::io::Result<()> {
+        let dir = paths::cache_dir().ok_or_else(paths::no_cache_dir)?;
+        std::fs::create_dir_all(&dir)?;
+        let pathname = dir.join(paths::HISTORY);
+        if self.read_only {
+            println!("Not overwriting {}", pathname.display());
+            return Ok(());
+        }
+        let data = ron::to_string(&HistoryFile {
+            version: VERSION,
+            entries: self.entries.iter().map(|item| &item.entry).collect(),
+            choices: &self.choices,
+        })
+        .unwrap();
+        std::fs::write(&pathname, data)?;
+        println!("History saved to {}", pathname.display());
+        Ok(())
     }
 
     pub fn add(&mut self, result: &SearchMatchKind, cache: &DesktopEntryCache) {
@@ -122,7 +360,7 @@ impl History {
         };
         // Remove old item for the same result
         for idx in 0..self.entries.len() {
-            if self.entries[idx] == entry {
+            if self.entries[idx].entry == entry {
                 self.entries.remove(idx);
                 break;
             }
@@ -131,14 +369,65 @@ impl History {
         if self.entries.len() == self.max_size {
             self.entries.pop_back();
         }
-        self.entries.push_front(entry);
+        self.entries.push_front(HistoryItem::new(entry, cache));
+    }
+
+    /// Remembers that `result` was committed for `query`.  Picking something
+    /// else for the same query weakens the remembered choice, until it's
+    /// replaced once it was outweighed.
+    pub fn learn(&mut self, query: &str, result: &SearchMatchKind, cache: &DesktopEntryCache) {
+        let SearchMatchKind::DeskopEntry(entry) = result else {
+            return;
+        };
+        let query = fold(query);
+        if query.chars().count() < MIN_CHOICE_QUERY_LEN {
+            return;
+        }
+        let file_name = &cache.get_entry(entry.id).file_name;
+        let mut choice = match self.choices.iter().position(|choice| choice.query == query) {
+            Some(idx) => self.choices.remove(idx).unwrap(),
+            None => Choice {
+                query,
+                file_name: file_name.clone(),
+                count: 0,
+            },
+        };
+        if choice.file_name == *file_name {
+            choice.count = (choice.count + 1).min(MAX_CHOICE_COUNT);
+        } else if choice.count > 1 {
+            choice.count -= 1;
+        } else {
+            choice.file_name = file_name.clone();
+            choice.count = 1;
+        }
+        self.choices.push_front(choice);
+        self.choices.truncate(MAX_CHOICES);
+    }
+
+    /// The id of the entry committed for `query`, or for a longer query
+    /// starting with it, the most recent one if there are several.
+    pub fn choice(&self, query: &str, cache: &DesktopEntryCache) -> Option<usize> {
+        let query = fold(query);
+        if query.chars().count() < MIN_CHOICE_QUERY_LEN {
+            return None;
+        }
+        let choice = self
+            .choices
+            .iter()
+            .find(|choice| choice.query == query)
+            .or_else(|| {
+                self.choices
+                    .iter()
+                    .find(|choice| choice.query.starts_with(&query))
+            })?;
+        cache.find_file(&choice.file_name)
     }
 
     pub fn desktop_ids(&self) -> &HashMap<usize, usize> {
         &self.desktop_ids
     }
 
-    pub fn entries(&mut self) -> &[Entry] {
+    pub fn entries(&mut self) -> &[HistoryItem] {
         self.entries.make_contiguous();
         self.entries.as_slices().0
     }
@@ -147,15 +436,295 @@ impl History {
         self.entries.is_empty()
     }
 
-    pub fn renew(&mut self, id: usize) {
-        let entry = self.entries.remove(id).unwrap();
-        self.entries.push_front(entry);
+    /// Gives `entry` the highest recency score and moves it to the front,
+    /// returns `false` if it's not in the history.
+    pub fn renew(&mut self, entry: &Entry, cache: &DesktopEntryCache) -> bool {
+        let Some(idx) = self.entries.iter().position(|item| item.entry == *entry) else {
+            return false;
+        };
+        let item = self.entries.remove(idx).unwrap();
+        if let Entry::DesktopEntry(file_name) = &item.entry {
+            if let Some(id) = cache.find_file(file_name) {
+                self.desktop_ids.insert(id, self.next_score);
+                self.next_score += 1;
+            }
+        }
+        self.entries.push_front(item);
+        true
+    }
+
+    /// Moves the desktop entries over to the cache `new` that was rebuilt from
+    /// `old`, entries that are no longer installed are dropped.
+    pub fn remap(&mut self, old: &DesktopEntryCache, new: &DesktopEntryCache) {
+        self.desktop_ids = self
+            .desktop_ids
+            .iter()
+            .filter_map(|(&id, &score)| {
+                let id = new.find_file(&old.get_entry(id).file_name)?;
+                Some((id, score))
+            })
+            .collect();
+        self.entries.retain_mut(|item| match &item.entry {
+            Entry::DesktopEntry(file_name) => {
+                item.resolved = Resolved::new(file_name, new);
+                item.resolved.is_some()
+            }
+            Entry::Path(_) => true,
+        });
+    }
+
+    /// Removes `entry` and the queries it was chosen for, returns `false` if
+    /// it's not in the history.
+    pub fn delete(&mut self, entry: &Entry, cache: &DesktopEntryCache) -> bool {
+        let Some(idx) = self.entries.iter().position(|item| item.entry == *entry) else {
+            return false;
+        };
+        if let Entry::DesktopEntry(file_name) = self.entries.remove(idx).unwrap().entry {
+            if let Some(id) = cache.find_file(&file_name) {
+                self.desktop_ids.remove(&id);
+            }
+            self.choices.retain(|choice| choice.file_name != file_name);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn desktop_entry(file_name: &str) -> Entry {
+        Entry::DesktopEntry(file_name.to_string())
+    }
+
+    fn history(cache: &DesktopEntryCache, file_names: &[&str]) -> History {
+        let mut history = History::new(DEFAULT_MAX_SIZE);
+        for file_name in file_names.iter().rev() {
+            history
+                .entries
+                .push_front(HistoryItem::new(desktop_entry(file_name), cache));
+            let id = cache.find_file(file_name).unwrap();
+            history.desktop_ids.insert(id, history.next_score);
+            history.next_score += 1;
+        }
+        history
+    }
+
+    fn order(history: &mut History) -> Vec<&str> {
+        history
+            .entries()
+            .iter()
+            .map(|item| match &item.entry {
+                Entry::DesktopEntry(file_name) => file_name.as_str(),
+                Entry::Path(_) => unreachable!(),
+            })
+            .collect()
+    }
+
+    fn score(history: &History, cache: &DesktopEntryCache, file_name: &str) -> Option<usize> {
+        let id = cache.find_file(file_name).unwrap();
+        history.desktop_ids().get(&id).copied()
+    }
+
+    #[test]
+    fn commit_after_delete() {
+        let cache = DesktopEntryCache::from_file_names(&["a", "b", "c", "d"]);
+        let mut history = history(&cache, &["a", "b", "c", "d"]);
+        assert!(history.delete(&desktop_entry("b"), &cache));
+        assert_eq!(score(&history, &cache, "b"), None);
+        assert!(!history.delete(&desktop_entry("b"), &cache));
+        // Index 1 is now "c", the same entry the list shows there
+        let committed = history.entries()[1].entry.clone();
+        assert!(history.renew(&committed, &cache));
+        assert_eq!(order(&mut history), ["c", "a", "d"]);
+        assert!(score(&history, &cache, "c") > score(&history, &cache, "a"));
+        assert!(!history.renew(&desktop_entry("b"), &cache));
+        assert_eq!(order(&mut history), ["c", "a", "d"]);
+    }
+
+    #[test]
+    fn file_versions() {
+        let entries = VecDeque::from([desktop_entry("a"), Entry::Path("/tmp".into())]);
+        let choices = VecDeque::from([Choice {
+            query: "ap".to_string(),
+            file_name: "a".to_string(),
+            count: 2,
+        }]);
+        let current = ron::to_string(&HistoryFile {
+            version: VERSION,
+            entries: entries.iter().collect(),
+            choices: &choices,
+        })
+        .unwrap();
+        let parsed = parse(&current).unwrap();
+        assert_eq!((parsed.entries, parsed.choices), (entries.clone(), choices));
+        let legacy = ron::to_string(&entries).unwrap();
+        assert_eq!(parse(&legacy).map(|file| file.entries), Ok(entries.clone()));
+        // Written before choices were remembered
+        let without_choices = format!(r#"(version: 1, entries: {})"#, legacy);
+        assert_eq!(
+            parse(&without_choices),
+            Ok(HistoryFileOwned {
+                entries,
+                choices: VecDeque::new()
+            })
+        );
+        assert_eq!(
+            parse(r#"(version: 7, entries: [Command("ls")], pinned: [])"#),
+            Err(LoadError::FutureVersion(7))
+        );
+        assert!(matches!(
+            parse(r#"[DesktopEntry("a""#),
+            Err(LoadError::Corrupt(_))
+        ));
+        assert!(matches!(
+            parse(r#"(version: 1, entries: [Command("ls")])"#),
+            Err(LoadError::Corrupt(_))
+        ));
+    }
+
+    #[test]
+    fn preserve_unreadable_files() {
+        let home = std::env::temp_dir().join(format!("launcher-history-{}", std::process::id()));
+        let dir = home.join(".cache/launcher");
+        std::fs::create_dir_all(&dir).unwrap();
+        let cache = DesktopEntryCache::from_file_names(&[]);
+        let future = r#"(version: 7, entries: [])"#;
+        paths::with_home(Some(&home), || {
+            for (data, backup) in [(future, "history.v7"), ("[", "history.corrupt")] {
+                std::fs::write(dir.join(paths::HISTORY), data).unwrap();
+                let mut history = History::load(&cache, 10);
+                assert!(history.is_empty());
+                assert!(history.take_notice().unwrap().contains(backup));
+                assert_eq!(history.take_notice(), None);
+                assert_eq!(std::fs::read_to_string(dir.join(backup)).unwrap(), data);
+                history.add(&SearchMatchKind::PathEntry(home.clone()), &cache);
+                history.store().unwrap();
+                let mut history = History::load(&cache, 10);
+                assert_eq!(history.take_notice(), None);
+                assert_eq!(history.entries()[0].entry, Entry::Path(home.clone()));
+                assert_eq!(history.entries().len(), 1);
+            }
+        });
+        std::fs::remove_dir_all(&home).unwrap();
     }
 
-    pub fn delete(&mut self, id: usize, cache: &DesktopEntryCache) {
-        if let Entry::DesktopEntry(file_name) = self.entries.remove(id).unwrap() {
-            let id = cache.find_file(&file_name).unwrap();
-            self.desktop_ids.remove(&id).unwrap();
+    #[test]
+    fn repeated_renews() {
+        let cache = DesktopEntryCache::from_file_names(&["a", "b", "c"]);
+        let mut history = history(&cache, &["a", "b", "c"]);
+        for file_name in ["c", "b", "b", "a", "c"] {
+            assert!(history.renew(&desktop_entry(file_name), &cache));
         }
+        assert_eq!(order(&mut history), ["c", "a", "b"]);
+        let scores = ["c", "a", "b"].map(|file_name| score(&history, &cache, file_name).unwrap());
+        assert!(scores[0] > scores[1] && scores[1] > scores[2]);
+        assert_eq!(history.desktop_ids().len(), 3);
+    }
+
+    #[test]
+    fn render_lookups() {
+        let file_names: Vec<String> = (0..2000).map(|i| format!("app{i}")).collect();
+        let file_names: Vec<&str> = file_names.iter().map(String::as_str).collect();
+        let cache = DesktopEntryCache::from_file_names(&file_names);
+        // The most recent entries are at the end of the cache, the worst case
+        // for looking them up.
+        let mut history = history(&cache, &file_names[1900..]);
+        assert_eq!(history.entries().len(), DEFAULT_MAX_SIZE);
+        // A name that's not borrowed from the resolved entry was looked up
+        let lookups = |history: &mut History, cache: &DesktopEntryCache| {
+            let mut lookups = 0;
+            for item in history.entries() {
+                let Entry::DesktopEntry(file_name) = &item.entry else {
+                    unreachable!()
+                };
+                let (name, _) = item.name_and_icon(file_name, cache);
+                let resolved = item.resolved.as_ref().unwrap();
+                if !std::ptr::eq(name, resolved.name.as_str()) {
+                    lookups += 1;
+                }
+            }
+            lookups
+        };
+        let render = |history: &mut History, cache: &DesktopEntryCache| -> Vec<String> {
+            history
+                .entries()
+                .iter()
+                .map(|item| item.markup("", cache))
+                .collect()
+        };
+        assert_eq!(lookups(&mut history, &cache), 0);
+        let markup = render(&mut history, &cache);
+        // Same entries with a different generation, like after a rebuild
+        let rebuilt = DesktopEntryCache::from_file_names(&file_names);
+        assert!(history.entries()[0].resolved(&rebuilt).is_none());
+        assert_eq!(lookups(&mut history, &rebuilt), DEFAULT_MAX_SIZE);
+        assert_eq!(render(&mut history, &rebuilt), markup);
+        assert_eq!(markup[0], "app1900");
+    }
+
+    #[test]
+    fn learned_choices() {
+        use crate::{
+            cache::Entry as CacheEntry,
+            search::{search, sort_search_results, SearchTuning},
+            text::Collator,
+        };
+        use std::sync::{Arc, Mutex};
+
+        let cache = Arc::new(Mutex::new(DesktopEntryCache::from_entries(vec![
+            CacheEntry::new(
+                "org.gnome.Terminal",
+                "GNOME Terminal",
+                Some("Terminal"),
+                "gnome-terminal",
+            ),
+            CacheEntry::new("kitty", "kitty", Some("Terminal emulator"), "kitty"),
+        ])));
+        let tuning = SearchTuning::default();
+        let first = |history: &History, query: &str| {
+            let (mut results, _) = search(query, cache.clone(), None, &tuning, None, None);
+            let choice = history.choice(query, &cache.lock().unwrap());
+            sort_search_results(
+                &mut results,
+                history.desktop_ids(),
+                choice,
+                &tuning,
+                &Collator::default(),
+            );
+            results[0].plain_text(&cache.lock().unwrap())
+        };
+        let commit = |history: &mut History, query: &str, name: &str| {
+            let (results, _) = search(query, cache.clone(), None, &tuning, None, None);
+            let cache = cache.lock().unwrap();
+            let result = results
+                .iter()
+                .find(|result| result.plain_text(&cache) == name)
+                .unwrap();
+            history.add(result.unwrap(), &cache);
+            history.learn(query, result.unwrap(), &cache);
+        };
+
+        let mut history = History::new(DEFAULT_MAX_SIZE);
+        assert_eq!(first(&history, "term"), "GNOME Terminal");
+        commit(&mut history, "term", "kitty");
+        commit(&mut history, "Term", "kitty");
+        // Launched more recently, but kitty is what's picked for "term"
+        commit(&mut history, "gnome", "GNOME Terminal");
+        assert_eq!(first(&history, "gnome"), "GNOME Terminal");
+        assert_eq!(first(&history, "term"), "kitty");
+        assert_eq!(first(&history, "te"), "kitty");
+        assert_eq!(first(&history, "terminal"), "GNOME Terminal");
+        // Picking something else once isn't enough to forget it
+        commit(&mut history, "term", "GNOME Terminal");
+        assert_eq!(first(&history, "term"), "kitty");
+        commit(&mut history, "term", "GNOME Terminal");
+        assert_eq!(first(&history, "term"), "GNOME Terminal");
+        // Deleting the entry forgets what it was picked for
+        commit(&mut history, "emul", "kitty");
+        assert_eq!(first(&history, "emul"), "kitty");
+        assert!(history.delete(&desktop_entry("kitty"), &cache.lock().unwrap()));
+        assert_eq!(history.choice("emul", &cache.lock().unwrap()), None);
+        assert_eq!(history.choices.len(), 2);
     }
 }
@@ -1,6 +1,14 @@
 // Copied from https://github.com/JaMo42/window_manager
+use crate::{paths, warnings::warning};
 use freedesktop_entry_parser::parse_entry;
-use std::{cell::RefCell, collections::HashMap, env::var, fs::metadata, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    env::var,
+    fs::metadata,
+    io::{Error, ErrorKind},
+    rc::Rc,
+};
 
 // https://specifications.freedesktop.org/icon-theme-spec/latest/
 
@@ -38,6 +46,25 @@ fn find_icon_dir(name: &str) -> Option<String> {
     None
 }
 
+const PIXMAPS_DIR: &str = "/usr/share/pixmaps";
+
+/// Extensions that are stripped from icon names.
+const ICON_EXTENSIONS: &[&str] = &["svg", "png", "xpm"];
+
+/// Extensions of icons in the pixmaps directory, in order of preference.
+/// XPM is not included since we can't draw it.
+const PIXMAP_EXTENSIONS: &[&str] = &["svg", "png"];
+
+/// The icon theme name set in the GTK settings.
+fn gtk_theme() -> Option<String> {
+    let config_home = paths::config_home()?;
+    ["gtk-3.0", "gtk-4.0"].iter().find_map(|version| {
+        let entry = parse_entry(config_home.join(version).join("settings.ini")).ok()?;
+        let name = entry.section("Settings").attr("gtk-icon-theme-name")?;
+        Some(name.trim_matches('"').to_string())
+    })
+}
+
 #[derive(Default, Debug)]
 pub struct IconRegistry {
     themes: Vec<IconTheme>,
@@ -46,29 +73,42 @@ pub struct IconRegistry {
 impl IconRegistry {
     // This the main entry point of the icon system, which is called with the
     // configured icon theme name.
-    pub fn new(theme: &str) -> std::io::Result<Self> {
+    //
+    // If the theme can't be loaded the GTK theme and then `hicolor` are tried,
+    // if none of them work the registry is empty and lookups only find
+    // absolute paths and pixmaps.
+    pub fn new(theme: &str) -> Self {
+        let mut candidates = vec![theme.to_string()];
+        for fallback in gtk_theme().into_iter().chain(["hicolor".to_string()]) {
+            if !candidates.contains(&fallback) {
+                candidates.push(fallback);
+            }
+        }
+        for name in candidates {
+            match Self::load(&name) {
+                Ok(registry) => return registry,
+                Err(error) => warning!("Failed to load icon theme {}: {}", name, error),
+            }
+        }
+        warning!("No icon theme could be loaded, continuing without icons");
+        Self::empty()
+    }
+
+    fn load(theme: &str) -> std::io::Result<Self> {
         println!("Looking for icon theme: {}", theme);
         // Themes are inserted such that the insertion order is the correct
         // order we want for lookups.
         let mut themes = HashMap::new();
         let mut in_order = Vec::new();
-        match find_icon_dir(theme).or_else(|| {
-            eprintln!("Configured theme not found: {}", theme);
-            find_icon_dir("hicolor")
-        }) {
-            Some(path) => {
-                println!("  Found main theme at: {}", path);
-                let name = theme;
-                let theme = Rc::new(RefCell::new(IconTheme::default()));
-                themes.insert(name.to_string(), theme.clone());
-                in_order.push(name.to_string());
-                theme
-                    .borrow_mut()
-                    .create(path, &mut themes, &mut in_order)?;
-            }
-            // This is exceptional; `hicolor` must exist
-            None => panic!("No icon theme found at all"),
-        };
+        let path = find_icon_dir(theme)
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "theme directory not found"))?;
+        println!("  Found main theme at: {}", path);
+        {
+            let main = Rc::new(RefCell::new(IconTheme::default()));
+            themes.insert(theme.to_string(), main.clone());
+            in_order.push(theme.to_string());
+            main.borrow_mut().create(path, &mut themes, &mut in_order)?;
+        }
         // Use Adwaita as additional fallback.
         if theme != "Adwaita" {
             if let Some(path) = find_icon_dir("Adwaita") {
@@ -98,10 +138,41 @@ impl IconRegistry {
         })
     }
 
+    /// A registry without themes, lookups only find absolute paths and
+    /// pixmaps.
+    pub const fn empty() -> Self {
+        Self { themes: Vec::new() }
+    }
+
+    /// A registry of a single theme with the given directories.
+    #[cfg(test)]
+    pub fn with_directories(directories: Vec<String>) -> Self {
+        Self {
+            themes: vec![IconTheme {
+                directories,
+                app_dir: None,
+            }],
+        }
+    }
+
+    /// Finds the icon for the `Icon` value of a desktop entry.
     pub fn lookup(&self, name: &str) -> Option<String> {
+        self.lookup_with_pixmaps(name, PIXMAPS_DIR)
+    }
+
+    fn lookup_with_pixmaps(&self, name: &str, pixmaps: &str) -> Option<String> {
         if name.starts_with('/') {
-            return Some(name.to_string());
+            if metadata(name).is_ok() {
+                return Some(name.to_string());
+            }
+            eprintln!("Icon not found: {}", name);
+            return None;
         }
+        // Names should not have an extension but some do anyways.
+        let name = ICON_EXTENSIONS
+            .iter()
+            .find_map(|extension| name.strip_suffix(extension)?.strip_suffix('.'))
+            .unwrap_or(name);
         for theme in &self.themes {
             for directory in &theme.directories {
                 let path = format!("{}/{}.svg", directory, name);
@@ -110,9 +181,11 @@ impl IconRegistry {
                 }
             }
         }
-        let path = format!("/usr/share/pixmaps/{}.svg", name);
-        if metadata(&path).is_ok() {
-            return Some(path);
+        for extension in PIXMAP_EXTENSIONS {
+            let path = format!("{}/{}.{}", pixmaps, name, extension);
+            if metadata(&path).is_ok() {
+                return Some(path);
+            }
         }
         None
     }
@@ -153,7 +226,7 @@ impl IconTheme {
                 insertion_order.push(name.to_string());
                 theme.borrow_mut().create(path, known, insertion_order)?;
             } else {
-                eprintln!("Inherited icon theme not found: {}", name);
+                warning!("Inherited icon theme not found: {}", name);
             }
         }
         if let Some(directories) = icon_theme.attr("Directories") {
@@ -174,8 +247,75 @@ impl IconTheme {
                 }
             }
         } else {
-            eprintln!("No `Directories` in index.theme: {}", basepathname);
+            warning!("No `Directories` in index.theme: {}", basepathname);
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, path::Path};
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn missing_theme() {
+        let home = std::env::temp_dir().join(format!("launcher-icons-{}", std::process::id()));
+        paths::with_home(Some(&home), || {
+            std::env::set_var("XDG_DATA_DIRS", home.join("share"));
+            // Nothing at all
+            let registry = IconRegistry::new("does-not-exist");
+            assert!(registry.themes.is_empty());
+            assert_eq!(registry.lookup("app"), None);
+            assert_eq!(registry.lookup("/does/not/exist.svg"), None);
+            // Falls back to the GTK theme
+            write(
+                &home.join(".config/gtk-3.0/settings.ini"),
+                "[Settings]\ngtk-icon-theme-name=Gtk\n",
+            );
+            let theme = home.join(".icons/Gtk");
+            write(
+                &theme.join("index.theme"),
+                "[Icon Theme]\nName=Gtk\nDirectories=apps\n\n[apps]\nType=Scalable\n",
+            );
+            write(&theme.join("apps/app.svg"), "");
+            let registry = IconRegistry::new("does-not-exist");
+            assert_eq!(
+                registry.lookup("app"),
+                Some(format!("{}/apps/app.svg", theme.display()))
+            );
+        });
+        fs::remove_dir_all(&home).unwrap();
+    }
+
+    #[test]
+    fn desktop_entry_icons() {
+        let dir = std::env::temp_dir().join(format!("launcher-icon-files-{}", std::process::id()));
+        let theme = dir.join("theme");
+        let pixmaps = dir.join("pixmaps");
+        write(&theme.join("app.svg"), "");
+        write(&pixmaps.join("legacy.png"), "");
+        write(&pixmaps.join("old.xpm"), "");
+        write(&dir.join("opt/icon.png"), "");
+        let registry = IconRegistry::with_directories(vec![theme.display().to_string()]);
+        let lookup = |name: &str| registry.lookup_with_pixmaps(name, pixmaps.to_str().unwrap());
+        let absolute = dir.join("opt/icon.png").display().to_string();
+        assert_eq!(lookup(&absolute), Some(absolute.clone()));
+        assert_eq!(lookup(&format!("{}/missing.png", dir.display())), None);
+        let themed = Some(theme.join("app.svg").display().to_string());
+        assert_eq!(lookup("app"), themed);
+        assert_eq!(lookup("app.svg"), themed);
+        assert_eq!(lookup("app.png"), themed);
+        let pixmap = Some(pixmaps.join("legacy.png").display().to_string());
+        assert_eq!(lookup("legacy"), pixmap);
+        assert_eq!(lookup("legacy.png"), pixmap);
+        assert_eq!(lookup("old"), None);
+        assert_eq!(lookup("missing"), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,10 +1,13 @@
-use crate::x::{lookup_keysym, Display, Window};
+use crate::{
+    ui::FocusDirection,
+    x::{lookup_keysym, Display, Window},
+};
 use std::ffi::{c_void, CStr, CString};
 use x11::xlib::{
-    ControlMask, ShiftMask, XBufferOverflow, XCreateIC, XFree, XIMPreeditNothing, XIMStatusNothing,
-    XKeyEvent, XNClientWindow, XNInputStyle, XNPreeditAttributes, XNSpotLocation, XOpenIM, XPoint,
-    XSetICFocus, XSetICValues, XSetLocaleModifiers, XVaCreateNestedList, Xutf8LookupString, XIC,
-    XIM,
+    ControlMask, Mod1Mask, ShiftMask, XBufferOverflow, XCreateIC, XFree, XIMPreeditNothing,
+    XIMStatusNothing, XKeyEvent, XNClientWindow, XNInputStyle, XNPreeditAttributes, XNSpotLocation,
+    XOpenIM, XPoint, XSetICFocus, XSetICValues, XSetLocaleModifiers, XVaCreateNestedList,
+    Xutf8LookupString, XIC, XIM,
 };
 
 /// Special keys, all text is handled through the input management engine.
@@ -21,12 +24,20 @@ pub enum Key {
     CtrlC,
     CtrlX,
     CtrlV,
+    CtrlI,
+    CtrlR,
+    CtrlT,
+    AltD,
     Enter,
     Backspace,
     Delete,
     Home,
     End,
+    PageUp,
+    PageDown,
     Tab,
+    Insert,
+    F5,
 }
 
 pub struct KeyEvent {
@@ -40,6 +51,15 @@ impl KeyEvent {
         use Key::*;
         matches!(self.key, Left | Right | Home | End)
     }
+
+    /// Direction to move the focus in when this is a tab press.
+    pub fn tab_direction(&self) -> FocusDirection {
+        if self.is_shift {
+            FocusDirection::Backward
+        } else {
+            FocusDirection::Forward
+        }
+    }
 }
 
 type XVaNestedList = *mut c_void;
@@ -204,6 +224,7 @@ pub fn init(display: &Display, window: &Window) -> InputContext {
 pub fn translate_key(event: &XKeyEvent) -> Option<KeyEvent> {
     use x11::keysym::*;
     let is_ctrl = event.state & ControlMask == ControlMask;
+    let is_alt = event.state & Mod1Mask == Mod1Mask;
     #[allow(non_upper_case_globals)]
     let key = match lookup_keysym(event) as u32 {
         XK_Up => Key::Up,
@@ -215,12 +236,21 @@ pub fn translate_key(event: &XKeyEvent) -> Option<KeyEvent> {
         XK_c | XK_C if is_ctrl => Key::CtrlC,
         XK_x | XK_X if is_ctrl => Key::CtrlX,
         XK_v | XK_V if is_ctrl => Key::CtrlV,
+        XK_i | XK_I if is_ctrl => Key::CtrlI,
+        XK_r | XK_R if is_ctrl => Key::CtrlR,
+        XK_t | XK_T if is_ctrl => Key::CtrlT,
+        XK_d | XK_D if is_alt => Key::AltD,
         XK_Return => Key::Enter,
         XK_BackSpace => Key::Backspace,
         XK_Delete => Key::Delete,
         XK_Home => Key::Home,
         XK_End => Key::End,
-        XK_Tab => Key::Tab,
+        XK_Prior => Key::PageUp,
+        XK_Next => Key::PageDown,
+        XK_Insert => Key::Insert,
+        // Shift+Tab usually produces ISO_Left_Tab
+        XK_Tab | XK_ISO_Left_Tab => Key::Tab,
+        XK_F5 => Key::F5,
         _ => return None,
     };
     Some(KeyEvent {
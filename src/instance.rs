@@ -0,0 +1,296 @@
+//! Keeping a second launcher from opening on top of a running one.
+//!
+//! The running instance holds a lock file containing its pid and, once it's
+//! created, the id of its window.  A lock whose process is gone is left over
+//! from a crash and gets replaced.
+
+use crate::{
+    paths,
+    x::{Display, XWindow},
+};
+use std::{
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+/// Message type of the client message asking the running instance to show
+/// itself.
+pub const PRESENT_ATOM: &std::ffi::CStr = c"_LAUNCHER_PRESENT";
+
+/// What to do when another instance is already running.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InstanceMode {
+    /// Allow multiple instances.
+    Off,
+    /// Exit silently.
+    Exit,
+    /// Ask the running instance to raise and focus its window, then exit.
+    Raise,
+}
+
+impl InstanceMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(Self::Off),
+            "exit" => Some(Self::Exit),
+            "raise" => Some(Self::Raise),
+            _ => None,
+        }
+    }
+}
+
+/// The process holding the lock.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Owner {
+    pub pid: u32,
+    pub window: Option<XWindow>,
+}
+
+impl Owner {
+    fn parse(content: &str) -> Option<Self> {
+        let mut fields = content.split_whitespace();
+        let pid = fields.next()?.parse().ok()?;
+        let window = fields.next().and_then(|window| window.parse().ok());
+        Some(Self { pid, window })
+    }
+
+    fn format(&self) -> String {
+        match self.window {
+            Some(window) => format!("{} {window}\n", self.pid),
+            None => format!("{}\n", self.pid),
+        }
+    }
+
+    /// Asks the owner to show its window.
+    pub fn present(&self, display: &Display) {
+        if let Some(window) = self.window {
+            let atom = display.intern_atom(PRESENT_ATOM);
+            display.send_client_message(window, atom);
+            display.flush();
+        }
+    }
+}
+
+/// The lock of the running instance, removed when dropped.
+#[derive(Debug)]
+pub struct InstanceLock {
+    path: PathBuf,
+}
+
+impl InstanceLock {
+    /// Records the window so other instances can ask us to show it.
+    pub fn set_window(&self, window: XWindow) -> std::io::Result<()> {
+        let owner = Owner {
+            pid: std::process::id(),
+            window: Some(window),
+        };
+        paths::write_atomic(&self.path, owner.format())
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+#[derive(Debug)]
+pub enum Acquired {
+    Lock(InstanceLock),
+    Running(Owner),
+}
+
+/// Whether `pid` is a running launcher.  The pid of a crashed instance may
+/// have been reused by another program, so its executable is compared as
+/// well where possible.
+pub fn is_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    let exists = unsafe { libc::kill(pid, 0) } == 0
+        || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM);
+    if !exists {
+        return false;
+    }
+    match (
+        std::fs::read_link(format!("/proc/{pid}/exe")),
+        std::env::current_exe(),
+    ) {
+        (Ok(exe), Ok(own)) => exe == own,
+        _ => true,
+    }
+}
+
+/// Removes the lock at `path` if it still contains `stale`.  Instances that
+/// found the same stale lock would otherwise remove the lock the first of
+/// them replaced it with, so the check and removal happen while holding a
+/// file lock on a guard next to it.  The guard is never removed, that would
+/// race the same way.
+fn remove_stale(path: &Path, stale: &str) -> std::io::Result<()> {
+    let mut guard = path.as_os_str().to_owned();
+    guard.push(".guard");
+    let guard = std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(PathBuf::from(guard))?;
+    guard.lock()?;
+    // Unlocked when the guard is closed
+    match std::fs::read_to_string(path) {
+        Ok(content) if content == stale => std::fs::remove_file(path),
+        Ok(_) => Ok(()),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(error),
+    }
+}
+
+/// Takes the lock at `path` unless a process for which `is_alive` returns
+/// `true` holds it.
+fn acquire_at(path: &Path, is_alive: impl Fn(u32) -> bool) -> std::io::Result<Acquired> {
+    let own = Owner {
+        pid: std::process::id(),
+        window: None,
+    };
+    // The pid is written to a temporary file first and then linked into place
+    // so other instances never see an empty lock.
+    let temp = paths::temp_path(path);
+    std::fs::File::create(&temp)?.write_all(own.format().as_bytes())?;
+    let mut result = Err(ErrorKind::AlreadyExists.into());
+    // Only retry once, if the lock keeps reappearing something else is going
+    // on.
+    for _ in 0..2 {
+        match std::fs::hard_link(&temp, path) {
+            Ok(()) => {
+                result = Ok(Acquired::Lock(InstanceLock {
+                    path: path.to_owned(),
+                }));
+                break;
+            }
+            Err(error) if error.kind() == ErrorKind::AlreadyExists => {}
+            Err(error) => {
+                result = Err(error);
+                break;
+            }
+        }
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            // Released in the meantime
+            Err(error) if error.kind() == ErrorKind::NotFound => continue,
+            Err(error) => {
+                result = Err(error);
+                break;
+            }
+        };
+        match Owner::parse(&content) {
+            Some(owner) if owner.pid != own.pid && is_alive(owner.pid) => {
+                result = Ok(Acquired::Running(owner));
+                break;
+            }
+            _ => {
+                if let Err(error) = remove_stale(path, &content) {
+                    result = Err(error);
+                    break;
+                }
+            }
+        }
+    }
+    std::fs::remove_file(&temp).ok();
+    result
+}
+
+/// Takes the single instance lock, or returns the instance holding it.
+pub fn acquire() -> std::io::Result<Acquired> {
+    let path = paths::runtime_file(paths::INSTANCE_LOCK).ok_or_else(paths::no_cache_dir)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    acquire_at(&path, is_alive)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lock_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("launcher-lock-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    #[test]
+    fn acquisition() {
+        let path = lock_path("acquisition");
+        let lock = match acquire_at(&path, |_| true).unwrap() {
+            Acquired::Lock(lock) => lock,
+            Acquired::Running(owner) => panic!("held by {owner:?}"),
+        };
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content, format!("{}\n", std::process::id()));
+        // Our own pid in the lock is never considered running
+        assert!(matches!(
+            acquire_at(&path, |_| true).unwrap(),
+            Acquired::Lock(_)
+        ));
+        lock.set_window(42).unwrap();
+        assert_eq!(
+            Owner::parse(&std::fs::read_to_string(&path).unwrap()),
+            Some(Owner {
+                pid: std::process::id(),
+                window: Some(42)
+            })
+        );
+        drop(lock);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn running_and_stale() {
+        let path = lock_path("running");
+        std::fs::write(&path, "1 1234\n").unwrap();
+        match acquire_at(&path, |pid| pid == 1).unwrap() {
+            Acquired::Running(owner) => assert_eq!(
+                owner,
+                Owner {
+                    pid: 1,
+                    window: Some(1234)
+                }
+            ),
+            Acquired::Lock(_) => panic!("acquired a held lock"),
+        }
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "1 1234\n");
+        for stale in ["2 1234\n", "", "garbage"] {
+            std::fs::write(&path, stale).unwrap();
+            let Acquired::Lock(_lock) = acquire_at(&path, |pid| pid == 1).unwrap() else {
+                panic!("stale lock {stale:?} was not replaced");
+            };
+            assert_eq!(
+                std::fs::read_to_string(&path).unwrap(),
+                format!("{}\n", std::process::id())
+            );
+        }
+        assert!(!path.exists());
+        assert!(!paths::temp_path(&path).exists());
+    }
+
+    #[test]
+    fn stale_lock_race() {
+        let path = lock_path("race");
+        // Another instance replaced the stale lock since it was read
+        std::fs::write(&path, "1\n").unwrap();
+        remove_stale(&path, "2\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "1\n");
+        remove_stale(&path, "1\n").unwrap();
+        assert!(!path.exists());
+        // Or released it
+        remove_stale(&path, "1\n").unwrap();
+    }
+
+    #[test]
+    fn liveness() {
+        assert!(is_alive(std::process::id()));
+        let child = std::process::Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait_with_output().unwrap();
+        assert!(!is_alive(pid));
+    }
+}
@@ -0,0 +1,137 @@
+//! Keeping a "Launching …" strip visible after committing, until the launched
+//! program maps a window or the configured time runs out.
+
+use crate::{
+    cache::{exec_name, DesktopEntryCache},
+    window_matching::{match_window, WindowClass},
+    x::XWindow,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+pub struct LaunchFeedback {
+    until: Instant,
+    /// The launched desktop entry.
+    entry: Option<usize>,
+    /// Folded name of the launched program, if neither this nor `entry` is
+    /// known any new window ends the feedback.
+    program: Option<String>,
+    /// Windows that existed before launching.
+    known: HashSet<XWindow>,
+}
+
+impl LaunchFeedback {
+    /// `clients` are the windows that exist before launching `command`.
+    pub fn new(
+        duration: Duration,
+        now: Instant,
+        entry: Option<usize>,
+        command: Option<&str>,
+        clients: &[XWindow],
+    ) -> Self {
+        Self {
+            until: now + duration,
+            entry,
+            program: command.map(exec_name).filter(|name| !name.is_empty()),
+            known: clients.iter().copied().collect(),
+        }
+    }
+
+    pub fn deadline(&self) -> Instant {
+        self.until
+    }
+
+    pub fn expired(&self, now: Instant) -> bool {
+        now >= self.until
+    }
+
+    /// Whether a window of the launched program is among `clients`,
+    /// `class_of` gets the `WM_CLASS` of a window.
+    pub fn window_appeared(
+        &mut self,
+        clients: &[XWindow],
+        class_of: impl Fn(XWindow) -> Option<WindowClass>,
+        cache: &DesktopEntryCache,
+        overrides: &HashMap<String, String>,
+    ) -> bool {
+        let expects_anything = self.entry.is_none() && self.program.is_none();
+        for &window in clients {
+            if !self.known.insert(window) {
+                continue;
+            }
+            if expects_anything {
+                return true;
+            }
+            let Some(class) = class_of(window) else {
+                continue;
+            };
+            let is_entry = self
+                .entry
+                .is_some_and(|entry| match_window(&class, cache, overrides) == Some(entry));
+            let is_program = self
+                .program
+                .as_deref()
+                .is_some_and(|program| class.matches(program));
+            if is_entry || is_program {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class_of(window: XWindow) -> Option<WindowClass> {
+        let (instance, class) = match window {
+            1 => ("xterm", "XTerm"),
+            2 => ("Navigator", "firefox"),
+            3 => ("code", "Code"),
+            4 => ("Navigator", "firefox"),
+            _ => return None,
+        };
+        Some(WindowClass {
+            instance: instance.to_string(),
+            class: class.to_string(),
+        })
+    }
+
+    #[test]
+    fn expected_windows() {
+        let mut cache = DesktopEntryCache::from_file_names(&["firefox.desktop", "code.desktop"]);
+        cache.set_exec("code.desktop", "/usr/share/code/code --unity-launch");
+        let overrides = HashMap::new();
+        let now = Instant::now();
+        let duration = Duration::from_secs(3);
+        let firefox = cache.find_file("firefox.desktop");
+
+        let mut feedback = LaunchFeedback::new(duration, now, firefox, Some("firefox"), &[2]);
+        // Already open before launching
+        assert!(!feedback.window_appeared(&[1, 2], class_of, &cache, &overrides));
+        assert!(!feedback.window_appeared(&[1, 2, 5], class_of, &cache, &overrides));
+        assert!(feedback.window_appeared(&[1, 2, 5, 4], class_of, &cache, &overrides));
+        assert_eq!(feedback.deadline(), now + duration);
+        assert!(!feedback.expired(now));
+        assert!(feedback.expired(now + duration));
+
+        // A `$` command, matched by the program name
+        let mut feedback =
+            LaunchFeedback::new(duration, now, None, Some("/usr/bin/xterm -e htop"), &[]);
+        assert!(!feedback.window_appeared(&[2, 3], class_of, &cache, &overrides));
+        assert!(feedback.window_appeared(&[1], class_of, &cache, &overrides));
+
+        // Matched by the desktop entry
+        let code = cache.find_file("code.desktop");
+        let mut feedback = LaunchFeedback::new(duration, now, code, Some("true"), &[]);
+        assert!(feedback.window_appeared(&[3], class_of, &cache, &overrides));
+
+        // Nothing known, the next new window ends it
+        let mut feedback = LaunchFeedback::new(duration, now, None, None, &[1]);
+        assert!(!feedback.window_appeared(&[1], class_of, &cache, &overrides));
+        assert!(feedback.window_appeared(&[1, 7], class_of, &cache, &overrides));
+    }
+}
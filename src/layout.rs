@@ -1,7 +1,7 @@
-use crate::config::Config;
+use crate::{config::Config, fonts, warnings::warning};
 use pango::FontDescription;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Rectangle {
     pub x: i32,
     pub y: i32,
@@ -77,12 +77,15 @@ impl LayoutBuilder {
         }
     }
 
+    /// Shrinks the available area on all sides, never past its center.
     fn margin(&mut self, margin: u32) {
+        let horizontal = margin.min(self.available.width / 2);
+        let vertical = margin.min(self.available.height / 2);
         self.available = Rectangle {
-            x: self.available.x + margin as i32,
-            y: self.available.y + margin as i32,
-            width: self.available.width - 2 * margin,
-            height: self.available.height - 2 * margin,
+            x: self.available.x + horizontal as i32,
+            y: self.available.y + vertical as i32,
+            width: self.available.width - 2 * horizontal,
+            height: self.available.height - 2 * vertical,
         };
     }
 
@@ -95,27 +98,35 @@ impl LayoutBuilder {
         u32::min(self.available.width, self.available.height)
     }
 
-    fn add_top_child(&mut self, size: u32, space: i32) -> LayoutBuilder {
+    /// Takes `size` from the top of the available area, followed by `space`.
+    /// Both are clamped to what's available.
+    fn add_top_child(&mut self, size: u32, space: u32) -> LayoutBuilder {
+        let size = size.min(self.available.height);
         let top = Rectangle {
             x: self.available.x,
             y: self.available.y,
             width: self.available.width,
             height: size,
         };
-        self.available.y += size as i32 + space;
-        self.available.height -= size + space as u32;
+        let taken = (size + space).min(self.available.height);
+        self.available.y += taken as i32;
+        self.available.height -= taken;
         LayoutBuilder::new(top)
     }
 
-    fn add_left_child(&mut self, size: u32, space: i32) -> LayoutBuilder {
+    /// Takes `size` from the left of the available area, followed by `space`.
+    /// Both are clamped to what's available.
+    fn add_left_child(&mut self, size: u32, space: u32) -> LayoutBuilder {
+        let size = size.min(self.available.width);
         let left = Rectangle {
             x: self.available.x,
             y: self.available.y,
             width: size,
             height: self.available.height,
         };
-        self.available.x += size as i32 + space;
-        self.available.width -= size + space as u32;
+        let taken = (size + space).min(self.available.width);
+        self.available.x += taken as i32;
+        self.available.width -= taken;
         LayoutBuilder::new(left)
     }
 
@@ -165,7 +176,7 @@ impl EntryLayout {
         box_.margin(margin);
         let icon = box_.add_left_child(box_.available_square_size(), 0);
         box_.available.x -= 4;
-        box_.available.width -= 8;
+        box_.available.width = box_.available.width.saturating_sub(8);
         let text = box_.available();
         let cursor_height = text.total.height * 80 / 100;
         let cursor_y = text.total.y + (text.total.height - cursor_height) as i32 / 2;
@@ -200,8 +211,8 @@ impl ListViewLayout {
         // items is created in `get_item_rects`.
         let mut item = list_view.add_top_child(config.list_item_height, 0);
         item.available.y += 4;
-        item.available.height -= 8;
-        item.available.width -= config.scroll_bar_width;
+        item.available.height = item.available.height.saturating_sub(8);
+        item.available.width = item.available.width.saturating_sub(config.scroll_bar_width);
         let icon = item.add_left_child(config.list_item_height, 4);
         let text = item.available();
         Self {
@@ -225,7 +236,7 @@ impl ListViewLayout {
     }
 
     pub fn add_secondary_icon(text: &mut Rectangle) -> Rectangle {
-        text.width -= text.height;
+        text.width = text.width.saturating_sub(text.height);
         let mut icon = Rectangle::new(text.x + text.width as i32, text.y, text.height, text.height);
         icon.scale(70);
         icon
@@ -247,7 +258,7 @@ impl SmartContentLayout {
         let reparent = smart_content.make_origin();
         let icon_size = real_height;
         let mut icon = smart_content.add_left_child(icon_size, 0).into_rect();
-        icon.y += (smart_content.total.height - icon_size) as i32 / 2;
+        icon.y += smart_content.total.height.saturating_sub(icon_size) as i32 / 2;
         icon.height = icon_size;
         smart_content.add_left_child(0, 10);
         let text = smart_content.available().into_rect();
@@ -260,7 +271,12 @@ impl SmartContentLayout {
     }
 }
 
+/// Space for text next to the icons the smallest layout still leaves.
+const MIN_TEXT_WIDTH: u32 = 100;
+
 impl Layout {
+    /// The requested window size, the layout may need more, see
+    /// `minimum_size`.
     pub fn window_size(screen_width: u32, screen_height: u32, config: &Config) -> (u32, u32) {
         (
             screen_width * config.window_width_percent / 100,
@@ -268,25 +284,68 @@ impl Layout {
         )
     }
 
+    /// Top-left corner of a window of the given size on `monitor`, `percent`
+    /// is how much of the space around it is to its left and above it.  A
+    /// window larger than the monitor overhangs it the same way.
+    pub fn window_position(
+        monitor: &Rectangle,
+        (x_percent, y_percent): (u32, u32),
+        width: u32,
+        height: u32,
+    ) -> (i32, i32) {
+        let offset = |space: i64, percent: u32| (space * percent as i64 / 100) as i32;
+        (
+            monitor.x + offset(monitor.width as i64 - width as i64, x_percent),
+            monitor.y + offset(monitor.height as i64 - height as i64, y_percent),
+        )
+    }
+
+    /// The smallest window that fits the entry, the smart content, and one
+    /// list item below it, with some room for text next to each icon.
+    fn minimum_size(config: &Config, smart_content_height: u32) -> (u32, u32) {
+        let delta = config.list_item_height.abs_diff(smart_content_height);
+        let width = 2 * 10
+            + MIN_TEXT_WIDTH
+            + config
+                .entry_height
+                .max(config.list_item_height + 4 + config.scroll_bar_width)
+                .max(smart_content_height + 10);
+        let height = 2 * 10
+            + config.entry_height
+            + 10
+            + smart_content_height
+            + delta
+            + config.list_item_height;
+        (width, height)
+    }
+
     pub fn new(
         screen_width: u32,
         screen_height: u32,
         config: &Config,
         font_height: impl Fn(&FontDescription) -> i32,
     ) -> Self {
+        let smart_content_height = {
+            let font = fonts::load(&config.smart_content_font);
+            font_height(&font).max(0) as u32
+        };
+        let (mut width, mut height) = Self::window_size(screen_width, screen_height, config);
+        let (min_width, min_height) = Self::minimum_size(config, smart_content_height);
+        if width < min_width || height < min_height {
+            width = width.max(min_width);
+            height = height.max(min_height);
+            warning!("Window too small for the layout, using {width}x{height}");
+        }
         let mut window = LayoutBuilder::new(Rectangle {
             x: 0,
             y: 0,
-            width: screen_width * config.window_width_percent / 100,
-            height: screen_height * config.window_height_percent / 100,
+            width,
+            height,
         });
         window.margin(10);
         let entry = window.add_top_child(config.entry_height, 10);
         let full_list_view = window.available();
-        let mut smart_content = {
-            let font = FontDescription::from_string(&config.smart_content_font);
-            window.add_top_child(font_height(&font) as _, 0)
-        };
+        let mut smart_content = window.add_top_child(smart_content_height, 0);
         let reduced_list_view = window.available();
         let mut entry = EntryLayout::new(entry);
         let mut full_list_view = ListViewLayout::new(full_list_view, config);
@@ -299,14 +358,17 @@ impl Layout {
         window.total.height -= full_list_view.window.height - full_list_view_height;
         full_list_view.window.height = full_list_view_height;
 
-        let reduced_list_view_height =
-            reduced_list_view.window.height / config.list_item_height * config.list_item_height;
-        let delta = (config.list_item_height as i32 - smart_content.total.height as i32).abs();
+        // The smart content row is grown by `delta` and the reduced list
+        // moved down by it, so it has that much less space.
+        let delta = config.list_item_height.abs_diff(smart_content.total.height);
+        let reduced_list_view_height = reduced_list_view.window.height.saturating_sub(delta)
+            / config.list_item_height
+            * config.list_item_height;
         reduced_list_view.window.height = reduced_list_view_height;
-        reduced_list_view.reparent.1 += delta;
+        reduced_list_view.reparent.1 += delta as i32;
 
         let real_height = smart_content.total.height;
-        smart_content.total.height += delta as u32;
+        smart_content.total.height += delta;
         smart_content.available.height = smart_content.total.height;
         let smart_content = SmartContentLayout::new(smart_content, real_height);
 
@@ -319,3 +381,106 @@ impl Layout {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ParsedConfig;
+
+    fn assert_within(name: &str, rect: &Rectangle, parent: &Rectangle) {
+        assert!(
+            rect.x >= 0
+                && rect.y >= 0
+                && rect.x as u32 + rect.width <= parent.width
+                && rect.y as u32 + rect.height <= parent.height,
+            "{name} {rect:?} not within {parent:?}"
+        );
+    }
+
+    #[test]
+    fn pathological_sizes() {
+        let config = Config::from_parsed(ParsedConfig::default(), false);
+        for (screen_width, screen_height) in [(1, 1), (50, 4000), (4000, 50), (0, 0)] {
+            let layout = Layout::new(screen_width, screen_height, &config, |_| 40);
+            let window = layout.window;
+            assert!(window.width >= MIN_TEXT_WIDTH && window.height >= config.list_item_height);
+
+            let entry = &layout.entry;
+            let entry_window = entry.window.at(entry.reparent);
+            assert_within("entry", &entry_window, &window);
+            for (name, rect) in [
+                ("entry box", entry.box_),
+                ("entry icon", entry.icon),
+                ("entry text", entry.text),
+            ] {
+                assert_within(name, &rect, &entry.window);
+            }
+
+            for (name, list_view) in [
+                ("full list", &layout.full_list_view),
+                ("reduced list", &layout.reduced_list_view),
+            ] {
+                assert_within(name, &list_view.window.at(list_view.reparent), &window);
+                assert!(list_view.window.height >= list_view.item_height, "{name}");
+                let (background, icon, text) = list_view.get_item_rects(0);
+                assert_within(name, &background, &list_view.window);
+                assert_within(name, &icon, &list_view.window);
+                assert_within(name, &text, &list_view.window);
+                assert!(text.width > 0, "{name}");
+            }
+
+            let smart_content = &layout.smart_content;
+            assert_within(
+                "smart content",
+                &smart_content.window.at(smart_content.reparent),
+                &window,
+            );
+            assert_within(
+                "smart content icon",
+                &smart_content.icon,
+                &smart_content.window,
+            );
+            assert_within(
+                "smart content text",
+                &smart_content.text,
+                &smart_content.window,
+            );
+        }
+    }
+
+    #[test]
+    fn large_window_is_unchanged() {
+        let config = Config::from_parsed(ParsedConfig::default(), false);
+        let layout = Layout::new(1920, 1080, &config, |_| 40);
+        assert_eq!(layout.window.width, 960);
+        // Shrunk to a multiple of the item height
+        let list_height = layout.full_list_view.window.height;
+        assert_eq!(list_height % config.list_item_height, 0);
+        assert_eq!(
+            layout.window.height,
+            10 + config.entry_height + 10 + list_height + 10
+        );
+    }
+
+    #[test]
+    fn window_positions() {
+        let side = Rectangle::new(2560, 0, 1080, 1920);
+        assert_eq!(
+            Layout::window_position(&side, (50, 50), 540, 960),
+            (2560 + 270, 480)
+        );
+        assert_eq!(
+            Layout::window_position(&side, (50, 20), 540, 960),
+            (2560 + 270, 192)
+        );
+        assert_eq!(
+            Layout::window_position(&side, (0, 100), 540, 960),
+            (2560, 960)
+        );
+        // Too large for the monitor
+        assert_eq!(
+            Layout::window_position(&Rectangle::new(0, 0, 100, 100), (50, 0), 300, 50),
+            (-100, 0)
+        );
+    }
+}
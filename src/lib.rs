@@ -0,0 +1,41 @@
+//! The launcher is an application, this library only exists so the benchmarks
+//! can use its search and content classification.
+
+pub mod app;
+mod browser;
+pub mod cache;
+mod calc;
+pub mod clean;
+mod clock;
+pub mod config;
+pub mod content;
+pub mod diag;
+pub mod draw;
+mod emoji;
+mod entry;
+mod fonts;
+mod gpu;
+mod history;
+mod icon_theme;
+pub mod input;
+pub mod instance;
+mod launch_feedback;
+mod layout;
+mod list_geometry;
+mod list_view;
+mod paths;
+pub mod perf;
+mod res;
+pub mod search;
+mod smart_content;
+mod static_units;
+pub mod text;
+pub mod theme;
+mod tooltip;
+mod tour;
+mod ui;
+mod units;
+mod util;
+pub mod warnings;
+mod window_matching;
+pub mod x;
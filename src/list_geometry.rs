@@ -0,0 +1,508 @@
+//! Scrolling and selection math of the list view, kept free of X so it can be
+//! tested.
+//!
+//! Positions are in pixels from the top of the first item, the view shows the
+//! range `scroll..scroll + viewport_height`.  At most `CAPACITY` items can be
+//! shown, any further items can't be scrolled to or selected.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// Maximum number of items the list view draws.
+pub const CAPACITY: usize = 100;
+
+/// Movements of the selection, these can also be sent from the entry.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Navigation {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    First,
+    Last,
+}
+
+impl Navigation {
+    /// The item selected after moving from `selected` in a list of `len`
+    /// items, `page` of which are visible at once.
+    fn target(self, selected: usize, len: usize, page: usize) -> usize {
+        let last = len.saturating_sub(1);
+        match self {
+            Navigation::Up => selected.saturating_sub(1),
+            Navigation::Down => (selected + 1).min(last),
+            Navigation::PageUp => selected.saturating_sub(page),
+            Navigation::PageDown => (selected + page).min(last),
+            Navigation::First => 0,
+            Navigation::Last => last,
+        }
+    }
+}
+
+/// How far back scrolling is looked at to decide whether it's fast, this is
+/// also how long it has to stop to count as settled.
+pub const FAST_SCROLL_WINDOW: Duration = Duration::from_millis(100);
+
+/// Scrolling past more rows than this within `FAST_SCROLL_WINDOW` is fast.
+pub const FAST_SCROLL_ROWS: usize = 10;
+
+/// Tracks how many rows were scrolled past recently, so rows passed while
+/// scrolling fast can be drawn cheaply.
+#[derive(Debug, Default)]
+pub struct ScrollVelocity {
+    moves: VecDeque<(Instant, usize)>,
+}
+
+impl ScrollVelocity {
+    /// The view moved by `rows` rows.
+    pub fn moved(&mut self, rows: usize, now: Instant) {
+        while self
+            .moves
+            .front()
+            .is_some_and(|&(time, _)| now.saturating_duration_since(time) > FAST_SCROLL_WINDOW)
+        {
+            self.moves.pop_front();
+        }
+        if rows > 0 {
+            self.moves.push_back((now, rows));
+        }
+    }
+
+    pub fn is_fast(&self, now: Instant) -> bool {
+        let recent: usize = self
+            .moves
+            .iter()
+            .filter(|&&(time, _)| now.saturating_duration_since(time) <= FAST_SCROLL_WINDOW)
+            .map(|&(_, rows)| rows)
+            .sum();
+        recent > FAST_SCROLL_ROWS
+    }
+
+    /// When scrolling counts as settled, unless it moves again.
+    pub fn settle_deadline(&self) -> Option<Instant> {
+        self.moves
+            .back()
+            .map(|&(time, _)| time + FAST_SCROLL_WINDOW)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ListGeometry {
+    len: usize,
+    item_height: u32,
+    viewport_height: u32,
+    scroll: i32,
+    selected: usize,
+}
+
+impl ListGeometry {
+    pub fn new(item_height: u32, viewport_height: u32) -> Self {
+        Self {
+            len: 0,
+            item_height,
+            viewport_height,
+            scroll: 0,
+            selected: 0,
+        }
+    }
+
+    pub fn scroll(&self) -> i32 {
+        self.scroll
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Number of items that can be shown.
+    fn shown(&self) -> usize {
+        self.len.min(CAPACITY)
+    }
+
+    /// Number of whole items that fit in the viewport, at least 1.
+    pub fn page(&self) -> usize {
+        (self.viewport_height / self.item_height).max(1) as usize
+    }
+
+    pub fn max_scroll(&self) -> i32 {
+        let content = self.shown() as i64 * self.item_height as i64;
+        (content - self.viewport_height as i64).max(0) as i32
+    }
+
+    /// The item at `offset` pixels from the top of the first item.
+    pub fn item_at(&self, offset: i32) -> usize {
+        (offset.max(0) as u32 / self.item_height) as usize
+    }
+
+    /// Number of rows passed when scrolling from `before` to the current
+    /// position.
+    pub fn rows_scrolled(&self, before: i32) -> usize {
+        (self.scroll - before)
+            .unsigned_abs()
+            .div_ceil(self.item_height) as usize
+    }
+
+    /// Offset of the top of the item.
+    pub fn position(&self, idx: usize) -> i32 {
+        (idx as u32 * self.item_height) as i32
+    }
+
+    /// The first and last item that are at least partially visible, the last
+    /// one may be past the end of the list.
+    pub fn visible_items(&self) -> (usize, usize) {
+        (
+            self.item_at(self.scroll),
+            self.item_at(self.scroll + self.viewport_height as i32 - 1),
+        )
+    }
+
+    /// Replaces the items, the view goes back to the top.
+    pub fn reset(&mut self, len: usize) {
+        self.len = len;
+        self.scroll = 0;
+        self.selected = 0;
+    }
+
+    /// Replaces the items, the selected item is now at `new_index` and stays
+    /// at the same position on screen; items inserted or removed above it
+    /// move the view by their height.
+    pub fn anchor(&mut self, len: usize, new_index: usize) {
+        let moved = (new_index as i32 - self.selected as i32) * self.item_height as i32;
+        self.len = len;
+        self.selected = new_index.min(self.shown().saturating_sub(1));
+        self.scroll = (self.scroll + moved).clamp(0, self.max_scroll());
+    }
+
+    /// Selects an item, returns the previously selected one.
+    pub fn select(&mut self, idx: usize) -> usize {
+        let idx = idx.min(self.shown().saturating_sub(1));
+        std::mem::replace(&mut self.selected, idx)
+    }
+
+    /// The item a navigation from the current selection leads to.
+    pub fn navigation_target(&self, navigation: Navigation) -> usize {
+        navigation.target(self.selected, self.shown(), self.page())
+    }
+
+    /// Like `navigation_target` but only lands on items `selectable` accepts.
+    /// Items it doesn't are skipped in the direction of the navigation, if
+    /// there is nothing selectable that way the closest one back toward the
+    /// selection is taken.  The selection stays if there is neither.
+    pub fn selectable_target(
+        &self,
+        navigation: Navigation,
+        selectable: impl Fn(usize) -> bool,
+    ) -> usize {
+        let selected = self.selected;
+        let target = self.navigation_target(navigation);
+        let found = if target > selected {
+            (target..self.shown())
+                .chain((selected + 1..target).rev())
+                .find(|&idx| selectable(idx))
+        } else {
+            (0..=target)
+                .rev()
+                .chain(target + 1..selected)
+                .find(|&idx| selectable(idx))
+        };
+        found.unwrap_or(selected)
+    }
+
+    /// The first item `selectable` accepts, 0 if there is none.
+    pub fn first_selectable(&self, selectable: impl Fn(usize) -> bool) -> usize {
+        (0..self.shown()).find(|&idx| selectable(idx)).unwrap_or(0)
+    }
+
+    /// Scrolls so the selection is visible, or at least its top if it's
+    /// higher than the viewport.
+    pub fn scroll_to_selection(&mut self) {
+        let top = self.position(self.selected);
+        let bottom = self.position(self.selected + 1);
+        if bottom > self.scroll + self.viewport_height as i32 {
+            self.scroll = bottom - self.viewport_height as i32;
+        }
+        if top < self.scroll {
+            self.scroll = top;
+        }
+        self.scroll = self.scroll.clamp(0, self.max_scroll());
+    }
+
+    /// The item closest to the selection that is inside the view.
+    pub fn selection_in_view(&self) -> usize {
+        let (first, last) = self.visible_items();
+        let last = last.min(self.shown().saturating_sub(1));
+        self.selected.clamp(first.min(last), last)
+    }
+
+    /// Like `selection_in_view` but only lands on items `selectable` accepts,
+    /// the closest one below wins a tie.  The selection stays if there is
+    /// none in the view.
+    pub fn selectable_in_view(&self, selectable: impl Fn(usize) -> bool) -> usize {
+        let (first, last) = self.visible_items();
+        let last = last.min(self.shown().saturating_sub(1));
+        let view = first.min(last)..=last;
+        let idx = self.selection_in_view();
+        (0..=last - view.start())
+            .flat_map(|distance| [Some(idx + distance), idx.checked_sub(distance)])
+            .flatten()
+            .filter(|idx| view.contains(idx))
+            .find(|&idx| selectable(idx))
+            .unwrap_or(self.selected)
+    }
+
+    /// Scrolls by `delta` pixels, returns whether the view moved.
+    pub fn scroll_by(&mut self, delta: i32) -> bool {
+        let before = self.scroll;
+        self.scroll = (self.scroll + delta).clamp(0, self.max_scroll());
+        self.scroll != before
+    }
+
+    /// Whether there are items above or below the view.
+    pub fn overflow(&self) -> (bool, bool) {
+        (self.scroll > 0, self.scroll < self.max_scroll())
+    }
+
+    /// Offset and height of the scroll bar inside the viewport, `None` if
+    /// everything fits.
+    pub fn scroll_bar(&self) -> Option<(i32, u32)> {
+        let max_scroll = self.max_scroll();
+        if max_scroll == 0 {
+            return None;
+        }
+        let visible = self.viewport_height as f64;
+        let content = self.shown() as f64 * self.item_height as f64;
+        let height = (visible * visible / content).round().max(1.0) as u32;
+        let travel = self.viewport_height.saturating_sub(height) as f64;
+        let y = (travel * self.scroll as f64 / max_scroll as f64) as i32;
+        Some((y, height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 8 rows of 10 pixels are visible.
+    fn geometry(len: usize) -> ListGeometry {
+        let mut geometry = ListGeometry::new(10, 80);
+        geometry.reset(len);
+        geometry
+    }
+
+    #[test]
+    fn navigation() {
+        use Navigation::*;
+        assert_eq!(Down.target(0, 20, 8), 1);
+        assert_eq!(Down.target(19, 20, 8), 19);
+        assert_eq!(Up.target(0, 20, 8), 0);
+        assert_eq!(PageDown.target(3, 20, 8), 11);
+        assert_eq!(PageDown.target(15, 20, 8), 19);
+        assert_eq!(PageUp.target(11, 20, 8), 3);
+        assert_eq!(PageUp.target(5, 20, 8), 0);
+        assert_eq!(Last.target(5, 20, 8), 19);
+        assert_eq!(First.target(5, 20, 8), 0);
+        assert_eq!(PageDown.target(0, 1, 8), 0);
+        assert_eq!(geometry(500).navigation_target(Last), CAPACITY - 1);
+    }
+
+    #[test]
+    fn skipping_unselectable_items() {
+        use Navigation::*;
+        // Headers at 0, 5 and 6, an info row at the end
+        let selectable = |idx: usize| ![0, 5, 6, 19].contains(&idx);
+        let mut list = geometry(20);
+        assert_eq!(list.first_selectable(selectable), 1);
+        list.select(1);
+        assert_eq!(list.selectable_target(Down, selectable), 2);
+        // Nothing selectable above the first item
+        assert_eq!(list.selectable_target(Up, selectable), 1);
+        assert_eq!(list.selectable_target(First, selectable), 1);
+        assert_eq!(list.selectable_target(Last, selectable), 18);
+        list.select(4);
+        assert_eq!(list.selectable_target(Down, selectable), 7);
+        list.select(7);
+        assert_eq!(list.selectable_target(Up, selectable), 4);
+        assert_eq!(list.selectable_target(PageDown, selectable), 15);
+        list.select(15);
+        // Past the end there's only the info row, so it goes back up
+        assert_eq!(list.selectable_target(PageDown, selectable), 18);
+        list.select(12);
+        assert_eq!(list.selectable_target(PageUp, selectable), 4);
+        assert_eq!(list.first_selectable(|_| false), 0);
+        assert_eq!(list.selectable_target(Down, |_| false), 12);
+
+        // Scrolling keeps the selection on a selectable item in the view
+        list = geometry(20);
+        list.select(1);
+        list.scroll_by(40);
+        assert_eq!(list.selection_in_view(), 4);
+        assert_eq!(list.selectable_in_view(selectable), 4);
+        list.scroll_by(10);
+        assert_eq!(list.selection_in_view(), 5);
+        assert_eq!(list.selectable_in_view(selectable), 7);
+        assert_eq!(list.selectable_in_view(|idx| idx < 6), 5);
+        assert_eq!(list.selectable_in_view(|_| false), 1);
+    }
+
+    #[test]
+    fn reflow() {
+        assert_eq!(geometry(5).max_scroll(), 0);
+        assert_eq!(geometry(20).max_scroll(), 120);
+        assert_eq!(geometry(500).max_scroll(), 920);
+        let anchored = |len, old: usize, new| {
+            let mut geometry = geometry(200);
+            geometry.select(old);
+            geometry.scroll_by(100 - geometry.scroll());
+            geometry.anchor(len, new);
+            geometry.scroll()
+        };
+        // Selected item at row 12 is the 3rd visible row, 4 items were
+        // inserted above it
+        assert_eq!(anchored(20, 12, 16), 120);
+        assert_eq!(anchored(28, 12, 16), 140);
+        // Items above it were removed
+        assert_eq!(anchored(28, 12, 9), 70);
+        assert_eq!(anchored(28, 12, 1), 0);
+    }
+
+    #[test]
+    fn scroll_velocity() {
+        let ms = Duration::from_millis;
+        let start = Instant::now();
+        let mut velocity = ScrollVelocity::default();
+        assert!(!velocity.is_fast(start));
+        assert_eq!(velocity.settle_deadline(), None);
+        // Holding a key at the usual repeat rate of ~30 per second
+        for step in 0..30 {
+            velocity.moved(1, start + ms(33 * step));
+            assert!(!velocity.is_fast(start + ms(33 * step)));
+        }
+        // A single page doesn't count as fast scrolling
+        let later = start + ms(2000);
+        velocity.moved(8, later);
+        assert!(!velocity.is_fast(later));
+        // Spinning the wheel
+        for step in 1..=6 {
+            velocity.moved(2, later + ms(10 * step));
+        }
+        let last = later + ms(60);
+        assert!(velocity.is_fast(last));
+        assert_eq!(velocity.settle_deadline(), Some(last + FAST_SCROLL_WINDOW));
+        assert!(velocity.is_fast(later + ms(100)));
+        // Once it stops it settles
+        assert!(!velocity.is_fast(last + FAST_SCROLL_WINDOW));
+        velocity.moved(0, last + FAST_SCROLL_WINDOW * 2);
+        assert_eq!(velocity.settle_deadline(), None);
+        let geometry = {
+            let mut geometry = geometry(100);
+            geometry.scroll_by(25);
+            geometry
+        };
+        assert_eq!(geometry.rows_scrolled(0), 3);
+        assert_eq!(geometry.rows_scrolled(25), 0);
+    }
+
+    #[test]
+    fn scroll_bar() {
+        assert_eq!(geometry(8).scroll_bar(), None);
+        let mut geometry = geometry(16);
+        assert_eq!(geometry.scroll_bar(), Some((0, 40)));
+        geometry.scroll_by(1000);
+        assert_eq!(geometry.scroll_bar(), Some((40, 40)));
+        // Only the shown items count
+        let mut geometry = super::tests::geometry(5000);
+        assert_eq!(geometry.scroll_bar(), Some((0, 6)));
+        geometry.scroll_by(1000);
+        assert_eq!(geometry.scroll_bar(), Some((74, 6)));
+    }
+
+    /// xorshift, so the operations are random but reproducible.
+    struct Random(u64);
+
+    impl Random {
+        fn next(&mut self, below: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % below
+        }
+    }
+
+    fn check_invariants(geometry: &ListGeometry) {
+        assert!((0..=geometry.max_scroll()).contains(&geometry.scroll()));
+        if geometry.len > 0 {
+            assert!(geometry.selected() < geometry.shown());
+        }
+        let (first, last) = geometry.visible_items();
+        assert!(first <= last);
+        if let Some((y, height)) = geometry.scroll_bar() {
+            assert!(y >= 0 && y as u32 + height <= geometry.viewport_height);
+        }
+    }
+
+    fn assert_selection_visible(geometry: &ListGeometry) {
+        let top = geometry.position(geometry.selected());
+        let bottom = geometry.position(geometry.selected() + 1);
+        assert!(top >= geometry.scroll());
+        // The selection may only be cut off if it doesn't fit at all
+        assert!(
+            bottom <= geometry.scroll() + geometry.viewport_height as i32
+                || geometry.item_height > geometry.viewport_height
+        );
+    }
+
+    #[test]
+    fn interleaved_operations() {
+        use Navigation::*;
+        let mut random = Random(0x2545_f491_4f6c_dd1d);
+        for _ in 0..200 {
+            let item_height = 1 + random.next(40) as u32;
+            let viewport_height = 1 + random.next(400) as u32;
+            let mut geometry = ListGeometry::new(item_height, viewport_height);
+            geometry.reset(random.next(300) as usize);
+            for _ in 0..200 {
+                match random.next(6) {
+                    0 | 1 => {
+                        let navigation =
+                            [Up, Down, PageUp, PageDown, First, Last][random.next(6) as usize];
+                        geometry.select(geometry.navigation_target(navigation));
+                        geometry.scroll_to_selection();
+                        check_invariants(&geometry);
+                        if geometry.len > 0 {
+                            assert_selection_visible(&geometry);
+                        }
+                    }
+                    2 | 3 => {
+                        // Mouse wheel
+                        let delta = random.next(200) as i32 - 100;
+                        geometry.scroll_by(delta);
+                        geometry.select(geometry.selection_in_view());
+                        check_invariants(&geometry);
+                        let (first, last) = geometry.visible_items();
+                        if geometry.len > 0 {
+                            assert!((first..=last).contains(&geometry.selected()));
+                        }
+                    }
+                    4 => {
+                        let len = random.next(300) as usize;
+                        let new_index = random.next(len.max(1) as u64) as usize;
+                        geometry.anchor(len, new_index);
+                        check_invariants(&geometry);
+                    }
+                    _ => {
+                        let click = geometry.item_at(
+                            geometry.scroll() + random.next(viewport_height as u64) as i32,
+                        );
+                        if click < geometry.shown() {
+                            geometry.select(click);
+                            geometry.scroll_to_selection();
+                            check_invariants(&geometry);
+                            assert_selection_visible(&geometry);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -2,29 +2,98 @@ use crate::{
     app::{send_signal, Signal},
     cache::DesktopEntryCache,
     config::Config,
-    draw::DrawingContext,
+    draw::{Color, DrawingContext, GradientSpec},
+    fonts,
+    history::Entry,
     input::{Key, KeyEvent},
     layout::{ListViewLayout, Rectangle},
+    list_geometry::{ListGeometry, Navigation, ScrollVelocity, CAPACITY},
+    perf::{self, Phase},
     res::{resources, Svg},
-    ui::colors,
+    ui::{colors, FocusDirection},
     x::{Display, Window},
 };
 use pango::{EllipsizeMode, FontDescription};
 use std::{
-    ops::Deref,
-    sync::{mpsc::Sender, Arc, Mutex},
+    cell::RefCell,
+    ops::Range,
+    rc::Rc,
+    sync::{mpsc::Sender, Arc, Mutex, MutexGuard},
+    time::Instant,
 };
-use x11::xlib::{Button1, Button4, Button5, Colormap, XButtonPressedEvent, XVisualInfo};
+use x11::xlib::{
+    Button1, Button4, Button5, Colormap, ExposureMask, XButtonPressedEvent, XExposeEvent,
+    XVisualInfo,
+};
+
+/// How a row of the list behaves, see `Render::kind`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+// The lists only have selectable items so far.
+#[allow(dead_code)]
+pub enum RowKind {
+    /// An item that is committed by its index, like a search result.
+    Selectable,
+    /// Names the rows below it.
+    Header,
+    /// Read-only text, like a notice that the list was cut short.
+    Info,
+    /// Committing it runs the named action instead of an item.
+    Action(&'static str),
+}
+
+impl RowKind {
+    pub fn is_selectable(self) -> bool {
+        matches!(self, Self::Selectable | Self::Action(_))
+    }
+
+    /// Rows that can't be selected stay on the plain background instead of
+    /// alternating with their neighbours.
+    fn background(self, idx: usize, selected: usize) -> Color {
+        if !self.is_selectable() {
+            colors::background()
+        } else if idx == selected {
+            colors::list_selected_background()
+        } else if idx % 2 == 0 {
+            colors::background()
+        } else {
+            colors::list_light_background()
+        }
+    }
+
+    fn text_color(self) -> Color {
+        match self {
+            Self::Header | Self::Info => colors::list_match_name(),
+            Self::Selectable | Self::Action(_) => colors::text(),
+        }
+    }
 
-const CAPACITY: u32 = 100;
+    fn markup(self, markup: String) -> String {
+        match self {
+            Self::Header => format!("<b>{markup}</b>"),
+            Self::Action(_) => format!("<i>{markup}</i>"),
+            Self::Selectable | Self::Info => markup,
+        }
+    }
+}
 
 pub struct Item {
+    kind: RowKind,
     icon: Option<Svg>,
     markup_text: String,
     is_in_history: bool,
+    is_executable: bool,
+    tooltip: Option<String>,
+    key: Option<String>,
+    history_entry: Option<Entry>,
 }
 
 pub trait Render {
+    /// Headers and info rows are skipped by the selection, action rows send
+    /// `Signal::CommitAction` instead of their index.
+    fn kind(&self) -> RowKind {
+        RowKind::Selectable
+    }
+
     fn icon(&self, _cache: &DesktopEntryCache) -> Option<Svg> {
         None
     }
@@ -33,9 +102,37 @@ pub trait Render {
         false
     }
 
+    /// Whether this is an executable from `PATH` rather than an application,
+    /// these are drawn with a terminal icon.
+    fn is_executable(&self) -> bool {
+        false
+    }
+
     fn markup(&self, search: &str, cache: &DesktopEntryCache) -> String;
+
+    /// Plain text drawn instead of the markup while scrolling fast.
+    fn plain_text(&self, cache: &DesktopEntryCache) -> String;
+
+    /// Markup with the full details of the item, shown when hovering it.
+    fn tooltip(&self, _cache: &DesktopEntryCache) -> Option<String> {
+        None
+    }
+
+    /// Identifies the item across updates of the list, so the selection can
+    /// stay on it.
+    fn key(&self, _cache: &DesktopEntryCache) -> Option<String> {
+        None
+    }
+
+    /// The history entry that deleting the item removes, `None` if there is
+    /// nothing to delete.
+    fn history_entry(&self, _cache: &DesktopEntryCache) -> Option<Entry> {
+        None
+    }
 }
 
+// Shown items are all rendered, boxing them would only add allocations.
+#[allow(clippy::large_enum_variant)]
 enum LazyItem {
     Rendered(Item),
     NotRendered(&'static dyn Render),
@@ -46,46 +143,259 @@ impl LazyItem {
         Self::NotRendered(renderable)
     }
 
-    fn get(&mut self, search: &str, cache: &Arc<Mutex<DesktopEntryCache>>) -> &Item {
-        match *self {
-            Self::Rendered(ref item) => item,
-            Self::NotRendered(renderable) => {
-                {
-                    let guard = cache.lock().unwrap();
-                    let cache = guard.deref();
-                    *self = Self::Rendered(Item {
-                        icon: renderable.icon(cache),
-                        markup_text: renderable.markup(search, cache),
-                        is_in_history: renderable.is_in_history(),
-                    });
-                }
-                self.get(search, cache)
-            }
+    fn kind(&self) -> RowKind {
+        match self {
+            Self::Rendered(item) => item.kind,
+            Self::NotRendered(renderable) => renderable.kind(),
+        }
+    }
+
+    fn key(&self, cache: &DesktopEntryCache) -> Option<String> {
+        match self {
+            Self::Rendered(item) => item.key.clone(),
+            Self::NotRendered(renderable) => renderable.key(cache),
+        }
+    }
+
+    fn history_entry(&self, cache: &DesktopEntryCache) -> Option<Entry> {
+        match self {
+            Self::Rendered(item) => item.history_entry.clone(),
+            Self::NotRendered(renderable) => renderable.history_entry(cache),
+        }
+    }
+
+    fn placeholder(&self, cache: &DesktopEntryCache) -> Option<String> {
+        match self {
+            Self::Rendered(_) => None,
+            Self::NotRendered(renderable) => Some(renderable.plain_text(cache)),
         }
     }
 
     fn is_rendered(&self) -> bool {
-        matches!(self, &Self::Rendered(_))
+        matches!(self, Self::Rendered(_))
+    }
+
+    fn render(&mut self, search: &str, cache: &DesktopEntryCache) {
+        if let Self::NotRendered(renderable) = *self {
+            let kind = renderable.kind();
+            *self = Self::Rendered(Item {
+                kind,
+                icon: renderable.icon(cache),
+                markup_text: kind.markup(renderable.markup(search, cache)),
+                is_in_history: renderable.is_in_history(),
+                is_executable: renderable.is_executable(),
+                tooltip: renderable.tooltip(cache),
+                key: renderable.key(cache),
+                history_entry: renderable.history_entry(cache),
+            });
+        }
+    }
+
+    fn rendered(&self) -> &Item {
+        match self {
+            Self::Rendered(item) => item,
+            Self::NotRendered(_) => panic!("item is not rendered"),
+        }
+    }
+}
+
+/// The cache shared with the app.  Tests count how often it's locked to
+/// check that items are rendered in batches.
+struct SharedCache {
+    cache: Arc<Mutex<DesktopEntryCache>>,
+    #[cfg(test)]
+    locks: std::cell::Cell<usize>,
+}
+
+impl SharedCache {
+    fn new(cache: Arc<Mutex<DesktopEntryCache>>) -> Self {
+        Self {
+            cache,
+            #[cfg(test)]
+            locks: Default::default(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, DesktopEntryCache> {
+        #[cfg(test)]
+        self.locks.set(self.locks.get() + 1);
+        self.cache.lock().unwrap()
     }
 }
 
-fn create_empty_screen(
-    display: &Display,
-    width: u32,
-    height: u32,
-    visual_info: &XVisualInfo,
-    font: &str,
-) -> DrawingContext {
-    let mut empty_screen = DrawingContext::create(display, width, height, visual_info);
-    empty_screen.fill(colors::BACKGROUND);
-    empty_screen.set_color(colors::TEXT);
-    empty_screen.set_font(&FontDescription::from_string(font));
-    empty_screen
-        .text("No results", Rectangle::new(0, 0, width, height), false)
-        .center_width()
-        .center_height()
-        .draw();
-    empty_screen
+/// The items shown by the list views.  Both list views share the same model
+/// so each item is only rendered once.
+pub struct ListModel {
+    items: Vec<LazyItem>,
+    search: String,
+    cache: SharedCache,
+    /// Incremented whenever the items change so the list views know when
+    /// to reset.
+    generation: u64,
+}
+
+impl ListModel {
+    pub fn new(cache: Arc<Mutex<DesktopEntryCache>>) -> Self {
+        Self {
+            items: Vec::new(),
+            search: String::new(),
+            cache: SharedCache::new(cache),
+            generation: 0,
+        }
+    }
+
+    pub fn set_items<T: Render + 'static>(&mut self, items: &[T], search: &str) {
+        self.items = items
+            .iter()
+            .map(|x| {
+                let as_static: &'static _ = unsafe { &*(x as *const T) };
+                LazyItem::new(as_static)
+            })
+            .collect();
+        self.search = search.to_string();
+        self.generation += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    fn get(&mut self, idx: usize) -> &Item {
+        self.render(idx..idx + 1);
+        self.items[idx].rendered()
+    }
+
+    /// Renders the items in `range` that are not rendered yet, locking the
+    /// cache only once for all of them.
+    fn render(&mut self, range: Range<usize>) {
+        let range = range.start.min(self.len())..range.end.min(self.len());
+        if self.items[range.clone()].iter().all(LazyItem::is_rendered) {
+            return;
+        }
+        let cache = self.cache.lock();
+        for item in &mut self.items[range] {
+            item.render(&self.search, &cache);
+        }
+    }
+
+    /// Text for a placeholder row if the item has not been rendered yet.
+    fn placeholder(&self, idx: usize) -> Option<String> {
+        let cache = self.cache.lock();
+        self.items[idx].placeholder(&cache)
+    }
+
+    /// `None` past the end of the list.
+    fn kind(&self, idx: usize) -> Option<RowKind> {
+        self.items.get(idx).map(LazyItem::kind)
+    }
+
+    fn is_selectable(&self, idx: usize) -> bool {
+        self.kind(idx).is_some_and(RowKind::is_selectable)
+    }
+
+    fn key(&self, idx: usize) -> Option<String> {
+        let cache = self.cache.lock();
+        self.items.get(idx)?.key(&cache)
+    }
+
+    fn history_entry(&self, idx: usize) -> Option<Entry> {
+        let cache = self.cache.lock();
+        self.items.get(idx)?.history_entry(&cache)
+    }
+
+    /// Index of the item with the given key.
+    fn position(&self, key: &str) -> Option<usize> {
+        let cache = self.cache.lock();
+        self.items
+            .iter()
+            .position(|item| item.key(&cache).as_deref() == Some(key))
+    }
+}
+
+/// The items drawn into the pixmap since it was last copied to the window.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct Damage {
+    items: Vec<usize>,
+    /// Scroll offset of the last copy to the window, `None` if the whole view
+    /// has to be copied again.
+    copied_scroll: Option<i32>,
+}
+
+impl Damage {
+    fn add(&mut self, idx: usize) {
+        self.items.push(idx);
+    }
+
+    /// Makes the next copy take the whole view.
+    fn invalidate(&mut self) {
+        self.copied_scroll = None;
+    }
+
+    /// Records that the window shows the pixmap at `scroll`.
+    fn copied_at(&mut self, scroll: i32) {
+        self.copied_scroll = Some(scroll);
+    }
+
+    /// The drawn items as runs of adjacent ones, first and last inclusive.
+    fn runs(&mut self) -> Vec<(usize, usize)> {
+        let mut items = std::mem::take(&mut self.items);
+        items.sort_unstable();
+        items.dedup();
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        for idx in items {
+            match runs.last_mut() {
+                Some((_, last)) if *last + 1 == idx => *last = idx,
+                _ => runs.push((idx, idx)),
+            }
+        }
+        runs
+    }
+
+    /// The areas of the pixmap to copy to a window of the given size, with
+    /// their position in the window.  Unless the view scrolled since the last
+    /// copy, that's the drawn rows and the scroll bar, so a selection change
+    /// copies two rows instead of the whole view.  The items are taken and
+    /// the copy is recorded.
+    fn take_copy_areas(
+        &mut self,
+        geometry: &ListGeometry,
+        (width, height): (u32, u32),
+        scroll_bar_width: u32,
+    ) -> Vec<(Rectangle, (i32, i32))> {
+        let scroll = geometry.scroll();
+        if self.copied_scroll.replace(scroll) != Some(scroll) {
+            self.items.clear();
+            return vec![(Rectangle::new(0, scroll, width, height), (0, 0))];
+        }
+        let mut areas = Vec::new();
+        for (first, last) in self.runs() {
+            let top = geometry.position(first).max(scroll);
+            let bottom = geometry.position(last + 1).min(scroll + height as i32);
+            if top < bottom {
+                areas.push((
+                    Rectangle::new(0, top, width, (bottom - top) as u32),
+                    (0, top - scroll),
+                ));
+            }
+        }
+        if scroll_bar_width != 0 {
+            let x = (width - scroll_bar_width) as i32;
+            areas.push((Rectangle::new(x, scroll, scroll_bar_width, height), (x, 0)));
+        }
+        areas
+    }
+}
+
+/// Number of pixels copied for `areas`.
+fn copied_pixels(areas: &[(Rectangle, (i32, i32))]) -> u64 {
+    areas
+        .iter()
+        .map(|(rect, _)| rect.width as u64 * rect.height as u64)
+        .sum()
 }
 
 pub struct ListView {
@@ -94,18 +404,66 @@ pub struct ListView {
     signal_sender: Sender<Signal>,
     layout: ListViewLayout,
     dc: DrawingContext,
-    items: Vec<LazyItem>,
-    scroll: i32,
-    max_scroll_offset: i32,
-    selected: usize,
+    model: Rc<RefCell<ListModel>>,
+    /// Generation of the model the view state belongs to.
+    generation: u64,
+    /// Which items have been drawn into `dc`.
+    drawn: Vec<bool>,
+    geometry: ListGeometry,
+    /// Key of the selected item if the selection was moved away from the
+    /// first item, the selection stays on it when the items change.
+    selected_key: Option<String>,
+    /// Whether the selection was moved since the items changed.
+    navigated: bool,
     click_item: usize,
     click_time: u64,
-    search: String,
-    empty_screen: DrawingContext,
-    cache: Arc<Mutex<DesktopEntryCache>>,
+    list_font: FontDescription,
+    empty_font: FontDescription,
     scroll_speed: i32,
-    scroll_bar_height: u32,
     history_icon: Svg,
+    executable_icon: Svg,
+    scroll_fade: bool,
+    /// Items that had a scroll fade drawn over them.
+    faded: Vec<usize>,
+    velocity: ScrollVelocity,
+    /// Items drawn as placeholders while scrolling fast, they are rendered
+    /// once scrolling settles.
+    placeholders: Vec<usize>,
+    damage: Damage,
+}
+
+/// The signal for committing the row at `idx`, `None` if it can't be.
+fn commit_signal(kind: Option<RowKind>, idx: usize) -> Option<Signal> {
+    match kind? {
+        RowKind::Selectable => Some(Signal::Commit(Some(idx))),
+        RowKind::Action(action) => Some(Signal::CommitAction(action)),
+        RowKind::Header | RowKind::Info => None,
+    }
+}
+
+/// The signal for pressing `key` on the row at `idx`.  Only items can be
+/// copied, picked or show their details, action rows can only be committed.
+pub fn row_key_signal(key: &KeyEvent, kind: Option<RowKind>, idx: usize) -> Option<Signal> {
+    if matches!(key.key, Key::Enter) && !key.is_ctrl && !key.is_shift {
+        return commit_signal(kind, idx);
+    }
+    if kind != Some(RowKind::Selectable) {
+        return None;
+    }
+    match key.key {
+        Key::Enter if key.is_ctrl => Some(Signal::CopyCommand(Some(idx))),
+        Key::CtrlC => Some(Signal::CopyCommand(Some(idx))),
+        Key::Enter => Some(Signal::Pick(Some(idx))),
+        Key::Right | Key::CtrlI => Some(Signal::ToggleDetails(Some(idx))),
+        _ => None,
+    }
+}
+
+/// Allocates the pixmaps of a list view with `create`.  The items are
+/// rendered once into a pixmap as tall as `CAPACITY` items, which also holds
+/// the "No results" banner while the list is empty, so it's the only one.
+fn create_pixmaps<T>(layout: &ListViewLayout, mut create: impl FnMut(u32, u32) -> T) -> T {
+    create(layout.window.width, layout.item_height * CAPACITY as u32)
 }
 
 impl ListView {
@@ -115,7 +473,7 @@ impl ListView {
         layout: ListViewLayout,
         visual_info: &XVisualInfo,
         colormap: Colormap,
-        cache: Arc<Mutex<DesktopEntryCache>>,
+        model: Rc<RefCell<ListModel>>,
         config: &Config,
     ) -> Self {
         let window = Window::builder(display)
@@ -124,177 +482,186 @@ impl ListView {
                 attributes
                     .colormap(colormap)
                     .border_pixel(0)
-                    .background_pixel(colors::BACKGROUND.pack());
+                    .background_pixel(colors::background().pack())
+                    .event_mask(ExposureMask);
             })
             .visual(visual_info.visual)
             .depth(visual_info.depth)
             .build();
-        let mut dc = DrawingContext::create(
-            display,
-            layout.window.width,
-            layout.item_height * CAPACITY,
-            visual_info,
-        );
-        dc.set_font(&FontDescription::from_string(&config.list_font));
-        // Since the items in the main drawing context are only rendered once we
-        // need separate contexts for dynamic visuals.
-        let empty_screen = create_empty_screen(
-            display,
-            layout.window.width,
-            layout.window.height,
-            visual_info,
-            &config.list_empty_font,
-        );
+        let mut dc = create_pixmaps(&layout, |width, height| {
+            DrawingContext::create(display, width, height, visual_info)
+        });
+        let list_font = fonts::load(&config.list_font);
+        dc.set_font(&list_font);
         Self {
             window,
             display: *display,
             signal_sender,
+            geometry: ListGeometry::new(layout.item_height, layout.window.height),
             layout,
             dc,
-            items: Vec::new(),
-            scroll: 0,
-            max_scroll_offset: 0,
-            selected: 0,
+            model,
+            generation: 0,
+            drawn: Vec::new(),
+            selected_key: None,
+            navigated: false,
             click_item: usize::MAX,
             click_time: 0,
-            search: String::new(),
-            empty_screen,
-            cache,
+            list_font,
+            empty_font: fonts::load(&config.list_empty_font),
             scroll_speed: config.scroll_speed,
-            scroll_bar_height: 0,
             history_icon: Svg::load(resources::HISTORY_ICON),
+            executable_icon: Svg::load(resources::TERMINAL_ICON),
+            scroll_fade: config.scroll_fade,
+            faded: Vec::new(),
+            velocity: ScrollVelocity::default(),
+            placeholders: Vec::new(),
+            damage: Damage::default(),
         }
     }
 
-    fn position_to_item_index(&self, offset: i32) -> usize {
-        (offset as u32 / self.layout.item_height) as usize
+    /// Resets the view if the items in the model changed since the last call,
+    /// this way a hidden list view only catches up once it's used.
+    fn sync(&mut self) {
+        let generation = self.model.borrow().generation;
+        if generation == self.generation {
+            return;
+        }
+        self.generation = generation;
+        let len = self.len();
+        self.drawn = vec![false; len];
+        self.faded.clear();
+        self.placeholders.clear();
+        self.damage.invalidate();
+        if len == 0 {
+            self.geometry.reset(0);
+            return;
+        }
+        self.dc.fill(colors::background());
+        let anchor = self
+            .selected_key
+            .as_deref()
+            .and_then(|key| self.model.borrow().position(key))
+            .filter(|&idx| idx < CAPACITY);
+        if let Some(idx) = anchor {
+            self.geometry.anchor(len, idx);
+        } else {
+            self.geometry.reset(len);
+            self.select_first();
+            self.selected_key = None;
+            self.navigated = false;
+        }
     }
 
-    fn item_index_to_position(&self, idx: usize) -> i32 {
-        (idx as u32 * self.layout.item_height) as i32
+    /// Moves the selection past headers and info rows at the top, without
+    /// drawing.
+    fn select_first(&mut self) {
+        let model = self.model.borrow();
+        let first = self
+            .geometry
+            .first_selectable(|idx| model.is_selectable(idx));
+        self.geometry.select(first);
     }
 
-    pub fn set_items<T: Render + 'static>(&mut self, items: &[T], search: &str, no_draw: bool) {
-        self.items = items
-            .iter()
-            .map(|x| {
-                let as_static: &'static _ = unsafe { &*(x as *const T) };
-                LazyItem::new(as_static)
-            })
-            .collect();
-        if self.items.is_empty() {
-            if !no_draw {
-                self.draw();
-            }
-            return;
-        }
-        let visible = (self.layout.window.height / self.layout.item_height) as i32;
-        self.max_scroll_offset =
-            (self.items.len() as i32 - visible) * self.layout.item_height as i32;
-        self.max_scroll_offset = self.max_scroll_offset.clamp(
-            0,
-            (CAPACITY * self.layout.item_height - self.layout.window.height) as i32,
-        );
-        self.dc.fill(colors::BACKGROUND);
-        self.search = search.to_string();
-        // TODO: if previously selected is in new list, keep it selected
-        self.scroll = 0;
-        self.selected = 0;
-        self.resize_scrollbar();
-        if !no_draw {
-            self.draw();
-        }
+    fn len(&self) -> usize {
+        self.model.borrow().len()
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.items.is_empty()
+    /// Moves the selection and the view back to the first item.
+    pub fn reset(&mut self) {
+        self.sync();
+        self.selected_key = None;
+        self.navigated = false;
+        self.velocity = ScrollVelocity::default();
+        self.geometry.reset(self.len());
+        self.select_first();
+        self.drawn.fill(false);
+        self.faded.clear();
+        self.placeholders.clear();
+        self.damage.invalidate();
+        self.dc.fill(colors::background());
     }
 
-    fn resize_scrollbar(&mut self) {
-        if self.layout.scroll_bar_width == 0 {
-            return;
-        }
-        let visible_height = self.layout.window.height;
-        let content_height = self.items.len() as u32 * self.layout.item_height;
-        if content_height <= visible_height {
-            self.scroll_bar_height = 0;
-            return;
-        }
-        let percentage = visible_height as f64 / content_height as f64;
-        self.scroll_bar_height = (visible_height as f64 * percentage).round() as u32;
+    pub fn is_empty(&self) -> bool {
+        self.model.borrow().is_empty()
     }
 
     fn draw_scrollbar(&mut self) {
-        if self.scroll_bar_height == 0 || self.layout.scroll_bar_width == 0 {
+        if self.layout.scroll_bar_width == 0 {
             return;
         }
-        let visible_height = self.layout.window.height;
-        let pos = self.scroll as f64 / self.max_scroll_offset as f64;
-        let y = ((visible_height - self.scroll_bar_height) as f64 * pos) as i32;
+        let Some((y, height)) = self.geometry.scroll_bar() else {
+            return;
+        };
         // Redraw visible item backgrounds below scrollbar area.
-        let first_visible = self.position_to_item_index(self.scroll);
-        let last_visible =
-            self.position_to_item_index(self.scroll + self.layout.window.height as i32);
+        let (first_visible, last_visible) = self.geometry.visible_items();
         for idx in first_visible..=last_visible {
             let rect = Rectangle::new(
                 (self.layout.window.width - self.layout.scroll_bar_width) as i32,
-                self.item_index_to_position(idx),
+                self.geometry.position(idx),
                 self.layout.scroll_bar_width,
                 self.layout.item_height,
             );
+            let kind = self.model.borrow().kind(idx).unwrap_or(RowKind::Selectable);
             self.dc
                 .rect(&rect)
-                .color(if idx == self.selected {
-                    colors::LIST_SELECTED_BACKGROUND
-                } else if idx % 2 == 0 {
-                    colors::BACKGROUND
-                } else {
-                    colors::LIST_LIGHT_BACKGROUND
-                })
+                .color(kind.background(idx, self.geometry.selected()))
                 .draw();
         }
         // Draw the scrollbar
         let rect = Rectangle::new(
             (self.layout.window.width - self.layout.scroll_bar_width) as i32,
-            self.scroll + y,
+            self.geometry.scroll() + y,
             self.layout.scroll_bar_width,
-            self.scroll_bar_height,
+            height,
         );
         self.dc
             .rect(&rect)
-            .color(colors::LIST_SCROLL_BAR)
+            .color(colors::list_scroll_bar())
             .corner_radius(0.499)
             .draw();
     }
 
     #[inline]
     fn draw_item(&mut self, idx: usize, redraw: bool) {
-        let i = &mut self.items[idx];
-        if redraw || !i.is_rendered() {
+        if redraw || !self.drawn[idx] {
+            self.drawn[idx] = true;
+            self.damage.add(idx);
             let (background, icon, mut text) = self.layout.get_item_rects(idx);
+            let mut model = self.model.borrow_mut();
+            let kind = model.kind(idx).unwrap_or(RowKind::Selectable);
             self.dc
                 .rect(&background)
                 // XXX: this will always ebe highlit, indicating it would be
                 // enter action, even if pressing enter would interact with the
                 // smart content.
-                .color(if idx == self.selected {
-                    colors::LIST_SELECTED_BACKGROUND
-                } else if idx % 2 == 0 {
-                    colors::BACKGROUND
-                } else {
-                    colors::LIST_LIGHT_BACKGROUND
-                })
+                .color(kind.background(idx, self.geometry.selected()))
                 .draw();
-            let item = i.get(&self.search, &self.cache);
+            if self.velocity.is_fast(Instant::now()) {
+                if let Some(placeholder) = model.placeholder(idx) {
+                    self.placeholders.push(idx);
+                    self.dc.set_color(colors::list_match_name());
+                    self.dc
+                        .text(&placeholder, text, false)
+                        .center_height()
+                        .ellipsize(EllipsizeMode::End)
+                        .draw();
+                    return;
+                }
+            }
+            let item = model.get(idx);
             if let Some(svg) = &item.icon {
                 self.dc.svg(svg, &icon);
+            } else if item.is_executable {
+                self.dc
+                    .colored_svg(&mut self.executable_icon, colors::list_match_name(), &icon);
             }
             if item.is_in_history {
                 let icon = ListViewLayout::add_secondary_icon(&mut text);
                 self.dc
-                    .colored_svg(&mut self.history_icon, colors::LIST_MATCH_NAME, &icon);
+                    .colored_svg(&mut self.history_icon, colors::list_match_name(), &icon);
             }
-            self.dc.set_color(colors::TEXT);
+            self.dc.set_color(item.kind.text_color());
             self.dc
                 .text(&item.markup_text, text, true)
                 .center_height()
@@ -304,109 +671,306 @@ impl ListView {
     }
 
     pub fn draw(&mut self) {
-        if self.items.is_empty() {
-            self.empty_screen.render(
-                self.window,
-                &Rectangle::new(0, 0, self.layout.window.width, self.layout.window.height),
-            );
+        self.sync();
+        if self.is_empty() {
+            self.draw_empty();
             return;
         }
-        for y in (self.scroll..(self.scroll + self.layout.window.height as i32))
-            .step_by(self.layout.item_height as usize)
-        {
-            let idx = self.position_to_item_index(y);
-            if idx == self.items.len() {
-                break;
+        let render_timer = perf::timer(Phase::Render);
+        for idx in std::mem::take(&mut self.faded) {
+            if idx < self.drawn.len() {
+                self.draw_item(idx, true);
             }
+        }
+        let (first_visible, last_visible) = self.geometry.visible_items();
+        let last_visible = last_visible.min(self.drawn.len() - 1);
+        // Placeholders are drawn instead while scrolling fast
+        if !self.velocity.is_fast(Instant::now()) {
+            self.model
+                .borrow_mut()
+                .render(first_visible..last_visible + 1);
+        }
+        for idx in first_visible..=last_visible {
             self.draw_item(idx, false);
         }
-        let mut rect = self.layout.window;
-        rect.y += self.scroll;
+        self.draw_scroll_fades();
         self.draw_scrollbar();
-        self.dc.render_to_00(self.window, &rect);
+        drop(render_timer);
+        self.draw_perf_hud();
+        let _timer = perf::timer(Phase::Copy);
+        let areas = self.damage.take_copy_areas(
+            &self.geometry,
+            (self.layout.window.width, self.layout.window.height),
+            self.layout.scroll_bar_width,
+        );
+        perf::copied(copied_pixels(&areas));
+        self.dc.render_areas(self.window, &areas);
+    }
+
+    /// Copies the part of the view the X server lost, like after the window
+    /// was mapped or the tooltip above it went away.
+    pub fn expose(&mut self, event: &XExposeEvent) {
+        let Some(scroll) = self.damage.copied_scroll else {
+            // Not drawn yet, or the next `draw` copies everything anyway
+            return;
+        };
+        let rect = Rectangle::new(
+            event.x,
+            event.y + scroll,
+            event.width as u32,
+            event.height as u32,
+        );
+        self.dc.render_at(self.window, &rect, (event.x, event.y));
+    }
+
+    /// Draws the timings over the bottom of the view if they are enabled, the
+    /// items below it are repainted by the next `draw` like the faded ones.
+    fn draw_perf_hud(&mut self) {
+        let Some(line) = perf::hud_line() else {
+            return;
+        };
+        let height = self.layout.item_height;
+        let y = self.geometry.scroll() + (self.layout.window.height - height) as i32;
+        let rect = Rectangle::new(0, y, self.layout.window.width, height);
+        self.dc.rect(&rect).color(colors::background()).draw();
+        self.dc.set_color(colors::list_match_name());
+        self.dc.text(&line, rect, false).center_height().draw();
+        let first = self.geometry.item_at(y);
+        let last = self
+            .geometry
+            .item_at(y + height as i32 - 1)
+            .min(self.drawn.len() - 1);
+        self.faded.extend(first..=last);
+        for idx in first..=last {
+            self.damage.add(idx);
+        }
+    }
+
+    /// Draws the "No results" banner over the top of `dc`, there are no items
+    /// that could be overwritten and the next items are all drawn anew.
+    fn draw_empty(&mut self) {
+        let rect = Rectangle::new(0, 0, self.layout.window.width, self.layout.window.height);
+        self.dc.rect(&rect).color(colors::background()).draw();
+        self.dc.set_color(colors::text());
+        self.dc.set_font(&self.empty_font);
+        self.dc
+            .text("No results", rect, false)
+            .center_width()
+            .center_height()
+            .draw();
+        self.dc.set_font(&self.list_font);
+        self.dc.render(self.window, &rect);
+        // The banner is at the top of the pixmap, exposures copy it from there
+        // and the next items are all copied anew.
+        self.damage.copied_at(0);
+    }
+
+    /// Fades out the top and bottom edge of the view if there are more items
+    /// beyond them.  Since items are only rendered once the faded items are
+    /// remembered so the next `draw` can repaint them.
+    fn draw_scroll_fades(&mut self) {
+        if !self.scroll_fade {
+            return;
+        }
+        let height = self.layout.item_height;
+        let transparent = colors::background().with_alpha(0);
+        let scroll = self.geometry.scroll();
+        let (above, below) = self.geometry.overflow();
+        let mut fades = Vec::new();
+        if above {
+            fades.push((
+                scroll,
+                GradientSpec::new_vertical(colors::background(), transparent),
+            ));
+        }
+        if below {
+            fades.push((
+                scroll + (self.layout.window.height - height) as i32,
+                GradientSpec::new_vertical(transparent, colors::background()),
+            ));
+        }
+        for &(y, _) in fades.iter() {
+            let first = self.geometry.item_at(y);
+            let last = self
+                .geometry
+                .item_at(y + height as i32 - 1)
+                .min(self.drawn.len() - 1);
+            self.faded.extend(first..=last);
+        }
+        // Repaint them so fades don't stack up on items that were not redrawn.
+        for idx in self.faded.clone() {
+            self.draw_item(idx, true);
+        }
+        let width = self.layout.window.width - self.layout.scroll_bar_width;
+        self.dc.blend(true);
+        for (y, spec) in fades {
+            self.dc
+                .rect(&Rectangle::new(0, y, width, height))
+                .gradient(spec)
+                .draw();
+        }
+        self.dc.blend(false);
     }
 
     /// Moves the view so the selection is visible
     fn adjust_view(&mut self) {
-        let sel_top = self.item_index_to_position(self.selected);
-        let sel_bot = self.item_index_to_position(self.selected + 1);
-        if sel_top < self.scroll {
-            self.scroll = sel_top;
-        } else if sel_bot >= self.scroll + self.layout.window.height as i32 {
-            self.scroll = sel_bot - self.layout.window.height as i32;
-        }
-        self.scroll = self.scroll.clamp(0, self.max_scroll_offset);
+        let before = self.geometry.scroll();
+        self.geometry.scroll_to_selection();
+        self.velocity
+            .moved(self.geometry.rows_scrolled(before), Instant::now());
+        self.draw();
+    }
+
+    /// When the placeholder rows should be rendered.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        if self.placeholders.is_empty() {
+            None
+        } else {
+            self.velocity.settle_deadline()
+        }
+    }
+
+    /// Renders the placeholder rows once scrolling settled.
+    pub fn tick(&mut self, now: Instant) {
+        if self.next_deadline().is_none_or(|deadline| deadline > now) {
+            return;
+        }
+        for idx in std::mem::take(&mut self.placeholders) {
+            if idx < self.drawn.len() {
+                self.drawn[idx] = false;
+            }
+        }
         self.draw();
     }
 
     /// Moves the selection so it's inside the view
     fn adjust_selection(&mut self) {
-        let min = self.position_to_item_index(self.scroll);
-        let max = self.position_to_item_index(self.scroll + self.layout.window.height as i32 - 1);
-        let selected = self.selected.clamp(min, max);
-        if selected != self.selected {
+        let model = self.model.borrow();
+        let selected = self
+            .geometry
+            .selectable_in_view(|idx| model.is_selectable(idx));
+        drop(model);
+        if selected != self.geometry.selected() {
             self.change_selected(selected);
         }
     }
 
+    /// Moves the selection, returns whether it changed.
+    pub fn navigate(&mut self, navigation: Navigation) -> bool {
+        self.sync();
+        if self.is_empty() {
+            return false;
+        }
+        let model = self.model.borrow();
+        let to = self
+            .geometry
+            .selectable_target(navigation, |idx| model.is_selectable(idx));
+        drop(model);
+        if to == self.geometry.selected() {
+            return false;
+        }
+        self.change_selected(to);
+        self.navigated = true;
+        self.adjust_view();
+        true
+    }
+
+    /// The selected item if the selection was moved away from the first item
+    /// since the items changed.
+    pub fn navigated_item(&mut self) -> Option<usize> {
+        self.sync();
+        let selected = self.geometry.selected();
+        // Also when it starts below a header
+        (self.navigated || selected != 0).then_some(selected)
+    }
+
+    /// The kind of the item, `None` past the end of the list.
+    pub fn row_kind(&mut self, idx: usize) -> Option<RowKind> {
+        self.sync();
+        self.model.borrow().kind(idx)
+    }
+
+    /// Commits the item, or runs its action for an action row.  Headers and
+    /// info rows can't be committed.
+    fn commit(&self, idx: usize) {
+        if let Some(signal) = commit_signal(self.model.borrow().kind(idx), idx) {
+            send_signal(&self.display, &self.signal_sender, signal);
+        }
+    }
+
     fn change_selected(&mut self, to: usize) {
-        let before = self.selected;
-        self.selected = to.min(CAPACITY as usize - 1);
+        let before = self.geometry.select(to);
+        let selected = self.geometry.selected();
         self.draw_item(before, true);
-        self.draw_item(self.selected, true);
+        self.draw_item(selected, true);
         self.click_item = usize::MAX;
+        self.selected_key = if selected == 0 {
+            None
+        } else {
+            self.model.borrow().key(selected)
+        };
     }
 
     pub fn key_press(&mut self, key: KeyEvent) {
-        if self.items.is_empty() {
+        self.sync();
+        if self.is_empty() {
             match key.key {
                 Key::Escape => send_signal(&self.display, &self.signal_sender, Signal::Quit),
-                Key::Tab => send_signal(&self.display, &self.signal_sender, Signal::SwapFocus),
+                Key::Tab => send_signal(
+                    &self.display,
+                    &self.signal_sender,
+                    Signal::SwapFocus(key.tab_direction()),
+                ),
                 _ => {}
             }
             return;
         }
         match key.key {
             Key::Down => {
-                if self.selected < self.items.len() - 1 {
-                    self.change_selected(self.selected + 1);
-                    self.adjust_view();
-                }
+                self.navigate(Navigation::Down);
             }
             Key::Up => {
-                if self.selected > 0 {
-                    self.change_selected(self.selected - 1);
-                    self.adjust_view();
-                } else {
-                    send_signal(&self.display, &self.signal_sender, Signal::SwapFocus);
+                if !self.navigate(Navigation::Up) {
+                    send_signal(
+                        &self.display,
+                        &self.signal_sender,
+                        Signal::SwapFocus(FocusDirection::Backward),
+                    );
                 }
             }
             Key::Home => {
-                if self.selected != 0 {
-                    self.change_selected(0);
-                    self.adjust_view();
-                }
+                self.navigate(Navigation::First);
             }
             Key::End => {
-                if self.selected != self.items.len() - 1 {
-                    self.change_selected(self.items.len() - 1);
-                    self.adjust_view();
-                }
+                self.navigate(Navigation::Last);
+            }
+            Key::PageUp => {
+                self.navigate(Navigation::PageUp);
+            }
+            Key::PageDown => {
+                self.navigate(Navigation::PageDown);
             }
 
-            Key::Enter => send_signal(
+            Key::Enter | Key::CtrlC | Key::Right | Key::CtrlI => {
+                let selected = self.geometry.selected();
+                let kind = self.model.borrow().kind(selected);
+                if let Some(signal) = row_key_signal(&key, kind, selected) {
+                    send_signal(&self.display, &self.signal_sender, signal);
+                }
+            }
+            Key::Escape => send_signal(&self.display, &self.signal_sender, Signal::Quit),
+            Key::Tab => send_signal(
                 &self.display,
                 &self.signal_sender,
-                Signal::Commit(Some(self.selected)),
+                Signal::SwapFocus(key.tab_direction()),
             ),
-            Key::Escape => send_signal(&self.display, &self.signal_sender, Signal::Quit),
-            Key::Tab => send_signal(&self.display, &self.signal_sender, Signal::SwapFocus),
             Key::Delete => {
                 if !self.is_empty() {
+                    let entry = self.model.borrow().history_entry(self.geometry.selected());
                     send_signal(
                         &self.display,
                         &self.signal_sender,
-                        Signal::DeleteEntry(self.selected),
+                        Signal::DeleteEntry(entry),
                     );
                 }
             }
@@ -418,59 +982,67 @@ impl ListView {
         self.layout.window.at(self.layout.reparent).contains(x, y)
     }
 
+    /// The item at the position relative to the main window.
+    pub fn item_at(&mut self, x: i32, y: i32) -> Option<usize> {
+        self.sync();
+        if !self.hit_test(x, y) {
+            return None;
+        }
+        let idx = self
+            .geometry
+            .item_at(self.geometry.scroll() + y - self.layout.reparent.1);
+        (idx < self.len()).then_some(idx)
+    }
+
+    pub fn item_tooltip(&mut self, idx: usize) -> Option<String> {
+        self.model.borrow_mut().get(idx).tooltip.clone()
+    }
+
     pub fn button_press(&mut self, event: &XButtonPressedEvent) {
-        if self.items.is_empty() {
+        self.sync();
+        if self.is_empty() {
             return;
         }
         const MOUSE_WHEEL_UP: u32 = Button4;
         const MOUSE_WHEEL_DOWN: u32 = Button5;
         let redraw;
-        let scroll_before = self.scroll;
         #[allow(non_upper_case_globals)]
         match event.button {
-            MOUSE_WHEEL_UP => {
-                self.scroll -= self.scroll_speed;
-                if self.scroll < 0 {
-                    self.scroll = 0;
-                }
-                redraw = self.scroll != scroll_before;
-                if redraw {
-                    self.adjust_selection();
-                }
-            }
-            MOUSE_WHEEL_DOWN => {
-                self.scroll += self.scroll_speed;
-                // TODO: which one?
-                //if self.scroll >= self.max_scroll_offset {
-                //  self.scroll = self.max_scroll_offset - 1;
-                //}
-                if self.scroll > self.max_scroll_offset {
-                    self.scroll = self.max_scroll_offset;
-                }
-                redraw = self.scroll != scroll_before;
+            MOUSE_WHEEL_UP | MOUSE_WHEEL_DOWN => {
+                let delta = if event.button == MOUSE_WHEEL_UP {
+                    -self.scroll_speed
+                } else {
+                    self.scroll_speed
+                };
+                let before = self.geometry.scroll();
+                redraw = self.geometry.scroll_by(delta);
+                self.velocity
+                    .moved(self.geometry.rows_scrolled(before), Instant::now());
                 if redraw {
                     self.adjust_selection();
                 }
             }
             Button1 => {
                 let y = event.y - self.layout.reparent.1;
-                let click_idx = self.position_to_item_index(self.scroll + y);
-                if click_idx >= self.items.len() {
+                let click_idx = self.geometry.item_at(self.geometry.scroll() + y);
+                if click_idx >= self.len() {
                     // We may have less items than the widget is high but will allow
                     // clicks anywhere on the widget.
                     return;
                 }
+                if !self.model.borrow().is_selectable(click_idx) {
+                    return;
+                }
                 if click_idx != self.click_item {
                     self.change_selected(click_idx);
                     self.click_item = click_idx;
                     // This already redraws
                     self.adjust_view();
                 } else if event.time - self.click_time < 500 {
-                    send_signal(
-                        &self.display,
-                        &self.signal_sender,
-                        Signal::Commit(Some(self.click_item)),
-                    );
+                    self.commit(self.click_item);
+                    // Another click starts a new double-click instead of
+                    // committing again.
+                    self.click_item = usize::MAX;
                 }
                 self.click_time = event.time;
                 return;
@@ -490,3 +1062,266 @@ impl Drop for ListView {
         self.dc.destroy();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::SearchTuning;
+    use std::cell::Cell;
+
+    thread_local! {
+        static MARKUP_CALLS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    struct Counted(usize);
+
+    impl Render for Counted {
+        fn markup(&self, search: &str, _cache: &DesktopEntryCache) -> String {
+            MARKUP_CALLS.with(|calls| calls.set(calls.get() + 1));
+            format!("{search} {}", self.0)
+        }
+
+        fn plain_text(&self, _cache: &DesktopEntryCache) -> String {
+            self.0.to_string()
+        }
+
+        fn key(&self, _cache: &DesktopEntryCache) -> Option<String> {
+            Some(self.0.to_string())
+        }
+    }
+
+    #[test]
+    fn item_keys() {
+        let cache = Arc::new(Mutex::new(DesktopEntryCache::new(
+            &None,
+            &SearchTuning::default(),
+        )));
+        let mut model = ListModel::new(cache);
+        let items: Vec<Counted> = (0..10).map(Counted).collect();
+        model.set_items(&items, "");
+        model.get(3);
+        assert_eq!(model.key(3).as_deref(), Some("3"));
+        let items: Vec<Counted> = (0..10).rev().map(Counted).collect();
+        model.set_items(&items, "");
+        assert_eq!(model.position("3"), Some(6));
+        assert_eq!(model.position("10"), None);
+    }
+
+    #[test]
+    fn one_pixmap_per_view() {
+        use crate::{config::ParsedConfig, layout::Layout};
+        let config = Config::from_parsed(ParsedConfig::default(), false);
+        let layout = Layout::new(1920, 1080, &config, |_| 40);
+        for layout in [&layout.full_list_view, &layout.reduced_list_view] {
+            let mut created = Vec::new();
+            create_pixmaps(layout, |width, height| created.push((width, height)));
+            assert_eq!(created.len(), 1);
+            // The banner is drawn into it
+            let (width, height) = created[0];
+            assert!(width >= layout.window.width && height >= layout.window.height);
+        }
+    }
+
+    #[test]
+    fn selection_changes_copy_their_rows() {
+        const WIDTH: u32 = 1900;
+        const HEIGHT: u32 = 1000;
+        const SCROLL_BAR: u32 = 8;
+        let mut geometry = ListGeometry::new(50, HEIGHT);
+        geometry.reset(100);
+        let mut damage = Damage::default();
+        // Draws the items in `drawn` and copies them like `draw`, returns the
+        // number of pixels copied.
+        let mut draw = |geometry: &ListGeometry, drawn: &[usize]| {
+            for &idx in drawn {
+                damage.add(idx);
+            }
+            let scroll = geometry.scroll();
+            let areas = damage.take_copy_areas(geometry, (WIDTH, HEIGHT), SCROLL_BAR);
+            for (rect, (x, y)) in &areas {
+                // Only visible parts of the pixmap, inside the window
+                assert!(rect.y >= scroll && rect.y + rect.height as i32 <= scroll + HEIGHT as i32);
+                assert_eq!((*x, *y), (rect.x, rect.y - scroll));
+            }
+            copied_pixels(&areas)
+        };
+        let full = (WIDTH * HEIGHT) as u64;
+        assert_eq!(draw(&geometry, &(0..20).collect::<Vec<_>>()), full);
+        // Pressing Down redraws the old and new selection
+        let before = geometry.select(1);
+        let down = draw(&geometry, &[before, 1]);
+        assert_eq!(down, (2 * 50 * WIDTH + SCROLL_BAR * HEIGHT) as u64);
+        assert!(down * 8 < full);
+        // Rows between the drawn ones are not, like with scroll fades at
+        // both ends
+        assert_eq!(
+            draw(&geometry, &[2, 5, 4, 2]),
+            (3 * 50 * WIDTH + SCROLL_BAR * HEIGHT) as u64
+        );
+        // Nothing drawn leaves only the scroll bar
+        assert_eq!(draw(&geometry, &[]), (SCROLL_BAR * HEIGHT) as u64);
+        // Scrolling copies everything again
+        geometry.select(30);
+        geometry.scroll_to_selection();
+        assert_eq!(draw(&geometry, &[1, 30]), full);
+        // Rows out of view aren't copied
+        let before = geometry.select(29);
+        assert_eq!(
+            draw(&geometry, &[0, before, 29]),
+            (2 * 50 * WIDTH + SCROLL_BAR * HEIGHT) as u64
+        );
+        // New items copy everything again
+        damage.invalidate();
+        damage.add(29);
+        let areas = damage.take_copy_areas(&geometry, (WIDTH, HEIGHT), SCROLL_BAR);
+        assert_eq!(copied_pixels(&areas), full);
+        assert!(damage.items.is_empty());
+    }
+
+    #[test]
+    fn items_are_rendered_once() {
+        let cache = Arc::new(Mutex::new(DesktopEntryCache::new(
+            &None,
+            &SearchTuning::default(),
+        )));
+        let mut model = ListModel::new(cache);
+        let items: Vec<Counted> = (0..20).map(Counted).collect();
+        for keystroke in ["f", "fi", "fir"] {
+            MARKUP_CALLS.with(|calls| calls.set(0));
+            model.set_items(&items, keystroke);
+            // The full list view shows 8 rows, then the smart content shows
+            // up and the reduced list view shows the first 6 of them.
+            for idx in (0..8).chain(0..6) {
+                assert_eq!(model.get(idx).markup_text, format!("{keystroke} {idx}"));
+            }
+            assert_eq!(MARKUP_CALLS.with(Cell::get), 8);
+        }
+    }
+
+    #[test]
+    fn visible_items_are_rendered_in_one_pass() {
+        let cache = Arc::new(Mutex::new(DesktopEntryCache::new(
+            &None,
+            &SearchTuning::default(),
+        )));
+        let mut model = ListModel::new(cache);
+        let items: Vec<Counted> = (0..500).map(Counted).collect();
+        model.set_items(&items, "");
+        // What the first `ListView::draw` does for the 8 visible rows
+        model.render(0..8);
+        for idx in 0..8 {
+            assert_eq!(model.get(idx).markup_text, format!(" {idx}"));
+        }
+        assert_eq!(model.cache.locks.get(), 1);
+        // The rows below are still rendered once they are scrolled to
+        assert!(!model.items[8].is_rendered());
+        model.render(495..600);
+        assert!(model.items[499].is_rendered());
+        assert_eq!(model.cache.locks.get(), 2);
+        model.render(0..8);
+        assert_eq!(model.cache.locks.get(), 2);
+    }
+
+    struct Row(RowKind);
+
+    impl Render for Row {
+        fn kind(&self) -> RowKind {
+            self.0
+        }
+
+        fn markup(&self, _search: &str, _cache: &DesktopEntryCache) -> String {
+            "row".to_string()
+        }
+
+        fn plain_text(&self, _cache: &DesktopEntryCache) -> String {
+            "row".to_string()
+        }
+    }
+
+    #[test]
+    fn row_kinds() {
+        use RowKind::*;
+        let cache = Arc::new(Mutex::new(DesktopEntryCache::new(
+            &None,
+            &SearchTuning::default(),
+        )));
+        let mut model = ListModel::new(cache);
+        let rows = [Row(Header), Row(Selectable), Row(Action("web")), Row(Info)];
+        model.set_items(&rows, "");
+        // Known before rendering
+        assert_eq!(model.kind(0), Some(Header));
+        assert_eq!(model.kind(4), None);
+        let selectable = (0..5)
+            .map(|idx| model.is_selectable(idx))
+            .collect::<Vec<_>>();
+        assert_eq!(selectable, [false, true, true, false, false]);
+        let markup = (0..4)
+            .map(|idx| model.get(idx).markup_text.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(markup, ["<b>row</b>", "row", "<i>row</i>", "row"]);
+        assert_eq!(model.kind(2), Some(Action("web")));
+
+        // Only selectable rows show the selection and alternate
+        assert_eq!(Header.background(0, 0), colors::background());
+        assert_eq!(Info.background(3, 3), colors::background());
+        assert_eq!(Info.background(1, 0), colors::background());
+        assert_eq!(
+            Selectable.background(1, 1),
+            colors::list_selected_background()
+        );
+        assert_eq!(
+            Action("web").background(1, 0),
+            colors::list_light_background()
+        );
+        assert_eq!(Selectable.background(2, 0), colors::background());
+        assert_eq!(Header.text_color(), colors::list_match_name());
+        assert_eq!(Info.text_color(), colors::list_match_name());
+        assert_eq!(Action("web").text_color(), colors::text());
+    }
+
+    #[test]
+    fn row_keys() {
+        use RowKind::*;
+        let key = |key, is_shift, is_ctrl| KeyEvent {
+            key,
+            is_shift,
+            is_ctrl,
+        };
+        let keys = [
+            key(Key::Enter, false, false),
+            key(Key::Enter, false, true),
+            key(Key::CtrlC, false, false),
+            key(Key::Enter, true, false),
+            key(Key::Right, false, false),
+            key(Key::CtrlI, false, false),
+        ];
+        let signals = |kind| keys.each_ref().map(|key| row_key_signal(key, kind, 2));
+        assert!(matches!(
+            signals(Some(Selectable)),
+            [
+                Some(Signal::Commit(Some(2))),
+                Some(Signal::CopyCommand(Some(2))),
+                Some(Signal::CopyCommand(Some(2))),
+                Some(Signal::Pick(Some(2))),
+                Some(Signal::ToggleDetails(Some(2))),
+                Some(Signal::ToggleDetails(Some(2))),
+            ]
+        ));
+        // Actions can only be committed
+        assert!(matches!(
+            signals(Some(Action("web"))),
+            [
+                Some(Signal::CommitAction("web")),
+                None,
+                None,
+                None,
+                None,
+                None
+            ]
+        ));
+        for kind in [Some(Header), Some(Info), None] {
+            assert!(signals(kind).iter().all(Option::is_none));
+        }
+        assert!(row_key_signal(&key(Key::Escape, false, false), Some(Selectable), 2).is_none());
+    }
+}
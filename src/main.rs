@@ -1,36 +1,86 @@
-use cache::DesktopEntryCache;
+use clap::{Parser, Subcommand};
+use launcher::{
+    app::{App, Exit},
+    cache::DesktopEntryCache,
+    clean,
+    config::Config,
+    diag, draw, input,
+    instance::{self, Acquired, InstanceMode},
+    perf, theme, warnings,
+    x::{self, Display},
+};
 use std::{
     sync::{Arc, Mutex},
     time::Instant,
 };
-use x::Display;
 
-mod app;
-mod cache;
-mod config;
-mod content;
-mod draw;
-mod entry;
-mod history;
-mod icon_theme;
-mod input;
-mod layout;
-mod list_view;
-mod res;
-mod search;
-mod smart_content;
-mod static_units;
-mod ui;
-mod units;
-mod util;
-mod x;
+const EXIT_STATUS: &str = "\
+Exit status:
+  0  something was launched, opened, or copied
+  1  closed without launching anything, or another instance is running
+  2  could not start, for example without a display";
+
+#[derive(Parser)]
+#[command(about = "Application launcher for X11", after_help = EXIT_STATUS)]
+struct Cli {
+    /// Only allow launching desktop entries, this cannot be turned off by the
+    /// config file.
+    #[arg(long)]
+    restricted: bool,
+    /// List the monitors by the output names `[position."<name>"]` takes, and
+    /// where the window would be placed on each.
+    #[arg(long)]
+    print_monitors: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
 
-use app::App;
-use config::Config;
+#[derive(Subcommand)]
+enum Command {
+    /// Remove cached data and history.
+    Clean(clean::CleanArgs),
+    /// Show diagnostic information.
+    Diag(diag::DiagArgs),
+}
 
 fn main() {
-    let config = Config::load();
-    let cache = Arc::new(Mutex::new(DesktopEntryCache::new(&config.locale)));
+    let cli = Cli::parse();
+    if let Some(Command::Clean(args)) = &cli.command {
+        std::process::exit(if clean::clean(args) { 0 } else { 1 });
+    }
+    perf::init();
+    let config = Config::load(cli.restricted);
+    if cli.print_monitors {
+        std::process::exit(if diag::print_monitors(&config) { 0 } else { 1 });
+    }
+    // Diagnostics don't open a window so they can run next to an instance.
+    let instance_lock = if cli.command.is_some() || config.single_instance == InstanceMode::Off {
+        None
+    } else {
+        match instance::acquire() {
+            Ok(Acquired::Lock(lock)) => Some(lock),
+            Ok(Acquired::Running(owner)) => {
+                if config.single_instance == InstanceMode::Raise {
+                    match Display::connect(None) {
+                        Ok(mut display) => {
+                            owner.present(&display);
+                            display.close();
+                        }
+                        Err(error) => eprintln!("{error}"),
+                    }
+                }
+                std::process::exit(Exit::Cancelled.code());
+            }
+            Err(error) => {
+                warnings::warning!("Failed to check for a running instance: {error}");
+                None
+            }
+        }
+    };
+    let cache = Arc::new(Mutex::new(DesktopEntryCache::new(
+        &config.locale,
+        &config.search_tuning,
+    )));
     {
         let mut cache = cache.lock().unwrap();
         let time = Instant::now();
@@ -45,9 +95,39 @@ fn main() {
             );
         }
     }
+    if let Some(Command::Diag(args)) = &cli.command {
+        let success = diag::diag(args, &cache.lock().unwrap(), &config);
+        std::process::exit(if success { 0 } else { 1 });
+    }
     x::init_threads();
     input::set_locale_info();
-    let mut display = Display::connect(None);
-    App::new(display, cache, config).run();
+    let mut display = match Display::connect(None) {
+        Ok(display) => display,
+        Err(error) => {
+            eprintln!("{error}");
+            drop(instance_lock);
+            std::process::exit(Exit::StartupError.code());
+        }
+    };
+    draw::set_opaque_colors(
+        config
+            .opaque_colors
+            .unwrap_or_else(|| !display.has_compositor()),
+    );
+    theme::apply(config.theme, &display);
+    let exit = {
+        let mut app = App::new(display, cache, config);
+        if let Some(lock) = &instance_lock {
+            if let Err(error) = lock.set_window(app.main_window()) {
+                warnings::warning!("Failed to record the window in the instance lock: {error}");
+            }
+        }
+        app.run()
+    };
     display.close();
+    drop(instance_lock);
+    if perf::enabled() {
+        print!("{}", perf::report());
+    }
+    std::process::exit(exit.code());
 }
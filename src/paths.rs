@@ -0,0 +1,214 @@
+//! Locations of the files the launcher reads and writes.
+//!
+//! These are based on `$HOME`, falling back to the XDG base directory
+//! variables if it's not set.  If neither is available the functions return
+//! `None` and the callers should skip persistence.  The cache directory can
+//! be overridden with `$LAUNCHER_CACHE_DIR`.
+
+use std::path::{Path, PathBuf};
+
+/// Name of the history file inside the cache directory.
+pub const HISTORY: &str = "history";
+/// Name of the file that marks the first-run tour as dismissed, inside the
+/// cache directory.
+pub const TOUR_DISMISSED: &str = "tour_dismissed";
+/// Name of the currency cache inside the cache directory.
+pub const CURRENCIES: &str = "currencies.json";
+/// Names of the currency cache files from before they were combined, these
+/// are only removed.
+pub const LEGACY_CURRENCY_FILES: [&str; 3] = ["units", "rates", "timestamp"];
+/// Name of the persisted desktop entry cache inside the cache directory.
+pub const DESKTOP_ENTRIES: &str = "desktop_entries";
+/// Name of the single instance lock inside the runtime directory.
+pub const INSTANCE_LOCK: &str = "launcher.lock";
+
+fn env_dir(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// `~/.config`, or `$XDG_CONFIG_HOME`
+pub fn config_home() -> Option<PathBuf> {
+    env_dir("HOME")
+        .map(|home| home.join(".config"))
+        .or_else(|| env_dir("XDG_CONFIG_HOME"))
+}
+
+/// `~/.cache`, or `$XDG_CACHE_HOME`
+fn cache_home() -> Option<PathBuf> {
+    env_dir("HOME")
+        .map(|home| home.join(".cache"))
+        .or_else(|| env_dir("XDG_CACHE_HOME"))
+}
+
+/// The directories desktop entries are read from, most important first:
+/// `$XDG_DATA_HOME` (`~/.local/share`) and then `$XDG_DATA_DIRS`.
+pub fn data_dirs() -> Vec<PathBuf> {
+    let data_home =
+        env_dir("XDG_DATA_HOME").or_else(|| env_dir("HOME").map(|home| home.join(".local/share")));
+    let system = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    data_home
+        .into_iter()
+        .chain(
+            system
+                .split(':')
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from),
+        )
+        .collect()
+}
+
+/// `~/.config/launcher.toml`
+pub fn config_file() -> Option<PathBuf> {
+    config_home().map(|dir| dir.join("launcher.toml"))
+}
+
+/// `~/.cache/launcher`, or `$LAUNCHER_CACHE_DIR`
+pub fn cache_dir() -> Option<PathBuf> {
+    env_dir("LAUNCHER_CACHE_DIR").or_else(|| cache_home().map(|dir| dir.join("launcher")))
+}
+
+/// A file inside the cache directory.
+pub fn cache_file(name: &str) -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join(name))
+}
+
+/// A file inside `$XDG_RUNTIME_DIR`, or the cache directory if it's not set.
+pub fn runtime_file(name: &str) -> Option<PathBuf> {
+    env_dir("XDG_RUNTIME_DIR")
+        .or_else(cache_dir)
+        .map(|dir| dir.join(name))
+}
+
+/// A file next to `path` for writing it atomically, named after our pid so
+/// other instances don't write the same one.
+pub fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", std::process::id()));
+    PathBuf::from(name)
+}
+
+/// Writes `contents` to a temporary file and renames it to `path`, so readers
+/// see either the old or the new contents but never a partial file.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let temp = temp_path(path);
+    std::fs::write(&temp, contents)?;
+    std::fs::rename(&temp, path).inspect_err(|_| {
+        std::fs::remove_file(&temp).ok();
+    })
+}
+
+/// Error for when there is no cache directory to persist something in.
+pub fn no_cache_dir() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        "neither HOME nor XDG_CACHE_HOME is set",
+    )
+}
+
+/// Runs `f` with `HOME` set to `home` and the other variables unset.  Tests
+/// changing the environment need to go through this so they don't race.
+#[cfg(test)]
+pub fn with_home<R>(home: Option<&std::path::Path>, f: impl FnOnce() -> R) -> R {
+    use std::sync::Mutex;
+    static LOCK: Mutex<()> = Mutex::new(());
+    let _guard = LOCK.lock().unwrap_or_else(|poison| poison.into_inner());
+    let vars = [
+        "HOME",
+        "XDG_CONFIG_HOME",
+        "XDG_CACHE_HOME",
+        "LAUNCHER_CACHE_DIR",
+        "XDG_DATA_HOME",
+        "XDG_DATA_DIRS",
+        "XDG_RUNTIME_DIR",
+    ];
+    let saved = vars.map(std::env::var_os);
+    for var in vars {
+        std::env::remove_var(var);
+    }
+    if let Some(home) = home {
+        std::env::set_var("HOME", home);
+    }
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+    for (var, value) in vars.iter().zip(saved) {
+        match value {
+            Some(value) => std::env::set_var(var, value),
+            None => std::env::remove_var(var),
+        }
+    }
+    result.unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fallbacks() {
+        with_home(None, || {
+            assert_eq!(cache_dir(), None);
+            std::env::set_var("XDG_CACHE_HOME", "/xdg/cache");
+            assert_eq!(
+                cache_file(HISTORY),
+                Some("/xdg/cache/launcher/history".into())
+            );
+            assert_eq!(config_file(), None);
+            std::env::set_var("LAUNCHER_CACHE_DIR", "/tmp/cache");
+            assert_eq!(cache_file(HISTORY), Some("/tmp/cache/history".into()));
+            assert_eq!(
+                data_dirs(),
+                [PathBuf::from("/usr/local/share"), "/usr/share".into()]
+            );
+        });
+        with_home(Some("/home/user".as_ref()), || {
+            std::env::set_var("XDG_CACHE_HOME", "/xdg/cache");
+            assert_eq!(cache_dir(), Some("/home/user/.cache/launcher".into()));
+            assert_eq!(
+                config_file(),
+                Some("/home/user/.config/launcher.toml".into())
+            );
+            std::env::set_var("XDG_DATA_DIRS", "/opt/share::/usr/share");
+            assert_eq!(
+                data_dirs(),
+                [
+                    PathBuf::from("/home/user/.local/share"),
+                    "/opt/share".into(),
+                    "/usr/share".into()
+                ]
+            );
+            std::env::set_var("XDG_DATA_HOME", "/xdg/data");
+            assert_eq!(data_dirs()[0], PathBuf::from("/xdg/data"));
+        });
+    }
+
+    #[test]
+    fn read_only_home() {
+        use crate::{
+            cache::DesktopEntryCache, history::History, search::SearchTuning, units::currency_cache,
+        };
+        use std::os::unix::fs::PermissionsExt;
+
+        let home = std::env::temp_dir().join(format!("launcher-home-{}", std::process::id()));
+        std::fs::create_dir_all(&home).unwrap();
+        // A file in place of the cache directory so this also fails as root
+        std::fs::write(home.join(".cache"), "").unwrap();
+        std::fs::set_permissions(&home, std::fs::Permissions::from_mode(0o555)).unwrap();
+        let cache = DesktopEntryCache::new(&None, &SearchTuning::default());
+        for home in [Some(home.as_path()), None] {
+            with_home(home, || {
+                let history = History::load(&cache, 10);
+                assert!(history.store().is_err());
+                assert!(
+                    currency_cache::put(&Default::default(), "eur", &Default::default()).is_err()
+                );
+                assert!(matches!(currency_cache::get("eur"), Ok(None)));
+            });
+        }
+        std::fs::set_permissions(&home, std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+}
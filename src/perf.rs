@@ -0,0 +1,338 @@
+//! Opt-in timing of the expensive parts, enabled with `LAUNCHER_PERF=1`.
+//!
+//! A disabled timer costs a relaxed atomic load.  When enabled the totals are
+//! printed when the launcher exits and the last durations are drawn as a line
+//! at the bottom of the list.
+
+use std::{
+    collections::VecDeque,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread::ThreadId,
+    time::{Duration, Instant},
+};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static RECORDS: Mutex<Records> = Mutex::new(Records::new());
+
+/// Number of spans kept for inspection, the totals include all of them.
+const MAX_SPANS: usize = 256;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Phase {
+    CacheRebuild,
+    /// Showing the history or an empty list when the launcher starts.
+    FirstPaint,
+    /// Everything `search::search` does, including waiting for the providers.
+    Search,
+    /// The desktop entry provider, nested in `Search`.
+    DesktopEntries,
+    /// The `PATH` provider, nested in `Search`.
+    Path,
+    Sort,
+    /// Drawing the items of the list view, including their markup and icons.
+    Render,
+    /// Copying a drawn frame to its window.
+    Copy,
+}
+
+impl Phase {
+    const ALL: [Phase; 8] = [
+        Phase::CacheRebuild,
+        Phase::FirstPaint,
+        Phase::Search,
+        Phase::DesktopEntries,
+        Phase::Path,
+        Phase::Sort,
+        Phase::Render,
+        Phase::Copy,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::CacheRebuild => "cache rebuild",
+            Phase::FirstPaint => "first paint",
+            Phase::Search => "search",
+            Phase::DesktopEntries => "search: desktop entries",
+            Phase::Path => "search: PATH",
+            Phase::Sort => "sort",
+            Phase::Render => "render",
+            Phase::Copy => "copy",
+        }
+    }
+}
+
+/// A single timed run of a phase.
+#[derive(Debug, Copy, Clone)]
+pub struct Span {
+    pub phase: Phase,
+    /// The thread the work was done for, providers running on their own
+    /// threads use the one that started the search.
+    pub origin: ThreadId,
+    pub start: Instant,
+    pub duration: Duration,
+}
+
+impl Span {
+    fn end(&self) -> Instant {
+        self.start + self.duration
+    }
+
+    /// Whether `other` happened entirely during this span.
+    pub fn contains(&self, other: &Span) -> bool {
+        other.start >= self.start && other.end() <= self.end()
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Totals {
+    count: u32,
+    total: Duration,
+    max: Duration,
+    last: Duration,
+}
+
+impl Totals {
+    const EMPTY: Self = Self {
+        count: 0,
+        total: Duration::ZERO,
+        max: Duration::ZERO,
+        last: Duration::ZERO,
+    };
+}
+
+/// Pixels copied to windows by the list view, see `copied`.
+#[derive(Debug, Copy, Clone)]
+struct Copied {
+    count: u32,
+    total: u64,
+    last: u64,
+}
+
+struct Records {
+    totals: [Totals; Phase::ALL.len()],
+    spans: VecDeque<Span>,
+    copied: Copied,
+}
+
+impl Records {
+    const fn new() -> Self {
+        Self {
+            totals: [Totals::EMPTY; Phase::ALL.len()],
+            spans: VecDeque::new(),
+            copied: Copied {
+                count: 0,
+                total: 0,
+                last: 0,
+            },
+        }
+    }
+
+    fn add(&mut self, span: Span) {
+        let totals = &mut self.totals[span.phase as usize];
+        totals.count += 1;
+        totals.total += span.duration;
+        totals.max = totals.max.max(span.duration);
+        totals.last = span.duration;
+        if self.spans.len() == MAX_SPANS {
+            self.spans.pop_front();
+        }
+        self.spans.push_back(span);
+    }
+}
+
+/// Enables the timers if `LAUNCHER_PERF` is `1`.
+pub fn init() {
+    set_enabled(std::env::var_os("LAUNCHER_PERF").is_some_and(|value| value == "1"));
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Records the time until it's dropped.
+#[must_use]
+pub struct Timer {
+    phase: Phase,
+    origin: ThreadId,
+    start: Option<Instant>,
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            let span = Span {
+                phase: self.phase,
+                origin: self.origin,
+                start,
+                duration: start.elapsed(),
+            };
+            RECORDS.lock().unwrap().add(span);
+        }
+    }
+}
+
+/// Times `phase` for the current thread.
+pub fn timer(phase: Phase) -> Timer {
+    timer_for(phase, std::thread::current().id())
+}
+
+/// Times `phase` done on behalf of the thread `origin`.
+pub fn timer_for(phase: Phase, origin: ThreadId) -> Timer {
+    Timer {
+        phase,
+        origin,
+        start: enabled().then(Instant::now),
+    }
+}
+
+/// Records that a frame of `pixels` pixels was copied to a window.
+pub fn copied(pixels: u64) {
+    if !enabled() {
+        return;
+    }
+    let copied = &mut RECORDS.lock().unwrap().copied;
+    copied.count += 1;
+    copied.total += pixels;
+    copied.last = pixels;
+}
+
+/// The recorded spans done for `origin` that are still kept, oldest first.
+pub fn spans(origin: ThreadId) -> Vec<Span> {
+    let records = RECORDS.lock().unwrap();
+    records
+        .spans
+        .iter()
+        .filter(|span| span.origin == origin)
+        .copied()
+        .collect()
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.2} ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// The last durations of the phases that make up a search, for the HUD.
+/// `None` if disabled.
+pub fn hud_line() -> Option<String> {
+    if !enabled() {
+        return None;
+    }
+    let records = RECORDS.lock().unwrap();
+    let line = [Phase::Search, Phase::Sort, Phase::Render, Phase::Copy]
+        .map(|phase| {
+            let last = records.totals[phase as usize].last;
+            format!("{} {}", phase.name(), format_duration(last))
+        })
+        .join(" · ");
+    Some(format!("{line} ({} px)", records.copied.last))
+}
+
+/// A table of the totals of all phases that ran at least once.
+pub fn report() -> String {
+    let records = RECORDS.lock().unwrap();
+    let mut report = String::new();
+    for phase in Phase::ALL {
+        let totals = records.totals[phase as usize];
+        if totals.count == 0 {
+            continue;
+        }
+        writeln!(
+            report,
+            "{:<24} {:>5}× total {:>10} avg {:>10} max {:>10}",
+            phase.name(),
+            totals.count,
+            format_duration(totals.total),
+            format_duration(totals.total / totals.count),
+            format_duration(totals.max),
+        )
+        .unwrap();
+    }
+    let copied = records.copied;
+    if copied.count != 0 {
+        writeln!(
+            report,
+            "{:<24} {:>5}× total {:>10} avg {:>10}",
+            "copied pixels",
+            copied.count,
+            copied.total,
+            copied.total / copied.count as u64,
+        )
+        .unwrap();
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cache::{DesktopEntryCache, Entry},
+        search::{search, sort_search_results, PathSearch, SearchTuning},
+        text::Collator,
+    };
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    #[test]
+    fn scripted_query() {
+        set_enabled(true);
+        let cache = Arc::new(Mutex::new(DesktopEntryCache::from_entries(vec![
+            Entry::new("firefox", "Firefox", Some("Web Browser"), "firefox %u"),
+            Entry::new("files", "Files", Some("File Manager"), "nautilus"),
+            Entry::new("gimp", "GIMP", Some("Image Editor"), "gimp"),
+        ])));
+        let tuning = SearchTuning::default();
+        let start = Instant::now();
+        let (mut results, _) = search(
+            "fire",
+            cache,
+            None,
+            &tuning,
+            Some(&PathSearch::default()),
+            None,
+        );
+        sort_search_results(
+            &mut results,
+            &HashMap::new(),
+            None,
+            &tuning,
+            &Collator::default(),
+        );
+        let elapsed = start.elapsed();
+        assert!(!results.is_empty());
+
+        // Other tests may search at the same time, their spans have another
+        // origin.
+        let spans = spans(std::thread::current().id());
+        let of = |phase| spans.iter().filter(move |span| span.phase == phase);
+        let [search] = of(Phase::Search).collect::<Vec<_>>()[..] else {
+            panic!("expected a single search span: {spans:?}");
+        };
+        let [sort] = of(Phase::Sort).collect::<Vec<_>>()[..] else {
+            panic!("expected a single sort span: {spans:?}");
+        };
+        // Both providers ran inside the search
+        for provider in [Phase::DesktopEntries, Phase::Path] {
+            let [provider] = of(provider).collect::<Vec<_>>()[..] else {
+                panic!("expected a single {provider:?} span: {spans:?}");
+            };
+            assert!(search.contains(provider), "{provider:?} not in {search:?}");
+        }
+        // Sorting comes after the search and neither is longer than the query
+        assert!(sort.start >= search.end());
+        assert!(search.duration + sort.duration <= elapsed);
+        assert!(hud_line().unwrap().starts_with("search "));
+        assert!(report().contains("search: PATH"));
+    }
+}
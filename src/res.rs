@@ -1,4 +1,4 @@
-use crate::config::ICON_THEME;
+use crate::warnings::warning;
 use cairo::Pattern;
 use gio::{Cancellable, File, MemoryInputStream};
 use glib::Bytes;
@@ -13,6 +13,8 @@ pub mod resources {
     pub static CALCULATE_ICON: &[u8] = include_bytes!("../res/calculate.svg");
     pub static CONVERSION_PATH_ICON: &[u8] = include_bytes!("../res/conversion_path.svg");
     pub static WARNING_ICON: &[u8] = include_bytes!("../res/warning.svg");
+    pub static SCHEDULE_ICON: &[u8] = include_bytes!("../res/schedule.svg");
+    pub static CALENDAR_ICON: &[u8] = include_bytes!("../res/calendar_today.svg");
 }
 
 pub struct Svg {
@@ -41,18 +43,88 @@ impl Svg {
         }
     }
 
-    pub fn open(path: &str) -> Self {
-        let handle = Box::new(Loader::new().read_path(path).unwrap());
+    /// Opens an SVG file, other images are wrapped in an SVG document so they
+    /// can be drawn the same way.
+    pub fn open(path: &str) -> Option<Self> {
+        let handle = if path.ends_with(".svg") {
+            Loader::new().read_path(path)
+        } else {
+            let document = raster_document(path);
+            let bytes = Bytes::from_owned(document.into_bytes());
+            let stream = MemoryInputStream::from_bytes(&bytes);
+            // The image is resolved relative to the base file and librsvg
+            // only allows loading images next to or below it.
+            Loader::new().read_stream(&stream, Some(&File::for_path(path)), None::<&Cancellable>)
+        };
+        let handle = match handle {
+            Ok(handle) => Box::new(handle),
+            Err(error) => {
+                warning!("Failed to load icon {path}: {error}");
+                return None;
+            }
+        };
         let static_handle: &'static _ = unsafe { &*(handle.as_ref() as *const SvgHandle) };
         let renderer = CairoRenderer::new(static_handle);
-        Self {
+        Some(Self {
             renderer,
             _handle: handle,
             pattern: None,
+        })
+    }
+}
+
+/// An SVG document showing the image at `path`, scaled to fit the viewport.
+fn raster_document(path: &str) -> String {
+    let file_name = std::path::Path::new(path)
+        .file_name()
+        .map(|name| name.as_encoded_bytes())
+        .unwrap_or_default();
+    let mut href = String::new();
+    for &byte in file_name {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            href.push(byte as char);
+        } else {
+            href.push_str(&format!("%{byte:02X}"));
         }
     }
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 1 1"><image width="1" height="1" href="{href}"/></svg>"#
+    )
 }
 
-pub fn find_icon(name: &str) -> Option<String> {
-    ICON_THEME.with_borrow(|t| t.lookup(name))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2x2 opaque red PNG.
+    const RED_PNG: &[u8] = &[
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x08, 0x06, 0x00, 0x00, 0x00, 0x72,
+        0xb6, 0x0d, 0x24, 0x00, 0x00, 0x00, 0x11, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0xf8,
+        0xcf, 0xc0, 0xf0, 0x1f, 0x84, 0x19, 0x60, 0x0c, 0x00, 0x47, 0xca, 0x07, 0xf9, 0x67, 0x59,
+        0x6e, 0xb7, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+    ];
+
+    #[test]
+    fn raster_icons() {
+        let dir = std::env::temp_dir().join(format!("launcher-raster-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("my icon#1.png");
+        std::fs::write(&path, RED_PNG).unwrap();
+        let svg = Svg::open(path.to_str().unwrap()).unwrap();
+        let mut surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 8, 8).unwrap();
+        {
+            let context = cairo::Context::new(&surface).unwrap();
+            svg.renderer
+                .render_document(&context, &cairo::Rectangle::new(0.0, 0.0, 8.0, 8.0))
+                .unwrap();
+        }
+        let data = surface.data().unwrap();
+        // Center pixel, premultiplied BGRA on little endian
+        let offset = 4 * 8 * 4 + 4 * 4;
+        assert_eq!(&data[offset..offset + 4], [0, 0, 255, 255]);
+        drop(data);
+        assert!(Svg::open(dir.join("missing.svg").to_str().unwrap()).is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
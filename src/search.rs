@@ -1,35 +1,45 @@
 use crate::{
-    cache::{DesktopEntryCache, MatchField},
+    cache::{DesktopEntryCache, ExecArgument, MatchField},
+    history,
     list_view::Render,
+    perf::{self, Phase},
     res::Svg,
+    text::{escape_markup, fold, fold_with_origins, push_markup_char, Collator},
     ui::colors,
+    warnings::warning,
 };
 use std::{
     cell::OnceCell,
     cmp::Ordering,
-    collections::HashMap,
+    collections::{hash_map::Entry, HashMap},
     os::unix::prelude::PermissionsExt,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{
-        mpsc::{channel, Sender},
+        atomic::{AtomicBool, Ordering as AtomicOrdering},
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender, TryRecvError},
         Arc, Mutex,
     },
     thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
 #[derive(Copy, Clone)]
 pub enum MatchKind {
     Exact,
+    /// The search text starts the value, only used for program names where
+    /// it's a stronger hint than any similarity, like `gimp` for `gimp-2.10`.
+    Prefix,
     Similar(f64),
 }
 
-mod scores {
+pub mod scores {
     // Desktop entry weights
     pub const LOCALIZED_NAME_WEIGHT: f64 = 1.4;
     pub const NAME_WEIGHT: f64 = 1.2;
     pub const LOCALIZED_GENERIC_NAME_WEIGHT: f64 = 1.3;
     pub const GENERIC_NAME_WEIGHT: f64 = 1.1;
     pub const FILE_NAME_WEIGHT: f64 = 0.8;
+    pub const EXEC_NAME_WEIGHT: f64 = 0.9;
     // Path weights
     pub const PATH_WEIGHT: f64 = 1.0;
 
@@ -39,15 +49,67 @@ mod scores {
 
     /// Two scores with a delta less than or equal to this are considered to be equal.
     pub const EQUAL_THREHOLD: f64 = 1e-3;
+
+    /// Scores of items in the history get multiplied by this and their recency.
+    pub const HISTORY_BOOST: f64 = 2.0;
+
+    /// The entry committed for the query before gets this times the best
+    /// score, so it's always first.
+    pub const CHOICE_BOOST: f64 = 2.0;
 }
 
 pub const SIMILARITY_THRESHHOLD: f64 = 0.75;
 
+/// Minimum similarity for the beginning of the search text to be taken as an
+/// application name when the rest is passed to it as a file or URL.
+pub const ARGUMENT_MATCH_THRESHOLD: f64 = 0.9;
+
+/// Minimum similarity of the closest entry to be suggested when nothing
+/// matched.
+pub const SUGGESTION_THRESHOLD: f64 = 0.6;
+
+/// Weights and thresholds used for scoring, the defaults are the constants in
+/// `scores` and `SIMILARITY_THRESHHOLD`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SearchTuning {
+    pub localized_name_weight: f64,
+    pub name_weight: f64,
+    pub localized_generic_name_weight: f64,
+    pub generic_name_weight: f64,
+    pub file_name_weight: f64,
+    pub exec_name_weight: f64,
+    pub path_weight: f64,
+    pub exact_base: f64,
+    pub similarity_threshold: f64,
+    pub history_boost: f64,
+    pub equal_threshold: f64,
+}
+
+impl Default for SearchTuning {
+    fn default() -> Self {
+        Self {
+            localized_name_weight: scores::LOCALIZED_NAME_WEIGHT,
+            name_weight: scores::NAME_WEIGHT,
+            localized_generic_name_weight: scores::LOCALIZED_GENERIC_NAME_WEIGHT,
+            generic_name_weight: scores::GENERIC_NAME_WEIGHT,
+            file_name_weight: scores::FILE_NAME_WEIGHT,
+            exec_name_weight: scores::EXEC_NAME_WEIGHT,
+            path_weight: scores::PATH_WEIGHT,
+            exact_base: scores::EXACT_BASE,
+            similarity_threshold: SIMILARITY_THRESHHOLD,
+            history_boost: scores::HISTORY_BOOST,
+            equal_threshold: scores::EQUAL_THREHOLD,
+        }
+    }
+}
+
 /// Desktop entry match data; actualy desktop entry data is in `cache::Entry`.
 pub struct DesktopEntryData {
     pub id: usize,
     pub name: String,
     pub match_name: Option<String>,
+    /// File or URL to open with the entry.
+    pub argument: Option<ExecArgument>,
 }
 
 pub enum SearchMatchKind {
@@ -59,6 +121,8 @@ pub struct SearchMatch {
     match_: SearchMatchKind,
     score: f64,
     is_in_history: bool,
+    /// Shown as "Did you mean …?", see `suggest`.
+    is_suggestion: bool,
 }
 
 impl SearchMatch {
@@ -68,6 +132,7 @@ impl SearchMatch {
             score,
             // This is set in `sort_search_results` when the score is boosted.
             is_in_history: false,
+            is_suggestion: false,
         }
     }
 
@@ -75,6 +140,14 @@ impl SearchMatch {
         &self.match_
     }
 
+    fn has_argument(&self) -> bool {
+        matches!(&self.match_, SearchMatchKind::DeskopEntry(entry) if entry.argument.is_some())
+    }
+
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+
     fn name(&self) -> &str {
         match &self.match_ {
             SearchMatchKind::PathEntry(path) => path.file_name().unwrap().to_str().unwrap(),
@@ -82,9 +155,9 @@ impl SearchMatch {
         }
     }
 
-    fn compare(&self, other: &Self) -> Ordering {
-        if (self.score - other.score).abs() <= scores::EQUAL_THREHOLD {
-            self.name().cmp(other.name())
+    fn compare(&self, other: &Self, tuning: &SearchTuning, collator: &Collator) -> Ordering {
+        if (self.score - other.score).abs() <= tuning.equal_threshold {
+            collator.compare(self.name(), other.name())
         } else {
             other.score.total_cmp(&self.score)
         }
@@ -94,24 +167,50 @@ impl SearchMatch {
 impl Render for SearchMatch {
     fn markup(&self, search: &str, _cache: &DesktopEntryCache) -> String {
         match &self.match_ {
+            SearchMatchKind::DeskopEntry(DesktopEntryData {
+                name,
+                argument: Some(argument),
+                ..
+            }) => {
+                format!(
+                    "{} <span color=\"{}\">— open {}</span>",
+                    escape_markup(name),
+                    colors::list_match_name(),
+                    escape_markup(&argument.display_name())
+                )
+            }
+            SearchMatchKind::DeskopEntry(entry) if self.is_suggestion => {
+                format!(
+                    "<span color=\"{}\">Did you mean</span> {}<span color=\"{}\">?</span>",
+                    colors::list_match_name(),
+                    escape_markup(&entry.name),
+                    colors::list_match_name(),
+                )
+            }
             SearchMatchKind::DeskopEntry(entry) => {
                 if let Some(match_name) = &entry.match_name {
                     format!(
                         "{} <span color=\"{}\">({})</span>",
-                        entry.name,
-                        colors::LIST_MATCH_NAME,
+                        escape_markup(&entry.name),
+                        colors::list_match_name(),
                         highlight_match(match_name, search)
                     )
                 } else {
                     highlight_match(&entry.name, search)
                 }
             }
-            SearchMatchKind::PathEntry(path) => {
-                highlight_match(path.file_name().unwrap().to_str().unwrap(), search)
-            }
+            SearchMatchKind::PathEntry(path) => path_markup(path, search),
         }
     }
 
+    fn plain_text(&self, _cache: &DesktopEntryCache) -> String {
+        self.name().to_string()
+    }
+
+    fn is_executable(&self) -> bool {
+        matches!(self.match_, SearchMatchKind::PathEntry(_))
+    }
+
     fn icon(&self, cache: &DesktopEntryCache) -> Option<Svg> {
         match &self.match_ {
             SearchMatchKind::PathEntry(_) => None,
@@ -119,32 +218,65 @@ impl Render for SearchMatch {
                 .get_entry(entry.id)
                 .icon
                 .as_ref()
-                .map(|icon_path| Svg::open(icon_path)),
+                .and_then(|icon_path| Svg::open(icon_path)),
         }
     }
 
     fn is_in_history(&self) -> bool {
         self.is_in_history
     }
-}
 
-fn send_finish(writer: Sender<Option<SearchMatch>>) {
-    let mut tries = 0;
-    while tries < 10 {
-        if writer.send(None).is_ok() {
-            return;
+    fn history_entry(&self, cache: &DesktopEntryCache) -> Option<history::Entry> {
+        match &self.match_ {
+            SearchMatchKind::DeskopEntry(entry) if self.is_in_history => Some(
+                history::Entry::DesktopEntry(cache.get_entry(entry.id).file_name.clone()),
+            ),
+            _ => None,
         }
-        tries += 1;
     }
-    panic!("Failed to send finish token {} times.", tries);
+
+    fn tooltip(&self, cache: &DesktopEntryCache) -> Option<String> {
+        Some(match &self.match_ {
+            SearchMatchKind::PathEntry(path) => escape_markup(&path.to_string_lossy()).into_owned(),
+            SearchMatchKind::DeskopEntry(entry) => cache.get_entry(entry.id).tooltip_markup(),
+        })
+    }
+
+    fn key(&self, cache: &DesktopEntryCache) -> Option<String> {
+        Some(match &self.match_ {
+            SearchMatchKind::PathEntry(path) => format!("path:{}", path.display()),
+            SearchMatchKind::DeskopEntry(entry) => {
+                let file_name = &cache.get_entry(entry.id).file_name;
+                match &entry.argument {
+                    Some(argument) => format!("desktop:{file_name}:{}", argument.display_name()),
+                    None => format!("desktop:{file_name}"),
+                }
+            }
+        })
+    }
 }
 
-fn path_entry_score(item: &str, target: &str) -> Option<f64> {
+/// What the providers send to `search`.
+enum Message {
+    Match(SearchMatch),
+    /// Sent once a provider has sent all its matches.
+    Finished(Phase),
+}
+
+fn send_finish(writer: Sender<Message>, provider: Phase) {
+    // Nobody waits for the late results of an outdated search, and the
+    // receiver is gone if the launcher quit in the meantime.
+    if writer.send(Message::Finished(provider)).is_err() {
+        eprintln!("{} finished after its search was dropped", provider.name());
+    }
+}
+
+fn path_entry_score(item: &str, target: &str, tuning: &SearchTuning) -> Option<f64> {
     if item == target {
-        Some(scores::EXACT_BASE)
+        Some(tuning.exact_base)
     } else {
         let sim = strsim::jaro_winkler(item, target);
-        if sim < SIMILARITY_THRESHHOLD {
+        if sim < tuning.similarity_threshold {
             None
         } else {
             Some(sim)
@@ -152,7 +284,7 @@ fn path_entry_score(item: &str, target: &str) -> Option<f64> {
     }
 }
 
-fn search_path(name: String, sender: Sender<Option<SearchMatch>>) {
+fn search_path(name: String, sender: Sender<Message>, tuning: SearchTuning) {
     let paths = std::env::var("PATH").unwrap();
     for path in paths.split(':') {
         if let Ok(dir) = std::fs::read_dir(path) {
@@ -160,13 +292,13 @@ fn search_path(name: String, sender: Sender<Option<SearchMatch>>) {
                 if entry.file_type().unwrap().is_file()
                     && entry.metadata().unwrap().permissions().mode() & 0o111 != 0
                 {
-                    let entry_name = entry.file_name().to_str().unwrap().to_lowercase();
-                    if let Some(score) = path_entry_score(&entry_name, &name) {
-                        if score >= SIMILARITY_THRESHHOLD {
+                    let entry_name = fold(entry.file_name().to_str().unwrap());
+                    if let Some(score) = path_entry_score(&entry_name, &name, &tuning) {
+                        if score >= tuning.similarity_threshold {
                             sender
-                                .send(Some(SearchMatch::new(
+                                .send(Message::Match(SearchMatch::new(
                                     SearchMatchKind::PathEntry(entry.path()),
-                                    score * scores::PATH_WEIGHT,
+                                    score * tuning.path_weight,
                                 )))
                                 .ok();
                         }
@@ -175,31 +307,33 @@ fn search_path(name: String, sender: Sender<Option<SearchMatch>>) {
             }
         }
     }
-    send_finish(sender);
 }
 
-fn get_field_scale(field: MatchField) -> f64 {
+fn get_field_scale(field: MatchField, tuning: &SearchTuning) -> f64 {
     match field {
-        MatchField::LocalizedName(_) => scores::LOCALIZED_NAME_WEIGHT,
-        MatchField::Name(_) => scores::NAME_WEIGHT,
-        MatchField::LocalizedGenericName(_) => scores::LOCALIZED_GENERIC_NAME_WEIGHT,
-        MatchField::GenericName(_) => scores::GENERIC_NAME_WEIGHT,
-        MatchField::FileName(_) => scores::FILE_NAME_WEIGHT,
+        MatchField::LocalizedName(_) => tuning.localized_name_weight,
+        MatchField::Name(_) => tuning.name_weight,
+        MatchField::LocalizedGenericName(_) => tuning.localized_generic_name_weight,
+        MatchField::GenericName(_) => tuning.generic_name_weight,
+        MatchField::FileName(_) => tuning.file_name_weight,
+        MatchField::ExecName(_) => tuning.exec_name_weight,
     }
 }
 
-fn desktop_entry_score(field: MatchField) -> f64 {
+fn desktop_entry_score(field: MatchField, tuning: &SearchTuning) -> f64 {
     match field.into_inner() {
-        MatchKind::Exact => scores::EXACT_BASE,
-        MatchKind::Similar(sim) => sim * get_field_scale(field),
+        MatchKind::Exact => tuning.exact_base,
+        MatchKind::Prefix => tuning.exact_base * get_field_scale(field, tuning),
+        MatchKind::Similar(sim) => sim * get_field_scale(field, tuning),
     }
 }
 
 fn search_desktop_entries(
     name: String,
-    sender: Sender<Option<SearchMatch>>,
+    sender: Sender<Message>,
     cache: Arc<Mutex<DesktopEntryCache>>,
     previous: Option<Vec<SearchMatch>>,
+    tuning: SearchTuning,
 ) {
     let cache = cache.as_ref().lock().unwrap();
     let matches = if let Some(previous) = previous {
@@ -218,7 +352,7 @@ fn search_desktop_entries(
     };
     for match_ in matches {
         let entry = cache.get_entry(match_.id);
-        let score = desktop_entry_score(match_.field);
+        let score = desktop_entry_score(match_.field, &tuning);
         let name = entry.name.clone();
         let matched_field = entry.get_field(match_.field);
         let match_name = if name == matched_field {
@@ -227,66 +361,311 @@ fn search_desktop_entries(
             Some(matched_field.to_owned())
         };
         sender
-            .send(Some(SearchMatch::new(
+            .send(Message::Match(SearchMatch::new(
                 SearchMatchKind::DeskopEntry(DesktopEntryData {
                     id: match_.id,
                     name: entry.name.clone(),
                     match_name,
+                    argument: None,
                 }),
                 score,
             )))
             .ok();
     }
-    send_finish(sender);
 }
 
+/// Finds desktop entries named by the beginning of `text` that can open the
+/// rest of it, an existing path or a URL, as their file argument.
+pub fn search_with_argument(
+    text: &str,
+    cache: &DesktopEntryCache,
+    tuning: &SearchTuning,
+) -> Vec<SearchMatch> {
+    let text = text.trim_start();
+    let mut results = Vec::new();
+    let split_points = text
+        .char_indices()
+        .filter(|(_, c)| c.is_whitespace())
+        .map(|(i, _)| i);
+    for split in split_points {
+        let (name, rest) = text.split_at(split);
+        let Some(argument) = ExecArgument::parse(rest) else {
+            continue;
+        };
+        for match_ in cache.find_all(&fold(name)) {
+            let strong = match match_.field.into_inner() {
+                MatchKind::Exact | MatchKind::Prefix => true,
+                MatchKind::Similar(sim) => sim >= ARGUMENT_MATCH_THRESHOLD,
+            };
+            let entry = cache.get_entry(match_.id);
+            if strong && entry.accepts(&argument) {
+                results.push(SearchMatch::new(
+                    SearchMatchKind::DeskopEntry(DesktopEntryData {
+                        id: match_.id,
+                        name: entry.name.clone(),
+                        match_name: None,
+                        argument: Some(argument.clone()),
+                    }),
+                    desktop_entry_score(match_.field, tuning),
+                ));
+            }
+        }
+    }
+    merge_duplicates(results)
+}
+
+/// The desktop entry closest to `name`, for when nothing reached the
+/// similarity threshold.  `None` if nothing is close at all.
+pub fn suggest(name: &str, cache: &DesktopEntryCache) -> Option<SearchMatch> {
+    let (id, similarity) = cache.closest(&fold(name))?;
+    if similarity < SUGGESTION_THRESHOLD {
+        return None;
+    }
+    let mut suggestion = SearchMatch::new(
+        SearchMatchKind::DeskopEntry(DesktopEntryData {
+            id,
+            name: cache.get_entry(id).name.clone(),
+            match_name: None,
+            argument: None,
+        }),
+        similarity,
+    );
+    suggestion.is_suggestion = true;
+    Some(suggestion)
+}
+
+/// Whether a `PATH` search is still running.  It's shared between searches
+/// so one that's late, like for a directory on a hung mount, isn't started
+/// again on every keystroke.
+#[derive(Clone, Default)]
+pub struct PathSearch(Arc<AtomicBool>);
+
+impl PathSearch {
+    pub fn is_running(&self) -> bool {
+        self.0.load(AtomicOrdering::Acquire)
+    }
+
+    /// Marks the search as running, returns `false` if it already is.
+    fn start(&self) -> bool {
+        !self.0.swap(true, AtomicOrdering::AcqRel)
+    }
+}
+
+/// Clears `PathSearch` when dropped, so it's also cleared if the search
+/// panics.
+struct PathSearchRunning(PathSearch);
+
+impl Drop for PathSearchRunning {
+    fn drop(&mut self) {
+        (self.0).0.store(false, AtomicOrdering::Release);
+    }
+}
+
+fn search_path_tracked(
+    name: String,
+    sender: Sender<Message>,
+    tuning: SearchTuning,
+    running: PathSearchRunning,
+) {
+    search_path(name, sender, tuning);
+    drop(running);
+}
+
+/// Providers that missed the deadline of a search.  Their matches are added
+/// to the results as they arrive, see `merge_late`.
+pub struct LateResults {
+    receiver: Receiver<Message>,
+    providers: Vec<Phase>,
+}
+
+impl LateResults {
+    /// The matches that arrived since the last call.
+    pub fn poll(&mut self) -> Vec<SearchMatch> {
+        let mut matches = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(Message::Match(match_)) => matches.push(match_),
+                Ok(Message::Finished(provider)) => self.providers.retain(|&p| p != provider),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.providers.clear();
+                    break;
+                }
+            }
+        }
+        matches
+    }
+
+    /// Whether all late providers have finished.
+    pub fn is_done(&self) -> bool {
+        self.providers.is_empty()
+    }
+
+    /// Whether `provider` is one of the late ones that hasn't finished.
+    pub fn is_running(&self, provider: Phase) -> bool {
+        self.providers.contains(&provider)
+    }
+}
+
+/// Searches desktop entries and, with `path`, executables in `PATH` unless
+/// the `PATH` search of an earlier call is still running.  Providers that
+/// are still running after `timeout` don't hold up the others, what they find
+/// later is returned as `LateResults`.  Without a timeout it waits for all of
+/// them.
 pub fn search(
     name: &str,
     cache: Arc<Mutex<DesktopEntryCache>>,
     previous: Option<Vec<SearchMatch>>,
-) -> Vec<SearchMatch> {
+    tuning: &SearchTuning,
+    path: Option<&PathSearch>,
+    timeout: Option<Duration>,
+) -> (Vec<SearchMatch>, Option<LateResults>) {
+    let _timer = perf::timer(Phase::Search);
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let origin = thread::current().id();
     let (sender, receiver) = channel();
     let mut results: Vec<SearchMatch> = Vec::new();
-    // Number of running search functions
-    let mut running = 0;
+    let tuning = *tuning;
     macro_rules! begin {
-        ($function:ident $(, $opt:expr)*) => {{
-            let my_name = name.to_lowercase();
+        ($phase:expr, $function:ident $(, $opt:expr)*) => {{
+            let my_name = fold(name);
             let my_writer = sender.clone();
-            let handle = thread::spawn(move || $function (my_name, my_writer, $($opt),*));
-            running += 1;
-            handle
+            let handle = thread::spawn(move || {
+                let _timer = perf::timer_for($phase, origin);
+                $function (my_name, my_writer.clone(), $($opt),*);
+                send_finish(my_writer, $phase);
+            });
+            ($phase, handle)
         }}
     }
-    let threads: [JoinHandle<()>; 2] = [
-        begin!(search_path),
-        begin!(search_desktop_entries, cache, previous),
-    ];
-    while running != 0 {
-        match receiver.recv() {
-            Ok(result_or_finish_token) => {
-                if let Some(result) = result_or_finish_token {
-                    results.push(result);
-                } else {
-                    running -= 1;
-                }
+    let mut threads: Vec<(Phase, JoinHandle<()>)> = vec![begin!(
+        Phase::DesktopEntries,
+        search_desktop_entries,
+        cache,
+        previous,
+        tuning
+    )];
+    if let Some(path) = path.filter(|path| path.start()) {
+        let running = PathSearchRunning(path.clone());
+        threads.push(begin!(Phase::Path, search_path_tracked, tuning, running));
+    }
+    // Otherwise the channel can't disconnect if a provider panics
+    drop(sender);
+    let mut running: Vec<Phase> = threads.iter().map(|(phase, _)| *phase).collect();
+    while !running.is_empty() {
+        let message = match deadline {
+            Some(deadline) => {
+                receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
             }
-            Err(error) => {
-                eprintln!("Receive error: {error}");
+            None => receiver.recv().map_err(RecvTimeoutError::from),
+        };
+        match message {
+            Ok(Message::Match(result)) => results.push(result),
+            Ok(Message::Finished(provider)) => running.retain(|&p| p != provider),
+            Err(RecvTimeoutError::Timeout) => break,
+            Err(RecvTimeoutError::Disconnected) => {
+                eprintln!("Search providers stopped without finishing: {running:?}");
+                running.clear();
             }
         }
     }
-    for i in threads {
-        i.join().ok();
+    for (phase, thread) in threads {
+        // Late providers are left to finish on their own
+        if !running.contains(&phase) {
+            thread.join().ok();
+        }
+    }
+    let late = (!running.is_empty()).then(|| {
+        for provider in &running {
+            warning!(
+                "{} took longer than {} ms, its results are added when it finishes",
+                provider.name(),
+                timeout.unwrap_or_default().as_millis()
+            );
+        }
+        LateResults {
+            receiver,
+            providers: running,
+        }
+    });
+    (merge_duplicates(results), late)
+}
+
+/// Adds the matches of late providers to `results`, keeping the better of two
+/// matches for the same item.  Matches with an argument are separate items.
+/// A suggestion is only for when nothing matched, so it's removed.
+pub fn merge_late(results: &mut Vec<SearchMatch>, late: Vec<SearchMatch>) {
+    results.retain(|result| !result.is_suggestion);
+    for match_ in late {
+        let identity = MatchIdentity::of(&match_);
+        let existing = results
+            .iter_mut()
+            .find(|result| !result.has_argument() && MatchIdentity::of(result) == identity);
+        match existing {
+            Some(existing) if match_.score > existing.score => *existing = match_,
+            Some(_) => {}
+            None => results.push(match_),
+        }
+    }
+}
+
+/// Identifies what a match refers to, independent of how it matched.
+#[derive(PartialEq, Eq, Hash)]
+enum MatchIdentity {
+    DesktopEntry(usize),
+    Path(PathBuf),
+}
+
+impl MatchIdentity {
+    fn of(match_: &SearchMatch) -> Self {
+        match &match_.match_ {
+            SearchMatchKind::DeskopEntry(entry) => Self::DesktopEntry(entry.id),
+            SearchMatchKind::PathEntry(path) => Self::Path(path.clone()),
+        }
     }
-    results
+}
+
+/// Merges multiple matches for the same desktop entry or path into a single
+/// one, keeping the highest scoring match.  The order of the first occurence
+/// of each item is preserved.
+fn merge_duplicates(results: Vec<SearchMatch>) -> Vec<SearchMatch> {
+    let mut indices: HashMap<MatchIdentity, usize> = HashMap::with_capacity(results.len());
+    let mut merged: Vec<SearchMatch> = Vec::with_capacity(results.len());
+    for result in results {
+        match indices.entry(MatchIdentity::of(&result)) {
+            Entry::Occupied(index) => {
+                let existing = &mut merged[*index.get()];
+                if result.score > existing.score {
+                    *existing = result;
+                }
+            }
+            Entry::Vacant(slot) => {
+                slot.insert(merged.len());
+                merged.push(result);
+            }
+        }
+    }
+    merged
 }
 
 /// Sorts the search results. If any of the results is in the history its score
-/// heavily adjusted toward how recent it is in the history.
-pub fn sort_search_results(results: &mut [SearchMatch], history: &HashMap<usize, usize>) {
+/// heavily adjusted toward how recent it is in the history.  `choice` is the
+/// id of the entry that was committed for this query before, see
+/// `History::choice`, it's moved to the top.
+pub fn sort_search_results(
+    results: &mut [SearchMatch],
+    history: &HashMap<usize, usize>,
+    choice: Option<usize>,
+    tuning: &SearchTuning,
+    collator: &Collator,
+) {
+    let _timer = perf::timer(Phase::Sort);
     for result in results.iter_mut() {
         if let SearchMatchKind::DeskopEntry(data) = &result.unwrap() {
+            // Already boosted when sorted before late matches were added
+            if result.is_in_history {
+                continue;
+            }
             if let Some(recency) = history.get(&data.id) {
                 // Original version:
                 // this will always place results in the history above those that are
@@ -295,64 +674,469 @@ pub fn sort_search_results(results: &mut [SearchMatch], history: &HashMap<usize,
 
                 // Note that only for old elements (recency 1 or 2) would this not
                 // have the same effect as the above implementation
-                result.score *= 2.0 * *recency as f64;
+                result.score *= tuning.history_boost * *recency as f64;
 
                 result.is_in_history = true;
             }
         }
     }
-    results.sort_by(|a, b| a.compare(b));
+    if let Some(choice) = choice {
+        let best = results
+            .iter()
+            .map(|result| result.score)
+            .fold(0.0, f64::max);
+        for result in results.iter_mut() {
+            if matches!(result.unwrap(), SearchMatchKind::DeskopEntry(data) if data.id == choice) {
+                result.score = best * scores::CHOICE_BOOST;
+            }
+        }
+    }
+    results.sort_by(|a, b| a.compare(b, tuning, collator));
 }
 
-fn highlight_match(match_str: &str, search: &str) -> String {
+/// The last two components of a directory, so the annotation for an
+/// executable stays short.
+fn short_directory(dir: &Path) -> String {
+    let components: Vec<_> = dir.components().collect();
+    if components.len() <= 3 {
+        return dir.display().to_string();
+    }
+    let last: PathBuf = components[components.len() - 2..].iter().collect();
+    format!("…/{}", last.display())
+}
+
+/// Markup for an executable, its name followed by a dim annotation of the
+/// directory it's in: `htop — /usr/bin`.
+pub fn path_markup(path: &Path, search: &str) -> String {
+    let name = highlight_match(&path.file_name().unwrap().to_string_lossy(), search);
+    match path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        Some(dir) => format!(
+            "{name} <span color=\"{}\">— {}</span>",
+            colors::list_match_name(),
+            escape_markup(&short_directory(dir))
+        ),
+        None => name,
+    }
+}
+
+pub fn highlight_match(match_str: &str, search: &str) -> String {
     const END_HIGHLIGHT: &str = "</span>";
     let cell = OnceCell::new();
     let begin_highlight =
-        cell.get_or_init(|| format!("<span color=\"{}\">", colors::LIST_MATCH_HIGHLIGHT));
+        cell.get_or_init(|| format!("<span color=\"{}\">", colors::list_match_highlight()));
     // Assume 75% of chars in search resulting in this: `<span color="#RRGGBB">X</span>`
     let mut result =
         String::with_capacity(match_str.len() + 30 * search.chars().count() * 75 / 100);
-    let mut match_chars = match_str.chars();
+    // Matching happens on the folded strings, but we need to highlight the
+    // original characters they came from.
+    let mut highlighted = vec![false; match_str.chars().count()];
+    let search = fold(search);
     let mut search_chars = search.chars().filter(|c| *c != ' ');
-    let mut s = search_chars.next().unwrap().to_ascii_lowercase();
-    let mut is_highlight = false;
+    let mut s = search_chars.next();
     // Highlight all matching in-order
-    for c in match_chars.by_ref() {
-        if c.to_ascii_lowercase() == s {
-            if !is_highlight {
-                is_highlight = true;
-                result.push_str(begin_highlight);
+    for (c, origin) in fold_with_origins(match_str) {
+        match s {
+            Some(search_char) if c == search_char => {
+                highlighted[origin] = true;
+                s = search_chars.next();
             }
-            if let Some(next_s) = search_chars.next() {
-                s = next_s.to_ascii_lowercase();
+            None => break,
+            _ => {}
+        }
+    }
+    let mut is_highlight = false;
+    for (c, highlight) in match_str.chars().zip(highlighted) {
+        if highlight != is_highlight {
+            is_highlight = highlight;
+            result.push_str(if highlight {
+                begin_highlight
             } else {
-                result.push(c);
-                break;
-            }
-        } else if is_highlight {
-            is_highlight = false;
-            result.push_str(END_HIGHLIGHT);
+                END_HIGHLIGHT
+            });
         }
-        result.push(c);
+        push_markup_char(&mut result, c);
     }
     if is_highlight {
         result.push_str(END_HIGHLIGHT);
     }
-    result.extend(match_chars);
     result
 }
 
 pub fn search_path_for_exact_match(name: &str) -> bool {
-    if let Ok(path) = std::env::var("PATH") {
-        for path in path.split(':') {
-            if let Ok(dir) = std::fs::read_dir(path) {
-                for entry in dir.flatten() {
-                    if entry.file_name().to_str().unwrap() == name {
-                        return true;
-                    }
+    std::env::var("PATH").is_ok_and(|path| is_in_path(name, &path))
+}
+
+/// Whether a file called `name` exists in any directory of the `PATH` value
+/// `path`.
+pub fn is_in_path(name: &str, path: &str) -> bool {
+    path.split(':')
+        .filter(|dir| !dir.is_empty())
+        .any(|dir| std::path::Path::new(dir).join(name).is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn desktop_match(id: usize, score: f64) -> SearchMatch {
+        SearchMatch::new(
+            SearchMatchKind::DeskopEntry(DesktopEntryData {
+                id,
+                name: format!("entry {id}"),
+                match_name: None,
+                argument: None,
+            }),
+            score,
+        )
+    }
+
+    fn path_match(path: &str, score: f64) -> SearchMatch {
+        SearchMatch::new(SearchMatchKind::PathEntry(PathBuf::from(path)), score)
+    }
+
+    fn highlighted(s: &str) -> String {
+        format!(
+            "<span color=\"{}\">{}</span>",
+            colors::list_match_highlight(),
+            s
+        )
+    }
+
+    #[test]
+    fn suggestions() {
+        let cache = DesktopEntryCache::from_file_names(&["Firefox", "Text Editor"]);
+        let suggested = |text: &str| {
+            suggest(text, &cache).map(|suggestion| {
+                assert!(suggestion.is_suggestion);
+                suggestion.markup(text, &cache)
+            })
+        };
+        assert!(cache.find_all(&fold("xofrief")).is_empty());
+        assert_eq!(
+            suggested("xofrief").as_deref(),
+            Some(
+                format!(
+                    "<span color=\"{0}\">Did you mean</span> Firefox<span color=\"{0}\">?</span>",
+                    colors::list_match_name()
+                )
+                .as_str()
+            )
+        );
+        assert_eq!(suggested("qqqq"), None);
+    }
+
+    #[test]
+    fn accent_insensitive_matching() {
+        let tuning = SearchTuning::default();
+        assert!(
+            path_entry_score(&fold("Téléchargements"), &fold("telecharger"), &tuning).is_some()
+        );
+        assert!(path_entry_score(&fold("Müsik"), &fold("musik"), &tuning).is_some());
+        assert!(
+            path_entry_score(&fold("Einstellungen"), &fold("Éinstellungen"), &tuning).is_some()
+        );
+    }
+
+    #[test]
+    fn accent_insensitive_highlight() {
+        assert_eq!(
+            highlight_match("Téléchargements", "tele"),
+            format!("{}chargements", highlighted("Télé"))
+        );
+        assert_eq!(highlight_match("Müsik", "musik"), highlighted("Müsik"));
+        assert_eq!(
+            highlight_match("Systemüberwachung", "uber"),
+            format!("System{}wachung", highlighted("über"))
+        );
+    }
+
+    #[test]
+    fn escaped_highlight() {
+        assert_eq!(
+            highlight_match("Tom & Jerry", "tj"),
+            format!("{}om &amp; {}erry", highlighted("T"), highlighted("J"))
+        );
+        assert_eq!(
+            highlight_match("a<b>", "b"),
+            format!("a&lt;{}&gt;", highlighted("b"))
+        );
+        let markup = highlight_match("🦊 <Fox>", "fox");
+        let (_, text, _) = pango::parse_markup(&markup, '\0').unwrap();
+        assert_eq!(text, "🦊 <Fox>");
+    }
+
+    #[test]
+    fn quotation_marks_in_names() {
+        let cache = DesktopEntryCache::from_file_names(&["3'x5' Cards", "5\" Bay Monitor"]);
+        for text in ["3'x5'", "5\""] {
+            let found = cache.find_all(&fold(text));
+            assert!(
+                found
+                    .iter()
+                    .any(|found| matches!(found.field.into_inner(), MatchKind::Exact)),
+                "{text}"
+            );
+        }
+        assert_eq!(
+            highlight_match("3'x5' Cards", "3'x5'"),
+            format!("{} Cards", highlighted("3&apos;x5&apos;"))
+        );
+        assert_eq!(
+            highlight_match("5\" Bay", "5\""),
+            format!("{} Bay", highlighted("5&quot;"))
+        );
+    }
+
+    #[test]
+    fn executable_markup() {
+        let dim = |s: &str| format!("<span color=\"{}\">— {s}</span>", colors::list_match_name());
+        assert_eq!(
+            path_markup(Path::new("/usr/bin/htop"), ""),
+            format!("htop {}", dim("/usr/bin"))
+        );
+        assert_eq!(
+            path_markup(Path::new("/usr/bin/htop"), "ht"),
+            format!("{}op {}", highlighted("ht"), dim("/usr/bin"))
+        );
+        assert_eq!(
+            path_markup(Path::new("/home/user/.local/bin/a&b"), ""),
+            format!("a&amp;b {}", dim("…/.local/bin"))
+        );
+        assert_eq!(
+            path_markup(Path::new("/opt/<x>/bin/tool"), ""),
+            format!("tool {}", dim("…/&lt;x&gt;/bin"))
+        );
+        assert_eq!(
+            path_markup(Path::new("/htop"), ""),
+            format!("htop {}", dim("/"))
+        );
+        let markup = path_markup(Path::new("/usr/local/bin/🦊"), "");
+        let (_, text, _) = pango::parse_markup(&markup, '\0').unwrap();
+        assert_eq!(text, "🦊 — …/local/bin");
+    }
+
+    #[test]
+    fn merge_duplicate_desktop_entries() {
+        let merged = merge_duplicates(vec![
+            desktop_match(1, 0.8),
+            desktop_match(2, 0.9),
+            desktop_match(1, 1.1),
+            desktop_match(1, 0.9),
+        ]);
+        assert_eq!(merged.len(), 2);
+        assert!(matches!(
+            merged[0].unwrap(),
+            SearchMatchKind::DeskopEntry(DesktopEntryData { id: 1, .. })
+        ));
+        assert_eq!(merged[0].score, 1.1);
+        assert_eq!(merged[1].score, 0.9);
+    }
+
+    #[test]
+    fn merge_duplicate_paths() {
+        let merged = merge_duplicates(vec![
+            path_match("/usr/bin/foo", 0.8),
+            path_match("/usr/bin/bar", 0.9),
+            path_match("/usr/bin/foo", 1.0),
+            desktop_match(0, 0.7),
+        ]);
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged[0].score, 1.0);
+        assert_eq!(merged[0].name(), "foo");
+    }
+
+    #[test]
+    fn merged_entries_are_boosted_once() {
+        let mut results = merge_duplicates(vec![desktop_match(3, 0.8), desktop_match(3, 0.9)]);
+        let history = HashMap::from([(3, 2)]);
+        sort_search_results(
+            &mut results,
+            &history,
+            None,
+            &SearchTuning::default(),
+            &Collator::default(),
+        );
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_in_history);
+        assert_eq!(results[0].score, 0.9 * 2.0 * 2.0);
+    }
+
+    #[test]
+    fn deletable_results() {
+        let cache = DesktopEntryCache::from_file_names(&["a", "b", "c", "d"]);
+        let mut results = vec![
+            desktop_match(3, 0.8),
+            desktop_match(1, 0.9),
+            path_match("/usr/bin/foo", 1.0),
+        ];
+        let history = HashMap::from([(3, 2)]);
+        sort_search_results(
+            &mut results,
+            &history,
+            None,
+            &SearchTuning::default(),
+            &Collator::default(),
+        );
+        let entries: Vec<_> = results
+            .iter()
+            .map(|result| result.history_entry(&cache))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                Some(history::Entry::DesktopEntry("d".to_string())),
+                None,
+                None
+            ]
+        );
+    }
+    #[test]
+    fn equal_scores_are_collated() {
+        let mut results: Vec<_> = ["zsh", "Éditeur", "editor", "Obsidian"]
+            .into_iter()
+            .enumerate()
+            .map(|(id, name)| {
+                let mut result = desktop_match(id, 1.0);
+                if let SearchMatchKind::DeskopEntry(entry) = &mut result.match_ {
+                    entry.name = name.to_string();
                 }
-            }
+                result
+            })
+            .chain([desktop_match(9, 1.5)])
+            .collect();
+        sort_search_results(
+            &mut results,
+            &HashMap::new(),
+            None,
+            &SearchTuning::default(),
+            &Collator::new("en_US.UTF-8"),
+        );
+        let names: Vec<_> = results.iter().map(SearchMatch::name).collect();
+        assert_eq!(names, ["entry 9", "Éditeur", "editor", "Obsidian", "zsh"]);
+    }
+
+    #[test]
+    fn argument_matches() {
+        let mut cache = DesktopEntryCache::from_file_names(&["gimp", "image viewer", "notes"]);
+        cache.set_exec("gimp", "gimp %U");
+        cache.set_exec("image viewer", "viewer %f");
+        let tuning = SearchTuning::default();
+        let file = std::env::temp_dir().join(format!("launcher-shot-{}.png", std::process::id()));
+        std::fs::write(&file, "").unwrap();
+        let argument = |text: &str| {
+            search_with_argument(text, &cache, &tuning)
+                .into_iter()
+                .map(|m| match m.match_ {
+                    SearchMatchKind::DeskopEntry(data) => (data.name, data.argument.unwrap()),
+                    _ => unreachable!(),
+                })
+                .collect::<Vec<_>>()
+        };
+        let path = ExecArgument::parse(file.to_str().unwrap()).unwrap();
+        let url = ExecArgument::Url("https://example.com".to_string());
+        assert_eq!(
+            argument(&format!("gimp {}", file.display())),
+            [("gimp".to_string(), path.clone())]
+        );
+        assert_eq!(
+            argument(&format!("image viewer {}", file.display())),
+            [("image viewer".to_string(), path)]
+        );
+        assert_eq!(
+            argument("gimp https://example.com"),
+            [("gimp".to_string(), url)]
+        );
+        // Only accepts files
+        assert!(argument("image viewer https://example.com").is_empty());
+        // No field code
+        assert!(argument(&format!("notes {}", file.display())).is_empty());
+        assert!(argument("gimp /does/not/exist.png").is_empty());
+        assert!(argument(&format!("paint {}", file.display())).is_empty());
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn late_providers() {
+        let cache = Arc::new(Mutex::new(DesktopEntryCache::from_file_names(&[
+            "Firefox", "Files",
+        ])));
+        let tuning = SearchTuning::default();
+        let timeout = Some(Duration::from_millis(10));
+        // The desktop entry provider can't start while the cache is locked
+        let locked = cache.lock().unwrap();
+        let (mut results, late) = search("firefox", cache.clone(), None, &tuning, None, timeout);
+        assert!(results.is_empty());
+        let mut late = late.expect("the desktop entry provider is late");
+        assert!(late.poll().is_empty());
+        assert!(!late.is_done());
+        drop(locked);
+        let start = Instant::now();
+        while !late.is_done() {
+            assert!(start.elapsed() < Duration::from_secs(10));
+            let matches = late.poll();
+            merge_late(&mut results, matches);
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name(), "Firefox");
+
+        let (results, late) = search("firefox", cache.clone(), None, &tuning, None, timeout);
+        assert!(late.is_none());
+        assert_eq!(results.len(), 1);
+        // Without a timeout it waits for the cache
+        let holder = cache.clone();
+        let (locked, wait) = channel();
+        let unlock = thread::spawn(move || {
+            let _locked = holder.lock().unwrap();
+            locked.send(()).unwrap();
+            thread::sleep(Duration::from_millis(20));
+        });
+        wait.recv().unwrap();
+        let (results, late) = search("firefox", cache.clone(), None, &tuning, None, None);
+        assert!(late.is_none());
+        assert_eq!(results.len(), 1);
+        unlock.join().unwrap();
+    }
+
+    #[test]
+    fn late_path_search() {
+        let cache = Arc::new(Mutex::new(DesktopEntryCache::from_file_names(&[])));
+        let tuning = SearchTuning::default();
+        let path = PathSearch::default();
+        // Still running from an earlier search, a new one would have cleared
+        // it when it finished.
+        let earlier = path.start().then(|| PathSearchRunning(path.clone()));
+        assert!(!path.start());
+        search("ls", cache.clone(), None, &tuning, Some(&path), None);
+        assert!(path.is_running());
+        drop(earlier);
+        assert!(!path.is_running());
+        search("ls", cache.clone(), None, &tuning, Some(&path), None);
+        assert!(!path.is_running());
+    }
+
+    #[test]
+    fn merging_late_matches() {
+        let mut suggestion = desktop_match(4, 0.6);
+        suggestion.is_suggestion = true;
+        let mut results = vec![suggestion];
+        merge_late(
+            &mut results,
+            vec![desktop_match(1, 0.8), path_match("/bin/a", 0.9)],
+        );
+        assert_eq!(results.len(), 2);
+        assert!(!results.iter().any(|result| result.is_suggestion));
+        let mut with_argument = desktop_match(1, 1.5);
+        if let SearchMatchKind::DeskopEntry(entry) = &mut with_argument.match_ {
+            entry.argument = Some(ExecArgument::Url("https://example.com".to_string()));
         }
+        results.insert(0, with_argument);
+        merge_late(
+            &mut results,
+            vec![desktop_match(1, 0.9), path_match("/bin/a", 0.7)],
+        );
+        let scores = results.iter().map(SearchMatch::score).collect::<Vec<_>>();
+        assert_eq!(scores, [1.5, 0.9, 0.9]);
+        assert!(!results[1].has_argument());
     }
-    false
 }
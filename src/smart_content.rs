@@ -1,35 +1,58 @@
-use std::borrow::Cow;
+use std::time::{Duration, Instant};
 
 /// This is the widget displaying the smart content, see content.rs for classification.
 use crate::{
+    clock::{self, ClockQuery},
     config::Config,
+    content::{DateCalc, NumberBase},
     draw::DrawingContext,
+    fonts,
     layout::{Rectangle, SmartContentLayout},
     res::{resources, Svg},
+    static_units::Factor,
+    text::escape_markup,
     ui::colors,
-    units::Unit,
+    units::{currency_date, Unit},
     util::copy,
     x::{Display, Window},
 };
-use pango::FontDescription;
+use chrono::{DateTime, Utc};
 use x11::xlib::{Colormap, XVisualInfo};
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum ReadyContent {
     Error(String),
     Expression(f64),
     /// (result, from, to)
     #[allow(unused)]
     Conversion(f64, Unit, Unit),
+    Factor(Factor),
+    /// (value, target base)
+    BaseConversion(i128, NumberBase),
     /// (kind (to pick icon), action, what)
     Action(Action, &'static str, String),
+    /// The current time or date, formatted when drawn.
+    Clock(ClockQuery),
+    DateCalc(DateCalc),
+    /// Name of a program that was just launched, see `launch_feedback`.
+    Launching(String),
+    /// What's missing for the action, shown dimmed and does nothing.
+    Hint(Action, &'static str),
 }
 
+/// How long the "Copied!" indicator is shown for.
+const COPIED_DURATION: Duration = Duration::from_millis(1500);
+
+/// Largest denominator of the fractions shown for expression results.
+const MAX_DENOMINATOR: u64 = 64;
+
 impl Default for ReadyContent {
     fn default() -> Self {
         Self::Expression(0.0)
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Action {
     Web,
     Path,
@@ -47,13 +70,21 @@ pub enum SmartContentCommitAction {
 impl ReadyContent {
     fn commit(self) -> Option<SmartContentCommitAction> {
         match self {
-            ReadyContent::Error(_) => None,
+            ReadyContent::Error(_) | ReadyContent::Launching(_) | ReadyContent::Hint(..) => None,
             ReadyContent::Expression(value) => {
                 Some(SmartContentCommitAction::Copy(value.to_string()))
             }
             ReadyContent::Conversion(result, _, _) => {
                 Some(SmartContentCommitAction::Copy(format!("{result}")))
             }
+            ReadyContent::Factor(factor) => Some(SmartContentCommitAction::Copy(factor.value)),
+            ReadyContent::BaseConversion(value, base) => {
+                Some(SmartContentCommitAction::Copy(base.format(value)))
+            }
+            ReadyContent::Clock(query) => Some(SmartContentCommitAction::Copy(query.format())),
+            ReadyContent::DateCalc(calc) => {
+                date_calc_value(&calc).map(SmartContentCommitAction::Copy)
+            }
             ReadyContent::Action(kind, _, what) => match kind {
                 Action::Web => Some(SmartContentCommitAction::OpenWeb(what)),
                 Action::Path => Some(SmartContentCommitAction::OpenPath(what)),
@@ -63,19 +94,178 @@ impl ReadyContent {
     }
 }
 
+/// The closest fraction to `value` with a denominator of at most
+/// `max_denominator`, if it's equal to `value` up to rounding errors.
+fn fraction(value: f64, max_denominator: u64) -> Option<(i64, u64)> {
+    if !value.is_finite() || value.fract() == 0.0 || value.abs() > 1e12 {
+        return None;
+    }
+    // Convergents of the continued fraction, each is the best approximation
+    // for its denominator.
+    let (mut p0, mut q0, mut p1, mut q1) = (0i64, 1u64, 1i64, 0u64);
+    let mut x = value.abs();
+    loop {
+        let a = x.floor();
+        if a * q1 as f64 + q0 as f64 > max_denominator as f64 {
+            break;
+        }
+        (p0, q0, p1, q1) = (p1, q1, a as i64 * p1 + p0, a as u64 * q1 + q0);
+        let rest = x - a;
+        if rest < 1e-9 {
+            break;
+        }
+        x = 1.0 / rest;
+    }
+    let p1 = p1 * value.signum() as i64;
+    if q1 <= 1 || (p1 as f64 / q1 as f64 - value).abs() > 1e-9 * value.abs().max(1.0) {
+        return None;
+    }
+    Some((p1, q1))
+}
+
+/// `value` as a mantissa between 1 and 1000 and an exponent that's a multiple
+/// of 3, `None` if the exponent would be 0.
+fn engineering(value: f64) -> Option<String> {
+    if !value.is_finite() || value == 0.0 {
+        return None;
+    }
+    let mut exponent = (value.abs().log10().floor() as i32).div_euclid(3) * 3;
+    let mut mantissa = value / 10f64.powi(exponent);
+    // Rounding to 3 decimals can carry into the next power of 1000
+    if (mantissa.abs() * 1000.0).round() >= 1e6 {
+        exponent += 3;
+        mantissa /= 1000.0;
+    }
+    if exponent == 0 {
+        return None;
+    }
+    let mantissa = format!("{mantissa:.3}");
+    let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+    Some(format!("{mantissa}e{exponent}"))
+}
+
+/// Other ways to write an expression result, only those that say more than
+/// the decimal value: fractions of long decimals and engineering notation of
+/// long numbers.
+fn alternate_formats(value: f64) -> Vec<String> {
+    let decimal = value.abs().to_string();
+    let fraction_digits = decimal
+        .split_once('.')
+        .map_or(0, |(_, digits)| digits.len());
+    let mut formats = Vec::new();
+    if fraction_digits > 6 {
+        if let Some((numerator, denominator)) = fraction(value, MAX_DENOMINATOR) {
+            formats.push(format!("{numerator}/{denominator}"));
+        }
+    }
+    if decimal.len() > 10 {
+        formats.extend(engineering(value));
+    }
+    formats
+}
+
+/// Pango markup for the content, with the value emphasized and the unit or
+/// annotation dimmed.  `number_formats` adds the `alternate_formats` of
+/// expression results.
+fn content_markup(content: &ReadyContent, now: DateTime<Utc>, number_formats: bool) -> String {
+    let value = |value: &str| {
+        format!(
+            "<span color=\"{}\" weight=\"bold\">{}</span>",
+            colors::smart_content_value(),
+            escape_markup(value)
+        )
+    };
+    let dim = |text: &str| {
+        format!(
+            "<span color=\"{}\">{}</span>",
+            colors::smart_content_dim(),
+            escape_markup(text)
+        )
+    };
+    match content {
+        ReadyContent::Error(error) => escape_markup(error).to_string(),
+        ReadyContent::Expression(result) => {
+            let formats = if number_formats {
+                alternate_formats(*result)
+            } else {
+                Vec::new()
+            };
+            if formats.is_empty() {
+                value(&result.to_string())
+            } else {
+                format!(
+                    "{} {}",
+                    value(&result.to_string()),
+                    dim(&formats.join(" · "))
+                )
+            }
+        }
+        ReadyContent::Conversion(result, _, to) => {
+            format!("{} {}", value(&result.to_string()), dim(&to.to_string()))
+        }
+        ReadyContent::Factor(factor) => format!(
+            "{} {} {}",
+            dim(&format!("1 {} =", factor.unit)),
+            value(&factor.value),
+            dim(&format!("{} ({})", factor.base, factor.note()))
+        ),
+        ReadyContent::BaseConversion(number, base) => {
+            format!("{} {}", value(&base.format(*number)), dim(base.name()))
+        }
+        ReadyContent::Clock(query) => value(&query.format_at(now)),
+        ReadyContent::DateCalc(calc) => match calc {
+            DateCalc::Week(week) => format!("{} {}", dim("Week"), value(&week.to_string())),
+            DateCalc::Days(days) => format!(
+                "{} {}",
+                value(&days.to_string()),
+                dim(if *days == 1 { "day" } else { "days" })
+            ),
+            DateCalc::Date(date) => format!(
+                "{} {}",
+                dim(&date.format("%A,").to_string()),
+                value(&date.format("%Y-%m-%d").to_string())
+            ),
+            DateCalc::Hint(hint) => dim(hint),
+        },
+        ReadyContent::Action(_, action, what) => {
+            format!("{} {}", dim(action), escape_markup(what))
+        }
+        ReadyContent::Launching(name) => format!("{} {}…", dim("Launching"), escape_markup(name)),
+        ReadyContent::Hint(_, hint) => dim(hint),
+    }
+}
+
+/// The value copied for a date calculation.
+fn date_calc_value(calc: &DateCalc) -> Option<String> {
+    match calc {
+        DateCalc::Week(week) => Some(week.to_string()),
+        DateCalc::Days(days) => Some(days.to_string()),
+        DateCalc::Date(date) => Some(date.to_string()),
+        DateCalc::Hint(_) => None,
+    }
+}
+
 pub struct SmartContent {
     pub window: Window,
     dc: DrawingContext,
     content: ReadyContent,
     layout: SmartContentLayout,
     pub selected: bool,
-    showing_copied: bool,
+    /// Until when to show the "Copied!" indicator.
+    copied_until: Option<Instant>,
+    /// When the displayed clock needs to be updated.
+    clock_update: Option<Instant>,
+    /// Resolved command line of an item, shown instead of the content.
+    details: Option<String>,
+    number_formats: bool,
     web_icon: Svg,
     path_icon: Svg,
     run_icon: Svg,
     calculate_icon: Svg,
     conversion_icon: Svg,
     error_icon: Svg,
+    clock_icon: Svg,
+    calendar_icon: Svg,
 }
 
 impl SmartContent {
@@ -92,7 +282,7 @@ impl SmartContent {
                 attributes
                     .colormap(colormap)
                     .border_pixel(0)
-                    .background_pixel(colors::BACKGROUND.pack());
+                    .background_pixel(colors::background().pack());
             })
             .visual(visual_info.visual)
             .depth(visual_info.depth)
@@ -103,7 +293,7 @@ impl SmartContent {
             layout.window.height,
             visual_info,
         );
-        dc.set_font(&FontDescription::from_string(&config.smart_content_font));
+        dc.set_font(&fonts::load(&config.smart_content_font));
 
         Self {
             window,
@@ -111,66 +301,139 @@ impl SmartContent {
             content: ReadyContent::default(),
             layout,
             selected: false,
-            showing_copied: false,
+            copied_until: None,
+            clock_update: None,
+            details: None,
+            number_formats: config.smart_content_number_formats,
             web_icon: Svg::load(&resources::LANGUAGE_ICON),
             path_icon: Svg::load(&resources::FOLDER_OPEN_ICON),
             run_icon: Svg::load(&resources::TERMINAL_ICON),
             calculate_icon: Svg::load(&resources::CALCULATE_ICON),
             conversion_icon: Svg::load(&resources::CONVERSION_PATH_ICON),
             error_icon: Svg::load(&resources::WARNING_ICON),
+            clock_icon: Svg::load(resources::SCHEDULE_ICON),
+            calendar_icon: Svg::load(resources::CALENDAR_ICON),
         }
     }
 
     pub fn set(&mut self, content: ReadyContent) {
         self.content = content;
         self.selected = false;
+        self.details = None;
+        self.copied_until = None;
+        self.clock_update = None;
+    }
+
+    pub fn set_details(&mut self, details: Option<String>) {
+        self.details = details;
+    }
+
+    pub fn showing_details(&self) -> bool {
+        self.details.is_some()
+    }
+
+    /// Shows the "Copied!" indicator for something copied from elsewhere.
+    pub fn show_copied(&mut self) {
+        self.copied_until = Some(Instant::now() + COPIED_DURATION);
+    }
+
+    /// Whether a currency conversion is shown, F5 refreshes its rates then.
+    pub fn shows_currency(&self) -> bool {
+        matches!(
+            self.content,
+            ReadyContent::Conversion(_, _, Unit::Currency(_))
+        )
+    }
+
+    /// The command that committing the content would run.
+    pub fn command(&self) -> Option<&str> {
+        match &self.content {
+            ReadyContent::Action(Action::Run, _, command) => Some(command),
+            _ => None,
+        }
     }
 
     fn render_content(&mut self) -> Rectangle {
-        let (icon, text): (&mut Svg, Cow<str>) = match &self.content {
-            ReadyContent::Error(e) => (&mut self.error_icon, e.as_str().into()),
-            ReadyContent::Expression(e) => (&mut self.calculate_icon, e.to_string().into()),
-            ReadyContent::Conversion(result, _, to) => (
-                &mut self.conversion_icon,
-                format!("{} {}", result, to).into(),
-            ),
-            ReadyContent::Action(kind, action, what) => (
-                match kind {
-                    Action::Web => &mut self.web_icon,
-                    Action::Path => &mut self.path_icon,
-                    Action::Run => &mut self.run_icon,
-                },
-                format!("{} {}", action, what).into(),
-            ),
+        if let Some(details) = &self.details {
+            self.dc
+                .colored_svg(&mut self.run_icon, colors::text(), &self.layout.icon);
+            return self
+                .dc
+                .text(details, self.layout.text, false)
+                .center_height()
+                .draw();
+        }
+        let now = Utc::now();
+        let icon = match &self.content {
+            ReadyContent::Error(_) => &mut self.error_icon,
+            ReadyContent::Expression(_) => &mut self.calculate_icon,
+            ReadyContent::Conversion(..)
+            | ReadyContent::Factor(_)
+            | ReadyContent::BaseConversion(..) => &mut self.conversion_icon,
+            ReadyContent::Clock(_) => {
+                self.clock_update = Some(Instant::now() + clock::until_next_second(now));
+                &mut self.clock_icon
+            }
+            ReadyContent::DateCalc(_) => &mut self.calendar_icon,
+            ReadyContent::Action(kind, _, _) | ReadyContent::Hint(kind, _) => match kind {
+                Action::Web => &mut self.web_icon,
+                Action::Path => &mut self.path_icon,
+                Action::Run => &mut self.run_icon,
+            },
+            ReadyContent::Launching(_) => &mut self.run_icon,
         };
-        self.dc.colored_svg(icon, colors::TEXT, &self.layout.icon);
+        self.dc.colored_svg(icon, colors::text(), &self.layout.icon);
         self.dc
-            .text(&text, self.layout.text, false)
+            .text(
+                &content_markup(&self.content, now, self.number_formats),
+                self.layout.text,
+                true,
+            )
             .center_height()
             .draw()
     }
 
     pub fn draw(&mut self) {
-        self.dc.fill(colors::LIST_LIGHT_BACKGROUND);
-        self.dc.set_color(colors::TEXT);
+        self.dc.fill(colors::list_light_background());
+        self.dc.set_color(colors::text());
         let content_rect = self.render_content();
-        if self.showing_copied {
+        if self.copied_until.is_some() {
             self.dc
-                .text("Copied!", self.layout.window, false)
+                .text("Copied!", self.layout.text, false)
+                .right_align()
+                .center_height()
+                .draw();
+        } else if let Some(date) =
+            currency_date().filter(|_| self.shows_currency() && self.details.is_none())
+        {
+            let markup = format!(
+                "<span size=\"small\" color=\"{}\">rates: {date}</span>",
+                colors::smart_content_dim()
+            );
+            self.dc
+                .text(&markup, self.layout.text, true)
                 .right_align()
                 .center_height()
                 .draw();
-            self.showing_copied = false;
         }
         if self.selected {
             let rect = content_rect.pad(4);
             self.dc.blend(true);
-            self.dc.rect(&rect).color(colors::ENTRY_SELECTION).draw();
+            self.dc.rect(&rect).color(colors::entry_selection()).draw();
             self.dc.blend(false);
         }
         self.dc.render(self.window, &self.layout.window);
     }
 
+    /// Position inside the main window.
+    pub fn reparent(&self) -> (i32, i32) {
+        self.layout.reparent
+    }
+
+    pub fn height(&self) -> u32 {
+        self.layout.window.height
+    }
+
     pub fn hit_test(&self, x: i32, y: i32) -> bool {
         self.layout.window.at(self.layout.reparent).contains(x, y)
     }
@@ -179,6 +442,13 @@ impl SmartContent {
         match &self.content {
             ReadyContent::Expression(value) => copy(&format!("{value}")),
             ReadyContent::Conversion(result, _, _) => copy(&format!("{result}")),
+            ReadyContent::Factor(factor) => copy(&factor.value),
+            ReadyContent::BaseConversion(value, base) => copy(&base.format(*value)),
+            ReadyContent::Clock(query) => copy(&query.format()),
+            ReadyContent::DateCalc(calc) => match date_calc_value(calc) {
+                Some(value) => copy(&value),
+                None => return false,
+            },
             _ => {
                 return false;
             }
@@ -193,19 +463,144 @@ impl SmartContent {
         } else {
             self.selected = false;
         }
-        if was_selected {
-            self.showing_copied = self.copy();
+        if was_selected && self.copy() {
+            self.copied_until = Some(Instant::now() + COPIED_DURATION);
         }
         if self.selected != was_selected {
             self.draw();
         }
     }
 
+    /// Selects the content for keyboard focus, unlike `set_selected` this
+    /// doesn't copy anything.
+    pub fn set_focused(&mut self, focused: bool) {
+        if self.selected != focused {
+            self.selected = focused;
+            self.draw();
+        }
+    }
+
+    /// When the next timed update is due.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        match (self.copied_until, self.clock_update) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Performs timed updates whose deadline has passed, returns whether the
+    /// widget needs to be redrawn.
+    pub fn tick(&mut self, now: Instant) -> bool {
+        let mut redraw = false;
+        if self.copied_until.is_some_and(|until| until <= now) {
+            self.copied_until = None;
+            redraw = true;
+        }
+        if self.clock_update.is_some_and(|update| update <= now) {
+            // Set again when drawn
+            self.clock_update = None;
+            redraw = true;
+        }
+        redraw
+    }
+
     pub fn is_useful(&self) -> bool {
-        !matches!(&self.content, ReadyContent::Error(_))
+        !matches!(
+            &self.content,
+            ReadyContent::Error(_)
+                | ReadyContent::DateCalc(DateCalc::Hint(_))
+                | ReadyContent::Launching(_)
+                | ReadyContent::Hint(..)
+        )
     }
 
     pub fn commit(&mut self) -> Option<SmartContentCommitAction> {
         std::mem::take(&mut self.content).commit()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain_text(markup: &str) -> String {
+        pango::parse_markup(markup, '\0').unwrap().1.to_string()
+    }
+
+    #[test]
+    fn markup() {
+        let now = Utc::now();
+        let path = ReadyContent::Action(Action::Path, "Open", "/tmp/a<b & c>".to_string());
+        assert_eq!(
+            plain_text(&content_markup(&path, now, true)),
+            "Open /tmp/a<b & c>"
+        );
+        let command = ReadyContent::Action(Action::Run, "Run", "echo '<b>'".to_string());
+        assert_eq!(
+            plain_text(&content_markup(&command, now, true)),
+            "Run echo '<b>'"
+        );
+        let error = ReadyContent::Error("Unexpected token <".to_string());
+        assert_eq!(
+            plain_text(&content_markup(&error, now, true)),
+            "Unexpected token <"
+        );
+        let base = ReadyContent::BaseConversion(255, NumberBase::Hexadecimal);
+        assert_eq!(
+            plain_text(&content_markup(&base, now, true)),
+            "0xFF hexadecimal"
+        );
+        let expression = content_markup(&ReadyContent::Expression(3.5), now, true);
+        assert!(expression.contains("weight=\"bold\">3.5<"));
+        let hint = ReadyContent::Hint(Action::Run, "Type a command after $");
+        assert_eq!(
+            plain_text(&content_markup(&hint, now, true)),
+            "Type a command after $"
+        );
+        let days = ReadyContent::DateCalc(DateCalc::Days(1));
+        assert_eq!(plain_text(&content_markup(&days, now, true)), "1 day");
+        let launching = ReadyContent::Launching("Firefox <ESR>".to_string());
+        assert_eq!(
+            plain_text(&content_markup(&launching, now, true)),
+            "Launching Firefox <ESR>…"
+        );
+        // The copied value stays unformatted
+        assert_eq!(date_calc_value(&DateCalc::Days(3)).unwrap(), "3");
+    }
+
+    #[test]
+    fn number_formats() {
+        assert_eq!(fraction(1.0 / 3.0, 64), Some((1, 3)));
+        assert_eq!(fraction(-2.0 / 3.0, 64), Some((-2, 3)));
+        assert_eq!(fraction(22.0 / 7.0, 64), Some((22, 7)));
+        assert_eq!(fraction(5.0 / 7.0, 64), Some((5, 7)));
+        assert_eq!(fraction(1.0 / 127.0, 64), None);
+        assert_eq!(fraction(std::f64::consts::PI, 64), None);
+        assert_eq!(fraction(0.1 + 0.2, 64), Some((3, 10)));
+
+        assert_eq!(alternate_formats(1.0 / 3.0), ["1/3", "333.333e-3"]);
+        assert_eq!(alternate_formats(-1.0 / 7.0), ["-1/7", "-142.857e-3"]);
+        assert_eq!(alternate_formats(10.0 / 3.0), ["10/3"]);
+        // Nothing to add for short and exact values
+        assert!(alternate_formats(42.0).is_empty());
+        assert!(alternate_formats(0.5).is_empty());
+        assert!(alternate_formats(1024.0).is_empty());
+        assert!(alternate_formats(0.0).is_empty());
+        assert!(alternate_formats(f64::INFINITY).is_empty());
+        // Exact integers get no fraction
+        assert_eq!(alternate_formats(123456789012.0), ["123.457e9"]);
+        assert_eq!(alternate_formats(6.02214076e23), ["602.214e21"]);
+        assert_eq!(alternate_formats(1e-12), ["1e-12"]);
+        assert_eq!(alternate_formats(999999999999.9), ["1e12"]);
+
+        let third = content_markup(&ReadyContent::Expression(1.0 / 3.0), Utc::now(), true);
+        assert_eq!(plain_text(&third), "0.3333333333333333 1/3 · 333.333e-3");
+        let plain = content_markup(&ReadyContent::Expression(1.0 / 3.0), Utc::now(), false);
+        assert_eq!(plain_text(&plain), "0.3333333333333333");
+        // The copied value stays the plain decimal
+        assert!(matches!(
+            ReadyContent::Expression(1.0 / 3.0).commit(),
+            Some(SmartContentCommitAction::Copy(value)) if value == "0.3333333333333333"
+        ));
+    }
+}
@@ -54,9 +54,78 @@ impl SiPrefix {
             Yocto => 1e-24,
         }
     }
+
+    /// The power of ten of the prefix.
+    fn exponent(self) -> i32 {
+        use SiPrefix::*;
+        match self {
+            Yotta => 24,
+            Zetta => 21,
+            Exa => 18,
+            Peta => 15,
+            Tera => 12,
+            Giga => 9,
+            Mega => 6,
+            Kilo => 3,
+            Hecto => 2,
+            Deka => 1,
+            Deci => -1,
+            Centi => -2,
+            None => 0,
+            Milli => -3,
+            Micro => -6,
+            Nano => -9,
+            Pico => -12,
+            Femto => -15,
+            Atto => -18,
+            Zepto => -21,
+            Yocto => -24,
+        }
+    }
 }
 
 impl SiPrefix {
+    pub const ALL: [SiPrefix; 21] = {
+        use SiPrefix::*;
+        [
+            Yotta, Zetta, Exa, Peta, Tera, Giga, Mega, Kilo, Hecto, Deka, Deci, Centi, None, Milli,
+            Micro, Nano, Pico, Femto, Atto, Zepto, Yocto,
+        ]
+    };
+
+    /// Full lowercase name of the prefix, empty for `None`.
+    pub fn name(self) -> &'static str {
+        use SiPrefix::*;
+        match self {
+            Yotta => "yotta",
+            Zetta => "zetta",
+            Exa => "exa",
+            Peta => "peta",
+            Tera => "tera",
+            Giga => "giga",
+            Mega => "mega",
+            Kilo => "kilo",
+            Hecto => "hecto",
+            Deka => "deka",
+            Deci => "deci",
+            Centi => "centi",
+            None => "",
+            Milli => "milli",
+            Micro => "micro",
+            Nano => "nano",
+            Pico => "pico",
+            Femto => "femto",
+            Atto => "atto",
+            Zepto => "zepto",
+            Yocto => "yocto",
+        }
+    }
+
+    /// Inverse of `name`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|prefix| prefix.name() == name)
+    }
+
     fn from_start_of_str(s: &str) -> Option<(Self, usize)> {
         use SiPrefix::*;
         // these must be sorted by length to avoid aliasing
@@ -142,6 +211,31 @@ impl std::fmt::Display for SiPrefix {
     }
 }
 
+/// Implements `canonical_name` and `from_canonical_name` for a unit type,
+/// these are used for the canonical string representation of `Unit`.  The
+/// names must never change since they may be stored.
+macro_rules! canonical_names {
+    ($type:ident $(, $prefixed:ident => $prefixed_name:literal)?; $($unit:ident => $name:literal),* $(,)?) => {
+        impl $type {
+            /// Returns the canonical name and the SI prefix of the unit.
+            pub fn canonical_name(self) -> (&'static str, SiPrefix) {
+                match self {
+                    $($type::$prefixed(prefix) => ($prefixed_name, prefix),)?
+                    $($type::$unit => ($name, SiPrefix::None),)*
+                }
+            }
+
+            pub fn from_canonical_name(name: &str, prefix: SiPrefix) -> Option<Self> {
+                match name {
+                    $($prefixed_name => Some($type::$prefixed(prefix)),)?
+                    $($name if prefix == SiPrefix::None => Some($type::$unit),)*
+                    _ => Option::None,
+                }
+            }
+        }
+    };
+}
+
 // XXX: use some multi-precision library
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -151,16 +245,24 @@ pub enum Distance {
     Feet,
     Yard,
     Mile,
+    NauticalMile,
 }
 
 impl Distance {
-    fn rate(self) -> f64 {
+    /// The unit `rate` is relative to.
+    pub const fn base_unit() -> Self {
+        Distance::Meter(SiPrefix::None)
+    }
+
+    /// How many of `base_unit` this is.
+    pub fn rate(self) -> f64 {
         match self {
             Distance::Meter(prefix) => prefix.num(),
             Distance::Inch => 0.0254,
             Distance::Feet => 0.3048,
             Distance::Yard => 0.9144,
             Distance::Mile => 1609.344,
+            Distance::NauticalMile => 1852.0,
         }
     }
 
@@ -169,6 +271,11 @@ impl Distance {
     }
 }
 
+canonical_names!(
+    Distance, Meter => "m";
+    Inch => "in", Feet => "ft", Yard => "yd", Mile => "mi", NauticalMile => "nmi",
+);
+
 impl std::fmt::Display for Distance {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         use Distance::*;
@@ -181,6 +288,7 @@ impl std::fmt::Display for Distance {
             Feet => write!(f, "ft"),
             Yard => write!(f, "yd"),
             Mile => write!(f, "mi"),
+            NauticalMile => write!(f, "nmi"),
         }
     }
 }
@@ -194,12 +302,19 @@ pub enum Mass {
 }
 
 impl Mass {
-    fn rate(self) -> f64 {
+    /// The unit `rate` is relative to.
+    pub const fn base_unit() -> Self {
+        Mass::Gram(SiPrefix::Kilo)
+    }
+
+    /// How many of `base_unit` this is.
+    pub fn rate(self) -> f64 {
         match self {
-            Mass::Gram(prefix) => prefix.num(),
-            Mass::Ounce => 28.349523125,
-            Mass::Pound => 453.59237,
-            Mass::Stone => 6350.29318,
+            // Exact where `num() / 1000.0` isn't, like for micrograms
+            Mass::Gram(prefix) => 10f64.powi(prefix.exponent() - 3),
+            Mass::Ounce => 0.028349523125,
+            Mass::Pound => 0.45359237,
+            Mass::Stone => 6.35029318,
         }
     }
 
@@ -208,6 +323,8 @@ impl Mass {
     }
 }
 
+canonical_names!(Mass, Gram => "g"; Ounce => "oz", Pound => "lb", Stone => "st");
+
 impl std::fmt::Display for Mass {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         use Mass::*;
@@ -233,9 +350,16 @@ pub enum Area {
 }
 
 impl Area {
-    fn rate(self) -> f64 {
+    /// The unit `rate` is relative to.
+    pub const fn base_unit() -> Self {
+        Area::SquareMeter(SiPrefix::None)
+    }
+
+    /// How many of `base_unit` this is.
+    pub fn rate(self) -> f64 {
         match self {
-            Area::SquareMeter(prefix) => prefix.num(),
+            // Squared like the meters, exact where `num().powi(2)` isn't
+            Area::SquareMeter(prefix) => 10f64.powi(2 * prefix.exponent()),
             Area::SquareInch => 0.00064516,
             Area::SquareFeet => 0.09290304,
             Area::SquareYard => 0.83612736,
@@ -250,6 +374,16 @@ impl Area {
     }
 }
 
+canonical_names!(
+    Area, SquareMeter => "m2";
+    SquareInch => "in2",
+    SquareFeet => "ft2",
+    SquareYard => "yd2",
+    SquareMile => "mi2",
+    Hectare => "ha",
+    Acre => "ac",
+);
+
 impl std::fmt::Display for Area {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         use Area::*;
@@ -278,9 +412,14 @@ pub enum Volume {
 }
 
 impl Volume {
-    // XXX: AI generated, now another AI has told me that the US and UK have
-    //      different definitions for some of these; no clue what is used here.
-    fn rate(self) -> f64 {
+    /// The unit `rate` is relative to.
+    pub const fn base_unit() -> Self {
+        Volume::Liter(SiPrefix::None)
+    }
+
+    /// How many of `base_unit` this is.  These are the US customary units,
+    /// the UK ones are larger, see `DEFINITIONS`.
+    pub fn rate(self) -> f64 {
         match self {
             Volume::Liter(prefix) => prefix.num(),
             Volume::Gallon => 3.785411784,
@@ -298,6 +437,17 @@ impl Volume {
     }
 }
 
+canonical_names!(
+    Volume, Liter => "l";
+    Gallon => "gal",
+    Quart => "qt",
+    Pint => "pt",
+    Cup => "cup",
+    FluidOunce => "floz",
+    Tablespoon => "tbsp",
+    Teaspoon => "tsp",
+);
+
 impl std::fmt::Display for Volume {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         use Volume::*;
@@ -343,6 +493,8 @@ impl Temperature {
     }
 }
 
+canonical_names!(Temperature; Celsius => "c", Fahrenheit => "f", Kelvin => "k");
+
 impl std::fmt::Display for Temperature {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         use Temperature::*;
@@ -354,6 +506,61 @@ impl std::fmt::Display for Temperature {
     }
 }
 
+/// A span of time.  Months aren't included since their length varies, years
+/// are Julian years.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Duration {
+    Second(SiPrefix),
+    Minute,
+    Hour,
+    Day,
+    Week,
+    Year,
+}
+
+impl Duration {
+    /// The unit `rate` is relative to.
+    pub const fn base_unit() -> Self {
+        Duration::Second(SiPrefix::None)
+    }
+
+    /// How many of `base_unit` this is.
+    pub fn rate(self) -> f64 {
+        use Duration::*;
+        match self {
+            Second(prefix) => prefix.num(),
+            Minute => 60.0,
+            Hour => 3600.0,
+            Day => 86400.0,
+            Week => 604800.0,
+            Year => 31557600.0,
+        }
+    }
+
+    pub fn convert(self, amount: f64, to: Duration) -> f64 {
+        amount * self.rate() / to.rate()
+    }
+}
+
+canonical_names!(
+    Duration, Second => "s";
+    Minute => "min", Hour => "h", Day => "d", Week => "wk", Year => "yr",
+);
+
+impl std::fmt::Display for Duration {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use Duration::*;
+        match self {
+            Second(prefix) => write!(f, "{}s", prefix),
+            Minute => write!(f, "min"),
+            Hour => write!(f, "h"),
+            Day => write!(f, "d"),
+            Week => write!(f, "wk"),
+            Year => write!(f, "yr"),
+        }
+    }
+}
+
 /// Time used as denominator for speed.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum SpeedTime {
@@ -383,6 +590,8 @@ impl SpeedTime {
     }
 }
 
+canonical_names!(SpeedTime, Second => "s"; Minute => "min", Hour => "h");
+
 impl std::fmt::Display for SpeedTime {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         use SpeedTime::*;
@@ -401,6 +610,12 @@ pub struct Speed {
 }
 
 impl Speed {
+    /// The unit `rate` is relative to.
+    pub const fn base_unit() -> Self {
+        Speed::mps()
+    }
+
+    /// How many of `base_unit` this is.
     pub fn rate(self) -> f64 {
         self.distance.rate() / self.time.rate()
     }
@@ -423,6 +638,14 @@ impl Speed {
         }
     }
 
+    /// Knots, nautical miles per hour.
+    pub const fn knot() -> Speed {
+        Speed {
+            distance: Distance::NauticalMile,
+            time: SpeedTime::Hour,
+        }
+    }
+
     pub const fn mps() -> Speed {
         Speed {
             distance: Distance::Meter(SiPrefix::None),
@@ -433,14 +656,205 @@ impl Speed {
 
 impl std::fmt::Display for Speed {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}/{}", self.distance, self.time)
+        if *self == Speed::knot() {
+            write!(f, "kt")
+        } else {
+            write!(f, "{}/{}", self.distance, self.time)
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Pressure {
+    Pascal(SiPrefix),
+    Bar,
+    Psi,
+    Atm,
+    Torr,
+}
+
+impl Pressure {
+    /// The unit `rate` is relative to.
+    pub const fn base_unit() -> Self {
+        Pressure::Pascal(SiPrefix::None)
+    }
+
+    /// How many of `base_unit` this is.
+    pub fn rate(self) -> f64 {
+        match self {
+            Pressure::Pascal(prefix) => prefix.num(),
+            Pressure::Bar => 1e5,
+            Pressure::Psi => 4.4482216152605 / 0.00064516,
+            Pressure::Atm => 101325.0,
+            Pressure::Torr => 101325.0 / 760.0,
+        }
+    }
+
+    /// `rate` as a quotient for the units whose decimal isn't exact.
+    pub fn exact_rate(self) -> String {
+        match self {
+            Pressure::Psi => "4.4482216152605/0.00064516".to_string(),
+            Pressure::Torr => "101325/760".to_string(),
+            _ => self.rate().to_string(),
+        }
+    }
+
+    pub fn convert(self, amount: f64, to: Pressure) -> f64 {
+        amount * self.rate() / to.rate()
+    }
+}
+
+canonical_names!(
+    Pressure, Pascal => "pa";
+    Bar => "bar", Psi => "psi", Atm => "atm", Torr => "torr",
+);
+
+impl std::fmt::Display for Pressure {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use Pressure::*;
+        match self {
+            Pascal(prefix) => write!(f, "{}Pa", prefix),
+            Bar => write!(f, "bar"),
+            Psi => write!(f, "psi"),
+            Atm => write!(f, "atm"),
+            Torr => write!(f, "Torr"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Angle {
+    Degree,
+    Radian,
+    Gradian,
+    Turn,
+}
+
+impl Angle {
+    /// The unit `rate` is relative to.
+    pub const fn base_unit() -> Self {
+        Angle::Radian
+    }
+
+    /// How many of `base_unit` this is.
+    pub fn rate(self) -> f64 {
+        use std::f64::consts::{PI, TAU};
+        match self {
+            Angle::Degree => PI / 180.0,
+            Angle::Radian => 1.0,
+            Angle::Gradian => PI / 200.0,
+            Angle::Turn => TAU,
+        }
+    }
+
+    /// `rate` written in terms of π, since the decimal isn't exact.
+    pub fn exact_rate(self) -> &'static str {
+        match self {
+            Angle::Degree => "π/180",
+            Angle::Radian => "1",
+            Angle::Gradian => "π/200",
+            Angle::Turn => "2π",
+        }
+    }
+
+    pub fn convert(self, amount: f64, to: Angle) -> f64 {
+        amount * self.rate() / to.rate()
+    }
+}
+
+canonical_names!(Angle; Degree => "deg", Radian => "rad", Gradian => "grad", Turn => "turn");
+
+impl std::fmt::Display for Angle {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use Angle::*;
+        match self {
+            Degree => write!(f, "°"),
+            Radian => write!(f, "rad"),
+            Gradian => write!(f, "grad"),
+            Turn => write!(f, "turn"),
+        }
+    }
+}
+
+/// Sizes of data, the decimal units are powers of 1000 and the binary ones
+/// powers of 1024.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DataSize {
+    Byte,
+    Kilobyte,
+    Megabyte,
+    Gigabyte,
+    Terabyte,
+    Kibibyte,
+    Mebibyte,
+    Gibibyte,
+    Tebibyte,
+}
+
+impl DataSize {
+    /// The unit `rate` is relative to.
+    pub const fn base_unit() -> Self {
+        DataSize::Byte
+    }
+
+    /// How many of `base_unit` this is.
+    pub fn rate(self) -> f64 {
+        use DataSize::*;
+        match self {
+            Byte => 1.0,
+            Kilobyte => 1e3,
+            Megabyte => 1e6,
+            Gigabyte => 1e9,
+            Terabyte => 1e12,
+            Kibibyte => 1024.0,
+            Mebibyte => 1024.0 * 1024.0,
+            Gibibyte => 1024.0 * 1024.0 * 1024.0,
+            Tebibyte => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        }
+    }
+
+    pub fn convert(self, amount: f64, to: DataSize) -> f64 {
+        amount * self.rate() / to.rate()
+    }
+}
+
+canonical_names!(
+    DataSize;
+    Byte => "b",
+    Kilobyte => "kb",
+    Megabyte => "mb",
+    Gigabyte => "gb",
+    Terabyte => "tb",
+    Kibibyte => "kib",
+    Mebibyte => "mib",
+    Gibibyte => "gib",
+    Tebibyte => "tib",
+);
+
+impl std::fmt::Display for DataSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use DataSize::*;
+        match self {
+            Byte => write!(f, "B"),
+            Kilobyte => write!(f, "kB"),
+            Megabyte => write!(f, "MB"),
+            Gigabyte => write!(f, "GB"),
+            Terabyte => write!(f, "TB"),
+            Kibibyte => write!(f, "KiB"),
+            Mebibyte => write!(f, "MiB"),
+            Gibibyte => write!(f, "GiB"),
+            Tebibyte => write!(f, "TiB"),
+        }
     }
 }
 
 /// Bi-directional mapping of static units and their default counterparts for
 /// conversions.
 pub static PAIRS: &[(Unit, Unit)] = {
-    use self::{Area::*, Distance::*, Mass::*, Temperature::*, Volume::*};
+    use self::{
+        Angle::*, Area::*, DataSize::*, Distance::*, Duration::*, Mass::*, Pressure::*,
+        Temperature::*, Volume::*,
+    };
     use SiPrefix::*;
     use Unit::*;
     &[
@@ -463,17 +877,32 @@ pub static PAIRS: &[(Unit, Unit)] = {
         (Temperature(Fahrenheit), Temperature(Celsius)),
         // Speed
         (Speed(self::Speed::kph()), Speed(self::Speed::mph())),
+        // Duration
+        (Duration(Minute), Duration(Hour)),
+        // Pressure
+        (Pressure(Bar), Pressure(Psi)),
+        // Angle
+        (Angle(Degree), Angle(Radian)),
+        // Data size
+        (DataSize(Kibibyte), DataSize(Kilobyte)),
+        (DataSize(Mebibyte), DataSize(Megabyte)),
+        (DataSize(Gibibyte), DataSize(Gigabyte)),
+        (DataSize(Tebibyte), DataSize(Terabyte)),
     ]
 };
 
 // (from, to)
 pub static ONE_WAY: &[(Unit, Unit)] = {
-    use self::{Area::*, Distance::*, Mass::*, Temperature::*, Volume::*};
+    use self::{
+        Angle::*, Area::*, DataSize::*, Distance::*, Duration::*, Mass::*, Pressure::*,
+        Temperature::*, Volume::*,
+    };
     use SiPrefix::*;
     use Unit::*;
     &[
         // Distance
         (Distance(Meter(Milli)), Distance(Inch)),
+        (Distance(NauticalMile), Distance(Meter(Kilo))),
         // Mass
         (Mass(Stone), Mass(Gram(Kilo))),
         (Mass(Gram(Mega)), Mass(Pound)),
@@ -491,117 +920,392 @@ pub static ONE_WAY: &[(Unit, Unit)] = {
         (Temperature(Kelvin), Temperature(Celsius)),
         // Speed
         (Speed(self::Speed::mps()), Speed(self::Speed::kph())),
+        (Speed(self::Speed::knot()), Speed(self::Speed::kph())),
+        // Duration
+        (Duration(Second(Milli)), Duration(Second(None))),
+        (Duration(Second(None)), Duration(Minute)),
+        (Duration(Day), Duration(Hour)),
+        (Duration(Week), Duration(Day)),
+        (Duration(Year), Duration(Day)),
+        // Pressure
+        (Pressure(Pascal(Kilo)), Pressure(Psi)),
+        (Pressure(Pascal(Mega)), Pressure(Bar)),
+        (Pressure(Atm), Pressure(Pascal(Kilo))),
+        (Pressure(Torr), Pressure(Pascal(Hecto))),
+        // Angle
+        (Angle(Gradian), Angle(Degree)),
+        (Angle(Turn), Angle(Degree)),
+        // Data size
+        (DataSize(Byte), DataSize(Kibibyte)),
     ]
 };
 
-pub fn static_unit_from_str(s: &str) -> Option<Unit> {
-    use self::{Area::*, Distance::*, Mass::*, Temperature::*, Volume::*};
+/// What the units that aren't metric are defined as, for `factor`.  Where
+/// countries disagree the convention used by the rates is named.
+static DEFINITIONS: &[(Unit, &str)] = {
+    use self::{
+        Angle::*, Area::*, DataSize::*, Distance::*, Duration::*, Mass::*, Pressure::*, Volume::*,
+    };
+    use Unit::*;
+    &[
+        // Distance
+        (Distance(Inch), "international inch, 25.4 mm"),
+        (Distance(Feet), "international foot, 12 in"),
+        (Distance(Yard), "international yard, 0.9144 m"),
+        (Distance(Mile), "international mile, 1760 yd"),
+        (
+            Distance(NauticalMile),
+            "international nautical mile, 1852 m",
+        ),
+        // Mass
+        (Mass(Ounce), "avoirdupois ounce, 1/16 lb"),
+        (Mass(Pound), "avoirdupois pound, 0.45359237 kg"),
+        (Mass(Stone), "14 lb"),
+        // Area
+        (Area(SquareInch), "square international inch"),
+        (Area(SquareFeet), "square international foot"),
+        (Area(SquareYard), "square international yard"),
+        (Area(SquareMile), "square international mile"),
+        (Area(Hectare), "100 m × 100 m"),
+        (Area(Acre), "international acre, 4840 yd²"),
+        // Volume
+        (Volume(Gallon), "US customary, 231 in³"),
+        (Volume(Quart), "US customary, 1/4 gal"),
+        (Volume(Pint), "US customary, 1/8 gal"),
+        (Volume(Cup), "US customary, 1/16 gal"),
+        (Volume(FluidOunce), "US customary, 1/128 gal"),
+        (Volume(Tablespoon), "US customary, 1/2 floz"),
+        (Volume(Teaspoon), "US customary, 1/6 floz"),
+        // Duration
+        (Duration(Minute), "60 s"),
+        (Duration(Hour), "60 min"),
+        (Duration(Day), "24 h"),
+        (Duration(Week), "7 d"),
+        (Duration(Year), "Julian year, 365.25 d"),
+        // Pressure
+        (Pressure(Bar), "100 kPa"),
+        (Pressure(Psi), "pound-force per square inch"),
+        (Pressure(Atm), "standard atmosphere, 101325 Pa"),
+        (Pressure(Torr), "1/760 atm"),
+        // Angle
+        (Angle(Degree), "1/360 turn"),
+        (Angle(Gradian), "1/400 turn"),
+        (Angle(Turn), "full circle"),
+        // Data size
+        (DataSize(Kibibyte), "binary, 1024 B"),
+        (DataSize(Mebibyte), "binary, 1024 KiB"),
+        (DataSize(Gibibyte), "binary, 1024 MiB"),
+        (DataSize(Tebibyte), "binary, 1024 GiB"),
+    ]
+};
+
+/// The conversion factor of a unit to the base unit of its dimension, shown
+/// for `factor <unit>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Factor {
+    pub unit: Unit,
+    pub base: Unit,
+    /// The factor with all its digits.  Speeds that aren't per second, psi,
+    /// and Torr are written as a quotient and angles in terms of π, since
+    /// that's exact where the decimal isn't.
+    pub value: String,
+    /// See `DEFINITIONS`.
+    pub definition: Option<&'static str>,
+}
+
+impl Factor {
+    /// All rates are exact, metric ones by the prefixes and the others by
+    /// their definition.
+    pub fn note(&self) -> String {
+        match self.definition {
+            Some(definition) => format!("exact, {definition}"),
+            Option::None => "exact".to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for Factor {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "1 {} = {} {} ({})",
+            self.unit,
+            self.value,
+            self.base,
+            self.note()
+        )
+    }
+}
+
+/// The factor of `unit`, `None` for temperatures which have an offset and
+/// currencies whose rates change.
+pub fn factor(unit: Unit) -> Option<Factor> {
+    let (base, value) = match unit {
+        Unit::Distance(d) => (Unit::Distance(Distance::base_unit()), d.rate().to_string()),
+        Unit::Mass(m) => (Unit::Mass(Mass::base_unit()), m.rate().to_string()),
+        Unit::Area(a) => (Unit::Area(Area::base_unit()), a.rate().to_string()),
+        Unit::Volume(v) => (Unit::Volume(Volume::base_unit()), v.rate().to_string()),
+        Unit::Duration(d) => (Unit::Duration(Duration::base_unit()), d.rate().to_string()),
+        Unit::Pressure(p) => (Unit::Pressure(Pressure::base_unit()), p.exact_rate()),
+        Unit::Angle(a) => (Unit::Angle(Angle::base_unit()), a.exact_rate().to_string()),
+        Unit::DataSize(d) => (Unit::DataSize(DataSize::base_unit()), d.rate().to_string()),
+        Unit::Speed(s) => {
+            let value = if s.time.rate() == 1.0 {
+                s.rate().to_string()
+            } else {
+                format!("{}/{}", s.distance.rate(), s.time.rate())
+            };
+            (Unit::Speed(Speed::base_unit()), value)
+        }
+        Unit::Temperature(_) | Unit::Currency(_) => return Option::None,
+    };
+    let definition = DEFINITIONS
+        .iter()
+        .find(|(defined, _)| *defined == unit)
+        .map(|(_, definition)| *definition);
+    Some(Factor {
+        unit,
+        base,
+        value,
+        definition,
+    })
+}
+
+/// How a unit in `NAMES` combines with SI prefixes.
+#[derive(Copy, Clone)]
+pub enum NamedUnit {
+    /// Takes any SI prefix, like `km` or `kilometer`.
+    Prefixed(fn(SiPrefix) -> Unit),
+    /// Takes no prefix.
+    Plain(Unit),
+}
+
+/// A unit and all the names it can be written as.
+pub struct UnitNames {
+    /// Category of the unit, as in its canonical string.
+    pub dimension: &'static str,
+    pub names: &'static [&'static str],
+    pub unit: NamedUnit,
+}
+
+/// Names of the static units, used for parsing and for listing them.
+/// Speeds can also be written as `<distance>/<time>`, see
+/// `static_unit_from_str`.
+static NAMES: &[UnitNames] = {
+    use self::{
+        Angle::*, Area::*, DataSize::*, Distance::*, Duration::*, Mass::*, Pressure::*,
+        Temperature::*, Volume::*,
+    };
+    use NamedUnit::*;
     use Unit::*;
+    macro_rules! names {
+        ($dimension:literal, [$($name:literal),+], $unit:expr) => {
+            UnitNames {
+                dimension: $dimension,
+                names: &[$($name),+],
+                unit: $unit,
+            }
+        };
+    }
+    &[
+        names!(
+            "distance",
+            ["m", "meter", "meters"],
+            Prefixed(|p| Distance(Meter(p)))
+        ),
+        names!("distance", ["in", "inch", "inches"], Plain(Distance(Inch))),
+        names!("distance", ["ft", "foot", "feet"], Plain(Distance(Feet))),
+        names!("distance", ["yd", "yard", "yards"], Plain(Distance(Yard))),
+        names!("distance", ["mi", "mile", "miles"], Plain(Distance(Mile))),
+        // Not `nm`, that's nanometers
+        names!(
+            "distance",
+            ["nmi", "nauticalmile", "nauticalmiles"],
+            Plain(Distance(NauticalMile))
+        ),
+        names!("mass", ["g", "gram", "grams"], Prefixed(|p| Mass(Gram(p)))),
+        names!(
+            "mass",
+            ["ton", "tons", "tonne", "tonnes"],
+            Plain(Mass(Gram(SiPrefix::Mega)))
+        ),
+        names!("mass", ["oz", "ounce", "ounces"], Plain(Mass(Ounce))),
+        names!("mass", ["lb", "pound", "pounds"], Plain(Mass(Pound))),
+        names!("mass", ["st", "stone", "stones"], Plain(Mass(Stone))),
+        names!("area", ["m2", "meter2"], Prefixed(|p| Area(SquareMeter(p)))),
+        names!("area", ["in2", "inch2"], Plain(Area(SquareInch))),
+        names!("area", ["ft2", "feet2"], Plain(Area(SquareFeet))),
+        names!("area", ["yd2", "yard2"], Plain(Area(SquareYard))),
+        names!("area", ["mi2", "mile2", "miles2"], Plain(Area(SquareMile))),
+        names!("area", ["ha", "hectare"], Plain(Area(Hectare))),
+        names!("area", ["ac", "acre"], Plain(Area(Acre))),
+        names!(
+            "volume",
+            ["l", "liter", "liters"],
+            Prefixed(|p| Volume(Liter(p)))
+        ),
+        names!(
+            "volume",
+            ["gal", "gallon", "gallons"],
+            Plain(Volume(Gallon))
+        ),
+        names!("volume", ["qt", "quart", "quarts"], Plain(Volume(Quart))),
+        names!("volume", ["pt", "pint", "pints"], Plain(Volume(Pint))),
+        names!("volume", ["cup", "cups"], Plain(Volume(Cup))),
+        // XXX: should be `fl oz`, but don't allow spaces
+        names!(
+            "volume",
+            ["floz", "fluidounce", "fluidounces"],
+            Plain(Volume(FluidOunce))
+        ),
+        names!(
+            "volume",
+            ["tbsp", "tablespoon", "tablespoons"],
+            Plain(Volume(Tablespoon))
+        ),
+        names!(
+            "volume",
+            ["tsp", "teaspoon", "teaspoons"],
+            Plain(Volume(Teaspoon))
+        ),
+        names!("temperature", ["C"], Plain(Temperature(Celsius))),
+        names!("temperature", ["F"], Plain(Temperature(Fahrenheit))),
+        names!("temperature", ["K"], Plain(Temperature(Kelvin))),
+        names!("speed", ["kph"], Plain(Speed(self::Speed::kph()))),
+        names!("speed", ["mph"], Plain(Speed(self::Speed::mph()))),
+        names!(
+            "speed",
+            ["kt", "kts", "knot", "knots"],
+            Plain(Speed(self::Speed::knot()))
+        ),
+        // Speeds use their own `SpeedTime`, these are only the durations
+        names!(
+            "duration",
+            ["s", "sec", "secs", "second", "seconds"],
+            Prefixed(|p| Duration(Second(p)))
+        ),
+        names!(
+            "duration",
+            ["min", "mins", "minute", "minutes"],
+            Plain(Duration(Minute))
+        ),
+        names!(
+            "duration",
+            ["h", "hr", "hrs", "hour", "hours"],
+            Plain(Duration(Hour))
+        ),
+        names!("duration", ["d", "day", "days"], Plain(Duration(Day))),
+        names!("duration", ["wk", "week", "weeks"], Plain(Duration(Week))),
+        names!(
+            "duration",
+            ["yr", "yrs", "year", "years"],
+            Plain(Duration(Year))
+        ),
+        names!(
+            "pressure",
+            ["Pa", "pascal", "pascals"],
+            Prefixed(|p| Pressure(Pascal(p)))
+        ),
+        // A millibar is a hectopascal
+        names!(
+            "pressure",
+            ["mbar", "millibar", "millibars"],
+            Plain(Pressure(Pascal(SiPrefix::Hecto)))
+        ),
+        names!("pressure", ["bar", "bars"], Plain(Pressure(Bar))),
+        names!("pressure", ["psi"], Plain(Pressure(Psi))),
+        names!(
+            "pressure",
+            ["atm", "atmosphere", "atmospheres"],
+            Plain(Pressure(Atm))
+        ),
+        names!("pressure", ["Torr", "torr"], Plain(Pressure(Torr))),
+        names!("angle", ["deg", "degree", "degrees"], Plain(Angle(Degree))),
+        names!("angle", ["rad", "radian", "radians"], Plain(Angle(Radian))),
+        names!(
+            "angle",
+            ["grad", "grads", "gradian", "gradians", "gon"],
+            Plain(Angle(Gradian))
+        ),
+        names!("angle", ["turn", "turns"], Plain(Angle(Turn))),
+        // No SI prefixes, `mB` would be millibytes and `Mb` megabits
+        names!("data", ["B", "byte", "bytes"], Plain(DataSize(Byte))),
+        names!(
+            "data",
+            ["kB", "KB", "kilobyte", "kilobytes"],
+            Plain(DataSize(Kilobyte))
+        ),
+        names!(
+            "data",
+            ["MB", "megabyte", "megabytes"],
+            Plain(DataSize(Megabyte))
+        ),
+        names!(
+            "data",
+            ["GB", "gigabyte", "gigabytes"],
+            Plain(DataSize(Gigabyte))
+        ),
+        names!(
+            "data",
+            ["TB", "terabyte", "terabytes"],
+            Plain(DataSize(Terabyte))
+        ),
+        names!(
+            "data",
+            ["KiB", "kibibyte", "kibibytes"],
+            Plain(DataSize(Kibibyte))
+        ),
+        names!(
+            "data",
+            ["MiB", "mebibyte", "mebibytes"],
+            Plain(DataSize(Mebibyte))
+        ),
+        names!(
+            "data",
+            ["GiB", "gibibyte", "gibibytes"],
+            Plain(DataSize(Gibibyte))
+        ),
+        names!(
+            "data",
+            ["TiB", "tebibyte", "tebibytes"],
+            Plain(DataSize(Tebibyte))
+        ),
+    ]
+};
+
+/// The unit names grouped by dimension, in table order.
+pub fn names_by_dimension() -> Vec<(&'static str, Vec<&'static UnitNames>)> {
+    let mut groups: Vec<(&str, Vec<&UnitNames>)> = Vec::new();
+    for names in NAMES {
+        match groups
+            .iter_mut()
+            .find(|(dimension, _)| *dimension == names.dimension)
+        {
+            Some((_, group)) => group.push(names),
+            None => groups.push((names.dimension, vec![names])),
+        }
+    }
+    groups
+}
+
+pub fn static_unit_from_str(s: &str) -> Option<Unit> {
     let candidates: &[(&str, SiPrefix)] =
         if let Some((prefix, len)) = SiPrefix::from_start_of_str(s) {
             &[(s, SiPrefix::None), (&s[len..], prefix)]
         } else {
             &[(s, SiPrefix::None)]
         };
-    for (s, prefix) in candidates.into_iter().cloned() {
-        // Could return a Result instead indicating that this unit doesn't
-        // allow SI prefixes.
-        macro_rules! noprefix {
-            ($unit:expr) => {
-                if prefix == SiPrefix::None {
-                    return Some($unit);
-                } else {
-                    return None;
-                }
-            };
-        }
-        match s {
-            // Distance
-            "m" => return Some(Distance(Meter(prefix))),
-            "meter" => return Some(Distance(Meter(prefix))),
-            "meters" => return Some(Distance(Meter(prefix))),
-            "in" => noprefix!(Distance(Inch)),
-            "inch" => noprefix!(Distance(Inch)),
-            "inches" => noprefix!(Distance(Inch)),
-            "ft" => noprefix!(Distance(Feet)),
-            "foot" => noprefix!(Distance(Feet)),
-            "feet" => noprefix!(Distance(Feet)),
-            "yd" => noprefix!(Distance(Yard)),
-            "yard" => noprefix!(Distance(Yard)),
-            "yards" => noprefix!(Distance(Yard)),
-            "mi" => noprefix!(Distance(Mile)),
-            "mile" => noprefix!(Distance(Mile)),
-            "miles" => noprefix!(Distance(Mile)),
-            // Mass
-            "g" => return Some(Mass(Gram(prefix))),
-            "gram" => return Some(Mass(Gram(prefix))),
-            "grams" => return Some(Mass(Gram(prefix))),
-            "ton" => return Some(Mass(Gram(SiPrefix::Mega))),
-            "tons" => return Some(Mass(Gram(SiPrefix::Mega))),
-            "tonne" => return Some(Mass(Gram(SiPrefix::Mega))),
-            "tonnes" => return Some(Mass(Gram(SiPrefix::Mega))),
-            "oz" => noprefix!(Mass(Ounce)),
-            "ounce" => noprefix!(Mass(Ounce)),
-            "ounces" => noprefix!(Mass(Ounce)),
-            "lb" => noprefix!(Mass(Pound)),
-            "pound" => noprefix!(Mass(Pound)),
-            "pounds" => noprefix!(Mass(Pound)),
-            "st" => noprefix!(Mass(Stone)),
-            "stone" => noprefix!(Mass(Stone)),
-            "stones" => noprefix!(Mass(Stone)),
-            // Area
-            "m2" => return Some(Area(SquareMeter(prefix))),
-            "meter2" => return Some(Area(SquareMeter(prefix))),
-            "in2" => noprefix!(Area(SquareInch)),
-            "inch2" => noprefix!(Area(SquareInch)),
-            "ft2" => noprefix!(Area(SquareFeet)),
-            "feet2" => noprefix!(Area(SquareFeet)),
-            "yd2" => noprefix!(Area(SquareYard)),
-            "yard2" => noprefix!(Area(SquareYard)),
-            "mi2" => noprefix!(Area(SquareMile)),
-            "mile2" => noprefix!(Area(SquareMile)),
-            "miles2" => noprefix!(Area(SquareMile)),
-            "ha" => noprefix!(Area(Hectare)),
-            "hectare" => noprefix!(Area(Hectare)),
-            "ac" => noprefix!(Area(Acre)),
-            "acre" => noprefix!(Area(Acre)),
-            // Volume
-            "l" => return Some(Volume(Liter(prefix))),
-            "liter" => return Some(Volume(Liter(prefix))),
-            "liters" => return Some(Volume(Liter(prefix))),
-            "gal" => noprefix!(Volume(Gallon)),
-            "gallon" => noprefix!(Volume(Gallon)),
-            "gallons" => noprefix!(Volume(Gallon)),
-            "qt" => noprefix!(Volume(Quart)),
-            "quart" => noprefix!(Volume(Quart)),
-            "quarts" => noprefix!(Volume(Quart)),
-            "pt" => noprefix!(Volume(Pint)),
-            "pint" => noprefix!(Volume(Pint)),
-            "pints" => noprefix!(Volume(Pint)),
-            "cup" => noprefix!(Volume(Cup)),
-            "cups" => noprefix!(Volume(Cup)),
-            // XXX: should be `fl oz`, but don't allow spaces
-            "floz" => noprefix!(Volume(FluidOunce)),
-            "fluidounce" => noprefix!(Volume(FluidOunce)),
-            "fluidounces" => noprefix!(Volume(FluidOunce)),
-            "tbsp" => noprefix!(Volume(Tablespoon)),
-            "tablespoon" => noprefix!(Volume(Tablespoon)),
-            "tablespoons" => noprefix!(Volume(Tablespoon)),
-            "tsp" => noprefix!(Volume(Teaspoon)),
-            "teaspoon" => noprefix!(Volume(Teaspoon)),
-            "teaspoons" => noprefix!(Volume(Teaspoon)),
-            // Temperature
-            "C" => noprefix!(Temperature(Celsius)),
-            "F" => noprefix!(Temperature(Fahrenheit)),
-            "K" => noprefix!(Temperature(Kelvin)),
-
-            _ => {}
-        }
-    }
-    match s {
-        "kph" => return Some(self::Speed::kph().into()),
-        "mph" => return Some(self::Speed::mph().into()),
-        _ => {}
+    for &(s, prefix) in candidates {
+        let Some(names) = NAMES.iter().find(|names| names.names.contains(&s)) else {
+            continue;
+        };
+        return match (names.unit, prefix) {
+            (NamedUnit::Prefixed(unit), prefix) => Some(unit(prefix)),
+            (NamedUnit::Plain(unit), SiPrefix::None) => Some(unit),
+            // Could return a Result instead indicating that this unit doesn't
+            // allow SI prefixes.
+            (NamedUnit::Plain(_), _) => None,
+        };
     }
     if let Some(middle) = s.find('/') {
         let diststr = &s[..middle];
@@ -612,7 +1316,7 @@ pub fn static_unit_from_str(s: &str) -> Option<Unit> {
         };
         let time = SpeedTime::from_str(timestr);
         if let Some((distance, time)) = dist.zip(time) {
-            return Some(Speed(self::Speed { distance, time }));
+            return Some(Unit::Speed(self::Speed { distance, time }));
         }
     }
     None
@@ -622,7 +1326,10 @@ pub fn static_unit_from_str(s: &str) -> Option<Unit> {
 mod test {
     use super::{static_unit_from_str, SiPrefix::*};
     #[allow(unused_imports)]
-    use super::{Area::*, Distance::*, Mass::*, Temperature::*, Volume::*};
+    use super::{
+        Angle::*, Area::*, DataSize::*, Distance::*, Duration::*, Mass::*, Pressure::*,
+        Temperature::*, Volume::*,
+    };
     use crate::units::Unit::*;
 
     #[test]
@@ -632,5 +1339,269 @@ mod test {
             static_unit_from_str("centimeter"),
             Some(Distance(Meter(Centi)))
         );
+        assert_eq!(static_unit_from_str("nm"), Some(Distance(Meter(Nano))));
+        assert_eq!(static_unit_from_str("nmi"), Some(Distance(NauticalMile)));
+        assert_eq!(
+            static_unit_from_str("nauticalmiles"),
+            Some(Distance(NauticalMile))
+        );
+    }
+
+    #[test]
+    fn nautical() {
+        use super::Speed as S;
+        for name in ["kt", "kts", "knots", "nmi/h"] {
+            assert_eq!(static_unit_from_str(name), Some(Speed(S::knot())), "{name}");
+        }
+        assert_eq!(S::knot().to_string(), "kt");
+        let kph = S::knot().convert(10.0, S::kph());
+        assert!((kph - 18.52).abs() < 1e-9, "{kph}");
+        let km = NauticalMile.convert(1.0, Meter(Kilo));
+        assert!((km - 1.852).abs() < 1e-12, "{km}");
+        let mapping = crate::units::default_unit_mapping();
+        assert_eq!(mapping[&Distance(NauticalMile)], Distance(Meter(Kilo)));
+        assert_eq!(mapping[&Speed(S::knot())], Speed(S::kph()));
+        // km/h stays paired with mph
+        assert_eq!(mapping[&Speed(S::kph())], Speed(S::mph()));
+    }
+
+    #[test]
+    fn name_table() {
+        use super::{names_by_dimension, NamedUnit, NAMES};
+        let mut seen = std::collections::HashSet::new();
+        for names in NAMES {
+            for &name in names.names {
+                assert!(seen.insert(name), "{name} is listed twice");
+                let expected = match names.unit {
+                    NamedUnit::Prefixed(unit) => unit(None),
+                    NamedUnit::Plain(unit) => unit,
+                };
+                assert_eq!(static_unit_from_str(name), Some(expected), "{name}");
+                assert_eq!(
+                    expected.to_canonical_string().split(':').next(),
+                    Some(names.dimension)
+                );
+            }
+        }
+        assert_eq!(static_unit_from_str("kg"), Some(Mass(Gram(Kilo))));
+        assert_eq!(
+            static_unit_from_str("kilometers"),
+            Some(Distance(Meter(Kilo)))
+        );
+        assert_eq!(static_unit_from_str("ml"), Some(Volume(Liter(Milli))));
+        assert_eq!(static_unit_from_str("mm2"), Some(Area(SquareMeter(Milli))));
+        // Only some units take prefixes
+        assert_eq!(static_unit_from_str("kin"), Option::None);
+        assert_eq!(static_unit_from_str("cfloz"), Option::None);
+        let dimensions: Vec<_> = names_by_dimension().into_iter().map(|(d, _)| d).collect();
+        assert_eq!(
+            dimensions,
+            [
+                "distance",
+                "mass",
+                "area",
+                "volume",
+                "temperature",
+                "speed",
+                "duration",
+                "pressure",
+                "angle",
+                "data"
+            ]
+        );
+    }
+
+    #[test]
+    fn duration() {
+        assert_eq!(static_unit_from_str("min"), Some(Duration(Minute)));
+        assert_eq!(static_unit_from_str("h"), Some(Duration(Hour)));
+        assert_eq!(static_unit_from_str("d"), Some(Duration(Day)));
+        assert_eq!(static_unit_from_str("ms"), Some(Duration(Second(Milli))));
+        assert_eq!(
+            static_unit_from_str("milliseconds"),
+            Some(Duration(Second(Milli)))
+        );
+        assert_eq!(Minute.convert(90.0, Hour), 1.5);
+        assert_eq!(Week.convert(2.0, Day), 14.0);
+        assert_eq!(Year.convert(1.0, Day), 365.25);
+        assert_eq!(Duration(Second(Micro)).to_string(), "µs");
+        let mapping = crate::units::default_unit_mapping();
+        assert_eq!(mapping[&Duration(Minute)], Duration(Hour));
+        assert_eq!(mapping[&Duration(Hour)], Duration(Minute));
+        assert_eq!(mapping[&Duration(Day)], Duration(Hour));
+        // Speeds still have their own times
+        use super::{Speed as S, SpeedTime};
+        assert_eq!(static_unit_from_str("km/h"), Some(Speed(S::kph())));
+        assert_eq!(
+            static_unit_from_str("m/min"),
+            Some(Speed(S {
+                distance: Meter(None),
+                time: SpeedTime::Minute,
+            }))
+        );
+    }
+
+    #[test]
+    fn pressure() {
+        use super::factor;
+        assert_eq!(static_unit_from_str("Pa"), Some(Pressure(Pascal(None))));
+        assert_eq!(static_unit_from_str("kPa"), Some(Pressure(Pascal(Kilo))));
+        assert_eq!(static_unit_from_str("hPa"), Some(Pressure(Pascal(Hecto))));
+        assert_eq!(static_unit_from_str("MPa"), Some(Pressure(Pascal(Mega))));
+        assert_eq!(
+            static_unit_from_str("kilopascals"),
+            Some(Pressure(Pascal(Kilo)))
+        );
+        assert_eq!(static_unit_from_str("bar"), Some(Pressure(Bar)));
+        assert_eq!(static_unit_from_str("mbar"), Some(Pressure(Pascal(Hecto))));
+        assert_eq!(static_unit_from_str("kbar"), Option::None);
+        let psi = Bar.convert(2.0, Psi);
+        assert!((psi - 29.007547546041).abs() < 1e-9, "{psi}");
+        assert_eq!(Atm.convert(1.0, Torr), 760.0);
+        assert_eq!(Atm.convert(1.0, Pascal(Hecto)), 1013.25);
+        assert_eq!(Pascal(Mega).convert(1.0, Bar), 10.0);
+        assert_eq!(Pressure(Pascal(Kilo)).to_string(), "kPa");
+        let mapping = crate::units::default_unit_mapping();
+        assert_eq!(mapping[&Pressure(Bar)], Pressure(Psi));
+        assert_eq!(mapping[&Pressure(Psi)], Pressure(Bar));
+        assert_eq!(
+            factor(Pressure(Torr)).unwrap().to_string(),
+            "1 Torr = 101325/760 Pa (exact, 1/760 atm)"
+        );
+    }
+
+    #[test]
+    fn angle() {
+        use super::factor;
+        use std::f64::consts::PI;
+        assert_eq!(static_unit_from_str("deg"), Some(Angle(Degree)));
+        assert_eq!(static_unit_from_str("radians"), Some(Angle(Radian)));
+        assert_eq!(static_unit_from_str("grad"), Some(Angle(Gradian)));
+        assert_eq!(static_unit_from_str("turn"), Some(Angle(Turn)));
+        assert_eq!(Degree.convert(180.0, Radian), PI);
+        let degrees = Gradian.convert(100.0, Degree);
+        assert!((degrees - 90.0).abs() < 1e-12, "{degrees}");
+        let degrees = Turn.convert(0.5, Degree);
+        assert!((degrees - 180.0).abs() < 1e-12, "{degrees}");
+        assert_eq!(Angle(Radian).to_string(), "rad");
+        let mapping = crate::units::default_unit_mapping();
+        assert_eq!(mapping[&Angle(Degree)], Angle(Radian));
+        assert_eq!(mapping[&Angle(Radian)], Angle(Degree));
+        assert_eq!(
+            factor(Angle(Degree)).unwrap().to_string(),
+            "1 ° = π/180 rad (exact, 1/360 turn)"
+        );
+    }
+
+    #[test]
+    fn data_size() {
+        assert_eq!(static_unit_from_str("MB"), Some(DataSize(Megabyte)));
+        assert_eq!(static_unit_from_str("MiB"), Some(DataSize(Mebibyte)));
+        assert_eq!(static_unit_from_str("KB"), Some(DataSize(Kilobyte)));
+        assert_eq!(static_unit_from_str("B"), Some(DataSize(Byte)));
+        // Bytes take no SI prefixes
+        assert_eq!(static_unit_from_str("mB"), Option::None);
+        assert_eq!(static_unit_from_str("PB"), Option::None);
+        assert_eq!(Gibibyte.convert(1.0, Megabyte), 1073.741824);
+        assert_eq!(Megabyte.convert(500.0, Mebibyte), 476.837158203125);
+        assert_eq!(Tebibyte.convert(1.0, Gibibyte), 1024.0);
+        assert_eq!(Byte.convert(2048.0, Kibibyte), 2.0);
+        assert_eq!(DataSize(Kibibyte).to_string(), "KiB");
+        assert_eq!(DataSize(Kilobyte).to_string(), "kB");
+        let mapping = crate::units::default_unit_mapping();
+        assert_eq!(mapping[&DataSize(Gibibyte)], DataSize(Gigabyte));
+        assert_eq!(mapping[&DataSize(Gigabyte)], DataSize(Gibibyte));
+        assert_eq!(mapping[&DataSize(Byte)], DataSize(Kibibyte));
+    }
+
+    #[test]
+    fn factors() {
+        use super::{factor, NamedUnit, Speed as S, DEFINITIONS, NAMES};
+        let shown = |unit| factor(unit).unwrap().to_string();
+        assert_eq!(
+            shown(Distance(Mile)),
+            "1 mi = 1609.344 m (exact, international mile, 1760 yd)"
+        );
+        assert_eq!(shown(Distance(Meter(Kilo))), "1 km = 1000 m (exact)");
+        assert_eq!(
+            shown(Mass(Pound)),
+            "1 lb = 0.45359237 kg (exact, avoirdupois pound, 0.45359237 kg)"
+        );
+        assert_eq!(shown(Mass(Gram(None))), "1 g = 0.001 kg (exact)");
+        assert_eq!(shown(Mass(Gram(Micro))), "1 µg = 0.000000001 kg (exact)");
+        assert_eq!(shown(Area(SquareMeter(Kilo))), "1 km² = 1000000 m² (exact)");
+        assert_eq!(shown(Area(SquareMeter(Centi))), "1 cm² = 0.0001 m² (exact)");
+        assert_eq!(
+            shown(Area(SquareMeter(Milli))),
+            "1 mm² = 0.000001 m² (exact)"
+        );
+        assert_eq!(
+            shown(Volume(Gallon)),
+            "1 gal = 3.785411784 L (exact, US customary, 231 in³)"
+        );
+        assert_eq!(shown(Speed(S::mps())), "1 m/s = 1 m/s (exact)");
+        assert_eq!(shown(Speed(S::kph())), "1 km/h = 1000/3600 m/s (exact)");
+        assert_eq!(factor(Temperature(Celsius)), Option::None);
+
+        // Every unit that isn't metric says what it's defined as
+        for names in NAMES {
+            let unit = match names.unit {
+                NamedUnit::Prefixed(unit) => unit(None),
+                NamedUnit::Plain(unit) => unit,
+            };
+            let metric = matches!(
+                unit,
+                Distance(Meter(_))
+                    | Mass(Gram(_))
+                    | Area(SquareMeter(_))
+                    | Volume(Liter(_))
+                    | Duration(Second(_))
+                    | Pressure(Pascal(_))
+                    | Angle(Radian)
+                    | DataSize(Byte | Kilobyte | Megabyte | Gigabyte | Terabyte)
+            );
+            let factor = factor(unit);
+            match unit {
+                Temperature(_) => assert!(factor.is_none()),
+                Speed(_) => assert!(factor.unwrap().definition.is_none()),
+                _ => assert_eq!(factor.unwrap().definition.is_some(), !metric, "{unit:?}"),
+            }
+        }
+        for (unit, definition) in DEFINITIONS {
+            assert_eq!(factor(*unit).unwrap().definition, Some(*definition));
+        }
+        // The factors agree with the definitions
+        let rate = |unit| match unit {
+            Distance(d) => d.rate(),
+            Mass(m) => m.rate(),
+            Area(a) => a.rate(),
+            Volume(v) => v.rate(),
+            _ => unreachable!(),
+        };
+        let close = |a: f64, b: f64| (a - b).abs() <= 1e-12 * b.abs();
+        let inch = rate(Distance(Inch));
+        assert!(close(rate(Volume(Gallon)), 231.0 * inch.powi(3) * 1000.0));
+        assert!(close(rate(Volume(Quart)) * 4.0, rate(Volume(Gallon))));
+        assert!(close(rate(Volume(Pint)) * 8.0, rate(Volume(Gallon))));
+        assert!(close(rate(Volume(Cup)) * 16.0, rate(Volume(Gallon))));
+        assert!(close(
+            rate(Volume(FluidOunce)) * 128.0,
+            rate(Volume(Gallon))
+        ));
+        assert!(close(
+            rate(Volume(Tablespoon)) * 2.0,
+            rate(Volume(FluidOunce))
+        ));
+        assert!(close(
+            rate(Volume(Teaspoon)) * 6.0,
+            rate(Volume(FluidOunce))
+        ));
+        assert!(close(rate(Mass(Ounce)) * 16.0, rate(Mass(Pound))));
+        assert!(close(rate(Mass(Stone)), 14.0 * rate(Mass(Pound))));
+        assert!(close(
+            rate(Area(Acre)),
+            4840.0 * rate(Distance(Yard)).powi(2)
+        ));
+        assert!(close(rate(Distance(Mile)), 1760.0 * rate(Distance(Yard))));
     }
 }
@@ -0,0 +1,247 @@
+use std::{borrow::Cow, cmp::Ordering};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Folds a character for matching; it's lowercased, decomposed, and combining
+/// marks are dropped so `É` becomes `e`.  A single character may fold into
+/// multiple ones (`ﬁ` becomes `fi`).
+fn fold_char(c: char) -> impl Iterator<Item = char> {
+    c.to_lowercase().nfkd().filter(|c| !is_combining_mark(*c))
+}
+
+/// Folds a string for accent and case insensitive matching.
+pub fn fold(s: &str) -> String {
+    s.chars().flat_map(fold_char).collect()
+}
+
+/// Like `fold` but pairs each folded character with the index of the
+/// character in `s` that it came from.
+pub fn fold_with_origins(s: &str) -> impl Iterator<Item = (char, usize)> + '_ {
+    s.chars()
+        .enumerate()
+        .flat_map(|(idx, c)| fold_char(c).map(move |folded| (folded, idx)))
+}
+
+fn push_escaped(out: &mut String, c: char) {
+    match c {
+        '&' => out.push_str("&amp;"),
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        '\'' => out.push_str("&apos;"),
+        '"' => out.push_str("&quot;"),
+        // Pango rejects raw control characters in markup
+        c if c.is_control() && !matches!(c, '\t' | '\n' | '\r') => {
+            out.push_str(&format!("&#x{:x};", c as u32))
+        }
+        c => out.push(c),
+    }
+}
+
+fn needs_escape(c: char) -> bool {
+    matches!(c, '&' | '<' | '>' | '\'' | '"')
+        || (c.is_control() && !matches!(c, '\t' | '\n' | '\r'))
+}
+
+/// Escapes text for Pango markup.  All external strings (names from desktop
+/// entries, paths, user input) must go through this before being put into
+/// markup, it only allocates if there is something to escape.
+pub fn escape_markup(s: &str) -> Cow<'_, str> {
+    if !s.contains(needs_escape) {
+        return Cow::Borrowed(s);
+    }
+    let mut escaped = String::with_capacity(s.len() + 16);
+    for c in s.chars() {
+        push_escaped(&mut escaped, c);
+    }
+    Cow::Owned(escaped)
+}
+
+/// Appends a single escaped character to markup being built.
+pub fn push_markup_char(markup: &mut String, c: char) {
+    push_escaped(markup, c);
+}
+
+/// Letters a language sorts as their own letter, as `(letter, after)` where
+/// `after` is the letter it comes after.  Letters after the same one are
+/// sorted in the order they are listed.
+type Tailoring = &'static [(char, char)];
+
+const TAILORINGS: &[(&[&str], Tailoring)] = &[
+    (
+        &["sv", "fi"],
+        &[('å', 'z'), ('ä', 'z'), ('æ', 'z'), ('ö', 'z'), ('ø', 'z')],
+    ),
+    (
+        &["da", "nb", "nn", "no"],
+        &[('æ', 'z'), ('ø', 'z'), ('å', 'z')],
+    ),
+    (&["es"], &[('ñ', 'n')]),
+];
+
+/// Orders names for display.  Case and accents only decide between names
+/// that are otherwise equal, so `Éditeur` sorts with `editor` instead of
+/// after `zsh`, and some languages sort letters like `ö` after `z`.
+#[derive(Debug, Clone, Default)]
+pub struct Collator {
+    tailoring: Tailoring,
+}
+
+impl Collator {
+    /// A collator for a locale like `sv_SE.UTF-8`, unknown languages use the
+    /// default order.
+    pub fn new(locale: &str) -> Self {
+        let language = locale.split(['_', '.', '@']).next().unwrap_or_default();
+        let tailoring = TAILORINGS
+            .iter()
+            .find(|(languages, _)| languages.contains(&language))
+            .map_or(&[][..], |(_, tailoring)| *tailoring);
+        Self { tailoring }
+    }
+
+    /// A collator for the collation locale of the environment.
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_COLLATE", "LANG"]
+            .into_iter()
+            .find_map(|var| std::env::var(var).ok().filter(|value| !value.is_empty()))
+            .map(|locale| Self::new(&locale))
+            .unwrap_or_default()
+    }
+
+    /// Letters as `(base letter, rank after it)`, tailored letters get a
+    /// rank above 0.
+    fn primary_key(&self, s: &str) -> Vec<(char, usize)> {
+        let mut key = Vec::with_capacity(s.len());
+        for c in s.chars().flat_map(char::to_lowercase) {
+            let tailored = self.tailoring.iter().position(|&(letter, _)| letter == c);
+            match tailored {
+                Some(rank) => key.push((self.tailoring[rank].1, rank + 1)),
+                None => key.extend(fold_char(c).map(|c| (c, 0))),
+            }
+        }
+        key
+    }
+
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        self.primary_key(a)
+            .cmp(&self.primary_key(b))
+            .then_with(|| a.to_lowercase().cmp(&b.to_lowercase()))
+            .then_with(|| b.cmp(a))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn french() {
+        assert_eq!(fold("Téléchargements"), "telechargements");
+        assert_eq!(fold("Éditeur de texte"), "editeur de texte");
+        assert_eq!(fold("Paramètres"), "parametres");
+    }
+
+    #[test]
+    fn german() {
+        assert_eq!(fold("Müsik"), "musik");
+        assert_eq!(
+            fold("Dateiverwaltung Für Öffentliche Äpfel"),
+            "dateiverwaltung fur offentliche apfel"
+        );
+        // Not a diacritic
+        assert_eq!(fold("Straße"), "straße");
+    }
+
+    #[test]
+    fn escaping() {
+        assert!(matches!(escape_markup("Firefox"), Cow::Borrowed("Firefox")));
+        assert!(matches!(escape_markup("🦊 Füchse"), Cow::Borrowed(_)));
+        assert_eq!(escape_markup("Tom & Jerry"), "Tom &amp; Jerry");
+        assert_eq!(
+            escape_markup("<b>\"x\"</b>"),
+            "&lt;b&gt;&quot;x&quot;&lt;/b&gt;"
+        );
+        assert_eq!(escape_markup("🦊 & <🐺>"), "🦊 &amp; &lt;🐺&gt;");
+        assert_eq!(escape_markup("a\x01b\tc"), "a&#x1;b\tc");
+        for s in ["Tom & Jerry", "a < b > c", "it's \"🦊\"", "a\x01b"] {
+            let (_, text, _) = pango::parse_markup(&escape_markup(s), '\0').unwrap();
+            assert_eq!(text, s);
+        }
+    }
+
+    /// All markup has to be escaped through `escape_markup`, and only the
+    /// drawing code hands markup to Pango.
+    #[test]
+    fn markup_escaping_is_centralized() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let mut files = vec![dir.clone()];
+        let mut offenders = Vec::new();
+        while let Some(path) = files.pop() {
+            if path.is_dir() {
+                files.extend(std::fs::read_dir(&path).unwrap().map(|e| e.unwrap().path()));
+                continue;
+            }
+            let name = path
+                .strip_prefix(&dir)
+                .unwrap()
+                .to_string_lossy()
+                .to_string();
+            let source = std::fs::read_to_string(&path).unwrap();
+            if name != "text.rs" && source.contains(concat!("markup_escape", "_text")) {
+                offenders.push(format!("{name} escapes markup itself"));
+            }
+            if !["draw.rs", "tooltip.rs"].contains(&name.as_str())
+                && source.contains(concat!("set_", "markup("))
+            {
+                offenders.push(format!("{name} sets markup outside of the drawing code"));
+            }
+        }
+        assert!(offenders.is_empty(), "{offenders:?}");
+    }
+
+    fn sorted(collator: &Collator, names: &[&str]) -> Vec<String> {
+        let mut names: Vec<String> = names.iter().map(|s| s.to_string()).collect();
+        names.sort_by(|a, b| collator.compare(a, b));
+        names
+    }
+
+    #[test]
+    fn collation() {
+        let names = [
+            "zsh", "Éditeur", "editor", "Editor", "Ödland", "obsidian", "Zathura", "año", "anki",
+        ];
+        assert_eq!(
+            sorted(&Collator::new("en_US.UTF-8"), &names),
+            [
+                "anki", "año", "Éditeur", "editor", "Editor", "obsidian", "Ödland", "Zathura",
+                "zsh"
+            ]
+        );
+        assert_eq!(
+            sorted(&Collator::new("sv_SE.UTF-8"), &names),
+            [
+                "anki", "año", "Éditeur", "editor", "Editor", "obsidian", "Zathura", "zsh",
+                "Ödland"
+            ]
+        );
+        assert_eq!(
+            sorted(&Collator::new("es"), &names),
+            [
+                "anki", "año", "Éditeur", "editor", "Editor", "obsidian", "Ödland", "Zathura",
+                "zsh"
+            ]
+        );
+        assert_eq!(
+            sorted(&Collator::new("es"), &["anzuelo", "año", "ama"]),
+            ["ama", "anzuelo", "año"]
+        );
+        assert_eq!(
+            sorted(&Collator::new("da_DK"), &["Ærø", "Åbo", "Øst", "zoo"]),
+            ["zoo", "Ærø", "Øst", "Åbo"]
+        );
+    }
+
+    #[test]
+    fn origins() {
+        let origins: Vec<_> = fold_with_origins("Éﬁx").collect();
+        assert_eq!(origins, [('e', 0), ('f', 1), ('i', 1), ('x', 2)]);
+    }
+}
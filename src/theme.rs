@@ -0,0 +1,285 @@
+//! Choosing between the dark and light palette.
+//!
+//! With `theme = "auto"` the desktop's preference is read once at startup.
+//! GTK and Qt desktops either run an XSettings manager, which publishes the
+//! GTK theme name, or leave it in `~/.config/gtk-3.0/settings.ini`.  Both are
+//! plain reads, so starting never waits for another process to answer.
+
+use crate::{
+    paths,
+    ui::colors::{self, Palette},
+    x::Display,
+};
+use std::path::Path;
+
+/// The `theme` config value.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Theme {
+    /// Follow the desktop, dark if it has no preference.
+    Auto,
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(Self::Auto),
+            "dark" => Some(Self::Dark),
+            "light" => Some(Self::Light),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ColorScheme {
+    Dark,
+    Light,
+}
+
+impl ColorScheme {
+    /// Themes don't say whether they're dark other than in their name, like
+    /// `Adwaita-dark` or `Breeze-Dark`.
+    fn from_theme_name(name: &str) -> Self {
+        if name.to_lowercase().contains("dark") {
+            Self::Dark
+        } else {
+            Self::Light
+        }
+    }
+
+    fn palette(self) -> &'static Palette {
+        match self {
+            Self::Dark => &colors::DARK,
+            Self::Light => &colors::LIGHT,
+        }
+    }
+}
+
+/// Reads the `Net/ThemeName` string from XSettings data, see the XSettings
+/// specification for the format.
+fn xsettings_theme_name(data: &[u8]) -> Option<String> {
+    let big_endian = *data.first()? == 1;
+    let mut pos = 12;
+    let mut take = |len: usize| {
+        let bytes = data.get(pos..pos + len)?;
+        pos += len;
+        Some(bytes)
+    };
+    let card16 = |b: &[u8]| {
+        let b = [b[0], b[1]];
+        if big_endian {
+            u16::from_be_bytes(b)
+        } else {
+            u16::from_le_bytes(b)
+        }
+    };
+    let card32 = |b: &[u8]| {
+        let b = [b[0], b[1], b[2], b[3]];
+        if big_endian {
+            u32::from_be_bytes(b)
+        } else {
+            u32::from_le_bytes(b)
+        }
+    };
+    let padded = |len: usize| (len + 3) & !3;
+    let count = card32(data.get(8..12)?);
+    for _ in 0..count {
+        let header = take(4)?;
+        let kind = header[0];
+        let name_len = card16(&header[2..]) as usize;
+        let name = take(padded(name_len))?[..name_len].to_vec();
+        // Serial of the last change
+        take(4)?;
+        let value = match kind {
+            // Integer
+            0 => {
+                take(4)?;
+                None
+            }
+            // String
+            1 => {
+                let len = card32(take(4)?) as usize;
+                Some(take(padded(len))?[..len].to_vec())
+            }
+            // Color, 4 16-bit channels
+            2 => {
+                take(8)?;
+                None
+            }
+            _ => return None,
+        };
+        if let (b"Net/ThemeName", Some(value)) = (name.as_slice(), value) {
+            return String::from_utf8(value).ok();
+        }
+    }
+    None
+}
+
+/// Reads the preference from the `[Settings]` section of a GTK settings
+/// file.  `gtk-application-prefer-dark-theme` wins over the theme name since
+/// it's what the desktop's dark mode switch sets.
+fn gtk_settings(content: &str) -> Option<ColorScheme> {
+    let mut in_settings = false;
+    let mut prefer_dark = None;
+    let mut theme_name = None;
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_settings = line == "[Settings]";
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=').filter(|_| in_settings) else {
+            continue;
+        };
+        match key.trim() {
+            "gtk-application-prefer-dark-theme" => {
+                prefer_dark = Some(matches!(value.trim(), "true" | "1"));
+            }
+            "gtk-theme-name" => theme_name = Some(value.trim().trim_matches('"').to_string()),
+            _ => {}
+        }
+    }
+    match (prefer_dark, theme_name) {
+        (Some(true), _) => Some(ColorScheme::Dark),
+        (_, Some(name)) => Some(ColorScheme::from_theme_name(&name)),
+        (Some(false), None) => Some(ColorScheme::Light),
+        (None, None) => None,
+    }
+}
+
+fn gtk_settings_file(path: &Path) -> Option<ColorScheme> {
+    gtk_settings(&std::fs::read_to_string(path).ok()?)
+}
+
+/// The desktop's preference, `None` if it has none we can find.  A dark theme
+/// name in XSettings is enough, but a light one may just be the default
+/// theme name with the dark preference only in the settings file.
+pub fn detect(display: &Display) -> Option<ColorScheme> {
+    let xsettings = display
+        .xsettings()
+        .and_then(|data| xsettings_theme_name(&data))
+        .map(|name| ColorScheme::from_theme_name(&name));
+    if xsettings == Some(ColorScheme::Dark) {
+        return xsettings;
+    }
+    paths::config_home()
+        .and_then(|dir| gtk_settings_file(&dir.join("gtk-3.0/settings.ini")))
+        .or(xsettings)
+}
+
+/// Selects the palette for `theme`.
+pub fn apply(theme: Theme, display: &Display) {
+    let scheme = match theme {
+        Theme::Auto => detect(display).unwrap_or(ColorScheme::Dark),
+        Theme::Dark => ColorScheme::Dark,
+        Theme::Light => ColorScheme::Light,
+    };
+    println!("Using the {scheme:?} theme");
+    colors::set_palette(scheme.palette());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gtk_settings_files() {
+        let dir = std::env::temp_dir().join(format!("launcher-gtk-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let fixtures = [
+            (
+                "[Settings]\ngtk-theme-name=Adwaita\ngtk-application-prefer-dark-theme=1\n",
+                Some(ColorScheme::Dark),
+            ),
+            (
+                "[Settings]\ngtk-theme-name = Adwaita\ngtk-application-prefer-dark-theme = false\n",
+                Some(ColorScheme::Light),
+            ),
+            (
+                "[Settings]\ngtk-theme-name=\"Breeze-Dark\"\ngtk-font-name=Noto Sans 10\n",
+                Some(ColorScheme::Dark),
+            ),
+            (
+                "[Settings]\ngtk-application-prefer-dark-theme=false\n",
+                Some(ColorScheme::Light),
+            ),
+            // Only the settings section counts
+            (
+                "[Other]\ngtk-application-prefer-dark-theme=true\n[Settings]\ngtk-cursor-theme-size=24\n",
+                None,
+            ),
+            ("", None),
+        ];
+        for (i, (content, expected)) in fixtures.into_iter().enumerate() {
+            let path = dir.join(format!("settings-{i}.ini"));
+            std::fs::write(&path, content).unwrap();
+            assert_eq!(gtk_settings_file(&path), expected, "{content}");
+        }
+        assert_eq!(gtk_settings_file(&dir.join("missing.ini")), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// XSettings data with the given settings, in the given byte order.
+    fn xsettings(big_endian: bool, settings: &[(&str, Option<&str>)]) -> Vec<u8> {
+        let card16 = |n: u16| {
+            if big_endian {
+                n.to_be_bytes()
+            } else {
+                n.to_le_bytes()
+            }
+        };
+        let card32 = |n: u32| {
+            if big_endian {
+                n.to_be_bytes()
+            } else {
+                n.to_le_bytes()
+            }
+        };
+        let pad = |data: &mut Vec<u8>| data.resize((data.len() + 3) & !3, 0);
+        let mut data = vec![big_endian as u8, 0, 0, 0];
+        data.extend(card32(7));
+        data.extend(card32(settings.len() as u32));
+        for (name, value) in settings {
+            data.extend([if value.is_some() { 1 } else { 0 }, 0]);
+            data.extend(card16(name.len() as u16));
+            data.extend(name.as_bytes());
+            pad(&mut data);
+            data.extend(card32(1));
+            match value {
+                Some(value) => {
+                    data.extend(card32(value.len() as u32));
+                    data.extend(value.as_bytes());
+                    pad(&mut data);
+                }
+                None => data.extend(card32(1)),
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn xsettings_theme() {
+        for big_endian in [false, true] {
+            let data = xsettings(
+                big_endian,
+                &[
+                    ("Xft/Antialias", None),
+                    ("Gtk/FontName", Some("Sans 10")),
+                    ("Net/ThemeName", Some("Adwaita-dark")),
+                ],
+            );
+            assert_eq!(xsettings_theme_name(&data).as_deref(), Some("Adwaita-dark"));
+            // Truncated data
+            assert_eq!(xsettings_theme_name(&data[..data.len() - 4]), None);
+            let data = xsettings(big_endian, &[("Gtk/FontName", Some("Sans 10"))]);
+            assert_eq!(xsettings_theme_name(&data), None);
+        }
+        assert_eq!(xsettings_theme_name(&[]), None);
+        assert_eq!(
+            ColorScheme::from_theme_name("Breeze-Dark"),
+            ColorScheme::Dark
+        );
+        assert_eq!(ColorScheme::from_theme_name("Arc"), ColorScheme::Light);
+    }
+}
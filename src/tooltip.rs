@@ -0,0 +1,188 @@
+//! Tooltip with the full details of the list item under the pointer.
+
+use crate::{
+    config::Config,
+    draw::DrawingContext,
+    fonts,
+    layout::Rectangle,
+    ui::colors,
+    x::{Display, Window},
+};
+use pango::{prelude::*, FontDescription, WrapMode};
+use std::time::{Duration, Instant};
+use x11::xlib::{Colormap, XVisualInfo};
+
+/// How long the pointer has to rest on an item before its tooltip is shown.
+pub const HOVER_DELAY: Duration = Duration::from_millis(700);
+
+/// Distance between the pointer and the tooltip.
+const POINTER_OFFSET: i32 = 16;
+const PADDING: i32 = 8;
+
+/// Tracks which list item the pointer rests on.
+#[derive(Debug, Default)]
+pub struct Hover {
+    item: Option<usize>,
+    deadline: Option<Instant>,
+    shown: bool,
+}
+
+impl Hover {
+    /// The pointer moved and is now over `item`, returns whether a shown
+    /// tooltip has to be hidden.
+    pub fn moved(&mut self, item: Option<usize>, now: Instant) -> bool {
+        if item == self.item {
+            return false;
+        }
+        self.item = item;
+        self.deadline = item.map(|_| now + HOVER_DELAY);
+        std::mem::take(&mut self.shown)
+    }
+
+    /// When the tooltip is due.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// The item whose tooltip should be shown now.
+    pub fn due(&mut self, now: Instant) -> Option<usize> {
+        if self.deadline.is_some_and(|deadline| deadline <= now) {
+            self.deadline = None;
+            self.shown = true;
+            self.item
+        } else {
+            None
+        }
+    }
+
+    /// Stops waiting for the current item, the tooltip is armed again once
+    /// the pointer moves to another item.  Returns whether a shown tooltip has
+    /// to be hidden.
+    pub fn cancel(&mut self) -> bool {
+        self.deadline = None;
+        std::mem::take(&mut self.shown)
+    }
+
+    /// Forgets the item, for when the items under the pointer change.
+    pub fn reset(&mut self) -> bool {
+        self.item = None;
+        self.cancel()
+    }
+}
+
+pub struct Tooltip {
+    display: Display,
+    visual_info: XVisualInfo,
+    colormap: Colormap,
+    font: FontDescription,
+    max_width: i32,
+    shown: Option<(Window, DrawingContext)>,
+}
+
+impl Tooltip {
+    pub fn new(
+        display: &Display,
+        visual_info: &XVisualInfo,
+        colormap: Colormap,
+        config: &Config,
+    ) -> Self {
+        let mut font = fonts::load(&config.list_font);
+        font.set_size(font.size() * 3 / 4);
+        Self {
+            display: *display,
+            visual_info: *visual_info,
+            colormap,
+            font,
+            max_width: display.width() as i32 / 3,
+            shown: None,
+        }
+    }
+
+    /// Shows the tooltip near the pointer at `(x, y)` in root coordinates.
+    pub fn show(&mut self, markup: &str, x: i32, y: i32) {
+        self.hide();
+        let layout = pango::Layout::new(&pangocairo::FontMap::default().create_context());
+        self.configure(&layout);
+        layout.set_markup(markup);
+        let (text_width, text_height) = layout.pixel_size();
+        let width = (text_width + 2 * PADDING) as u32;
+        let height = (text_height + 2 * PADDING) as u32;
+        // Keep it on the screen, flipping it to the other side of the
+        // pointer if needed.
+        let (screen_width, screen_height) = self.display.size();
+        let mut x = x + POINTER_OFFSET;
+        let mut y = y + POINTER_OFFSET;
+        if x + width as i32 > screen_width as i32 {
+            x = (x - 2 * POINTER_OFFSET - width as i32).max(0);
+        }
+        if y + height as i32 > screen_height as i32 {
+            y = (y - 2 * POINTER_OFFSET - height as i32).max(0);
+        }
+        let window = Window::builder(&self.display)
+            .position(x, y)
+            .size(width, height)
+            .attributes(|attributes| {
+                attributes
+                    .colormap(self.colormap)
+                    .border_pixel(0)
+                    .background_pixel(colors::background().pack())
+                    .override_redirect(true);
+            })
+            .visual(self.visual_info.visual)
+            .depth(self.visual_info.depth)
+            .build();
+        let mut dc = DrawingContext::create(&self.display, width, height, &self.visual_info);
+        self.configure(dc.layout());
+        dc.fill(colors::background());
+        dc.set_color(colors::text());
+        dc.text(
+            markup,
+            Rectangle::new(PADDING, PADDING, text_width as u32, text_height as u32),
+            true,
+        )
+        .draw();
+        window.map_raised();
+        dc.render(window, &Rectangle::new(0, 0, width, height));
+        self.shown = Some((window, dc));
+    }
+
+    fn configure(&self, layout: &pango::Layout) {
+        layout.set_font_description(Some(&self.font));
+        layout.set_width(self.max_width * pango::SCALE);
+        layout.set_wrap(WrapMode::WordChar);
+    }
+
+    pub fn hide(&mut self) {
+        if let Some((window, mut dc)) = self.shown.take() {
+            window.destroy();
+            dc.destroy();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hover() {
+        let start = Instant::now();
+        let mut hover = Hover::default();
+        assert!(!hover.moved(Some(2), start));
+        assert_eq!(hover.deadline(), Some(start + HOVER_DELAY));
+        assert_eq!(hover.due(start), None);
+        // Moving inside the same item doesn't restart the delay
+        assert!(!hover.moved(Some(2), start + HOVER_DELAY / 2));
+        assert_eq!(hover.due(start + HOVER_DELAY), Some(2));
+        assert_eq!(hover.deadline(), None);
+        // Moving to another item hides it and arms the delay again
+        let later = start + HOVER_DELAY * 2;
+        assert!(hover.moved(Some(3), later));
+        assert_eq!(hover.deadline(), Some(later + HOVER_DELAY));
+        assert!(!hover.cancel());
+        assert_eq!(hover.due(later + HOVER_DELAY), None);
+        assert!(!hover.moved(Some(3), later + HOVER_DELAY));
+        assert!(!hover.moved(None, later + HOVER_DELAY));
+        assert_eq!(hover.deadline(), None);
+    }
+}
@@ -0,0 +1,130 @@
+//! A few read-only items describing the main features, shown instead of an
+//! empty list until the launcher is first used.
+//!
+//! The history file is written on every exit, so a fresh install is
+//! recognized by an empty history rather than a missing file.  Committing
+//! anything or pressing delete on the tour hides it for good, this is
+//! remembered by a file next to the history.
+
+use crate::{
+    cache::DesktopEntryCache,
+    list_view::Render,
+    paths,
+    res::{resources, Svg},
+    text::escape_markup,
+    ui::colors,
+    warnings::warning,
+};
+use std::path::Path;
+
+pub struct TourItem {
+    text: &'static str,
+    hint: &'static str,
+    icon: &'static [u8],
+}
+
+pub static ITEMS: [TourItem; 4] = [
+    TourItem {
+        text: "Type to search apps",
+        hint: "by name, description, or keyword",
+        icon: resources::SEARCH_ICON,
+    },
+    TourItem {
+        text: "Try 5km to mi",
+        hint: "units, currencies, and math are shown above the list",
+        icon: resources::CONVERSION_PATH_ICON,
+    },
+    TourItem {
+        text: "Start with $ to run a command",
+        hint: "like $ htop",
+        icon: resources::TERMINAL_ICON,
+    },
+    TourItem {
+        text: "Press Tab to switch panes",
+        hint: "between the text entry and the list",
+        icon: resources::HISTORY_ICON,
+    },
+];
+
+impl Render for TourItem {
+    fn markup(&self, _search: &str, _cache: &DesktopEntryCache) -> String {
+        format!(
+            "{} <span color=\"{}\">{}</span>",
+            escape_markup(self.text),
+            colors::list_match_name(),
+            escape_markup(self.hint)
+        )
+    }
+
+    fn plain_text(&self, _cache: &DesktopEntryCache) -> String {
+        self.text.to_string()
+    }
+
+    fn icon(&self, _cache: &DesktopEntryCache) -> Option<Svg> {
+        Some(Svg::load(self.icon))
+    }
+
+    fn tooltip(&self, _cache: &DesktopEntryCache) -> Option<String> {
+        Some(format!(
+            "<b>{}</b>\n{}\n\n<i>Launch anything or press delete to hide these.</i>",
+            escape_markup(self.text),
+            escape_markup(self.hint)
+        ))
+    }
+}
+
+fn is_first_run(dismissed: &Path, config: Option<&Path>) -> bool {
+    !dismissed.exists() && !config.is_some_and(Path::exists)
+}
+
+/// Whether to show the tour for a history that is `history_is_empty`.  It's
+/// not shown once there is a config file, and without a cache directory
+/// dismissing it couldn't be remembered.
+pub fn should_show(history_is_empty: bool) -> bool {
+    let Some(dismissed) = paths::cache_file(paths::TOUR_DISMISSED) else {
+        return false;
+    };
+    history_is_empty && is_first_run(&dismissed, paths::config_file().as_deref())
+}
+
+/// Remembers that the tour was dismissed.
+pub fn dismiss() {
+    let Some(dir) = paths::cache_dir() else {
+        return;
+    };
+    let result = std::fs::create_dir_all(&dir)
+        .and_then(|_| std::fs::write(dir.join(paths::TOUR_DISMISSED), ""));
+    if let Err(error) = result {
+        warning!("Failed to dismiss the tour: {error}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dismissing() {
+        let home = std::env::temp_dir().join(format!("launcher-tour-{}", std::process::id()));
+        std::fs::create_dir_all(&home).unwrap();
+        paths::with_home(Some(&home), || {
+            assert!(should_show(true));
+            assert!(!should_show(false));
+            // Shown again on the next start until it's dismissed
+            assert!(should_show(true));
+            dismiss();
+            assert!(!should_show(true));
+            assert!(!should_show(true));
+        });
+        // Someone who already wrote a config knows their way around
+        let config_home = home.join("config-home");
+        paths::with_home(Some(&config_home), || {
+            let config = paths::config_file().unwrap();
+            std::fs::create_dir_all(config.parent().unwrap()).unwrap();
+            std::fs::write(&config, "").unwrap();
+            assert!(!should_show(true));
+        });
+        paths::with_home(None, || assert!(!should_show(true)));
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+}
@@ -1,63 +1,203 @@
 use crate::{
     app::{send_signal, Signal},
     cache::DesktopEntryCache,
-    config::Config,
+    config::{Config, WindowPlacement},
     draw::DrawingContext,
     entry::Entry,
-    input::KeyEvent,
+    input::{Key, KeyEvent},
     layout::{Layout, Rectangle},
-    list_view::{ListView, Render},
+    list_view::{ListModel, ListView, Render},
     smart_content::{ReadyContent, SmartContent},
-    x::{display::ScopedInputGrab, Display, Window},
+    tooltip::{Hover, Tooltip},
+    warnings::warning,
+    x::{display::ScopedInputGrab, Display, Monitor, Window, XInput2, XWindow},
 };
 use std::{
-    ffi::c_void,
+    cell::RefCell,
+    rc::Rc,
     sync::{mpsc::Sender, Arc, Mutex},
+    time::Instant,
 };
 use x11::xlib::{
-    AllocNone, Button4, Button5, ButtonPressMask, KeyPressMask, TrueColor, XButtonPressedEvent,
+    AllocNone, Button4, Button5, ButtonPressMask, FocusChangeMask, KeyPressMask, NotifyNonlinear,
+    NotifyNonlinearVirtual, NotifyNormal, NotifyWhileGrabbed, PointerMotionMask, PointerRoot,
+    TrueColor, XButtonPressedEvent, XExposeEvent, XFocusChangeEvent, XMotionEvent, XA_WM_NAME,
 };
 
+/// Title of the main window, for taskbars and compositor rules.
+const TITLE: &str = "Launcher";
+
 pub mod colors {
     use crate::draw::Color;
+    use std::sync::OnceLock;
 
-    pub const BACKGROUND: Color = Color::new(44, 44, 46, 204);
-    pub const TEXT: Color = Color::new(174, 174, 178, 255);
-    pub const ACCENT: Color = Color::new(10, 132, 255, 255);
-
-    pub const ENTRY_BACKGROUND: Color = BACKGROUND.scale(90);
-    pub const ENTRY_CURSOR: Color = TEXT.scale(125);
-    pub const ENTRY_SELECTION: Color = ENTRY_FOCUSED_BORDER.with_alpha(96);
-    pub const ENTRY_FOCUSED_BORDER: Color = ACCENT;
-    pub const ENTRY_NORMAL_BORDER: Color = BACKGROUND.scale(110);
-    pub const ENTRY_PLACEHOLDER_TEXT: Color = TEXT.scale(70);
-
-    pub const LIST_MATCH_NAME: Color = TEXT.scale(70);
-    pub const LIST_LIGHT_BACKGROUND: Color = BACKGROUND.scale(120);
-    pub const LIST_MATCH_HIGHLIGHT: Color = ACCENT;
-    pub const LIST_SELECTED_BACKGROUND: Color = BACKGROUND.scale(60).with_alpha(229);
-    pub const LIST_SCROLL_BAR: Color = TEXT.with_alpha(204).scale(50);
-}
+    macro_rules! palette {
+        ($($name:ident),* $(,)?) => {
+            /// The colors everything is drawn with, each one also has a
+            /// function returning it from the palette in use.
+            #[derive(Debug)]
+            pub struct Palette {
+                $(pub $name: Color,)*
+            }
 
-fn main_screen_size(display: &Display) -> (u32, u32) {
-    use x11::xinerama::*;
-    use x11::xlib::XFree;
-    unsafe {
-        if XineramaIsActive(display.as_raw()) == 0 {
-            display.size()
-        } else {
-            let mut len = 0;
-            let data = XineramaQueryScreens(display.as_raw(), &mut len);
-            let result = std::slice::from_raw_parts(data, len as usize).to_vec();
-            XFree(data as *mut c_void);
-            for screen_info in &result {
-                if screen_info.screen_number == 0 {
-                    return (screen_info.width as u32, screen_info.height as u32);
+            $(
+                pub fn $name() -> Color {
+                    palette().$name
                 }
-            }
-            (result[0].width as u32, result[0].height as u32)
-        }
+            )*
+        };
+    }
+
+    palette! {
+        background,
+        text,
+        entry_background,
+        entry_cursor,
+        entry_selection,
+        entry_focused_border,
+        entry_normal_border,
+        entry_placeholder_text,
+        list_match_name,
+        list_light_background,
+        list_match_highlight,
+        list_selected_background,
+        list_scroll_bar,
+        smart_content_value,
+        smart_content_dim,
     }
+
+    const DARK_BACKGROUND: Color = Color::new(44, 44, 46, 204);
+    const DARK_TEXT: Color = Color::new(174, 174, 178, 255);
+    const DARK_ACCENT: Color = Color::new(10, 132, 255, 255);
+
+    pub const DARK: Palette = Palette {
+        background: DARK_BACKGROUND,
+        text: DARK_TEXT,
+        entry_background: DARK_BACKGROUND.scale(90),
+        entry_cursor: DARK_TEXT.scale(125),
+        entry_selection: DARK_ACCENT.with_alpha(96),
+        entry_focused_border: DARK_ACCENT,
+        entry_normal_border: DARK_BACKGROUND.scale(110),
+        entry_placeholder_text: DARK_TEXT.scale(70),
+        list_match_name: DARK_TEXT.scale(70),
+        list_light_background: DARK_BACKGROUND.scale(120),
+        list_match_highlight: DARK_ACCENT,
+        list_selected_background: DARK_BACKGROUND.scale(60).with_alpha(229),
+        list_scroll_bar: DARK_TEXT.with_alpha(204).scale(50),
+        smart_content_value: DARK_TEXT.scale(125),
+        smart_content_dim: DARK_TEXT.scale(70),
+    };
+
+    const LIGHT_BACKGROUND: Color = Color::new(242, 242, 247, 204);
+    const LIGHT_TEXT: Color = Color::new(60, 60, 67, 255);
+    const LIGHT_ACCENT: Color = Color::new(0, 122, 255, 255);
+
+    // Darker is more prominent here, so the scales go the other way.
+    pub const LIGHT: Palette = Palette {
+        background: LIGHT_BACKGROUND,
+        text: LIGHT_TEXT,
+        entry_background: Color::new(255, 255, 255, 204),
+        entry_cursor: LIGHT_TEXT.scale(50),
+        entry_selection: LIGHT_ACCENT.with_alpha(64),
+        entry_focused_border: LIGHT_ACCENT,
+        entry_normal_border: LIGHT_BACKGROUND.scale(90),
+        entry_placeholder_text: LIGHT_TEXT.scale(160),
+        list_match_name: LIGHT_TEXT.scale(160),
+        list_light_background: LIGHT_BACKGROUND.scale(95),
+        list_match_highlight: LIGHT_ACCENT,
+        list_selected_background: LIGHT_BACKGROUND.scale(85).with_alpha(229),
+        list_scroll_bar: LIGHT_TEXT.with_alpha(153).scale(200),
+        smart_content_value: LIGHT_TEXT.scale(50),
+        smart_content_dim: LIGHT_TEXT.scale(160),
+    };
+
+    static PALETTE: OnceLock<&'static Palette> = OnceLock::new();
+
+    /// Selects the palette, this has to happen before anything is drawn and
+    /// only the first call has an effect.
+    pub fn set_palette(palette: &'static Palette) {
+        PALETTE.set(palette).ok();
+    }
+
+    /// The palette in use, dark if none was selected.
+    pub fn palette() -> &'static Palette {
+        PALETTE.get().copied().unwrap_or(&DARK)
+    }
+}
+
+/// The widget receiving keyboard input.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Focus {
+    Entry,
+    SmartContent,
+    List,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FocusDirection {
+    Forward,
+    Backward,
+}
+
+impl Focus {
+    /// Focus order, top to bottom.
+    const ORDER: [Focus; 3] = [Focus::Entry, Focus::SmartContent, Focus::List];
+
+    /// The next widget in `direction` for which `focusable` returns true,
+    /// wrapping around at either end.
+    fn next(self, direction: FocusDirection, focusable: impl Fn(Focus) -> bool) -> Focus {
+        let len = Self::ORDER.len();
+        let current = Self::ORDER.iter().position(|&f| f == self).unwrap();
+        (1..=len)
+            .map(|step| match direction {
+                FocusDirection::Forward => Self::ORDER[(current + step) % len],
+                FocusDirection::Backward => Self::ORDER[(current + len - step) % len],
+            })
+            .find(|&focus| focusable(focus))
+            .unwrap_or(Focus::Entry)
+    }
+}
+
+/// The name of this machine, `WM_CLIENT_MACHINE` has to be set along with
+/// `_NET_WM_PID` since the PID alone doesn't say where the process runs.
+fn host_name() -> Option<String> {
+    let mut buffer = [0u8; 256];
+    if unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) } != 0 {
+        return None;
+    }
+    let name = std::ffi::CStr::from_bytes_until_nul(&buffer).ok()?;
+    Some(name.to_string_lossy().into_owned())
+}
+
+/// Sets the title, the PID, and the protocols the window manager can use.
+fn set_window_properties(display: &Display, window: Window) {
+    window.set_text_property(XA_WM_NAME, TITLE);
+    window.set_text_property(display.intern_atom(c"_NET_WM_NAME"), TITLE);
+    if let Some(host_name) = host_name() {
+        window.set_text_property(display.intern_atom(c"WM_CLIENT_MACHINE"), &host_name);
+        window.set_cardinal_property(display.intern_atom(c"_NET_WM_PID"), &[std::process::id()]);
+    }
+    window.set_wm_protocols(&[display.intern_atom(c"WM_DELETE_WINDOW")]);
+}
+
+/// XInput2 for grabbing the keyboards if it's `enabled`, the core grab is used
+/// if the server doesn't support it.
+fn xinput2(display: &Display, enabled: bool) -> Option<XInput2> {
+    if !enabled {
+        return None;
+    }
+    let xi2 = display.xinput2();
+    if xi2.is_none() {
+        warning!("XInput 2 is not available, using the core keyboard grab");
+    }
+    xi2
+}
+
+/// Position of a window of the given size on `monitor`, see
+/// `WindowPlacement::resolve`.
+fn placed(monitor: &Monitor, placement: &WindowPlacement, width: u32, height: u32) -> (i32, i32) {
+    let rect = Rectangle::new(monitor.x, monitor.y, monitor.width, monitor.height);
+    Layout::window_position(&rect, placement.resolve(&monitor.name), width, height)
 }
 
 pub struct Ui {
@@ -66,15 +206,29 @@ pub struct Ui {
     entry: Entry,
     // The list view was designed for a variable layout, and just adding a
     // second one is quite painless.
+    list_model: Rc<RefCell<ListModel>>,
     full_list_view: ListView,
-    reduced_list_view: ListView,
+    /// Not created if smart content is disabled since it would never be shown.
+    reduced_list_view: Option<ListView>,
     pub smart_content: SmartContent,
     showing_smart_content: bool,
-    input_focus: bool,
+    focus: Focus,
     width: i32,
     height: i32,
+    /// Position of the main window on the screen.
+    position: (i32, i32),
+    placement: WindowPlacement,
     signal_sender: Sender<Signal>,
-    _input_grab: ScopedInputGrab,
+    input_grab: Option<ScopedInputGrab>,
+    /// Grabs each master keyboard instead of using the core grab.
+    xi2: Option<XInput2>,
+    /// The window that had the input focus before we took it.
+    previous_focus: XWindow,
+    close_on_focus_loss: bool,
+    tooltip: Tooltip,
+    hover: Hover,
+    /// Last pointer position in root coordinates.
+    pointer: (i32, i32),
 }
 
 impl Ui {
@@ -84,12 +238,19 @@ impl Ui {
         cache: Arc<Mutex<DesktopEntryCache>>,
         config: &Config,
     ) -> Self {
-        let screen_size = main_screen_size(display);
+        let previous_focus = display.get_input_focus();
+        let monitor = display.main_monitor();
+        let screen_size = (monitor.width, monitor.height);
         let visual_info = display.match_visual_info(32, TrueColor).unwrap();
         let colormap = display.create_colormap(visual_info.visual, AllocNone);
 
         let window_size = Layout::window_size(screen_size.0, screen_size.1, config);
-        let mut dc = DrawingContext::create(display, window_size.0, window_size.1, &visual_info);
+        let mut dc = DrawingContext::create(
+            display,
+            window_size.0.max(1),
+            window_size.1.max(1),
+            &visual_info,
+        );
 
         let layout = Layout::new(screen_size.0, screen_size.1, config, |font| {
             let layout = dc.layout();
@@ -99,25 +260,32 @@ impl Ui {
         });
         let width = layout.window.width;
         let height = layout.window.height;
+        if (width, height) != window_size {
+            // Raised to the minimum size
+            dc.destroy();
+            dc = DrawingContext::create(display, width, height, &visual_info);
+        }
 
+        let position = placed(&monitor, &config.position, width, height);
         let main_window = Window::builder(display)
             .size(width, height)
-            .position(
-                (screen_size.0 - width) as i32 / 2,
-                (screen_size.1 - height) as i32 / 2,
-            )
+            .position(position.0, position.1)
             .attributes(|attributes| {
                 attributes
-                    .background_pixel(colors::BACKGROUND.pack())
+                    .background_pixel(colors::background().pack())
                     .override_redirect(!cfg!(debug_assertions))
                     .colormap(colormap)
                     .border_pixel(0)
-                    .event_mask(KeyPressMask | ButtonPressMask);
+                    .event_mask(
+                        KeyPressMask | ButtonPressMask | PointerMotionMask | FocusChangeMask,
+                    );
             })
             .visual(visual_info.visual)
             .depth(visual_info.depth)
             .build();
         main_window.set_class_hint("Launcher", "launcher");
+        set_window_properties(display, main_window);
+        let xi2 = xinput2(display, config.xi2);
 
         let p = layout.entry.reparent;
         let entry = Entry::create(
@@ -140,6 +308,7 @@ impl Ui {
         );
         smart_content.window.reparent(main_window, p.0, p.1);
 
+        let list_model = Rc::new(RefCell::new(ListModel::new(cache)));
         let p = layout.full_list_view.reparent;
         let full_list_view = ListView::create(
             display,
@@ -147,30 +316,37 @@ impl Ui {
             layout.full_list_view,
             &visual_info,
             colormap,
-            cache.clone(),
+            list_model.clone(),
             config,
         );
         full_list_view.window.reparent(main_window, p.0, p.1);
 
-        let p = layout.reduced_list_view.reparent;
-        let reduced_list_view = ListView::create(
-            display,
-            signal_sender.clone(),
-            layout.reduced_list_view,
-            &visual_info,
-            colormap,
-            cache,
-            config,
-        );
-        reduced_list_view.window.reparent(main_window, p.0, p.1);
+        let reduced_list_view = config.smart_content_options.enabled.then(|| {
+            let p = layout.reduced_list_view.reparent;
+            let reduced_list_view = ListView::create(
+                display,
+                signal_sender.clone(),
+                layout.reduced_list_view,
+                &visual_info,
+                colormap,
+                list_model.clone(),
+                config,
+            );
+            reduced_list_view.window.reparent(main_window, p.0, p.1);
+            reduced_list_view
+        });
+
+        let tooltip = Tooltip::new(display, &visual_info, colormap, config);
 
         // Map all windows and draw background
         main_window.map_subwindows();
         // Smart content is only visibe when there is something to show, and
         // since we create the list view with its full size it would overlap.
         smart_content.window.unmap();
-        reduced_list_view.window.unmap();
-        dc.fill(colors::BACKGROUND);
+        if let Some(reduced_list_view) = &reduced_list_view {
+            reduced_list_view.window.unmap();
+        }
+        dc.fill(colors::background());
         main_window.map_raised();
         dc.render(main_window, &Rectangle::new(0, 0, width, height));
         dc.destroy();
@@ -180,37 +356,165 @@ impl Ui {
             display: *display,
             main_window,
             entry,
+            list_model,
             full_list_view,
             reduced_list_view,
             smart_content,
             showing_smart_content: false,
-            input_focus: true,
+            focus: Focus::Entry,
             width: width as i32,
             height: height as i32,
+            position,
+            placement: config.position.clone(),
             signal_sender,
-            _input_grab: display.scoped_input_grab(main_window, ButtonPressMask),
+            input_grab: Some(display.scoped_input_grab(
+                main_window,
+                ButtonPressMask | PointerMotionMask,
+                xi2,
+            )),
+            xi2,
+            previous_focus,
+            close_on_focus_loss: config.close_on_focus_loss,
+            tooltip,
+            hover: Hover::default(),
+            pointer: (0, 0),
+        }
+    }
+
+    fn restore_focus(&self) {
+        // `None` and `PointerRoot` are not windows, there is nothing to
+        // restore for them.
+        if self.previous_focus <= PointerRoot as XWindow {
+            return;
+        }
+        if self.display.window_exists(self.previous_focus) {
+            // The window may still go away or become unviewable before the
+            // request is processed.
+            self.display
+                .trap_errors(|| self.display.set_input_focus(self.previous_focus));
         }
     }
 
     fn layout(&mut self, show_smart_content: bool) {
+        let Some(reduced_list_view) = &self.reduced_list_view else {
+            return;
+        };
         if show_smart_content {
             self.smart_content.window.map_raised();
-            self.reduced_list_view.window.map_raised();
+            reduced_list_view.window.map_raised();
             self.full_list_view.window.unmap();
         } else {
             self.smart_content.window.unmap();
-            self.reduced_list_view.window.unmap();
+            reduced_list_view.window.unmap();
             self.full_list_view.window.map_raised();
         }
         self.showing_smart_content = show_smart_content;
     }
 
+    /// Set if the keyboards are grabbed with XInput2, their key events need
+    /// to be turned into core events.
+    pub fn xi2(&self) -> Option<XInput2> {
+        self.xi2
+    }
+
     pub fn list_view(&mut self) -> &mut ListView {
-        if self.showing_smart_content {
-            &mut self.reduced_list_view
-        } else {
-            &mut self.full_list_view
+        match &mut self.reduced_list_view {
+            Some(reduced_list_view) if self.showing_smart_content => reduced_list_view,
+            _ => &mut self.full_list_view,
+        }
+    }
+
+    /// Raises and focuses the window, for when another instance was started.
+    pub fn present(&self) {
+        self.main_window.map_raised();
+        self.display.set_input_focus(self.main_window);
+    }
+
+    /// Unmaps the window and gives the input back, keeping everything so it
+    /// can be shown again quickly.
+    pub fn hide(&mut self) {
+        self.hover.cancel();
+        self.tooltip.hide();
+        self.main_window.unmap();
+        self.input_grab.take();
+        self.restore_focus();
+        self.display.sync(false);
+    }
+
+    /// Shows the window again after `hide` or `show_launching`, placed on the
+    /// main monitor as it is now, with an empty entry.  Only `App::show`
+    /// should call this, the app has to reset its session too.
+    pub fn show(&mut self) {
+        self.previous_focus = self.display.get_input_focus();
+        self.reset_session();
+        let monitor = self.display.main_monitor();
+        self.position = placed(
+            &monitor,
+            &self.placement,
+            self.width as u32,
+            self.height as u32,
+        );
+        self.main_window.move_resize(
+            self.position.0,
+            self.position.1,
+            self.width as u32,
+            self.height as u32,
+        );
+        // Undo `show_launching`
+        let (x, y) = self.smart_content.reparent();
+        self.smart_content.window.reparent(self.main_window, x, y);
+        self.entry.window.map_raised();
+        self.full_list_view.window.map_raised();
+        self.layout(false);
+        self.main_window.map_raised();
+        self.display.set_input_focus(self.main_window);
+        self.input_grab = Some(self.display.scoped_input_grab(
+            self.main_window,
+            ButtonPressMask | PointerMotionMask,
+            self.xi2,
+        ));
+        self.redraw();
+    }
+
+    /// Resets everything that shouldn't carry over into the next time the
+    /// window is shown.  Clearing the entry makes the app show the history.
+    fn reset_session(&mut self) {
+        self.hover.cancel();
+        self.tooltip.hide();
+        self.set_smart_content(None);
+        self.smart_content.set(ReadyContent::default());
+        self.set_focus(Focus::Entry);
+        self.entry.clear();
+        self.full_list_view.reset();
+        if let Some(reduced_list_view) = &mut self.reduced_list_view {
+            reduced_list_view.reset();
+        }
+    }
+
+    /// Shrinks the window to the smart content row showing `name` as being
+    /// launched, and releases the input so the launched program can get it.
+    pub fn show_launching(&mut self, name: String) {
+        self.hover.cancel();
+        self.tooltip.hide();
+        self.input_grab.take();
+        self.entry.window.unmap();
+        self.full_list_view.window.unmap();
+        if let Some(reduced_list_view) = &self.reduced_list_view {
+            reduced_list_view.window.unmap();
         }
+        self.smart_content.set(ReadyContent::Launching(name));
+        let (x, y) = self.smart_content.reparent();
+        let height = self.smart_content.height() as i32 + 2 * x;
+        self.main_window.move_resize(
+            self.position.0,
+            self.position.1 + y - x,
+            self.width as u32,
+            height as u32,
+        );
+        self.smart_content.window.reparent(self.main_window, x, x);
+        self.smart_content.window.map_raised();
+        self.showing_smart_content = true;
+        self.smart_content.draw();
     }
 
     pub fn redraw(&mut self) {
@@ -222,20 +526,82 @@ impl Ui {
         }
     }
 
+    /// When the next timed update is due, see `tick`.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        let smart_content = if self.showing_smart_content || self.smart_content.showing_details() {
+            self.smart_content.next_deadline()
+        } else {
+            None
+        };
+        [
+            smart_content,
+            self.hover.deadline(),
+            self.full_list_view.next_deadline(),
+            self.reduced_list_view
+                .as_ref()
+                .and_then(ListView::next_deadline),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+    }
+
+    /// Performs timed updates whose deadline has passed.
+    pub fn tick(&mut self, now: Instant) {
+        if self.smart_content.tick(now)
+            && (self.showing_smart_content || self.smart_content.showing_details())
+        {
+            self.smart_content.draw();
+        }
+        // Both, so the hidden one has no placeholders left when it's shown
+        self.full_list_view.tick(now);
+        if let Some(reduced_list_view) = &mut self.reduced_list_view {
+            reduced_list_view.tick(now);
+        }
+        if let Some(idx) = self.hover.due(now) {
+            if let Some(markup) = self.list_view().item_tooltip(idx) {
+                self.tooltip.show(&markup, self.pointer.0, self.pointer.1);
+            }
+        }
+    }
+
+    pub fn pointer_motion(&mut self, event: &XMotionEvent) {
+        self.pointer = (event.x_root, event.y_root);
+        let item = self.list_view().item_at(event.x, event.y);
+        if self.hover.moved(item, Instant::now()) {
+            self.tooltip.hide();
+        }
+    }
+
+    /// Hides the tooltip until the pointer moves to another item.
+    fn dismiss_tooltip(&mut self) {
+        if self.hover.cancel() {
+            self.tooltip.hide();
+        }
+    }
+
     pub fn text_input(&mut self, text: &str) {
-        if self.input_focus {
+        if self.focus == Focus::Entry {
             self.entry.text_input(text);
         }
     }
 
     pub fn set_items<T: Render + 'static>(&mut self, items: &[T], search: &str) {
-        self.full_list_view
-            .set_items(items, search, self.showing_smart_content);
-        self.reduced_list_view
-            .set_items(items, search, !self.showing_smart_content);
+        if self.hover.reset() {
+            self.tooltip.hide();
+        }
+        self.list_model.borrow_mut().set_items(items, search);
+        // The hidden list view catches up when it's drawn the next time.
+        self.list_view().draw();
     }
 
+    /// Shows or hides the smart content row, does nothing if smart content is
+    /// disabled.
     pub fn set_smart_content(&mut self, content: Option<ReadyContent>) {
+        self.hide_details();
+        if self.reduced_list_view.is_none() {
+            return;
+        }
         if let Some(text) = content {
             self.smart_content.set(text);
             self.layout(true);
@@ -244,21 +610,101 @@ impl Ui {
             self.smart_content.window.unmap();
             self.layout(false);
         }
+        if self.focus == Focus::SmartContent && !self.showing_useful_smart_content() {
+            self.set_focus(Focus::Entry);
+        }
     }
 
     pub fn showing_useful_smart_content(&self) -> bool {
         self.showing_smart_content && self.smart_content.is_useful()
     }
 
-    pub fn key_press(&mut self, event: KeyEvent) {
-        if self.input_focus {
-            self.entry.key_press(event);
+    /// Shows the given command line in place of the smart content, if there
+    /// is no smart content it's shown on top of the list instead.
+    pub fn show_details(&mut self, command: String) {
+        self.smart_content.set_details(Some(command));
+        if !self.showing_smart_content {
+            self.smart_content.window.map_raised();
+        }
+        self.smart_content.draw();
+    }
+
+    /// Shows a command line that was copied to the clipboard like the details,
+    /// with the "Copied!" indicator.
+    pub fn show_copied(&mut self, command: String) {
+        self.smart_content.show_copied();
+        self.show_details(command);
+    }
+
+    /// Hides the details, returns `false` if they weren't shown.
+    pub fn hide_details(&mut self) -> bool {
+        if !self.smart_content.showing_details() {
+            return false;
+        }
+        self.smart_content.set_details(None);
+        if self.showing_smart_content {
+            self.smart_content.draw();
         } else {
-            self.list_view().key_press(event);
+            self.smart_content.window.unmap();
+            self.full_list_view.draw();
         }
+        true
+    }
+
+    pub fn key_press(&mut self, event: KeyEvent) {
+        self.dismiss_tooltip();
+        if self.smart_content.showing_details() {
+            match event.key {
+                Key::Escape => {
+                    self.hide_details();
+                    return;
+                }
+                // These either commit the item or toggle the details again.
+                Key::Enter | Key::CtrlI => {}
+                Key::Right if self.focus == Focus::List => {}
+                // Anything else could change what the details are for.
+                _ => {
+                    self.hide_details();
+                }
+            }
+        }
+        if matches!(event.key, Key::F5)
+            && self.showing_smart_content
+            && self.smart_content.shows_currency()
+        {
+            send_signal(
+                &self.display,
+                &self.signal_sender,
+                Signal::RefreshCurrencies,
+            );
+            return;
+        }
+        if matches!(event.key, Key::F5 | Key::CtrlR) {
+            send_signal(&self.display, &self.signal_sender, Signal::RebuildCache);
+            return;
+        }
+        match self.focus {
+            Focus::Entry => self.entry.key_press(event),
+            Focus::SmartContent => self.smart_content_key_press(event),
+            Focus::List => self.list_view().key_press(event),
+        }
+    }
+
+    fn smart_content_key_press(&mut self, event: KeyEvent) {
+        let signal = match event.key {
+            Key::Tab => Signal::SwapFocus(event.tab_direction()),
+            Key::Down => Signal::SwapFocus(FocusDirection::Forward),
+            Key::Up => Signal::SwapFocus(FocusDirection::Backward),
+            Key::Enter => Signal::Commit(None),
+            Key::CtrlI => Signal::ToggleDetails(None),
+            Key::Escape => Signal::Quit,
+            _ => return,
+        };
+        send_signal(&self.display, &self.signal_sender, signal);
     }
 
     pub fn button_press(&mut self, event: &mut XButtonPressedEvent) {
+        self.dismiss_tooltip();
         // Button4 and Button5 are the mouse wheel, we can always allow it.
         if event.button != Button4 && event.button != Button5 {
             if event.x < 0 || event.y < 0 || event.x > self.width || event.y > self.height {
@@ -269,35 +715,193 @@ impl Ui {
         }
         if self.entry.hit_test(event.x, event.y) {
             self.entry.set_focused(true);
-            self.input_focus = true;
+            self.focus = Focus::Entry;
             self.smart_content.set_selected(false);
         } else if self.showing_smart_content && self.smart_content.hit_test(event.x, event.y) {
             self.entry.set_focused(false);
-            self.input_focus = false;
+            self.focus = Focus::SmartContent;
             self.smart_content.set_selected(true);
         } else if self.list_view().hit_test(event.x, event.y) {
             self.entry.set_focused(false);
-            self.input_focus = false;
+            self.focus = Focus::List;
             self.list_view().button_press(event);
             self.smart_content.set_selected(false);
         }
     }
 
-    pub fn swap_focus(&mut self) {
-        self.input_focus = !self.input_focus;
-        if !self.input_focus && self.list_view().is_empty() {
-            self.input_focus = true;
-        } else {
-            self.entry.set_focused(self.input_focus);
+    /// Repaints the part of a list view the X server lost.
+    pub fn expose(&mut self, event: &XExposeEvent) {
+        if self.full_list_view.window == event.window {
+            self.full_list_view.expose(event);
+        } else if let Some(reduced_list_view) = self
+            .reduced_list_view
+            .as_mut()
+            .filter(|list_view| list_view.window == event.window)
+        {
+            reduced_list_view.expose(event);
+        }
+    }
+
+    /// Closes the launcher if another client took the focus, for when the
+    /// pointer grab failed and clicks outside the window don't reach us.
+    pub fn focus_out(&mut self, event: &XFocusChangeEvent) {
+        if self.close_on_focus_loss && is_focus_loss(event, self.main_window.handle()) {
+            send_signal(&self.display, &self.signal_sender, Signal::Quit);
         }
     }
+
+    pub fn swap_focus(&mut self, direction: FocusDirection) {
+        let smart_content = self.showing_useful_smart_content();
+        let list = !self.list_view().is_empty();
+        let focus = self.focus.next(direction, |focus| match focus {
+            Focus::Entry => true,
+            Focus::SmartContent => smart_content,
+            Focus::List => list,
+        });
+        self.set_focus(focus);
+    }
+
+    fn set_focus(&mut self, focus: Focus) {
+        self.focus = focus;
+        self.entry.set_focused(focus == Focus::Entry);
+        self.smart_content.set_focused(focus == Focus::SmartContent);
+    }
+}
+
+/// Whether a `FocusOut` means the focus went to another client.  Focus moving
+/// between the main window and its subwindows has an inferior, ancestor, or
+/// virtual detail, and taking or releasing our keyboard grab has the grab or
+/// ungrab mode.  The main window sees a nonlinear virtual detail if the focus
+/// was in one of its subwindows.
+#[allow(non_upper_case_globals)]
+fn is_focus_loss(event: &XFocusChangeEvent, main_window: XWindow) -> bool {
+    event.window == main_window
+        && matches!(event.mode, NotifyNormal | NotifyWhileGrabbed)
+        && matches!(event.detail, NotifyNonlinear | NotifyNonlinearVirtual)
 }
 
 impl Drop for Ui {
     fn drop(&mut self) {
+        self.tooltip.hide();
         self.main_window.unmap();
         self.main_window.destroy();
+        // Focus can't be given away while we're still holding the keyboard.
+        self.input_grab.take();
+        self.restore_focus();
         self.display.sync(true);
         self.display.flush();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use FocusDirection::*;
+
+    fn all(_: Focus) -> bool {
+        true
+    }
+
+    #[test]
+    fn focus_cycle() {
+        use Focus::*;
+        assert_eq!(Entry.next(Forward, all), SmartContent);
+        assert_eq!(SmartContent.next(Forward, all), List);
+        assert_eq!(List.next(Forward, all), Entry);
+        assert_eq!(Entry.next(Backward, all), List);
+        assert_eq!(List.next(Backward, all), SmartContent);
+        assert_eq!(SmartContent.next(Backward, all), Entry);
+    }
+
+    #[test]
+    fn focus_skips_unavailable() {
+        use Focus::*;
+        let no_smart_content = |focus| focus != SmartContent;
+        assert_eq!(Entry.next(Forward, no_smart_content), List);
+        assert_eq!(List.next(Backward, no_smart_content), Entry);
+        assert_eq!(List.next(Forward, no_smart_content), Entry);
+        let empty_list = |focus| focus != List;
+        assert_eq!(SmartContent.next(Forward, empty_list), Entry);
+        assert_eq!(Entry.next(Backward, empty_list), SmartContent);
+        let only_entry = |focus| focus == Entry;
+        assert_eq!(Entry.next(Forward, only_entry), Entry);
+        assert_eq!(Entry.next(Backward, only_entry), Entry);
+        // Leaving a widget that became unavailable
+        assert_eq!(SmartContent.next(Backward, only_entry), Entry);
+        assert_eq!(List.next(Forward, only_entry), Entry);
+    }
+
+    #[test]
+    fn centering() {
+        let centered = |(width, height), window_width, window_height| {
+            let monitor = Monitor {
+                name: "DP-1".to_string(),
+                x: 0,
+                y: 0,
+                width,
+                height,
+                primary: true,
+            };
+            placed(
+                &monitor,
+                &WindowPlacement::default(),
+                window_width,
+                window_height,
+            )
+        };
+        assert_eq!(centered((1920, 1080), 800, 600), (560, 240));
+        assert_eq!(centered((1280, 720), 800, 600), (240, 60));
+        // Larger than the screen
+        assert_eq!(centered((640, 480), 800, 600), (-80, -60));
+    }
+
+    #[test]
+    fn focus_loss() {
+        use x11::xlib::{
+            FocusOut, NotifyAncestor, NotifyGrab, NotifyInferior, NotifyUngrab, NotifyVirtual,
+        };
+        let main_window = 42;
+        let event = |window, mode, detail| XFocusChangeEvent {
+            type_: FocusOut,
+            serial: 0,
+            send_event: 0,
+            display: std::ptr::null_mut(),
+            window,
+            mode,
+            detail,
+        };
+        assert!(is_focus_loss(
+            &event(main_window, NotifyNormal, NotifyNonlinear),
+            main_window
+        ));
+        // Another client setting the focus while we hold the keyboard
+        assert!(is_focus_loss(
+            &event(main_window, NotifyWhileGrabbed, NotifyNonlinear),
+            main_window
+        ));
+        // The focus was in one of our subwindows
+        assert!(is_focus_loss(
+            &event(main_window, NotifyNormal, NotifyNonlinearVirtual),
+            main_window
+        ));
+        // Our keyboard grab starting or ending
+        for mode in [NotifyGrab, NotifyUngrab] {
+            assert!(!is_focus_loss(
+                &event(main_window, mode, NotifyNonlinear),
+                main_window
+            ));
+        }
+        // Moving between the main window and its subwindows
+        for detail in [NotifyInferior, NotifyAncestor, NotifyVirtual] {
+            assert!(!is_focus_loss(
+                &event(main_window, NotifyNormal, detail),
+                main_window
+            ));
+        }
+        // A subwindow losing the focus
+        assert!(!is_focus_loss(
+            &event(7, NotifyNormal, NotifyNonlinear),
+            main_window
+        ));
+    }
+}
@@ -1,6 +1,16 @@
-use crate::static_units::*;
+use crate::{
+    cache::DesktopEntryCache,
+    content::ClassificationError,
+    list_view::Render,
+    res::{resources, Svg},
+    static_units::*,
+    text::escape_markup,
+    ui::colors,
+    warnings::warning,
+};
 use libc::{localeconv, setlocale, LC_MONETARY};
 use reqwest::blocking::get;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
 use slotmap::{new_key_type, SlotMap};
 use std::{
     cell::RefCell,
@@ -18,9 +28,9 @@ impl CurrencyKey {
         CURRENCIES.with_borrow(|c| c[self].full_name.clone())
     }
 
-    //pub fn code(self) -> String {
-    //    CURRENCIES.with_borrow(|c| c[self].currency_code.clone())
-    //}
+    pub fn code(self) -> String {
+        CURRENCIES.with_borrow(|c| c[self].currency_code.clone())
+    }
 
     pub fn rate(self) -> f64 {
         CURRENCIES.with_borrow(|c| c[self].rate)
@@ -40,7 +50,7 @@ pub struct Currency {
     /// The full name of the currency, may be empty.
     pub full_name: String,
     /// The currency code in lowercase.
-    //pub currency_code: String,
+    pub currency_code: String,
     /// Conversion rate, based on the configured default currency.
     pub rate: f64,
 }
@@ -53,6 +63,8 @@ thread_local! {
     /// All names and codes.
     pub static CURRENCY_IDENTIFIERS: RefCell<HashSet<String>> = Default::default();
     static DEFAULT: RefCell<CurrencyKey> = Default::default();
+    /// The day the installed rates are from.
+    static RATES_DATE: RefCell<Option<String>> = Default::default();
 }
 
 /// Get the default default currenct from the locale.
@@ -80,11 +92,21 @@ pub fn user_currency() -> String {
     }
 }
 
-/// Get the currency key for a currency name or code.
+/// Get the currency key for a currency name or code.  Codes are also accepted
+/// in uppercase.
 pub fn currency(name_or_code: &str) -> Option<CurrencyKey> {
     CURRENCY_NAMES
         .with_borrow(|c| c.get(name_or_code).copied())
         .or_else(|| CURRENCY_CODES.with_borrow(|c| c.get(name_or_code).copied()))
+        .or_else(|| {
+            CURRENCY_CODES.with_borrow(|c| c.get(&name_or_code.to_ascii_lowercase()).copied())
+        })
+}
+
+/// The day the currency rates are from as `YYYY-MM-DD`, `None` before they're
+/// loaded.
+pub fn currency_date() -> Option<String> {
+    RATES_DATE.with_borrow(Clone::clone)
 }
 
 /// Convert `amount` from `from` to `to`.
@@ -94,12 +116,13 @@ pub fn convert_currency(amount: f64, from: CurrencyKey, to: CurrencyKey) -> f64
     amount * to_rate / from_rate
 }
 
-mod currency_cache {
-    use chrono::{DateTime, Datelike, NaiveDate, Utc};
-    use std::{
-        fs::{create_dir_all, read_to_string, write},
-        time::SystemTime,
-    };
+pub(crate) mod currency_cache {
+    use super::CurrencyData;
+    use crate::paths::{self, CURRENCIES};
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use serde_json::{Map, Value};
+    use std::{fs::create_dir_all, path::PathBuf, time::SystemTime};
 
     //
     // The conversion rate response from the api gives a date with day
@@ -107,103 +130,212 @@ mod currency_cache {
     // We completely base this off system time so we can avoid any api calls.
     // We could use the APIs date when saving the cache but it shouldn't matter.
     //
+    // Everything is kept in a single file so the names and rates always come
+    // from the same day.  The rates map reference currencies to the API
+    // response for them, so switching the default currency back and forth
+    // doesn't need to fetch the rates again on the same day.
+    //
 
-    fn path(file: &str) -> String {
-        format!(
-            "{}/.cache/launcher/{}",
-            std::env::var("HOME").unwrap(),
-            file
-        )
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    struct CacheFile {
+        /// The day the cache was written, as `YYYY-MM-DD`.
+        date: String,
+        /// Currency codes mapped to their names.
+        units: Map<String, Value>,
+        /// Rates responses keyed by their reference currency.
+        rates: Map<String, Value>,
     }
 
-    pub fn is_up_to_date() -> bool {
-        let mut dir = path("");
-        dir.pop();
-        if let Err(e) = create_dir_all(dir) {
-            eprintln!("Failed to create cache directory: {}", e);
-        }
-        fn falliable() -> Option<bool> {
-            let current_time = SystemTime::now();
-            let current_time: DateTime<Utc> = current_time.into();
-            let current_time = current_time.naive_utc().date();
-            let cache_time = read_to_string(path("timestamp")).ok()?;
-            let cache_time: NaiveDate = cache_time.parse().ok()?;
-            Some(current_time.day() as i32 - cache_time.day() as i32 == 0)
-        }
-        falliable().unwrap_or(false)
+    fn path() -> std::io::Result<PathBuf> {
+        paths::cache_file(CURRENCIES).ok_or_else(paths::no_cache_dir)
+    }
+
+    pub fn today() -> String {
+        let current_time: DateTime<Utc> = SystemTime::now().into();
+        current_time.naive_utc().date().to_string()
     }
 
-    pub fn units() -> Option<String> {
-        read_to_string(path("units")).ok()
+    /// Reads the cache file, `Ok(None)` if there is none.
+    fn read() -> Result<Option<CacheFile>, String> {
+        let Some(data) = path().and_then(std::fs::read_to_string).ok() else {
+            return Ok(None);
+        };
+        serde_json::from_str(&data)
+            .map(Some)
+            .map_err(|error| error.to_string())
+    }
+
+    /// The cached currencies for `reference`, `Ok(None)` if they need to be
+    /// fetched and `Err` if the cache is corrupt.
+    pub fn get(reference: &str) -> Result<Option<CurrencyData>, String> {
+        get_from(reference, Some(&today()))
+    }
+
+    /// Like `get` but also uses rates from previous days, for when they
+    /// can't be fetched.
+    pub fn get_stale(reference: &str) -> Result<Option<CurrencyData>, String> {
+        get_from(reference, None)
+    }
+
+    fn get_from(reference: &str, day: Option<&str>) -> Result<Option<CurrencyData>, String> {
+        let Some(mut cache) = read()? else {
+            return Ok(None);
+        };
+        if day.is_some_and(|day| cache.date != day) {
+            return Ok(None);
+        }
+        let Some(rates) = cache.rates.remove(reference) else {
+            return Ok(None);
+        };
+        CurrencyData::new(cache.units, rates, reference, cache.date).map(Some)
     }
 
-    pub fn rates() -> Option<String> {
-        read_to_string(path("rates")).ok()
+    /// Stores the currency names and the rates response for `reference`,
+    /// keeping rates for other reference currencies if they're from today.
+    pub fn put(units: &Map<String, Value>, reference: &str, rates: &Value) -> std::io::Result<()> {
+        put_dated(units, reference, rates, today())
     }
 
-    pub fn put(units: &str, rates: &str) {
-        let current_time = SystemTime::now();
-        let current_time: DateTime<Utc> = current_time.into();
-        let current_time = current_time.naive_utc().date();
-        write(path("timestamp"), current_time.to_string()).unwrap();
-        write(path("units"), units).unwrap();
-        write(path("rates"), rates).unwrap();
+    /// Like `put` but as if it was `date`.
+    pub fn put_dated(
+        units: &Map<String, Value>,
+        reference: &str,
+        rates: &Value,
+        date: String,
+    ) -> std::io::Result<()> {
+        let mut cache = match read() {
+            Ok(Some(cache)) if cache.date == date => cache,
+            _ => CacheFile::default(),
+        };
+        cache.date = date;
+        cache.units = units.clone();
+        cache.rates.insert(reference.to_string(), rates.clone());
+        create_dir_all(paths::cache_dir().ok_or_else(paths::no_cache_dir)?)?;
+        paths::write_atomic(&path()?, serde_json::to_string(&cache)?)?;
         println!("Saved currency cache");
+        Ok(())
     }
+}
 
-    pub fn invalidate() {
-        let bad_time = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
-        write(path("timestamp"), bad_time.to_string()).unwrap();
-        println!("Invalidated currency cache");
+/// Currency names and their rates relative to the reference currency.
+#[derive(Debug)]
+pub struct CurrencyData {
+    units: serde_json::Map<String, serde_json::Value>,
+    rates: serde_json::Map<String, serde_json::Value>,
+    /// The day the rates were cached, as `YYYY-MM-DD`.
+    date: String,
+}
+
+pub type CurrencyResult = Result<CurrencyData, Box<dyn std::error::Error + Send + Sync>>;
+
+impl CurrencyData {
+    /// Checks that the names in `units` and the rates response for `reference`
+    /// fit together.  Currencies without a rate are dropped, but the reference
+    /// currency and the one it converts to by default must be there.
+    fn new(
+        mut units: serde_json::Map<String, serde_json::Value>,
+        mut response: serde_json::Value,
+        reference: &str,
+        date: String,
+    ) -> Result<Self, String> {
+        use serde_json::Value;
+        let rates = match response.get_mut(reference).map(Value::take) {
+            Some(Value::Object(rates)) => rates,
+            _ => return Err(format!("No rates for {reference}")),
+        };
+        if let Some((code, _)) = units.iter().find(|(_, name)| !name.is_string()) {
+            return Err(format!("Invalid name for {code}"));
+        }
+        units.retain(|code, _| rates.get(code).is_some_and(Value::is_number));
+        let fallback = if reference == "eur" { "usd" } else { "eur" };
+        for code in [reference, fallback] {
+            if !units.contains_key(code) {
+                return Err(format!("No rate for {code}"));
+            }
+        }
+        Ok(Self { units, rates, date })
     }
 }
 
-fn get_currencies(reference: &str) -> Result<(), Box<dyn std::error::Error>> {
-    use serde_json::*;
-    // We can't combine `if let` with another condition so we have to use
-    // `and_then` in order to have a single `else` branch.
-    let can_use_cached = if currency_cache::is_up_to_date() {
-        Some(())
+/// Gets the currency names and rates from the cache or the internet, with
+/// `refresh` always from the internet.  If they can't be fetched the cached
+/// rates from a previous day are used, except for `refresh` where the old
+/// rates are still installed.  This doesn't touch the currency tables so it
+/// can run on another thread, the result is installed with `add_currencies`.
+pub fn fetch_currencies(reference: &str, refresh: bool) -> CurrencyResult {
+    match currency_cache::get(reference) {
+        _ if refresh => {}
+        Ok(Some(data)) => {
+            println!("Using cached currencies");
+            return Ok(data);
+        }
+        Ok(None) => {}
+        // Fetching overwrites the cache, so this only happens once.
+        Err(error) => warning!("Corrupted currency cache: {error}"),
+    }
+    let fetched = download_currencies(reference);
+    if refresh {
+        fetched
     } else {
-        None
-    };
-    macro_rules! get {
-        ($what:ident, $url:expr,) => {
-            if let Some($what) = can_use_cached.and_then(|_| currency_cache::$what()) {
-                println!("Using cached currency {}", stringify!($what));
-                let res = from_str(&$what);
-                if res.is_err() {
-                    eprintln!("Corruped currency {} cache", stringify!($what));
-                    currency_cache::invalidate();
-                    return get_currencies(reference);
-                }
-                unsafe { res.unwrap_unchecked() }
-            } else {
-                let url = $url;
-                println!("Fetching currency {} from {}", stringify!($what), url);
-                let resp = get(url)?.text()?;
-                from_str(&resp)?
-            }
-        };
+        fetched.or_else(|error| stale_currencies(reference, error))
     }
-    let units: Map<String, Value> = get!(
-        units,
-        "https://cdn.jsdelivr.net/npm/@fawazahmed0/currency-api@latest/v1/currencies.min.json",
-    );
-    let mut rates: Map<String, Value> = get!(
-        rates,
-        format!(
-            "https://cdn.jsdelivr.net/npm/@fawazahmed0/currency-api@latest/v1/currencies/{}.min.json",
-            reference,
-        ),
-    );
-    if can_use_cached.is_none() {
-        currency_cache::put(&to_string(&units).unwrap(), &to_string(&rates).unwrap());
+}
+
+/// The cached currencies from any day if there are any, `error` otherwise.
+fn stale_currencies(
+    reference: &str,
+    error: Box<dyn std::error::Error + Send + Sync>,
+) -> CurrencyResult {
+    match currency_cache::get_stale(reference) {
+        Ok(Some(data)) => {
+            warning!(
+                "Failed to fetch currencies, using the rates from {} which may be outdated: {error}",
+                data.date
+            );
+            Ok(data)
+        }
+        _ => Err(error),
     }
-    let rates = match rates.remove(reference).unwrap() {
-        Value::Object(rates) => rates,
-        _ => unreachable!(),
+}
+
+/// Fetches the currency names and rates and caches them.
+fn download_currencies(reference: &str) -> CurrencyResult {
+    use serde_json::*;
+    let fetch =
+        |url: String| -> std::result::Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+            println!("Fetching currencies from {}", url);
+            Ok(from_str(&get(url)?.text()?)?)
+        };
+    let units = match fetch(
+        "https://cdn.jsdelivr.net/npm/@fawazahmed0/currency-api@latest/v1/currencies.min.json"
+            .to_string(),
+    )? {
+        Value::Object(units) => units,
+        _ => return Err("Invalid currency names".into()),
     };
+    let rates = fetch(format!(
+        "https://cdn.jsdelivr.net/npm/@fawazahmed0/currency-api@latest/v1/currencies/{}.min.json",
+        reference,
+    ))?;
+    let data = CurrencyData::new(
+        units.clone(),
+        rates.clone(),
+        reference,
+        currency_cache::today(),
+    )?;
+    if let Err(error) = currency_cache::put(&units, reference, &rates) {
+        warning!("Failed to save currency cache: {error}");
+    }
+    Ok(data)
+}
+
+/// Makes the fetched currencies available and adds their default conversions
+/// to `mapping`.  Currencies that are already known get the new rates and keep
+/// their keys.
+pub fn add_currencies(data: CurrencyData, default: &str, mapping: &mut HashMap<Unit, Unit>) {
+    let CurrencyData { units, rates, date } = data;
+    RATES_DATE.with_borrow_mut(|d| *d = Some(date));
+    // `CurrencyData::new` checked the names and that every currency has a rate.
     for (code, name_val) in units {
         let name = unsafe { name_val.as_str().unwrap_unchecked() };
         let rate = unsafe {
@@ -213,12 +345,20 @@ fn get_currencies(reference: &str) -> Result<(), Box<dyn std::error::Error>> {
                 .as_f64()
                 .unwrap_unchecked()
         };
+        let known = CURRENCY_CODES.with_borrow(|c| c.get(&code).copied());
         let key = CURRENCIES.with_borrow_mut(|c| {
-            c.insert(Currency {
+            let currency = Currency {
                 full_name: name.to_string(),
-                //currency_code: code.to_string(),
+                currency_code: code.to_string(),
                 rate,
-            })
+            };
+            match known.filter(|&key| c.contains_key(key)) {
+                Some(key) => {
+                    c[key] = currency;
+                    key
+                }
+                None => c.insert(currency),
+            }
         });
         CURRENCY_NAMES.with_borrow_mut(|c| {
             c.insert(name.to_string(), key);
@@ -229,10 +369,6 @@ fn get_currencies(reference: &str) -> Result<(), Box<dyn std::error::Error>> {
             c.insert(code.to_ascii_lowercase(), key);
         });
     }
-    Ok(())
-}
-
-fn add_currencties(default: &str, mapping: &mut HashMap<Unit, Unit>) {
     let default_key = CURRENCY_CODES.with_borrow(|c| c.get(default).copied().unwrap());
     DEFAULT.with_borrow_mut(|d| *d = default_key);
     CURRENCIES.with_borrow(|c| {
@@ -259,6 +395,10 @@ pub enum Unit {
     Volume(Volume),
     Temperature(Temperature),
     Speed(Speed),
+    Duration(Duration),
+    Pressure(Pressure),
+    Angle(Angle),
+    DataSize(DataSize),
     Currency(CurrencyKey),
 }
 
@@ -298,6 +438,30 @@ impl From<Speed> for Unit {
     }
 }
 
+impl From<Duration> for Unit {
+    fn from(d: Duration) -> Self {
+        Unit::Duration(d)
+    }
+}
+
+impl From<Pressure> for Unit {
+    fn from(p: Pressure) -> Self {
+        Unit::Pressure(p)
+    }
+}
+
+impl From<Angle> for Unit {
+    fn from(a: Angle) -> Self {
+        Unit::Angle(a)
+    }
+}
+
+impl From<DataSize> for Unit {
+    fn from(d: DataSize) -> Self {
+        Unit::DataSize(d)
+    }
+}
+
 impl From<CurrencyKey> for Unit {
     fn from(c: CurrencyKey) -> Self {
         Unit::Currency(c)
@@ -308,26 +472,18 @@ impl From<CurrencyKey> for Unit {
 // and mi/h; we would need a wrapper around the hashmap to return km/h as the
 // default for any unit but I think we can just ingore it as well.
 
-#[derive(Debug, Default)]
-pub struct UnitMappingResult {
-    pub mapping: HashMap<Unit, Unit>,
-    pub currency_error: Option<Box<dyn std::error::Error>>,
-}
-
-pub fn default_unit_mapping(default_currency: &str) -> UnitMappingResult {
-    let mut result = UnitMappingResult::default();
+/// Default conversions for the static units, currencies are added once they
+/// are loaded.
+pub fn default_unit_mapping() -> HashMap<Unit, Unit> {
+    let mut mapping = HashMap::new();
     for (l, r) in crate::static_units::PAIRS.into_iter().copied() {
-        result.mapping.insert(l, r);
-        result.mapping.insert(r, l);
+        mapping.insert(l, r);
+        mapping.insert(r, l);
     }
     for (from, to) in crate::static_units::ONE_WAY.into_iter().copied() {
-        result.mapping.insert(from, to);
+        mapping.insert(from, to);
     }
-    match get_currencies(default_currency) {
-        Ok(_) => add_currencties(default_currency, &mut result.mapping),
-        Err(e) => result.currency_error = Some(e),
-    }
-    result
+    mapping
 }
 
 impl Unit {
@@ -344,10 +500,120 @@ impl Unit {
     pub fn valid_conversion(&self, other: &Self) -> bool {
         discriminant(self) == discriminant(other)
     }
+
+    /// Name of what the unit measures, the category of `to_canonical_string`.
+    pub fn dimension(self) -> &'static str {
+        match self {
+            Unit::Distance(_) => "distance",
+            Unit::Mass(_) => "mass",
+            Unit::Area(_) => "area",
+            Unit::Volume(_) => "volume",
+            Unit::Temperature(_) => "temperature",
+            Unit::Speed(_) => "speed",
+            Unit::Duration(_) => "duration",
+            Unit::Pressure(_) => "pressure",
+            Unit::Angle(_) => "angle",
+            Unit::DataSize(_) => "data",
+            Unit::Currency(_) => "currency",
+        }
+    }
+
+    /// Unambiguous string representation of the unit in the form
+    /// `category:name[:prefix]`, for example `distance:m:kilo`, `speed:mi/h`,
+    /// or `currency:eur`.
+    pub fn to_canonical_string(self) -> String {
+        match self {
+            Unit::Distance(d) => format!("distance:{}", canonical_part(d.canonical_name())),
+            Unit::Mass(m) => format!("mass:{}", canonical_part(m.canonical_name())),
+            Unit::Area(a) => format!("area:{}", canonical_part(a.canonical_name())),
+            Unit::Volume(v) => format!("volume:{}", canonical_part(v.canonical_name())),
+            Unit::Temperature(t) => {
+                format!("temperature:{}", canonical_part(t.canonical_name()))
+            }
+            Unit::Speed(s) => format!(
+                "speed:{}/{}",
+                canonical_part(s.distance.canonical_name()),
+                canonical_part(s.time.canonical_name())
+            ),
+            Unit::Duration(d) => format!("duration:{}", canonical_part(d.canonical_name())),
+            Unit::Pressure(p) => format!("pressure:{}", canonical_part(p.canonical_name())),
+            Unit::Angle(a) => format!("angle:{}", canonical_part(a.canonical_name())),
+            Unit::DataSize(d) => format!("data:{}", canonical_part(d.canonical_name())),
+            Unit::Currency(c) => format!("currency:{}", c.code()),
+        }
+    }
+
+    /// Inverse of `to_canonical_string`.  Currencies can only be parsed once
+    /// they are loaded.
+    pub fn from_canonical_string(s: &str) -> Option<Self> {
+        let (category, rest) = s.split_once(':')?;
+        macro_rules! parse {
+            ($type:ident, $s:expr) => {{
+                let (name, prefix) = parse_canonical_part($s)?;
+                $type::from_canonical_name(name, prefix)
+            }};
+        }
+        match category {
+            "distance" => parse!(Distance, rest).map(Unit::Distance),
+            "mass" => parse!(Mass, rest).map(Unit::Mass),
+            "area" => parse!(Area, rest).map(Unit::Area),
+            "volume" => parse!(Volume, rest).map(Unit::Volume),
+            "temperature" => parse!(Temperature, rest).map(Unit::Temperature),
+            "speed" => {
+                let (distance, time) = rest.split_once('/')?;
+                Some(Unit::Speed(Speed {
+                    distance: parse!(Distance, distance)?,
+                    time: parse!(SpeedTime, time)?,
+                }))
+            }
+            "duration" => parse!(Duration, rest).map(Unit::Duration),
+            "pressure" => parse!(Pressure, rest).map(Unit::Pressure),
+            "angle" => parse!(Angle, rest).map(Unit::Angle),
+            "data" => parse!(DataSize, rest).map(Unit::DataSize),
+            "currency" => CURRENCY_CODES
+                .with_borrow(|c| c.get(rest).copied())
+                .map(Unit::Currency),
+            _ => None,
+        }
+    }
+}
+
+fn canonical_part((name, prefix): (&str, SiPrefix)) -> String {
+    if prefix == SiPrefix::None {
+        name.to_string()
+    } else {
+        format!("{}:{}", name, prefix.name())
+    }
+}
+
+fn parse_canonical_part(s: &str) -> Option<(&str, SiPrefix)> {
+    match s.split_once(':') {
+        // An explicit empty prefix is not canonical
+        Some((name, prefix)) => Some((
+            name,
+            SiPrefix::from_name(prefix).filter(|&p| p != SiPrefix::None)?,
+        )),
+        None => Some((s, SiPrefix::None)),
+    }
+}
+
+impl Serialize for Unit {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_canonical_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Unit {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Unit::from_canonical_string(&s)
+            .ok_or_else(|| D::Error::custom(format!("invalid unit: {s}")))
+    }
 }
 
-pub fn convert(value: f64, from: Unit, to: Unit) -> f64 {
-    match (from, to) {
+/// Converts `value` from `from` to `to`, which must have the same dimension.
+pub fn convert(value: f64, from: Unit, to: Unit) -> Result<f64, ClassificationError> {
+    Ok(match (from, to) {
         (Unit::Currency(from), Unit::Currency(to)) => convert_currency(value, from, to),
         (Unit::Distance(from), Unit::Distance(to)) => from.convert(value, to),
         (Unit::Mass(from), Unit::Mass(to)) => from.convert(value, to),
@@ -355,11 +621,12 @@ pub fn convert(value: f64, from: Unit, to: Unit) -> f64 {
         (Unit::Volume(from), Unit::Volume(to)) => from.convert(value, to),
         (Unit::Temperature(from), Unit::Temperature(to)) => from.convert(value, to),
         (Unit::Speed(from), Unit::Speed(to)) => from.convert(value, to),
-        _ => {
-            eprintln!("Invalid or conversion: {:?} -> {:?}", from, to);
-            0.0
-        }
-    }
+        (Unit::Duration(from), Unit::Duration(to)) => from.convert(value, to),
+        (Unit::Pressure(from), Unit::Pressure(to)) => from.convert(value, to),
+        (Unit::Angle(from), Unit::Angle(to)) => from.convert(value, to),
+        (Unit::DataSize(from), Unit::DataSize(to)) => from.convert(value, to),
+        _ => return Err(ClassificationError::InvalidConversion(from, to)),
+    })
 }
 
 impl std::fmt::Display for Unit {
@@ -371,7 +638,438 @@ impl std::fmt::Display for Unit {
             Unit::Volume(v) => write!(f, "{}", v),
             Unit::Temperature(t) => write!(f, "{}", t),
             Unit::Speed(s) => write!(f, "{}", s),
+            Unit::Duration(d) => write!(f, "{}", d),
+            Unit::Pressure(p) => write!(f, "{}", p),
+            Unit::Angle(a) => write!(f, "{}", a),
+            Unit::DataSize(d) => write!(f, "{}", d),
             Unit::Currency(c) => write!(f, "{}", c),
         }
     }
 }
+
+/// Entry text that lists the known units, optionally followed by a
+/// dimension.
+pub const UNITS_COMMAND: &str = "units";
+
+/// How many currency codes are listed without `units currency`.
+const LISTED_CURRENCIES: usize = 10;
+
+/// A row of the `units` listing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnitListing {
+    pub dimension: &'static str,
+    pub names: String,
+}
+
+impl UnitListing {
+    fn new(dimension: &'static str, names: String) -> Self {
+        Self { dimension, names }
+    }
+}
+
+impl Render for UnitListing {
+    fn markup(&self, _search: &str, _cache: &DesktopEntryCache) -> String {
+        format!(
+            "<span color=\"{}\">{}</span> {}",
+            colors::list_match_name(),
+            self.dimension,
+            escape_markup(&self.names)
+        )
+    }
+
+    fn plain_text(&self, _cache: &DesktopEntryCache) -> String {
+        self.names.clone()
+    }
+
+    fn icon(&self, _cache: &DesktopEntryCache) -> Option<Svg> {
+        Some(Svg::load(resources::CONVERSION_PATH_ICON))
+    }
+}
+
+/// The listing for the `units [dimension]` query, `None` if `query` is not
+/// such a query.
+pub fn list_units(query: &str) -> Option<Vec<UnitListing>> {
+    let rest = query.strip_prefix(UNITS_COMMAND)?;
+    let dimension = match rest.strip_prefix(' ') {
+        Some(dimension) => Some(dimension.trim().to_lowercase()),
+        None if rest.is_empty() => None,
+        None => return None,
+    };
+    if dimension.as_deref() == Some("currency") {
+        return Some(list_currencies(None));
+    }
+    let mut listing = Vec::new();
+    for (name, group) in names_by_dimension() {
+        if dimension.as_ref().is_some_and(|d| d != name) {
+            continue;
+        }
+        for names in group {
+            let mut row = names.names.join(", ");
+            if matches!(names.unit, NamedUnit::Prefixed(_)) {
+                row.push_str(" (SI prefixes)");
+            }
+            listing.push(UnitListing::new(name, row));
+        }
+        if name == "speed" {
+            listing.push(UnitListing::new(name, "<distance>/h, /min, /s".to_string()));
+        }
+    }
+    if dimension.is_none() {
+        listing.extend(list_currencies(Some(LISTED_CURRENCIES)));
+    }
+    (!listing.is_empty()).then_some(listing)
+}
+
+/// Currency rows sorted by code, a single summary row if limited to `limit`
+/// codes.
+fn list_currencies(limit: Option<usize>) -> Vec<UnitListing> {
+    let mut currencies = CURRENCIES.with_borrow(|c| {
+        c.values()
+            .map(|c| (c.currency_code.clone(), c.full_name.clone()))
+            .collect::<Vec<_>>()
+    });
+    if currencies.is_empty() {
+        return vec![UnitListing::new("currency", "not loaded yet".to_string())];
+    }
+    currencies.sort();
+    match limit {
+        Some(limit) => {
+            let count = currencies.len();
+            let mut row = currencies
+                .into_iter()
+                .take(limit)
+                .map(|(code, _)| code)
+                .collect::<Vec<_>>()
+                .join(", ");
+            if count > limit {
+                row.push_str(&format!(", … ({count} total, type `units currency`)"));
+            }
+            vec![UnitListing::new("currency", row)]
+        }
+        None => currencies
+            .into_iter()
+            .map(|(code, name)| {
+                let row = if name.is_empty() {
+                    code
+                } else {
+                    format!("{code} — {name}")
+                };
+                UnitListing::new("currency", row)
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs `f` with an empty cache directory of its own.
+    fn with_cache_dir(f: impl FnOnce(&std::path::Path)) {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        let call = CALLS.fetch_add(1, Ordering::Relaxed);
+        let dir =
+            std::env::temp_dir().join(format!("launcher-cache-{}-{call}", std::process::id()));
+        crate::paths::with_home(None, || {
+            std::env::set_var("LAUNCHER_CACHE_DIR", &dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            f(&dir);
+            std::fs::remove_dir_all(&dir).unwrap();
+        });
+    }
+
+    fn json(s: &str) -> serde_json::Value {
+        serde_json::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn currency_cache_per_reference() {
+        with_cache_dir(|dir| {
+            let units = json(r#"{"eur":"Euro","usd":"US Dollar"}"#);
+            let units = units.as_object().unwrap();
+            let eur = json(r#"{"date":"2024-01-01","eur":{"eur":1,"usd":1.1}}"#);
+            let usd = json(r#"{"date":"2024-01-01","usd":{"eur":0.9,"usd":1}}"#);
+            let rate = |reference: &str, code: &str| {
+                let data = currency_cache::get(reference).unwrap()?;
+                data.rates.get(code)?.as_f64()
+            };
+            // First run with EUR as the default currency
+            currency_cache::put(units, "eur", &eur).unwrap();
+            assert_eq!(rate("eur", "usd"), Some(1.1));
+            // The default currency changed to USD between runs
+            assert_eq!(rate("usd", "eur"), None);
+            currency_cache::put(units, "usd", &usd).unwrap();
+            assert_eq!(rate("usd", "eur"), Some(0.9));
+            assert_eq!(rate("eur", "usd"), Some(1.1));
+            // Rates from another day are dropped
+            let file = dir.join(crate::paths::CURRENCIES);
+            let old = std::fs::read_to_string(&file).unwrap();
+            let today = old.split('"').nth(3).unwrap().to_string();
+            std::fs::write(&file, old.replace(&today, "2024-01-01")).unwrap();
+            assert_eq!(rate("usd", "eur"), None);
+            currency_cache::put(units, "usd", &usd).unwrap();
+            assert_eq!(rate("eur", "usd"), None);
+            assert_eq!(rate("usd", "eur"), Some(0.9));
+            assert!(!crate::paths::temp_path(&file).exists());
+        });
+    }
+
+    #[test]
+    fn stale_currency_cache() {
+        with_cache_dir(|_| {
+            let offline = || -> Box<dyn std::error::Error + Send + Sync> { "offline".into() };
+            // Nothing to fall back to
+            let error = stale_currencies("eur", offline()).unwrap_err();
+            assert_eq!(error.to_string(), "offline");
+            let units = json(r#"{"eur":"Euro","usd":"US Dollar"}"#);
+            let rates = json(r#"{"eur":{"eur":1,"usd":1.1}}"#);
+            let date = "2024-01-01".to_string();
+            currency_cache::put_dated(units.as_object().unwrap(), "eur", &rates, date).unwrap();
+            assert!(currency_cache::get("eur").unwrap().is_none());
+            // Yesterday's rates are better than none
+            let data = stale_currencies("eur", offline()).unwrap();
+            assert_eq!(data.date, "2024-01-01");
+            assert_eq!(data.rates["usd"].as_f64(), Some(1.1));
+            // But only for the same reference currency
+            assert!(stale_currencies("usd", offline()).is_err());
+        });
+    }
+
+    #[test]
+    fn corrupt_currency_cache() {
+        with_cache_dir(|dir| {
+            let units = json(r#"{"eur":"Euro","usd":"US Dollar","xyz":"Unknown"}"#);
+            let units = units.as_object().unwrap();
+            let file = dir.join(crate::paths::CURRENCIES);
+            currency_cache::put(units, "eur", &json(r#"{"eur":{"eur":1,"usd":1.1}}"#)).unwrap();
+            // Currencies without a rate are dropped
+            let data = currency_cache::get("eur").unwrap().unwrap();
+            assert_eq!(data.units.len(), 2);
+            assert!(!data.units.contains_key("xyz"));
+            let valid = std::fs::read_to_string(&file).unwrap();
+            let corrupt = [
+                valid[..valid.len() / 2].to_string(),
+                valid.replace(r#""usd":1.1"#, r#""usd":"1.1""#),
+                valid.replace(r#""Euro""#, "1"),
+                valid.replace(r#""eur":{"eur":1"#, r#""eur":{"gbp":1"#),
+                valid.replace(r#""rates":{"eur":{"eur":{"#, r#""rates":{"eur":{"gbp":{"#),
+            ];
+            for data in corrupt {
+                std::fs::write(&file, &data).unwrap();
+                assert!(currency_cache::get("eur").is_err(), "{data}");
+            }
+            // Missing rates for the reference are not an error, they're just
+            // not cached yet.
+            std::fs::write(
+                &file,
+                valid.replace(r#""rates":{"eur""#, r#""rates":{"gbp""#),
+            )
+            .unwrap();
+            assert!(matches!(currency_cache::get("eur"), Ok(None)));
+            // Files from before the cache was combined are ignored
+            std::fs::remove_file(&file).unwrap();
+            std::fs::write(dir.join("rates"), r#"{"eur":{"usd":1.1}}"#).unwrap();
+            std::fs::write(dir.join("units"), r#"{"eur":"Euro"}"#).unwrap();
+            assert!(matches!(currency_cache::get("eur"), Ok(None)));
+        });
+    }
+
+    #[test]
+    fn refreshed_currencies() {
+        let units = json(r#"{"eur":"Euro","usd":"US Dollar"}"#);
+        let units = units.as_object().unwrap();
+        let data = |usd: f64, date: &str| {
+            let rates = json(&format!(r#"{{"eur":{{"eur":1,"usd":{usd}}}}}"#));
+            CurrencyData::new(units.clone(), rates, "eur", date.to_string()).unwrap()
+        };
+        let mut mapping = HashMap::new();
+        assert_eq!(currency_date(), None);
+        add_currencies(data(1.5, "2024-05-13"), "eur", &mut mapping);
+        let usd = currency("usd").unwrap();
+        let eur = currency("eur").unwrap();
+        assert_eq!(convert_currency(10.0, eur, usd), 15.0);
+        assert_eq!(currency_date().as_deref(), Some("2024-05-13"));
+        // Refreshed rates replace the old ones, the keys stay valid
+        add_currencies(data(1.25, "2024-05-14"), "eur", &mut mapping);
+        assert_eq!(currency("usd"), Some(usd));
+        assert_eq!(CURRENCIES.with_borrow(SlotMap::len), 2);
+        assert_eq!(convert_currency(10.0, eur, usd), 12.5);
+        assert_eq!(currency_date().as_deref(), Some("2024-05-14"));
+        assert_eq!(mapping.len(), 2);
+    }
+
+    fn static_units() -> Vec<Unit> {
+        use self::{
+            Angle::*, Area::*, DataSize::*, Distance::*, Duration::*, Mass::*, Pressure::*,
+            Temperature::*, Volume::*,
+        };
+        let mut units = Vec::new();
+        let mut distances = vec![Inch, Feet, Yard, Mile, NauticalMile];
+        let mut times = vec![SpeedTime::Minute, SpeedTime::Hour];
+        for prefix in SiPrefix::ALL {
+            distances.push(Meter(prefix));
+            times.push(SpeedTime::Second(prefix));
+            units.push(Unit::Mass(Gram(prefix)));
+            units.push(Unit::Area(SquareMeter(prefix)));
+            units.push(Unit::Volume(Liter(prefix)));
+            units.push(Unit::Duration(Second(prefix)));
+            units.push(Unit::Pressure(Pascal(prefix)));
+        }
+        units.extend([Minute, Hour, Day, Week, Year].map(Unit::Duration));
+        units.extend([Bar, Psi, Atm, Torr].map(Unit::Pressure));
+        units.extend([Degree, Radian, Gradian, Turn].map(Unit::Angle));
+        units.extend([Ounce, Pound, Stone].map(Unit::Mass));
+        units.extend(
+            [
+                SquareInch, SquareFeet, SquareYard, SquareMile, Hectare, Acre,
+            ]
+            .map(Unit::Area),
+        );
+        units
+            .extend([Gallon, Quart, Pint, Cup, FluidOunce, Tablespoon, Teaspoon].map(Unit::Volume));
+        units.extend([Celsius, Fahrenheit, Kelvin].map(Unit::Temperature));
+        units.extend(
+            [
+                Byte, Kilobyte, Megabyte, Gigabyte, Terabyte, Kibibyte, Mebibyte, Gibibyte,
+                Tebibyte,
+            ]
+            .map(Unit::DataSize),
+        );
+        for &distance in distances.iter() {
+            for &time in times.iter() {
+                units.push(Unit::Speed(Speed { distance, time }));
+            }
+        }
+        units.extend(distances.into_iter().map(Unit::Distance));
+        units
+    }
+
+    fn add_currency(code: &str, full_name: &str) -> Unit {
+        let key = CURRENCIES.with_borrow_mut(|c| {
+            c.insert(Currency {
+                full_name: full_name.to_string(),
+                currency_code: code.to_string(),
+                rate: 1.0,
+            })
+        });
+        CURRENCY_CODES.with_borrow_mut(|c| c.insert(code.to_string(), key));
+        Unit::Currency(key)
+    }
+
+    #[test]
+    fn canonical_round_trip() {
+        let mut units = static_units();
+        units.push(add_currency("eur", "Euro"));
+        units.push(add_currency("usd", "US Dollar"));
+        units.push(add_currency("1inch", "1inch Network"));
+        let mut seen = HashSet::new();
+        for unit in units {
+            let s = unit.to_canonical_string();
+            assert!(seen.insert(s.clone()), "duplicate canonical string {s}");
+            assert_eq!(Unit::from_canonical_string(&s), Some(unit), "{s}");
+        }
+    }
+
+    #[test]
+    fn canonical_strings() {
+        let ton = Unit::Mass(Mass::Gram(SiPrefix::Mega));
+        assert_eq!(ton.to_string(), "ton");
+        assert_eq!(ton.to_canonical_string(), "mass:g:mega");
+        assert_eq!(
+            Unit::Speed(Speed::kph()).to_canonical_string(),
+            "speed:m:kilo/h"
+        );
+        assert_eq!(
+            Unit::Distance(Distance::Mile).to_canonical_string(),
+            "distance:mi"
+        );
+        for invalid in [
+            "",
+            "m",
+            "distance",
+            "distance:m:",
+            "distance:mi:kilo",
+            "distance:m:k",
+            "mass:m",
+            "speed:m",
+            "currency:eur",
+        ] {
+            assert_eq!(Unit::from_canonical_string(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn serde() {
+        let units = vec![
+            Unit::Distance(Distance::Meter(SiPrefix::Kilo)),
+            Unit::Temperature(Temperature::Kelvin),
+            add_currency("eur", "Euro"),
+        ];
+        let serialized = ron::to_string(&units).unwrap();
+        assert_eq!(
+            serialized,
+            r#"["distance:m:kilo","temperature:k","currency:eur"]"#
+        );
+        assert_eq!(ron::from_str::<Vec<Unit>>(&serialized).unwrap(), units);
+        assert!(ron::from_str::<Unit>(r#""distance:parsec""#).is_err());
+    }
+
+    #[test]
+    fn unit_listing() {
+        assert_eq!(list_units("unit"), None);
+        assert_eq!(list_units("unitsx"), None);
+        assert_eq!(list_units("units color"), None);
+        let all = list_units("units").unwrap();
+        assert!(all
+            .iter()
+            .any(|row| row.names == "m, meter, meters (SI prefixes)"));
+        assert_eq!(all.last().unwrap().dimension, "currency");
+        let volume = list_units("units Volume").unwrap();
+        assert!(volume.iter().all(|row| row.dimension == "volume"));
+        assert!(volume.iter().any(|row| row.names == "cup, cups"));
+        let speed = list_units("units speed").unwrap();
+        assert_eq!(speed.last().unwrap().names, "<distance>/h, /min, /s");
+
+        for i in 0..12 {
+            add_currency(&format!("c{i:02}"), "");
+        }
+        add_currency("eur", "Euro");
+        let summary = list_units("units").unwrap().pop().unwrap();
+        assert!(summary.names.starts_with("c00, c01,"), "{}", summary.names);
+        assert!(summary.names.ends_with("(13 total, type `units currency`)"));
+        let currencies = list_units("units currency").unwrap();
+        assert_eq!(currencies.len(), 13);
+        assert_eq!(currencies[0].names, "c00");
+        assert_eq!(currencies[12].names, "eur — Euro");
+    }
+
+    #[test]
+    fn mismatched_conversions() {
+        let mut units = static_units();
+        units.push(add_currency("usd", "US Dollar"));
+        for &from in units.iter() {
+            for &to in units.iter() {
+                let result = convert(1.0, from, to);
+                if from.valid_conversion(&to) {
+                    assert!(result.is_ok(), "{from:?} -> {to:?}");
+                } else {
+                    assert!(
+                        matches!(result, Err(ClassificationError::InvalidConversion(a, b)) if a == from && b == to),
+                        "{from:?} -> {to:?}"
+                    );
+                }
+            }
+        }
+        let usd = add_currency("usd", "US Dollar");
+        let kg = Unit::Mass(Mass::Gram(SiPrefix::Kilo));
+        assert_eq!(
+            convert(5.0, usd, kg).unwrap_err().to_string(),
+            "Can't convert US Dollar (currency) to kg (mass)"
+        );
+        assert_eq!(
+            convert(5.0, kg, usd).unwrap_err().to_string(),
+            "Can't convert kg (mass) to US Dollar (currency)"
+        );
+    }
+}
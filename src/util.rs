@@ -1,16 +1,88 @@
+use crate::warnings::warning;
 use libc::{
-    _exit, c_char, close, dup2, execl, fork, open, setsid, waitpid, O_RDWR, STDERR_FILENO,
+    _exit, c_char, close, dup2, execve, fork, open, setsid, waitpid, O_RDWR, STDERR_FILENO,
     STDIN_FILENO, STDOUT_FILENO,
 };
 use std::{
     ffi::CString,
     io::Write,
+    os::unix::ffi::OsStrExt,
     process::{Command, Stdio},
 };
 
+/// Quotes `s` as a single shell word.
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// The command line `launch_orphan` passes to `bash -c` for `command`.
+pub fn shell_command(command: &str) -> String {
+    format!("bash -c {}", shell_quote(command))
+}
+
+/// `shell_command` with the variables `launch_orphan_with_env` adds to the
+/// environment.
+pub fn shell_command_with_env(command: &str, environment: &[(String, String)]) -> String {
+    if environment.is_empty() {
+        return shell_command(command);
+    }
+    let assignments: Vec<String> = environment
+        .iter()
+        .map(|(name, value)| format!("{name}={}", shell_quote(value)))
+        .collect();
+    format!("env {} {}", assignments.join(" "), shell_command(command))
+}
+
+/// Our environment with the given variables added or replaced, in the format
+/// expected by `execve`.
+fn child_environment(environment: &[(String, String)]) -> Vec<CString> {
+    let mut result: Vec<CString> = std::env::vars_os()
+        .filter(|(name, _)| {
+            !environment
+                .iter()
+                .any(|(n, _)| name.as_bytes() == n.as_bytes())
+        })
+        .filter_map(|(name, value)| {
+            let mut entry = name.as_bytes().to_vec();
+            entry.push(b'=');
+            entry.extend_from_slice(value.as_bytes());
+            CString::new(entry).ok()
+        })
+        .collect();
+    result.extend(
+        environment
+            .iter()
+            .filter_map(|(name, value)| CString::new(format!("{name}={value}")).ok()),
+    );
+    result
+}
+
 /// Launches and orphans the given command, making it a child of init and not
 /// ourself. Any errors are ignored.
 pub fn launch_orphan(command: &str) {
+    launch_orphan_with_env(command, &[]);
+}
+
+/// `launch_orphan` with additional environment variables for the command.
+pub fn launch_orphan_with_env(command: &str, environment: &[(String, String)]) {
+    // Everything the child needs is prepared before forking since allocating
+    // after forking a multi-threaded process is not safe.
+    let comm = CString::new(shell_command(command)).unwrap();
+    let path = CString::new("/bin/bash").unwrap();
+    let arg0 = CString::new("bash").unwrap();
+    let arg1 = CString::new("-c").unwrap();
+    let argv = [
+        arg0.as_ptr(),
+        arg1.as_ptr(),
+        comm.as_ptr(),
+        std::ptr::null::<c_char>(),
+    ];
+    let envp_strings = child_environment(environment);
+    let envp: Vec<*const c_char> = envp_strings
+        .iter()
+        .map(|entry| entry.as_ptr())
+        .chain(std::iter::once(std::ptr::null()))
+        .collect();
     unsafe {
         let pid = fork();
         let null = CString::new("/dev/null").unwrap();
@@ -28,17 +100,7 @@ pub fn launch_orphan(command: &str) {
                 _exit(1)
             }
             if pid == 0 {
-                let comm = CString::new(format!("bash -c '{}'", command)).unwrap();
-                let path = CString::new("/bin/bash").unwrap();
-                let arg0 = CString::new("bash").unwrap();
-                let arg1 = CString::new("-c").unwrap();
-                execl(
-                    path.as_ptr(),
-                    arg0.as_ptr(),
-                    arg1.as_ptr(),
-                    comm.as_ptr(),
-                    std::ptr::null::<c_char>(),
-                );
+                execve(path.as_ptr(), argv.as_ptr(), envp.as_ptr());
                 close(null);
                 _exit(1);
             }
@@ -64,7 +126,7 @@ pub fn copy(text: &str) {
         Ok(())
     }
     if let Err(error) = innner(text) {
-        eprintln!("Failed to copy to clipboard: {}", error);
+        warning!("Failed to copy to clipboard: {}", error);
     }
 }
 
@@ -80,7 +142,7 @@ pub fn paste() -> String {
     {
         Ok(output) => output,
         Err(error) => {
-            eprintln!("Failed to read clipboard: {}", error);
+            warning!("Failed to read clipboard: {}", error);
             return String::new();
         }
     };
@@ -0,0 +1,114 @@
+//! Recent warnings, so problems can be seen without a terminal by typing
+//! `:log`.
+//!
+//! Warnings can come from any thread, they are kept in a bounded buffer behind
+//! a mutex.
+
+use crate::{
+    cache::DesktopEntryCache,
+    list_view::Render,
+    res::{resources, Svg},
+    text::escape_markup,
+    ui::colors,
+};
+use chrono::{DateTime, Local};
+use std::{collections::VecDeque, sync::Mutex};
+
+/// Entry text that shows the warnings.
+pub const LOG_COMMAND: &str = ":log";
+
+/// How many warnings are kept.
+const CAPACITY: usize = 50;
+
+static WARNINGS: Mutex<VecDeque<Warning>> = Mutex::new(VecDeque::new());
+
+/// Prints a warning to stderr and keeps it for `:log`.
+#[macro_export]
+macro_rules! warning {
+    ($($arg:tt)*) => {{
+        let message = format!($($arg)*);
+        eprintln!("{message}");
+        $crate::warnings::push(message);
+    }};
+}
+pub use crate::warning;
+
+#[derive(Clone, Debug)]
+pub struct Warning {
+    pub time: DateTime<Local>,
+    pub message: String,
+}
+
+pub fn push(message: String) {
+    let mut warnings = WARNINGS.lock().unwrap_or_else(|poison| poison.into_inner());
+    if warnings.len() == CAPACITY {
+        warnings.pop_front();
+    }
+    warnings.push_back(Warning {
+        time: Local::now(),
+        message,
+    });
+}
+
+/// The kept warnings, newest first.
+pub fn recent() -> Vec<Warning> {
+    let warnings = WARNINGS.lock().unwrap_or_else(|poison| poison.into_inner());
+    warnings.iter().rev().cloned().collect()
+}
+
+impl Render for Warning {
+    fn markup(&self, _search: &str, _cache: &DesktopEntryCache) -> String {
+        format!(
+            "<span color=\"{}\">{}</span> {}",
+            colors::list_match_name(),
+            self.time.format("%H:%M:%S"),
+            escape_markup(&self.message)
+        )
+    }
+
+    fn plain_text(&self, _cache: &DesktopEntryCache) -> String {
+        self.message.clone()
+    }
+
+    fn icon(&self, _cache: &DesktopEntryCache) -> Option<Svg> {
+        Some(Svg::load(resources::WARNING_ICON))
+    }
+
+    fn tooltip(&self, _cache: &DesktopEntryCache) -> Option<String> {
+        Some(escape_markup(&self.message).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_buffer() {
+        let threads: Vec<_> = (0..4)
+            .map(|thread| {
+                std::thread::spawn(move || {
+                    for idx in 0..CAPACITY {
+                        warning!("thread {thread} warning {idx}");
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        warning!("last <warning> & more");
+        let recent = recent();
+        assert_eq!(recent.len(), CAPACITY);
+        assert!(recent.windows(2).all(|w| w[0].time >= w[1].time));
+        // Other tests may warn at the same time
+        let last = recent
+            .iter()
+            .find(|warning| warning.message == "last <warning> & more")
+            .unwrap();
+        let cache = DesktopEntryCache::from_file_names(&[]);
+        let markup = last.markup("", &cache);
+        let (_, text, _) = pango::parse_markup(&markup, '\0').unwrap();
+        assert!(text.ends_with(" last <warning> & more"));
+    }
+}
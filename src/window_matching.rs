@@ -0,0 +1,145 @@
+//! Mapping open windows to desktop entries by their `WM_CLASS`.
+//!
+//! Applications don't reliably set `StartupWMClass`, so this tries a few
+//! heuristics after it.  Windows that still end up with the wrong entry can be
+//! fixed with the `window_class_overrides` config table.
+
+use crate::cache::DesktopEntryCache;
+use std::collections::HashMap;
+
+/// The `WM_CLASS` property of a window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowClass {
+    pub instance: String,
+    pub class: String,
+}
+
+impl WindowClass {
+    pub fn matches(&self, name: &str) -> bool {
+        !name.is_empty()
+            && (name.eq_ignore_ascii_case(&self.class) || name.eq_ignore_ascii_case(&self.instance))
+    }
+}
+
+fn stem(file_name: &str) -> &str {
+    file_name.strip_suffix(".desktop").unwrap_or(file_name)
+}
+
+/// Finds the desktop entry a window belongs to, `overrides` maps a window
+/// class or instance to a desktop file name.
+pub fn match_window(
+    window: &WindowClass,
+    cache: &DesktopEntryCache,
+    overrides: &HashMap<String, String>,
+) -> Option<usize> {
+    if let Some(file_name) = overrides
+        .get(&window.class)
+        .or_else(|| overrides.get(&window.instance))
+    {
+        return cache.find_file(file_name).or_else(|| {
+            if file_name.ends_with(".desktop") {
+                None
+            } else {
+                cache.find_file(&format!("{file_name}.desktop"))
+            }
+        });
+    }
+    let entries = cache.entries();
+    let by_startup_wm_class = || {
+        entries.iter().position(|entry| {
+            entry
+                .startup_wm_class
+                .as_deref()
+                .is_some_and(|class| window.matches(class))
+        })
+    };
+    // `firefox.desktop` or `org.gnome.Nautilus.desktop`
+    let by_file_name = || {
+        entries
+            .iter()
+            .position(|entry| window.matches(stem(&entry.file_name)))
+    };
+    // `org.gnome.Nautilus.desktop` for a window with class `Nautilus`
+    let by_file_name_suffix = || {
+        entries.iter().position(|entry| {
+            stem(&entry.file_name)
+                .rsplit_once('.')
+                .is_some_and(|(_, suffix)| window.matches(suffix))
+        })
+    };
+    let by_executable = || {
+        entries.iter().position(|entry| {
+            entry
+                .exec
+                .split_whitespace()
+                .next()
+                .and_then(|program| program.rsplit('/').next())
+                .is_some_and(|program| window.matches(program))
+        })
+    };
+    by_startup_wm_class()
+        .or_else(by_file_name)
+        .or_else(by_file_name_suffix)
+        .or_else(by_executable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(instance: &str, class: &str) -> WindowClass {
+        WindowClass {
+            instance: instance.to_string(),
+            class: class.to_string(),
+        }
+    }
+
+    #[test]
+    fn heuristics() {
+        let mut cache = DesktopEntryCache::from_file_names(&[
+            "firefox.desktop",
+            "org.gnome.Nautilus.desktop",
+            "code.desktop",
+            "terminal.desktop",
+        ]);
+        cache.set_startup_wm_class("code.desktop", "Code");
+        cache.set_exec("terminal.desktop", "/usr/bin/alacritty -e tmux");
+        let find = |instance, class| {
+            match_window(&window(instance, class), &cache, &HashMap::new())
+                .map(|id| cache.get_entry(id).file_name.as_str())
+        };
+        assert_eq!(find("code", "Code"), Some("code.desktop"));
+        assert_eq!(find("Navigator", "firefox"), Some("firefox.desktop"));
+        assert_eq!(
+            find("org.gnome.Nautilus", "org.gnome.Nautilus"),
+            Some("org.gnome.Nautilus.desktop")
+        );
+        assert_eq!(
+            find("nautilus", "Nautilus"),
+            Some("org.gnome.Nautilus.desktop")
+        );
+        assert_eq!(find("Alacritty", "Alacritty"), Some("terminal.desktop"));
+        assert_eq!(find("steam", "Steam"), None);
+    }
+
+    #[test]
+    fn overrides() {
+        let cache = DesktopEntryCache::from_file_names(&["firefox.desktop", "navigator.desktop"]);
+        let find = |overrides: &[(&str, &str)]| {
+            let overrides = overrides
+                .iter()
+                .map(|(class, file_name)| (class.to_string(), file_name.to_string()))
+                .collect();
+            match_window(&window("Navigator", "firefox-esr"), &cache, &overrides)
+                .map(|id| cache.get_entry(id).file_name.as_str())
+        };
+        assert_eq!(find(&[]), Some("navigator.desktop"));
+        assert_eq!(
+            find(&[("Navigator", "firefox.desktop")]),
+            Some("firefox.desktop")
+        );
+        assert_eq!(find(&[("firefox-esr", "firefox")]), Some("firefox.desktop"));
+        // An override for a missing entry doesn't fall back to the heuristics
+        assert_eq!(find(&[("firefox-esr", "missing.desktop")]), None);
+    }
+}
@@ -1,4 +1,87 @@
-use super::{window::ToXWindow, *};
+use super::{
+    window::{PropertyData, ToXWindow},
+    xinput2::XInput2,
+    *,
+};
+use libc::{poll, pollfd, POLLIN};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+static ERROR_OCCURRED: AtomicBool = AtomicBool::new(false);
+
+unsafe extern "C" fn record_error(_: XDisplay, _: *mut XErrorEvent) -> c_int {
+    ERROR_OCCURRED.store(true, Ordering::Relaxed);
+    0
+}
+
+/// Why the X server could not be reached, displayed as a hint on what to do
+/// about it.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ConnectError {
+    /// No display name and `DISPLAY` is not set.
+    NoDisplay,
+    /// Only `WAYLAND_DISPLAY` is set, so XWayland isn't running.
+    WaylandOnly(String),
+    /// `XAUTHORITY` names a file that doesn't exist, so the server will most
+    /// likely refuse us.
+    MissingXauthority { display: String, path: PathBuf },
+    /// The server isn't running or refused the connection.
+    Refused(String),
+}
+
+impl ConnectError {
+    /// Guesses the reason from the environment, `var` reads environment
+    /// variables and `exists` checks files.
+    fn diagnose(
+        name: Option<&str>,
+        var: impl Fn(&str) -> Option<String>,
+        exists: impl Fn(&Path) -> bool,
+    ) -> Self {
+        let var = |name| var(name).filter(|value| !value.is_empty());
+        let Some(display) = name.map(str::to_string).or_else(|| var("DISPLAY")) else {
+            return match var("WAYLAND_DISPLAY") {
+                Some(wayland) => Self::WaylandOnly(wayland),
+                None => Self::NoDisplay,
+            };
+        };
+        match var("XAUTHORITY").map(PathBuf::from) {
+            Some(path) if !exists(&path) => Self::MissingXauthority { display, path },
+            _ => Self::Refused(display),
+        }
+    }
+}
+
+impl fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoDisplay => write!(
+                f,
+                "DISPLAY is not set, run the launcher inside an X session or set DISPLAY, \
+                 for example to :0"
+            ),
+            Self::WaylandOnly(wayland) => write!(
+                f,
+                "Only a Wayland session is running ({wayland}) but the launcher needs X11, \
+                 enable XWayland in the compositor"
+            ),
+            Self::MissingXauthority { display, path } => write!(
+                f,
+                "Could not open display {display}: XAUTHORITY is set to {} which does not \
+                 exist, point it to the X server's authority file",
+                path.display()
+            ),
+            Self::Refused(display) => write!(
+                f,
+                "Could not open display {display}: the X server is not running or refused the \
+                 connection, check that DISPLAY and XAUTHORITY match the running session"
+            ),
+        }
+    }
+}
 
 #[derive(Copy, Clone)]
 pub struct Display {
@@ -8,32 +91,41 @@ pub struct Display {
 }
 
 impl Display {
-    pub fn connect(name: Option<&str>) -> Self {
+    /// Connects to the display `name`, or `DISPLAY` if it's `None`.
+    pub fn connect(name: Option<&str>) -> Result<Self, ConnectError> {
         let connection;
         let root;
         let screen;
 
+        let c_name = name.map(|name| CString::new(name).unwrap_or_default());
         unsafe {
             connection = XOpenDisplay(
-                name.map(|s| s.as_ptr() as *const c_char)
+                c_name
+                    .as_ref()
+                    .map(|name| name.as_ptr())
                     .unwrap_or(std::ptr::null()),
             );
             if connection.is_null() {
-                let name = name
-                    .map(|s| s.to_string())
-                    .or_else(|| std::env::var("DISPLAY").ok())
-                    .unwrap_or_default();
-                panic!("Could not open display: {}", name);
+                return Err(ConnectError::diagnose(
+                    name,
+                    |name| std::env::var(name).ok(),
+                    Path::exists,
+                ));
             }
             root = XDefaultRootWindow(connection);
             screen = XDefaultScreen(connection);
         }
 
-        Self {
+        Ok(Self {
             connection,
             screen,
             root,
-        }
+        })
+    }
+
+    /// Like `connect` but panics, for tests that need a display.
+    pub fn connect_or_die(name: Option<&str>) -> Self {
+        Self::connect(name).unwrap_or_else(|error| panic!("{error}"))
     }
 
     pub fn root(&self) -> XWindow {
@@ -83,6 +175,28 @@ impl Display {
         }
     }
 
+    /// Like `next_event` but gives up after `timeout`, returns whether an
+    /// event was received.
+    pub fn next_event_timeout(&self, event_out: &mut XEvent, timeout: Duration) -> bool {
+        unsafe {
+            if XPending(self.connection) == 0 {
+                let mut fd = pollfd {
+                    fd: XConnectionNumber(self.connection),
+                    events: POLLIN,
+                    revents: 0,
+                };
+                let timeout = timeout.as_millis().min(c_int::MAX as u128) as c_int;
+                // The connection may also become readable without there
+                // being an event, so check again.
+                if poll(&mut fd, 1, timeout) <= 0 || XPending(self.connection) == 0 {
+                    return false;
+                }
+            }
+            XNextEvent(self.connection, event_out);
+        }
+        true
+    }
+
     pub fn push_event(&self, mut event: XEvent) {
         unsafe {
             XPutBackEvent(self.connection, &mut event);
@@ -100,6 +214,8 @@ impl Display {
         }
     }
 
+    // The visual comes from Xlib and is only passed back to it.
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
     pub fn create_colormap(&self, visual: *mut Visual, alloc: i32) -> Colormap {
         unsafe { XCreateColormap(self.connection, self.root, visual, alloc) }
     }
@@ -130,7 +246,17 @@ impl Display {
         }
     }
 
-    pub fn scoped_keyboard_grab(&self, window: Window) -> ScopedKeyboardGrab {
+    /// Grabs the keyboard for `window`, with `xi2` each master keyboard is
+    /// grabbed on its own.  The core grab is used if `xi2` is `None` or none
+    /// of the devices could be grabbed.
+    pub fn scoped_keyboard_grab(&self, window: Window, xi2: Option<XInput2>) -> ScopedKeyboardGrab {
+        let devices = xi2.map_or_else(Vec::new, |xi2| xi2.grab_keyboards(self, window));
+        if !devices.is_empty() {
+            return ScopedKeyboardGrab {
+                connection: self.connection,
+                devices,
+            };
+        }
         unsafe {
             XGrabKeyboard(
                 self.connection,
@@ -143,6 +269,7 @@ impl Display {
         }
         ScopedKeyboardGrab {
             connection: self.connection,
+            devices: Vec::new(),
         }
     }
 
@@ -150,13 +277,119 @@ impl Display {
         &self,
         window: Window,
         mouse_mask: i64,
+        xi2: Option<XInput2>,
     ) -> (ScopedKeyboardGrab, Option<ScopedPointerGrab>) {
         (
-            self.scoped_keyboard_grab(window),
+            self.scoped_keyboard_grab(window, xi2),
             self.scoped_pointer_grab(window, mouse_mask),
         )
     }
 
+    pub fn get_input_focus(&self) -> XWindow {
+        let mut focus = NONE;
+        let mut revert_to = 0;
+        unsafe {
+            XGetInputFocus(self.connection, &mut focus, &mut revert_to);
+        }
+        focus
+    }
+
+    /// Runs `f` with a temporary error handler installed and returns whether
+    /// any of the requests made by it generated an error.
+    pub fn trap_errors<F: FnOnce()>(&self, f: F) -> bool {
+        unsafe {
+            XSync(self.connection, False);
+            ERROR_OCCURRED.store(false, Ordering::Relaxed);
+            let previous = XSetErrorHandler(Some(record_error));
+            f();
+            XSync(self.connection, False);
+            XSetErrorHandler(previous);
+        }
+        ERROR_OCCURRED.load(Ordering::Relaxed)
+    }
+
+    pub fn window_exists<W: ToXWindow>(&self, window: W) -> bool {
+        let mut exists = false;
+        let error = self.trap_errors(|| unsafe {
+            let mut attributes: XWindowAttributes = std::mem::MaybeUninit::zeroed().assume_init();
+            exists =
+                XGetWindowAttributes(self.connection, window.to_xwindow(), &mut attributes) != 0;
+        });
+        exists && !error
+    }
+
+    /// Whether a compositing manager is running, it owns the
+    /// `_NET_WM_CM_S<screen>` selection.
+    pub fn has_compositor(&self) -> bool {
+        let name = CString::new(format!("_NET_WM_CM_S{}", self.screen)).unwrap();
+        unsafe {
+            let atom = XInternAtom(self.connection, name.as_ptr(), FALSE);
+            XGetSelectionOwner(self.connection, atom) != NONE
+        }
+    }
+
+    /// The managed windows listed in `_NET_CLIENT_LIST` by the window manager.
+    pub fn client_list(&self) -> Vec<XWindow> {
+        let atom = self.intern_atom(c"_NET_CLIENT_LIST");
+        match Window::from_handle(self, self.root).get_property(atom, XA_WINDOW) {
+            Some(PropertyData::Format32(windows)) => windows,
+            _ => Vec::new(),
+        }
+    }
+
+    /// The raw `_XSETTINGS_SETTINGS` property of the XSettings manager, `None`
+    /// if no manager is running.
+    pub fn xsettings(&self) -> Option<Vec<u8>> {
+        let selection = CString::new(format!("_XSETTINGS_S{}", self.screen)).unwrap();
+        unsafe {
+            let owner = XGetSelectionOwner(
+                self.connection,
+                XInternAtom(self.connection, selection.as_ptr(), FALSE),
+            );
+            if owner == NONE {
+                return None;
+            }
+            let atom = XInternAtom(self.connection, c"_XSETTINGS_SETTINGS".as_ptr(), FALSE);
+            let mut settings = None;
+            // The manager may exit between the two requests
+            self.trap_errors(|| {
+                settings = Window::from_handle(self, owner).get_property(atom, atom);
+            });
+            match settings {
+                Some(PropertyData::Format8(settings)) => Some(settings),
+                _ => None,
+            }
+        }
+    }
+
+    pub fn select_input<W: ToXWindow>(&self, window: W, mask: c_long) {
+        unsafe {
+            XSelectInput(self.connection, window.to_xwindow(), mask);
+        }
+    }
+
+    pub fn intern_atom(&self, name: &CStr) -> Atom {
+        unsafe { XInternAtom(self.connection, name.as_ptr(), FALSE) }
+    }
+
+    /// Sends a client message without data to `window`.
+    pub fn send_client_message<W: ToXWindow>(&self, window: W, message_type: Atom) {
+        unsafe {
+            let mut event: XEvent = std::mem::zeroed();
+            event.client_message.type_ = ClientMessage;
+            event.client_message.window = window.to_xwindow();
+            event.client_message.message_type = message_type;
+            event.client_message.format = 32;
+            XSendEvent(
+                self.connection,
+                window.to_xwindow(),
+                FALSE,
+                NoEventMask,
+                &mut event,
+            );
+        }
+    }
+
     pub fn set_input_focus<W: ToXWindow>(&self, window: W) {
         unsafe {
             XSetInputFocus(
@@ -199,14 +432,65 @@ impl Drop for ScopedPointerGrab {
 
 pub struct ScopedKeyboardGrab {
     connection: XDisplay,
+    /// The XInput2 devices that were grabbed, empty for the core grab.
+    devices: Vec<c_int>,
 }
 
 impl Drop for ScopedKeyboardGrab {
     fn drop(&mut self) {
         unsafe {
-            XUngrabKeyboard(self.connection, CurrentTime);
+            if self.devices.is_empty() {
+                XUngrabKeyboard(self.connection, CurrentTime);
+            }
+            for &device in self.devices.iter() {
+                x11::xinput2::XIUngrabDevice(self.connection, device, CurrentTime);
+            }
         }
     }
 }
 
 pub type ScopedInputGrab = (ScopedKeyboardGrab, Option<ScopedPointerGrab>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn connect_errors() {
+        let diagnose = |name, env: &[(&str, &str)], exists| {
+            let env: HashMap<_, _> = env.iter().copied().collect();
+            ConnectError::diagnose(
+                name,
+                |name| env.get(name).map(|value| value.to_string()),
+                |_| exists,
+            )
+        };
+        assert_eq!(diagnose(None, &[], true), ConnectError::NoDisplay);
+        assert_eq!(
+            diagnose(None, &[("DISPLAY", "")], true),
+            ConnectError::NoDisplay
+        );
+        assert_eq!(
+            diagnose(None, &[("WAYLAND_DISPLAY", "wayland-0")], true),
+            ConnectError::WaylandOnly("wayland-0".to_string())
+        );
+        let xauthority = ("XAUTHORITY", "/run/user/1000/gdm/Xauthority");
+        assert_eq!(
+            diagnose(None, &[("DISPLAY", ":1"), xauthority], false),
+            ConnectError::MissingXauthority {
+                display: ":1".to_string(),
+                path: PathBuf::from(xauthority.1),
+            }
+        );
+        assert_eq!(
+            diagnose(None, &[("DISPLAY", ":1"), xauthority], true),
+            ConnectError::Refused(":1".to_string())
+        );
+        // An explicit name wins over the environment
+        assert_eq!(
+            diagnose(Some(":2"), &[("WAYLAND_DISPLAY", "wayland-0")], false),
+            ConnectError::Refused(":2".to_string())
+        );
+    }
+}
@@ -7,16 +7,19 @@ pub type XWindow = x11::xlib::Window;
 
 pub const NONE: c_ulong = 0;
 pub const FALSE: c_int = 0;
-#[allow(dead_code)]
 pub const TRUE: c_int = 1;
 
 pub mod display;
+pub mod monitors;
 pub mod window;
 pub mod window_builder;
+pub mod xinput2;
 
 // Shadow xlib types with wrappers
 pub use display::Display;
+pub use monitors::Monitor;
 pub use window::Window;
+pub use xinput2::XInput2;
 
 pub fn lookup_keysym(event: &XKeyEvent) -> KeySym {
     unsafe { XLookupKeysym(event as *const XKeyEvent as *mut XKeyEvent, 0) }
@@ -0,0 +1,118 @@
+//! The monitors of the screen, named after their outputs like `DP-1`.
+//!
+//! Names come from RandR 1.5 monitors.  Without it the Xinerama screens, or
+//! the whole display, are used and have no name.
+
+use super::{display::Display, *};
+use x11::{xinerama::*, xrandr::*};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Monitor {
+    /// Empty if it's unknown.
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub primary: bool,
+}
+
+impl Display {
+    fn has_randr_monitors(&self) -> bool {
+        let mut event = 0;
+        let mut error = 0;
+        let mut major = 0;
+        let mut minor = 0;
+        unsafe {
+            XRRQueryExtension(self.as_raw(), &mut event, &mut error) != FALSE
+                && XRRQueryVersion(self.as_raw(), &mut major, &mut minor) != 0
+                && (major, minor) >= (1, 5)
+        }
+    }
+
+    fn atom_name(&self, atom: Atom) -> String {
+        unsafe {
+            let name = XGetAtomName(self.as_raw(), atom);
+            if name.is_null() {
+                return String::new();
+            }
+            let result = CStr::from_ptr(name).to_string_lossy().into_owned();
+            XFree(name as *mut c_void);
+            result
+        }
+    }
+
+    /// The active RandR monitors, empty if the server doesn't support RandR
+    /// 1.5.
+    pub fn monitors(&self) -> Vec<Monitor> {
+        if !self.has_randr_monitors() {
+            return Vec::new();
+        }
+        let mut count = 0;
+        unsafe {
+            let infos = XRRGetMonitors(self.as_raw(), self.root(), TRUE, &mut count);
+            if infos.is_null() {
+                return Vec::new();
+            }
+            let monitors = std::slice::from_raw_parts(infos, count as usize)
+                .iter()
+                .map(|info| Monitor {
+                    name: self.atom_name(info.name),
+                    x: info.x,
+                    y: info.y,
+                    width: info.width as u32,
+                    height: info.height as u32,
+                    primary: info.primary != FALSE,
+                })
+                .collect();
+            XRRFreeMonitors(infos);
+            monitors
+        }
+    }
+
+    /// The first Xinerama screen, or the whole display without Xinerama.
+    fn xinerama_screen(&self) -> Monitor {
+        let whole = Monitor {
+            name: String::new(),
+            x: 0,
+            y: 0,
+            width: self.width(),
+            height: self.height(),
+            primary: true,
+        };
+        unsafe {
+            if XineramaIsActive(self.as_raw()) == 0 {
+                return whole;
+            }
+            let mut len = 0;
+            let data = XineramaQueryScreens(self.as_raw(), &mut len);
+            if data.is_null() {
+                return whole;
+            }
+            let screens = std::slice::from_raw_parts(data, len as usize);
+            let screen = screens
+                .iter()
+                .find(|screen| screen.screen_number == 0)
+                .or(screens.first())
+                .map(|screen| Monitor {
+                    x: screen.x_org as i32,
+                    y: screen.y_org as i32,
+                    width: screen.width as u32,
+                    height: screen.height as u32,
+                    ..whole.clone()
+                });
+            XFree(data as *mut c_void);
+            screen.unwrap_or(whole)
+        }
+    }
+
+    /// The monitor the launcher is shown on: the primary one, or the first.
+    pub fn main_monitor(&self) -> Monitor {
+        main_of(self.monitors()).unwrap_or_else(|| self.xinerama_screen())
+    }
+}
+
+fn main_of(monitors: Vec<Monitor>) -> Option<Monitor> {
+    let primary = monitors.iter().position(|monitor| monitor.primary);
+    monitors.into_iter().nth(primary.unwrap_or(0))
+}
@@ -2,6 +2,34 @@ use super::display::ToXDisplay;
 use super::window_builder::WindowBuilder;
 use super::*;
 
+/// The items of a window property, their width is the property's format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropertyData {
+    Format8(Vec<u8>),
+    Format16(Vec<u16>),
+    /// Xlib returns format 32 items as longs, even if those are 64 bits wide.
+    Format32(Vec<c_ulong>),
+}
+
+impl PropertyData {
+    /// Copies `count` items of `format` from Xlib's buffer.
+    unsafe fn from_raw(format: c_int, data: *const c_uchar, count: usize) -> Option<Self> {
+        Some(match format {
+            8 => Self::Format8(std::slice::from_raw_parts(data, count).to_vec()),
+            16 => Self::Format16(
+                std::slice::from_raw_parts(data as *const c_short, count)
+                    .iter()
+                    .map(|&item| item as u16)
+                    .collect(),
+            ),
+            32 => {
+                Self::Format32(std::slice::from_raw_parts(data as *const c_ulong, count).to_vec())
+            }
+            _ => return None,
+        })
+    }
+}
+
 #[derive(Copy, Clone)]
 pub struct Window {
     handle: XWindow,
@@ -53,6 +81,12 @@ impl Window {
         }
     }
 
+    pub fn move_resize(&self, x: c_int, y: c_int, width: c_uint, height: c_uint) {
+        unsafe {
+            XMoveResizeWindow(self.display(), self.handle, x, y, width, height);
+        }
+    }
+
     pub fn reparent<W: ToXWindow>(&self, parent: W, x: c_int, y: c_int) {
         unsafe {
             XReparentWindow(self.display(), self.handle, parent.to_xwindow(), x, y);
@@ -70,6 +104,126 @@ impl Window {
             XSetClassHint(self.display(), self.handle, &mut h);
         }
     }
+
+    /// Replaces `property` with `count` items of `format`, format 32 items
+    /// are passed as longs.
+    unsafe fn change_property(
+        &self,
+        property: Atom,
+        type_: Atom,
+        format: c_int,
+        data: *const c_uchar,
+        count: usize,
+    ) {
+        XChangeProperty(
+            self.display(),
+            self.handle,
+            property,
+            type_,
+            format,
+            PropModeReplace,
+            data,
+            count as c_int,
+        );
+    }
+
+    /// Sets `property` to UTF-8 text, like `_NET_WM_NAME`.
+    pub fn set_text_property(&self, property: Atom, text: &str) {
+        unsafe {
+            let utf8_string = XInternAtom(self.display(), c"UTF8_STRING".as_ptr(), FALSE);
+            self.change_property(property, utf8_string, 8, text.as_ptr(), text.len());
+        }
+    }
+
+    /// Sets `property` to a list of 32-bit numbers, like `_NET_WM_PID`.
+    pub fn set_cardinal_property(&self, property: Atom, values: &[u32]) {
+        let longs = values
+            .iter()
+            .map(|&value| value as c_ulong)
+            .collect::<Vec<_>>();
+        unsafe {
+            self.change_property(
+                property,
+                XA_CARDINAL,
+                32,
+                longs.as_ptr().cast(),
+                longs.len(),
+            );
+        }
+    }
+
+    /// Reads all of `property` if it has the type `type_`, which can be
+    /// `AnyPropertyType`.  `None` if it's not set or has another type.
+    pub fn get_property(&self, property: Atom, type_: Atom) -> Option<PropertyData> {
+        let mut actual_type = 0;
+        let mut actual_format = 0;
+        let mut count = 0;
+        let mut bytes_after = 0;
+        let mut data: *mut c_uchar = std::ptr::null_mut();
+        unsafe {
+            let status = XGetWindowProperty(
+                self.display(),
+                self.handle,
+                property,
+                0,
+                c_long::MAX,
+                FALSE,
+                type_,
+                &mut actual_type,
+                &mut actual_format,
+                &mut count,
+                &mut bytes_after,
+                &mut data,
+            );
+            if status != Success as c_int || data.is_null() {
+                return None;
+            }
+            // With another type only the actual type is returned, as an empty
+            // format 8 property.
+            let property = (type_ == AnyPropertyType as Atom || actual_type == type_)
+                .then(|| PropertyData::from_raw(actual_format, data, count as usize))
+                .flatten();
+            XFree(data.cast());
+            property
+        }
+    }
+
+    /// Sets the `WM_PROTOCOLS` the window takes part in, like
+    /// `WM_DELETE_WINDOW`.
+    pub fn set_wm_protocols(&self, protocols: &[Atom]) {
+        let mut protocols = protocols.to_vec();
+        unsafe {
+            XSetWMProtocols(
+                self.display(),
+                self.handle,
+                protocols.as_mut_ptr(),
+                protocols.len() as c_int,
+            );
+        }
+    }
+
+    /// Gets the `WM_CLASS` property as `(instance, class)`.
+    pub fn class_hint(&self) -> Option<(String, String)> {
+        unsafe {
+            let mut h = XClassHint {
+                res_name: std::ptr::null_mut(),
+                res_class: std::ptr::null_mut(),
+            };
+            if XGetClassHint(self.display(), self.handle, &mut h) == 0 {
+                return None;
+            }
+            let take = |s: *mut c_char| {
+                if s.is_null() {
+                    String::new()
+                } else {
+                    let owned = CStr::from_ptr(s).to_string_lossy().into_owned();
+                    XFree(s.cast());
+                    owned
+                }
+            };
+            Some((take(h.res_name), take(h.res_class)))
+        }
+    }
 }
 
 pub trait ToXWindow {
@@ -107,3 +261,30 @@ impl std::fmt::Display for Window {
         write!(f, "{}", self.handle)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn property_formats() {
+        let bytes = *b"Launcher";
+        let shorts: [c_short; 2] = [1, -1];
+        let longs: [c_ulong; 2] = [0x1200007, 4321];
+        unsafe {
+            assert_eq!(
+                PropertyData::from_raw(8, bytes.as_ptr(), bytes.len()),
+                Some(PropertyData::Format8(b"Launcher".to_vec()))
+            );
+            assert_eq!(
+                PropertyData::from_raw(16, shorts.as_ptr().cast(), 2),
+                Some(PropertyData::Format16(vec![1, 0xffff]))
+            );
+            assert_eq!(
+                PropertyData::from_raw(32, longs.as_ptr().cast(), 2),
+                Some(PropertyData::Format32(vec![0x1200007, 4321]))
+            );
+            assert_eq!(PropertyData::from_raw(0, bytes.as_ptr(), 0), None);
+        }
+    }
+}
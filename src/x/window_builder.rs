@@ -8,6 +8,12 @@ pub struct WindowAttributes {
     valuemask: c_ulong,
 }
 
+impl Default for WindowAttributes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[allow(dead_code)]
 impl WindowAttributes {
     pub fn new() -> Self {
@@ -0,0 +1,180 @@
+//! Grabbing each master keyboard with XInput2.
+//!
+//! With several master keyboards, like from KVMs or some remote desktops, the
+//! core keyboard grab can miss keys typed on the secondary ones.  The key
+//! events of the grabbed devices arrive as generic events and are turned into
+//! core key events, so input methods and the rest of the key handling don't
+//! need to know about them.
+
+use super::{display::Display, window::Window, *};
+use x11::xinput2::*;
+
+#[derive(Copy, Clone, Debug)]
+pub struct XInput2 {
+    opcode: c_int,
+}
+
+/// The keyboard events that are selected and grabbed, `mask` has to outlive
+/// the result.
+fn key_event_mask(deviceid: c_int, mask: &mut [u8; 1]) -> XIEventMask {
+    XISetMask(mask, XI_KeyPress);
+    XISetMask(mask, XI_KeyRelease);
+    XIEventMask {
+        deviceid,
+        mask_len: mask.len() as c_int,
+        mask: mask.as_mut_ptr(),
+    }
+}
+
+impl XInput2 {
+    /// The IDs of the master keyboards.
+    pub fn master_keyboards(&self, display: &Display) -> Vec<c_int> {
+        let mut count = 0;
+        unsafe {
+            let devices = XIQueryDevice(display.as_raw(), XIAllMasterDevices, &mut count);
+            if devices.is_null() {
+                return Vec::new();
+            }
+            let keyboards = std::slice::from_raw_parts(devices, count as usize)
+                .iter()
+                .filter(|device| device._use == XIMasterKeyboard)
+                .map(|device| device.deviceid)
+                .collect();
+            XIFreeDeviceInfo(devices);
+            keyboards
+        }
+    }
+
+    /// Selects the key events of each master keyboard on `window` and grabs
+    /// them, returns the devices that could be grabbed.
+    pub fn grab_keyboards(&self, display: &Display, window: Window) -> Vec<c_int> {
+        let mut grabbed = Vec::new();
+        for device in self.master_keyboards(display) {
+            let mut mask = [0];
+            let mut event_mask = key_event_mask(device, &mut mask);
+            let status = unsafe {
+                XISelectEvents(display.as_raw(), window.handle(), &mut event_mask, 1);
+                XIGrabDevice(
+                    display.as_raw(),
+                    device,
+                    window.handle(),
+                    CurrentTime,
+                    NONE,
+                    GrabModeAsync,
+                    GrabModeAsync,
+                    FALSE,
+                    &mut event_mask,
+                )
+            };
+            if status == GrabSuccess {
+                grabbed.push(device);
+            }
+        }
+        grabbed
+    }
+
+    /// Turns a key event of a grabbed keyboard into a core key event, `None`
+    /// for any other event.
+    pub fn key_event(&self, display: &Display, event: &mut XEvent) -> Option<XKeyEvent> {
+        unsafe {
+            let cookie = &mut event.generic_event_cookie;
+            if cookie.type_ != GenericEvent || cookie.extension != self.opcode {
+                return None;
+            }
+            if XGetEventData(display.as_raw(), cookie) == FALSE {
+                return None;
+            }
+            #[allow(non_upper_case_globals)]
+            let type_ = match cookie.evtype {
+                XI_KeyPress => Some(KeyPress),
+                XI_KeyRelease => Some(KeyRelease),
+                _ => None,
+            };
+            let key = type_.map(|type_| core_key_event(type_, &*(cookie.data as *const _)));
+            XFreeEventData(display.as_raw(), cookie);
+            key
+        }
+    }
+}
+
+impl Display {
+    /// `None` if the server doesn't support XInput 2.0.
+    pub fn xinput2(&self) -> Option<XInput2> {
+        let mut opcode = 0;
+        let mut event = 0;
+        let mut error = 0;
+        let mut major = 2;
+        let mut minor = 0;
+        unsafe {
+            if XQueryExtension(
+                self.as_raw(),
+                c"XInputExtension".as_ptr(),
+                &mut opcode,
+                &mut event,
+                &mut error,
+            ) == FALSE
+            {
+                return None;
+            }
+            if XIQueryVersion(self.as_raw(), &mut major, &mut minor) != Success as c_int {
+                return None;
+            }
+        }
+        Some(XInput2 { opcode })
+    }
+}
+
+/// The core event with the same meaning as `event`.  The group goes into
+/// bits 13 and 14 of the state like it does for core events.
+fn core_key_event(type_: c_int, event: &XIDeviceEvent) -> XKeyEvent {
+    XKeyEvent {
+        type_,
+        serial: event.serial,
+        send_event: event.send_event,
+        display: event.display,
+        window: event.event,
+        root: event.root,
+        subwindow: event.child,
+        time: event.time,
+        x: event.event_x as c_int,
+        y: event.event_y as c_int,
+        x_root: event.root_x as c_int,
+        y_root: event.root_y as c_int,
+        state: (event.mods.effective | event.group.effective << 13) as c_uint,
+        keycode: event.detail as c_uint,
+        same_screen: TRUE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_key_events() {
+        let mut event = XIDeviceEvent {
+            serial: 7,
+            event: 42,
+            root: 1,
+            time: 1234,
+            detail: 38,
+            event_x: 10.6,
+            event_y: 20.2,
+            root_x: 110.6,
+            root_y: 220.2,
+            ..Default::default()
+        };
+        event.mods.effective = (ShiftMask | ControlMask) as c_int;
+        event.group.effective = 1;
+        let key = core_key_event(KeyPress, &event);
+        assert_eq!(key.type_, KeyPress);
+        assert_eq!(key.serial, 7);
+        assert_eq!(key.window, 42);
+        assert_eq!(key.root, 1);
+        assert_eq!(key.time, 1234);
+        assert_eq!(key.keycode, 38);
+        assert_eq!((key.x, key.y, key.x_root, key.y_root), (10, 20, 110, 220));
+        assert_eq!(key.state, ShiftMask | ControlMask | 1 << 13);
+        assert_eq!(key.same_screen, TRUE);
+    }
+}
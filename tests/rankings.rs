@@ -0,0 +1,21 @@
+//! Compares the search results for the benchmark queries with the golden
+//! file, run with `UPDATE_RANKINGS=1` to accept changed rankings.
+
+#[path = "../benches/corpus/mod.rs"]
+mod corpus;
+
+const GOLDEN: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/benches/fixtures/rankings.txt");
+
+#[test]
+fn rankings() {
+    let rankings = corpus::rankings();
+    if std::env::var_os("UPDATE_RANKINGS").is_some() {
+        std::fs::write(GOLDEN, &rankings).unwrap();
+        return;
+    }
+    let golden = std::fs::read_to_string(GOLDEN).unwrap_or_default();
+    assert!(
+        rankings == golden,
+        "Rankings differ from {GOLDEN}, run with UPDATE_RANKINGS=1 to update it:\n{rankings}"
+    );
+}